@@ -4,6 +4,7 @@
 //! and metadata structures for bidirectional references.
 
 mod span;
+mod source_map;
 mod types;
 mod expr;
 mod stmt;
@@ -14,6 +15,7 @@ mod snippet;
 pub mod printer;
 
 pub use span::*;
+pub use source_map::*;
 pub use types::*;
 pub use expr::*;
 pub use stmt::*;
@@ -5,7 +5,7 @@
 
 use crate::{
     BodySection, Branch, CallArg, CallStep, Condition, ConditionKind, ComputeStep,
-    ContentSection, CovenantQuery, DeleteStep, DialectQuery, EffectDecl, EffectsSection,
+    ContentSection, CovenantQuery, DeleteStep, DialectQuery, EffectDecl, EffectSignature, EffectsSection,
     EnumSignature, FieldAssignment, ForStep, FunctionSignature, HandleBlock, HandleCase,
     IfStep, Input, InputSource, InsertStep, IsolationLevel, MatchCase, MatchPattern,
     MatchStep, MetadataEntry, MetadataSection, Note, Operation, ParallelStep, ParamBinding,
@@ -18,7 +18,7 @@ use crate::{
     TransactionStep, TraverseDepth, TraverseDirection, TraverseStep, TypeDecl, TypesSection,
     UnionMember, UpdateStep, VariantConstruction, BindStep, BindSource,
 };
-use crate::{Literal, Type, TypeKind, TypePath};
+use crate::{walk_steps, Comment, Literal, Span, Type, TypeKind, TypePath};
 
 /// Trait for converting AST nodes to canonical Covenant text format.
 pub trait ToCov {
@@ -76,6 +76,10 @@ impl ToCov for Snippet {
     fn to_cov(&self, indent: usize) -> String {
         let mut lines = Vec::new();
         let ind = indent_str(indent);
+        let comments = CommentPlacement::new(self);
+
+        // Comments before the snippet
+        lines.extend(comments.emit(&CommentAnchor::Header, &ind));
 
         // Snippet header
         let kind_str = self.kind.to_cov(0);
@@ -86,72 +90,211 @@ impl ToCov for Snippet {
             lines.push(note.to_cov(indent));
         }
 
-        // Sections in canonical order: effects, requires, types, tools, signature, body, tests, metadata, relations, content, schema
-        let mut effects = Vec::new();
-        let mut requires = Vec::new();
-        let mut types = Vec::new();
-        let mut tools = Vec::new();
-        let mut signature = Vec::new();
-        let mut body = Vec::new();
-        let mut tests = Vec::new();
-        let mut metadata = Vec::new();
-        let mut relations = Vec::new();
-        let mut content = Vec::new();
-        let mut schema = Vec::new();
-
-        for section in &self.sections {
-            match section {
-                Section::Effects(s) => effects.push(s),
-                Section::Requires(s) => requires.push(s),
-                Section::Types(s) => types.push(s),
-                Section::Tools(s) => tools.push(s),
-                Section::Signature(s) => signature.push(s),
-                Section::Body(s) => body.push(s),
-                Section::Tests(s) => tests.push(s),
-                Section::Metadata(s) => metadata.push(s),
-                Section::Relations(s) => relations.push(s),
-                Section::Content(s) => content.push(s),
-                Section::Schema(s) => schema.push(s),
-            }
+        // Sections in canonical order
+        let mut order: Vec<usize> = (0..self.sections.len()).collect();
+        order.sort_by_key(|&i| self.sections[i].canonical_rank());
+
+        for index in order {
+            let section = &self.sections[index];
+            let text = match section {
+                Section::Effects(s) => s.to_cov(indent),
+                Section::Requires(s) => s.to_cov(indent),
+                Section::Types(s) => s.to_cov(indent),
+                Section::Tools(s) => s.to_cov(indent),
+                Section::Signature(s) => s.to_cov(indent),
+                Section::Body(s) => s.to_cov(indent),
+                Section::Tests(s) => s.to_cov(indent),
+                Section::Metadata(s) => s.to_cov(indent),
+                Section::Relations(s) => s.to_cov(indent),
+                Section::Content(s) => s.to_cov(indent),
+                Section::Schema(s) => s.to_cov(indent),
+            };
+            lines.extend(comments.emit(&CommentAnchor::Before(CommentNode::Section(index)), &ind));
+            lines.push(comments.insert_into_section(index, section, &text));
         }
 
-        // Add sections in canonical order
-        for s in effects {
-            lines.push(s.to_cov(indent));
-        }
-        for s in requires {
-            lines.push(s.to_cov(indent));
-        }
-        for s in types {
-            lines.push(s.to_cov(indent));
-        }
-        for s in tools {
-            lines.push(s.to_cov(indent));
-        }
-        for s in signature {
-            lines.push(s.to_cov(indent));
-        }
-        for s in body {
-            lines.push(s.to_cov(indent));
-        }
-        for s in tests {
-            lines.push(s.to_cov(indent));
-        }
-        for s in metadata {
-            lines.push(s.to_cov(indent));
+        lines.extend(comments.emit(&CommentAnchor::End(CommentNode::Snippet), &ind));
+        lines.push(format!("{}end", ind));
+        lines.extend(comments.emit(&CommentAnchor::Trailing, &ind));
+        lines.join("\n")
+    }
+}
+
+// ===== Comments =====
+
+/// A node comments can be placed around
+#[derive(Debug, Clone, PartialEq)]
+enum CommentNode<'a> {
+    Snippet,
+    /// Index into the snippet's sections
+    Section(usize),
+    /// Step ID, unique within a snippet
+    Step(&'a str),
+}
+
+/// Where a comment is re-emitted
+#[derive(Debug, Clone, PartialEq)]
+enum CommentAnchor<'a> {
+    /// Before the snippet header
+    Header,
+    /// On the lines before a section or step
+    Before(CommentNode<'a>),
+    /// Before the closing `end` of a snippet, section or step
+    End(CommentNode<'a>),
+    /// After the snippet's closing `end`
+    Trailing,
+}
+
+/// Canonical positions of a snippet's comments: each comment goes before
+/// the next section or step at its nesting level, or before the `end` of
+/// the block containing it if none follows.
+///
+/// Inside a section, a comment whose code line is printed unchanged stays
+/// with that line instead: after it on the same line if it trailed code,
+/// otherwise on its own line before it. `end` lines are too ambiguous to
+/// match, and lines are matched in source order.
+struct CommentPlacement<'a> {
+    placed: Vec<(CommentAnchor<'a>, &'a Comment)>,
+}
+
+impl<'a> CommentPlacement<'a> {
+    fn new(snippet: &'a Snippet) -> Self {
+        if snippet.comments.is_empty() {
+            return Self { placed: Vec::new() };
+        }
+
+        // Nodes with source spans; nodes added after parsing have none
+        let mut nodes: Vec<(CommentNode<'a>, Span)> = Vec::new();
+        for (index, section) in snippet.sections.iter().enumerate() {
+            nodes.push((CommentNode::Section(index), section.span()));
+            if let Section::Body(body) = section {
+                walk_steps(&body.steps, &mut |step| {
+                    nodes.push((CommentNode::Step(&step.id), step.span));
+                });
+            }
         }
-        for s in relations {
-            lines.push(s.to_cov(indent));
+        nodes.retain(|(_, span)| span.start < span.end);
+
+        // Innermost node strictly containing a span
+        let container = |span: Span| {
+            nodes
+                .iter()
+                .filter(|(_, n)| n.start < span.start && span.end <= n.end)
+                .min_by_key(|(_, n)| n.end - n.start)
+                .map(|(node, _)| node.clone())
+                .unwrap_or(CommentNode::Snippet)
+        };
+
+        let placed = snippet
+            .comments
+            .iter()
+            .map(|comment| {
+                let span = comment.span;
+                let anchor = if span.end <= snippet.span.start {
+                    CommentAnchor::Header
+                } else if span.start >= snippet.span.end {
+                    CommentAnchor::Trailing
+                } else {
+                    let parent = container(span);
+                    nodes
+                        .iter()
+                        .filter(|(_, n)| n.start >= span.end && container(*n) == parent)
+                        .min_by_key(|(_, n)| n.start)
+                        .map(|(node, _)| CommentAnchor::Before(node.clone()))
+                        .unwrap_or(CommentAnchor::End(parent))
+                };
+                (anchor, comment)
+            })
+            .collect();
+        Self { placed }
+    }
+
+    /// Comment lines for an anchor, indented with `ind`
+    fn emit(&self, anchor: &CommentAnchor<'_>, ind: &str) -> Vec<String> {
+        self.emit_unmatched(anchor, ind, &[])
+    }
+
+    /// Comment lines for an anchor, skipping the placed comments in `matched`
+    fn emit_unmatched(&self, anchor: &CommentAnchor<'_>, ind: &str, matched: &[usize]) -> Vec<String> {
+        self.placed
+            .iter()
+            .enumerate()
+            .filter(|(i, (a, _))| a == anchor && !matched.contains(i))
+            .map(|(_, (_, comment))| format!("{}{}", ind, comment.text))
+            .collect()
+    }
+
+    /// Insert the comments anchored inside a section into its printed text
+    fn insert_into_section(&self, index: usize, section: &'a Section, text: &str) -> String {
+        if self.placed.is_empty() {
+            return text.to_string();
+        }
+        let lines: Vec<&str> = text.lines().collect();
+        let leading = |line: &str| line[..line.len() - line.trim_start().len()].to_string();
+
+        // Comments to insert before and append after each line
+        let mut before: Vec<Vec<String>> = vec![Vec::new(); lines.len()];
+        let mut after: Vec<Vec<&str>> = vec![Vec::new(); lines.len()];
+
+        // Comments whose code line is printed unchanged stay with it
+        let span = section.span();
+        let mut matched = Vec::new();
+        let mut cursor = 0;
+        for (i, (_, comment)) in self.placed.iter().enumerate() {
+            let inside = span.start < comment.span.start && comment.span.end <= span.end;
+            if !inside || comment.line.is_empty() || comment.line == "end" {
+                continue;
+            }
+            let Some(offset) = lines[cursor.min(lines.len())..].iter().position(|l| l.trim() == comment.line) else {
+                continue;
+            };
+            let line = cursor + offset;
+            if comment.trailing {
+                after[line].push(&comment.text);
+                cursor = line + 1;
+            } else {
+                before[line].push(format!("{}{}", leading(lines[line]), comment.text));
+                cursor = line;
+            }
+            matched.push(i);
         }
-        for s in content {
-            lines.push(s.to_cov(indent));
+
+        if let Some(last) = lines.len().checked_sub(1) {
+            let ind = format!("{}  ", leading(lines[0]));
+            before[last].extend(self.emit_unmatched(&CommentAnchor::End(CommentNode::Section(index)), &ind, &matched));
         }
-        for s in schema {
-            lines.push(s.to_cov(indent));
+        if let Section::Body(body) = section {
+            walk_steps(&body.steps, &mut |step| {
+                let header = format!("step id=\"{}\"", step.id);
+                let Some(start) = lines.iter().position(|l| l.trim_start().starts_with(&header)) else {
+                    return;
+                };
+                let ind = leading(lines[start]);
+                let anchor = CommentAnchor::Before(CommentNode::Step(&step.id));
+                let comments = self.emit_unmatched(&anchor, &ind, &matched);
+                // Before any comment already kept with the header line
+                before[start].splice(0..0, comments);
+
+                let end_line = format!("{}end", ind);
+                if let Some(offset) = lines[start..].iter().position(|l| *l == end_line) {
+                    let inner = format!("{}  ", ind);
+                    let anchor = CommentAnchor::End(CommentNode::Step(&step.id));
+                    before[start + offset].extend(self.emit_unmatched(&anchor, &inner, &matched));
+                }
+            });
         }
 
-        lines.push(format!("{}end", ind));
-        lines.join("\n")
+        let mut out = Vec::new();
+        for ((line, comments), trailing) in lines.iter().zip(before).zip(after) {
+            out.extend(comments);
+            let mut line = line.to_string();
+            for comment in trailing {
+                line.push(' ');
+                line.push_str(comment);
+            }
+            out.push(line);
+        }
+        out.join("\n")
     }
 }
 
@@ -168,6 +311,7 @@ impl ToCov for SnippetKind {
             SnippetKind::ExternImpl => "extern-impl".to_string(),
             SnippetKind::Test => "test".to_string(),
             SnippetKind::Data => "data".to_string(),
+            SnippetKind::Effect => "effect".to_string(),
         }
     }
 }
@@ -198,6 +342,9 @@ impl ToCov for EffectsSection {
         for effect in &self.effects {
             lines.push(effect.to_cov(indent + 1));
         }
+        for var in &self.vars {
+            lines.push(format!("{}effect of=\"{}\"", indent_str(indent + 1), var.param));
+        }
 
         lines.push(format!("{}end", ind));
         lines.join("\n")
@@ -348,6 +495,7 @@ impl ToCov for SignatureKind {
             SignatureKind::Function(f) => f.to_cov(indent),
             SignatureKind::Struct(s) => s.to_cov(indent),
             SignatureKind::Enum(e) => e.to_cov(indent),
+            SignatureKind::Effect(e) => e.to_cov(indent),
         }
     }
 }
@@ -482,6 +630,31 @@ impl ToCov for EnumSignature {
     }
 }
 
+impl ToCov for EffectSignature {
+    fn to_cov(&self, indent: usize) -> String {
+        let ind = indent_str(indent);
+        let mut lines = vec![format!("{}effect name=\"{}\"", ind, self.name)];
+
+        for parent in &self.parents {
+            lines.push(format!("{}parent effect=\"{}\"", indent_str(indent + 1), parent));
+        }
+        for implied in &self.implies {
+            lines.push(format!("{}implies effect=\"{}\"", indent_str(indent + 1), implied));
+        }
+        for param in &self.params {
+            lines.push(format!(
+                "{}param name=\"{}\" rule=\"{}\"",
+                indent_str(indent + 1),
+                param.name,
+                param.rule.name()
+            ));
+        }
+
+        lines.push(format!("{}end", ind));
+        lines.join("\n")
+    }
+}
+
 impl ToCov for SnippetVariantDecl {
     fn to_cov(&self, indent: usize) -> String {
         let ind = indent_str(indent);
@@ -712,6 +885,7 @@ impl ToCov for InputSource {
             InputSource::Field { of, field } => {
                 format!("input field=\"{}\" of=\"{}\"", field, of)
             }
+            InputSource::FnRef(name) => format!("input fn=\"{}\"", name),
         }
     }
 }
@@ -742,6 +916,7 @@ impl ToCov for CallArg {
             InputSource::Field { of, field } => {
                 format!("{}arg name=\"{}\" from=\"{}.{}\"", ind, self.name, of, field)
             }
+            InputSource::FnRef(f) => format!("{}arg name=\"{}\" fn=\"{}\"", ind, self.name, f),
         }
     }
 }
@@ -852,6 +1027,7 @@ impl ToCov for ConditionKind {
                     InputSource::Var(v) => format!("var=\"{}\"", v),
                     InputSource::Lit(l) => format!("lit={}", l.to_cov(0)),
                     InputSource::Field { of, field } => format!("field=\"{}.{}\"", of, field),
+                    InputSource::FnRef(f) => format!("fn=\"{}\"", f),
                 };
                 format!("{}equals field=\"{}\" {}", ind, field, val_str)
             }
@@ -860,6 +1036,7 @@ impl ToCov for ConditionKind {
                     InputSource::Var(v) => format!("var=\"{}\"", v),
                     InputSource::Lit(l) => format!("lit={}", l.to_cov(0)),
                     InputSource::Field { of, field } => format!("field=\"{}.{}\"", of, field),
+                    InputSource::FnRef(f) => format!("fn=\"{}\"", f),
                 };
                 format!("{}not_equals field=\"{}\" {}", ind, field, val_str)
             }
@@ -868,6 +1045,7 @@ impl ToCov for ConditionKind {
                     InputSource::Var(v) => format!("var=\"{}\"", v),
                     InputSource::Lit(l) => format!("lit={}", l.to_cov(0)),
                     InputSource::Field { of, field } => format!("field=\"{}.{}\"", of, field),
+                    InputSource::FnRef(f) => format!("fn=\"{}\"", f),
                 };
                 format!("{}contains field=\"{}\" {}", ind, field, val_str)
             }
@@ -983,6 +1161,7 @@ impl ToCov for FieldAssignment {
             InputSource::Field { of, field } => {
                 format!("{}field name=\"{}\" from=\"{}.{}\"", ind, self.name, of, field)
             }
+            InputSource::FnRef(f) => format!("{}field name=\"{}\" fn=\"{}\"", ind, self.name, f),
         }
     }
 }
@@ -1011,6 +1190,7 @@ impl ToCov for IfStep {
             InputSource::Var(v) => format!("\"{}\"", v),
             InputSource::Lit(l) => l.to_cov(0),
             InputSource::Field { of, field } => format!("\"{}.{}\"", of, field),
+            InputSource::FnRef(f) => format!("\"{}\"", f),
         };
         lines.push(format!("{}condition={}", ind, cond_str));
 
@@ -1108,6 +1288,7 @@ impl ToCov for InsertStep {
                 InputSource::Var(v) => format!("from=\"{}\"", v),
                 InputSource::Lit(l) => format!("lit={}", l.to_cov(0)),
                 InputSource::Field { of, field } => format!("from=\"{}.{}\"", of, field),
+                InputSource::FnRef(f) => format!("fn=\"{}\"", f),
             };
             lines.push(format!("{}set field=\"{}\" {}", ind, assignment.name, val_str));
         }
@@ -1126,6 +1307,7 @@ impl ToCov for UpdateStep {
                 InputSource::Var(v) => format!("from=\"{}\"", v),
                 InputSource::Lit(l) => format!("lit={}", l.to_cov(0)),
                 InputSource::Field { of, field } => format!("from=\"{}.{}\"", of, field),
+                InputSource::FnRef(f) => format!("fn=\"{}\"", f),
             };
             lines.push(format!("{}set field=\"{}\" {}", ind, assignment.name, val_str));
         }
@@ -1522,7 +1704,7 @@ mod tests {
     use crate::Span;
 
     fn dummy_span() -> Span {
-        Span { start: 0, end: 0 }
+        Span::dummy()
     }
 
     #[test]
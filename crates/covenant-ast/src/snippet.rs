@@ -17,6 +17,24 @@ pub struct Snippet {
     pub implements: Option<String>,
     /// For extern-impl: the target platform
     pub platform: Option<String>,
+    /// Source comments within or before the snippet, re-emitted by the printer
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub comments: Vec<Comment>,
+    pub span: Span,
+}
+
+/// A source comment, `// ...` or `(* ... *)`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    /// Comment text including its delimiters
+    pub text: String,
+    /// Whether code precedes the comment on its line
+    #[serde(default)]
+    pub trailing: bool,
+    /// The trimmed code the comment belongs to: the code before it on its
+    /// line if trailing, otherwise the next line of code
+    #[serde(default)]
+    pub line: String,
     pub span: Span,
 }
 
@@ -35,6 +53,8 @@ pub enum SnippetKind {
     ExternImpl,
     Test,
     Data,
+    /// Effect declaration (declares an effect and how its parameters are subsumed)
+    Effect,
 }
 
 /// A note annotation (can be multilingual)
@@ -61,11 +81,68 @@ pub enum Section {
     Tools(ToolsSection),
 }
 
+/// Section keywords in canonical order
+pub const CANONICAL_SECTION_ORDER: [&str; 11] = [
+    "effects", "requires", "types", "tools", "signature", "body", "tests", "metadata", "relations", "content",
+    "schema",
+];
+
+impl Section {
+    /// The keyword opening the section
+    pub fn keyword(&self) -> &'static str {
+        CANONICAL_SECTION_ORDER[self.canonical_rank()]
+    }
+
+    /// Position in [`CANONICAL_SECTION_ORDER`]
+    pub fn canonical_rank(&self) -> usize {
+        match self {
+            Section::Effects(_) => 0,
+            Section::Requires(_) => 1,
+            Section::Types(_) => 2,
+            Section::Tools(_) => 3,
+            Section::Signature(_) => 4,
+            Section::Body(_) => 5,
+            Section::Tests(_) => 6,
+            Section::Metadata(_) => 7,
+            Section::Relations(_) => 8,
+            Section::Content(_) => 9,
+            Section::Schema(_) => 10,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Section::Effects(s) => s.span,
+            Section::Requires(s) => s.span,
+            Section::Signature(s) => s.span,
+            Section::Body(s) => s.span,
+            Section::Tests(s) => s.span,
+            Section::Metadata(s) => s.span,
+            Section::Relations(s) => s.span,
+            Section::Content(s) => s.span,
+            Section::Schema(s) => s.span,
+            Section::Types(s) => s.span,
+            Section::Tools(s) => s.span,
+        }
+    }
+}
+
 // ===== Effects Section =====
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EffectsSection {
     pub effects: Vec<EffectDecl>,
+    /// Effect variables: `effect of="f"` takes on the effects of parameter `f`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vars: Vec<EffectVar>,
+    pub span: Span,
+}
+
+/// An effect variable naming a function-typed parameter whose effects the
+/// snippet has, instantiated with the function passed at each call site
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EffectVar {
+    pub param: String,
     pub span: Span,
 }
 
@@ -142,6 +219,7 @@ pub enum SignatureKind {
     Function(FunctionSignature),
     Struct(StructSignature),
     Enum(EnumSignature),
+    Effect(EffectSignature),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -211,6 +289,72 @@ pub struct SnippetVariantDecl {
     pub span: Span,
 }
 
+/// Signature of an effect snippet: the parameters its declarations accept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EffectSignature {
+    pub name: String,
+    pub params: Vec<EffectParamDecl>,
+    /// Broader effects whose declaration covers this one (`parent effect="database"`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parents: Vec<String>,
+    /// Narrower effects that declaring this one covers (`implies effect="database.read"`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub implies: Vec<String>,
+    pub span: Span,
+}
+
+/// An effect parameter and the rule deciding when one value covers another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EffectParamDecl {
+    pub name: String,
+    pub rule: SubsumptionRule,
+    pub span: Span,
+}
+
+/// How a declared effect parameter value subsumes a required one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SubsumptionRule {
+    /// Values must be equal
+    Exact,
+    /// Path prefix by whole segments; `*` matches within a segment, `**` any segments
+    Path,
+    /// Whole-value glob; `*` matches any characters, `?` a single character
+    Glob,
+    /// URL or host: scheme and port when declared, `*.` host wildcards, path prefix
+    Url,
+    /// Numeric range `min..max` (either bound optional) containing the required value
+    Range,
+    /// Comma-separated set of allowed values containing every required value
+    Set,
+}
+
+impl SubsumptionRule {
+    /// Parse a rule name as written in `rule="..."`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "exact" => Some(SubsumptionRule::Exact),
+            "path" => Some(SubsumptionRule::Path),
+            "glob" => Some(SubsumptionRule::Glob),
+            "url" => Some(SubsumptionRule::Url),
+            "range" => Some(SubsumptionRule::Range),
+            "set" => Some(SubsumptionRule::Set),
+            _ => None,
+        }
+    }
+
+    /// The rule name as written in `rule="..."`
+    pub fn name(&self) -> &'static str {
+        match self {
+            SubsumptionRule::Exact => "exact",
+            SubsumptionRule::Path => "path",
+            SubsumptionRule::Glob => "glob",
+            SubsumptionRule::Url => "url",
+            SubsumptionRule::Range => "range",
+            SubsumptionRule::Set => "set",
+        }
+    }
+}
+
 // ===== Body Section =====
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -227,6 +371,46 @@ pub struct Step {
     pub span: Span,
 }
 
+/// Visit every step of a body, including nested ones, in source order
+pub fn walk_steps<'a>(steps: &'a [Step], visit: &mut impl FnMut(&'a Step)) {
+    for step in steps {
+        visit(step);
+        match &step.kind {
+            StepKind::If(if_step) => {
+                walk_steps(&if_step.then_steps, visit);
+                if let Some(else_steps) = &if_step.else_steps {
+                    walk_steps(else_steps, visit);
+                }
+            }
+            StepKind::For(for_step) => walk_steps(&for_step.steps, visit),
+            StepKind::Match(match_step) => {
+                for case in &match_step.cases {
+                    walk_steps(&case.steps, visit);
+                }
+            }
+            StepKind::Call(call) => {
+                if let Some(handle) = &call.handle {
+                    for case in &handle.cases {
+                        walk_steps(&case.steps, visit);
+                    }
+                }
+            }
+            StepKind::Transaction(tx) => walk_steps(&tx.steps, visit),
+            StepKind::Parallel(parallel) => {
+                for branch in &parallel.branches {
+                    walk_steps(&branch.steps, visit);
+                }
+            }
+            StepKind::Race(race) => {
+                for branch in &race.branches {
+                    walk_steps(&branch.steps, visit);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StepKind {
     Compute(ComputeStep),
@@ -381,6 +565,8 @@ pub enum InputSource {
     Var(String),
     Lit(Literal),
     Field { of: String, field: String },
+    /// A function passed as a value, by snippet ID or function name
+    FnRef(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
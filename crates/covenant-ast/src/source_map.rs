@@ -0,0 +1,143 @@
+//! Source files of a compilation
+//!
+//! A [`SourceMap`] owns the text of every file being compiled and gives each
+//! a [`FileId`]. Spans carry the id of their file, so any span can be
+//! resolved to a file name, line and column.
+
+use crate::{FileId, Span};
+
+/// A source file and its text
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub id: FileId,
+    /// Path or other name the file is reported under
+    pub name: String,
+    pub source: String,
+    /// Byte offset of the start of each line
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    fn new(id: FileId, name: String, source: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { id, name, source, line_starts }
+    }
+
+    /// 1-based line and column (in characters) of a byte offset
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let offset = char_boundary(&self.source, offset);
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = self.line_starts[line - 1];
+        (line, self.source[line_start..offset].chars().count() + 1)
+    }
+
+    /// Number of lines
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+}
+
+/// The files of a compilation, indexed by [`FileId`]
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file. Ids are assigned in order, starting at 0.
+    pub fn add(&mut self, name: impl Into<String>, source: impl Into<String>) -> FileId {
+        let id = FileId(self.files.len() as u32);
+        self.files.push(SourceFile::new(id, name.into(), source.into()));
+        id
+    }
+
+    pub fn get(&self, id: FileId) -> Option<&SourceFile> {
+        self.files.get(id.0 as usize)
+    }
+
+    /// The file a span is in
+    pub fn file_of(&self, span: Span) -> Option<&SourceFile> {
+        self.get(span.file)
+    }
+
+    /// Name of a file, or an empty string for an unknown id
+    pub fn name(&self, id: FileId) -> &str {
+        self.get(id).map_or("", |file| file.name.as_str())
+    }
+
+    /// Look up a file by name
+    pub fn find(&self, name: &str) -> Option<FileId> {
+        self.files.iter().find(|file| file.name == name).map(|file| file.id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SourceFile> {
+        self.files.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// 1-based line and column (in characters) of a byte offset into `source`
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let offset = char_boundary(source, offset);
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// `offset` clamped to the source and moved back to a character boundary
+fn char_boundary(source: &str, offset: usize) -> usize {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_column() {
+        let source = "snippet id=\"a\"\n  effects\n  ñ end";
+        let mut map = SourceMap::new();
+        let id = map.add("a.cov", source);
+        let file = map.get(id).unwrap();
+        let end = source.find("end").unwrap();
+        for (offset, expected) in [(0, (1, 1)), (17, (2, 3)), (end, (3, 5)), (1000, (3, 8))] {
+            assert_eq!(line_column(source, offset), expected);
+            assert_eq!(file.line_column(offset), expected);
+        }
+        assert_eq!(file.line_count(), 3);
+    }
+
+    #[test]
+    fn test_files_by_span() {
+        let mut map = SourceMap::new();
+        let a = map.add("a.cov", "snippet");
+        let b = map.add("b.cov", "\n\nend");
+        assert_eq!((a, b), (FileId(0), FileId(1)));
+
+        let span = Span::new(2, 5).in_file(b);
+        let file = map.file_of(span).unwrap();
+        assert_eq!(file.name, "b.cov");
+        assert_eq!(file.line_column(span.start), (3, 1));
+        assert_eq!(map.find("a.cov"), Some(a));
+        assert_eq!(map.name(FileId(7)), "");
+        assert_eq!(span.merge(Span::new(0, 1)).file, b);
+    }
+}
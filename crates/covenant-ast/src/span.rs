@@ -2,6 +2,17 @@
 
 use serde::{Deserialize, Serialize};
 
+/// Identifies a file in a [`SourceMap`](crate::SourceMap). Sources parsed
+/// on their own are file 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub struct FileId(pub u32);
+
+impl FileId {
+    fn is_default(&self) -> bool {
+        self.0 == 0
+    }
+}
+
 /// A span representing a range in source code
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Span {
@@ -9,22 +20,37 @@ pub struct Span {
     pub start: usize,
     /// Byte offset of the end (exclusive)
     pub end: usize,
+    /// File the offsets are into
+    #[serde(default, skip_serializing_if = "FileId::is_default")]
+    pub file: FileId,
 }
 
 impl Span {
     pub fn new(start: usize, end: usize) -> Self {
-        Self { start, end }
+        Self { start, end, file: FileId::default() }
     }
 
     pub fn dummy() -> Self {
-        Self { start: 0, end: 0 }
+        Self::new(0, 0)
+    }
+
+    /// Whether this is a placeholder rather than a source position
+    pub fn is_dummy(&self) -> bool {
+        self.start == 0 && self.end == 0
+    }
+
+    /// The same offsets in another file
+    pub fn in_file(self, file: FileId) -> Span {
+        Span { file, ..self }
     }
 
-    /// Merge two spans into one that covers both
+    /// Merge two spans into one that covers both. The result is in the
+    /// file of `self`.
     pub fn merge(self, other: Span) -> Span {
         Span {
             start: self.start.min(other.start),
             end: self.end.max(other.end),
+            file: self.file,
         }
     }
 }
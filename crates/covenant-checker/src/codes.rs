@@ -0,0 +1,754 @@
+//! Error code registry
+//!
+//! Every diagnostic code the compiler and runtime emit, with its title,
+//! default severity, a longer description and an example fix. Titles match
+//! the headings in ERROR_CODES.md, which also documents each code's auto-fix.
+
+use crate::diagnostics::Severity;
+
+/// Where the codes are documented, relative to the repository root
+pub const ERROR_CODES_DOC: &str = "docs/design/ERROR_CODES.md";
+
+/// A registered diagnostic code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCode {
+    /// Code, e.g. "E-EFFECT-002"
+    pub code: &'static str,
+    /// Short title, e.g. "Missing Effect Declaration"
+    pub title: &'static str,
+    /// Severity unless the emitting phase decides otherwise
+    pub severity: Severity,
+    /// What the code means and what usually causes it
+    pub description: &'static str,
+    /// Source showing the fix
+    pub example_fix: &'static str,
+}
+
+impl ErrorCode {
+    const fn error(
+        code: &'static str,
+        title: &'static str,
+        description: &'static str,
+        example_fix: &'static str,
+    ) -> Self {
+        Self { code, title, severity: Severity::Error, description, example_fix }
+    }
+
+    const fn warning(
+        code: &'static str,
+        title: &'static str,
+        description: &'static str,
+        example_fix: &'static str,
+    ) -> Self {
+        Self { code, title, severity: Severity::Warning, description, example_fix }
+    }
+
+    /// Heading anchor of the code in ERROR_CODES.md
+    pub fn doc_anchor(&self) -> String {
+        format!("{}: {}", self.code, self.title)
+            .to_lowercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-')
+            .map(|c| if c == ' ' { '-' } else { c })
+            .collect()
+    }
+
+    /// Link to the code's section in ERROR_CODES.md
+    pub fn doc_link(&self) -> String {
+        format!("{}#{}", ERROR_CODES_DOC, self.doc_anchor())
+    }
+}
+
+/// All registered codes, grouped by phase
+pub const ERROR_CODES: &[ErrorCode] = &[
+    // Parse errors
+    ErrorCode::error(
+        "E-PARSE-001",
+        "Unexpected Token",
+        "The parser found a token that does not fit the grammar at this point. \
+         This is usually a block closed too early, a misspelled keyword or a missing `=`.",
+        r#"signature
+  fn name="bar"
+    param name="x" type="Int"
+  end  // close the fn block before the signature
+end"#,
+    ),
+    ErrorCode::error(
+        "E-PARSE-002",
+        "Missing Required Field",
+        "A block is missing a field its kind requires, such as the `as` binding of a step.",
+        r#"step id="s1" kind="call"
+  fn="math.add"
+  as="sum"  // every step binds its result
+end"#,
+    ),
+    ErrorCode::error(
+        "E-PARSE-003",
+        "Invalid Canonical Ordering",
+        "Sections or fields appear in a different order than the canonical one, \
+         which keeps every program in exactly one textual form.",
+        r#"snippet id="app.main" kind="fn"
+effects  // effects come before signature
+  effect console
+end
+signature
+  fn name="main"
+  end
+end
+end"#,
+    ),
+    ErrorCode::error(
+        "E-PARSE-004",
+        "Unterminated Block",
+        "A block was opened but the file ended before its `end`.",
+        r#"snippet id="app.main" kind="fn"
+body
+end
+end  // every snippet, section and step closes with `end`"#,
+    ),
+    ErrorCode::error(
+        "E-PARSE-005",
+        "Invalid Snippet Kind",
+        "The `kind` of a snippet is not one the language defines, such as `fn`, \
+         `struct`, `enum`, `extern`, `module` or `database`.",
+        r#"snippet id="app.main" kind="fn"  // not kind="function"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-PARSE-006",
+        "Invalid Step Kind",
+        "The `kind` of a step is not one the language defines, such as `compute`, \
+         `call`, `bind`, `return`, `if`, `match`, `for` or `query`.",
+        r#"step id="s1" kind="call"  // not kind="invoke"
+  fn="io.print"
+  as="_"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-PARSE-007",
+        "Invalid Operation",
+        "The `op` of a compute step is not a known operation.",
+        r#"step id="s1" kind="compute"
+  op=add  // not op=plus
+  input var="a"
+  input var="b"
+  as="sum"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-PARSE-008",
+        "Unexpected Section",
+        "A section appears in a snippet kind that does not allow it, or its name is \
+         not a section at all.",
+        r#"snippet id="app.main" kind="fn"
+signature  // not `header`
+  fn name="main"
+  end
+end
+end"#,
+    ),
+    ErrorCode::error(
+        "E-PARSE-009",
+        "Invalid Subsumption Rule",
+        "An effect parameter declares a subsumption rule other than `exact`, `path`, \
+         `glob`, `url`, `range` or `set`.",
+        r#"effect name="queue"
+  param name="topic" rule="glob"  // not rule="wildcard"
+end"#,
+    ),
+    // Type errors
+    ErrorCode::error(
+        "E-TYPE-001",
+        "Type Mismatch",
+        "A value has a different type than the context expects.",
+        r#"step id="s1" kind="compute"
+  op=add
+  input var="count"
+  input lit=1  // not lit="1"
+  as="next"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-TYPE-002",
+        "Undefined Type",
+        "A type annotation names a type no snippet defines.",
+        r#"param name="u" type="User"  // not type="Usr""#,
+    ),
+    ErrorCode::error(
+        "E-TYPE-003",
+        "Incompatible Union Members",
+        "A union contains members that cannot be told apart at runtime, or a value \
+         is used as a union it is not a member of.",
+        r#"returns union
+  type="String"
+  type="ParseError"  // each member type appears once
+end"#,
+    ),
+    ErrorCode::error(
+        "E-TYPE-004",
+        "Non-Exhaustive Pattern Match",
+        "A match does not cover every variant of the type it matches on.",
+        r#"step id="s1" kind="match"
+  on="result"
+  case variant type="Ok" bindings=("v")
+  end
+  case wildcard  // or one case per remaining variant
+  end
+  as="handled"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-TYPE-005",
+        "Conflicting Type Argument",
+        "The arguments of a call bind one type parameter to two different types.",
+        r#"// pair takes a: T and b: T
+step id="s1" kind="call"
+  fn="util.pair"
+  arg name="a" lit=1
+  arg name="b" lit=2  // both arguments are Int
+  as="p"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-TYPE-006",
+        "Uninferred Type Parameter",
+        "A type parameter appears in no parameter type, so no call can infer it.",
+        r#"fn name="first"
+  generic name="T"
+  param name="items" type="List<T>"  // T appears in a parameter
+  returns type="T"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-TYPE-007",
+        "Missing Field",
+        "A struct construction does not set a required field. Only `optional` and \
+         `auto` fields may be omitted.",
+        r#"step id="s1" kind="construct"
+  type="User"
+  field name="id" lit=1
+  field name="name" lit="Ada"  // set every required field
+  as="user"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-TYPE-008",
+        "Field Type Mismatch",
+        "A struct construction sets a field to a value of the wrong type.",
+        r#"step id="s1" kind="construct"
+  type="User"
+  field name="id" lit=1  // id is Int, not lit="1"
+  as="user"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-TYPE-009",
+        "Too Many Pattern Bindings",
+        "A match case binds more values than the variant has fields. Bindings are \
+         matched to fields by position.",
+        r#"case variant type="Some" bindings=("value")  // Some has one field
+end"#,
+    ),
+    ErrorCode::error(
+        "E-TYPE-010",
+        "Unknown Field",
+        "A field access or construction names a field the struct does not have.",
+        r#"step id="s1" kind="bind"
+  field="name" of="user"  // not field="nmae"
+  as="name"
+end"#,
+    ),
+    // Call errors
+    ErrorCode::error(
+        "E-CALL-001",
+        "Missing Argument",
+        "A call does not pass one of the callee's parameters. Every declared \
+         parameter must be given an `arg`.",
+        r#"step id="s1" kind="call"
+  fn="io.print"
+  arg name="msg" lit="hello"  // pass every parameter
+  as="_"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-CALL-002",
+        "Unknown Argument",
+        "A call passes an argument the callee has no parameter for.",
+        r#"step id="s1" kind="call"
+  fn="io.print"
+  arg name="msg" lit="hello"  // not name="message"
+  as="_"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-CALL-003",
+        "Argument Type Mismatch",
+        "An argument has a different type than the parameter it is passed to.",
+        r#"step id="s1" kind="call"
+  fn="math.square"
+  arg name="x" lit=4  // x is Int, not lit="4"
+  as="sq"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-CALL-004",
+        "Unhandleable Error Type",
+        "A `handle` case names an error type the callee cannot return.",
+        r#"step id="s1" kind="call"
+  fn="db.find_user"
+  arg name="id" from="id"
+  as="user"
+  handle
+    case type="DbError"  // one of the callee's error types
+    end
+  end
+end"#,
+    ),
+    ErrorCode::error(
+        "E-CALL-005",
+        "Argument Count Mismatch",
+        "A call through a function value passes a different number of arguments \
+         than the function type takes. Arguments to a function value are matched by position.",
+        r#"// f has type (Int, Int) -> Int
+step id="s1" kind="call"
+  fn="f"
+  arg name="a" lit=1
+  arg name="b" lit=2
+  as="r"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-CALL-006",
+        "Duplicate Argument",
+        "A call passes the same argument name twice. Each parameter takes exactly one `arg`.",
+        r#"step id="s1" kind="call"
+  fn="math.sub"
+  arg name="a" lit=10
+  arg name="b" lit=1  // not a second name="a"
+  as="diff"
+end"#,
+    ),
+    // Scope errors
+    ErrorCode::error(
+        "E-SCOPE-001",
+        "Duplicate Binding",
+        "A step binds a name an earlier step already bound. Bindings are immutable, \
+         so each step must bind a new name.",
+        r#"step id="s2" kind="compute"
+  op=add
+  input var="total"
+  input lit=1
+  as="new_total"  // not as="total" again
+end"#,
+    ),
+    ErrorCode::error(
+        "E-SCOPE-002",
+        "Use Before Definition",
+        "A step uses a name that a later step defines.",
+        r#"step id="s1" kind="bind"
+  lit=1
+  as="x"
+end
+step id="s2" kind="compute"  // uses x after s1 binds it
+  op=add
+  input var="x"
+  input lit=1
+  as="y"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-SCOPE-003",
+        "Branch-Local Binding",
+        "A step uses a name bound only inside one branch of an earlier `if` or \
+         `match`. Use the output binding of the branching step instead.",
+        r#"step id="s1" kind="if"
+  condition="ok"
+  then
+    step id="s1a" kind="bind"
+      lit=1
+      as="inner"
+    end
+  end
+  as="choice"  // later steps use `choice`, not `inner`
+end"#,
+    ),
+    ErrorCode::error(
+        "E-SCOPE-004",
+        "Duplicate Step ID",
+        "Two steps in one snippet have the same id. Step ids must be unique within a snippet.",
+        r#"step id="s1" kind="bind"
+  lit=1
+  as="a"
+end
+step id="s2" kind="bind"  // not id="s1" again
+  lit=2
+  as="b"
+end"#,
+    ),
+    // Effect errors
+    ErrorCode::error(
+        "E-EFFECT-001",
+        "Pure Function Calls Effectful Code",
+        "A function that declares no effects calls a function with effects, directly \
+         or through other calls.",
+        r#"snippet id="app.greet" kind="fn"
+effects
+  effect console  // declare what the callees need
+end
+end"#,
+    ),
+    ErrorCode::error(
+        "E-EFFECT-002",
+        "Missing Effect Declaration",
+        "A function declares some effects but not every effect its callees need.",
+        r#"effects
+  effect database
+  effect network  // add the missing effect
+end"#,
+    ),
+    ErrorCode::error(
+        "E-EFFECT-003",
+        "Effect Transitivity Violation",
+        "A function calls code whose effects come from further down the call chain \
+         and are not declared along the way.",
+        r#"snippet id="middleware.audit" kind="fn"
+effects
+  effect filesystem  // declare the effect where it enters the chain
+end
+end"#,
+    ),
+    ErrorCode::error(
+        "E-EFFECT-004",
+        "Effectful Function Passed Where Pure Expected",
+        "An effectful function is passed to a function-typed parameter whose callee \
+         does not declare `effect of` for it, so the parameter only accepts pure functions.",
+        r#"snippet id="list.map" kind="fn"
+effects
+  effect of="f"  // the effects of f reach callers of map
+end
+end"#,
+    ),
+    ErrorCode::error(
+        "E-EFFECT-005",
+        "Undeclared Effect Variable",
+        "A function forwards one of its function-typed parameters to a callee's \
+         effect variable without declaring `effect of` for it.",
+        r#"snippet id="app.apply" kind="fn"
+effects
+  effect of="callback"  // forward the parameter's effects
+end
+end"#,
+    ),
+    ErrorCode::error(
+        "E-EFFECT-006",
+        "Invalid Effect Variable",
+        "`effect of` names something other than a function-typed parameter.",
+        r#"signature
+  fn name="map"
+    param name="f" type="(Int) -> Int"  // effect of="f" needs a function type
+  end
+end"#,
+    ),
+    ErrorCode::error(
+        "E-EFFECT-007",
+        "Effect Policy Violation",
+        "A snippet uses an effect, directly or through its callees, that the project \
+         effect policy in covenant-policy.toml forbids for it.",
+        r#"# covenant-policy.toml
+[[rule]]
+snippets = "app.*"
+allow = ["network"]  # or remove the call that needs the effect"#,
+    ),
+    ErrorCode::error(
+        "E-EFFECT-008",
+        "Effect Parameter Not Covered",
+        "A function declares a parameterized effect whose value does not cover what \
+         a callee requires, such as a narrower path or a different host.",
+        r#"effects
+  effect filesystem(path="/data")  // covers the callee's /data/users
+end"#,
+    ),
+    // Requirement errors
+    ErrorCode::warning(
+        "E-REQ-001",
+        "Uncovered Requirement",
+        "A requirement has no test declaring `covers` for it. The severity follows \
+         the requirement's priority, and critical requirements are errors.",
+        r#"tests
+  test id="T-1" kind="unit" covers="R-1"
+  end
+end"#,
+    ),
+    ErrorCode::error(
+        "E-REQ-002",
+        "Test References Nonexistent Requirement",
+        "A test declares `covers` for a requirement id no snippet defines.",
+        r#"test id="T-1" kind="unit" covers="R-AUTH-001"  // an existing requirement id
+end"#,
+    ),
+    ErrorCode::error(
+        "E-REQ-003",
+        "Duplicate Requirement ID",
+        "Two requirements have the same id. Requirement ids are global across the project.",
+        r#"req id="R-AUTH-002"  // not R-AUTH-001 again
+end"#,
+    ),
+    ErrorCode::error(
+        "E-REQ-004",
+        "Duplicate Test ID",
+        "Two tests have the same id. Test ids are global across the project.",
+        r#"test id="T-AUTH-002" kind="unit"  // not T-AUTH-001 again
+end"#,
+    ),
+    // Symbol errors
+    ErrorCode::error(
+        "E-SYMBOL-001",
+        "Undefined Reference",
+        "A snippet refers to a symbol no snippet defines, usually because of a typo \
+         or a missing import.",
+        r#"step id="s1" kind="call"
+  fn="auth.login"  // not fn="auth.logn"
+  as="session"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-SYMBOL-002",
+        "Duplicate Symbol ID",
+        "Two snippets have the same id. Snippet ids must be unique.",
+        r#"snippet id="auth.logout" kind="fn"  // not auth.login again
+end"#,
+    ),
+    ErrorCode::error(
+        "E-SYMBOL-003",
+        "Circular Import",
+        "The import graph contains a cycle.",
+        r#"// a imports b and b imports a: move what both need into a third module
+snippet id="shared.types" kind="module"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-REL-001",
+        "Relation Target Not Found",
+        "A `relations` entry points at a snippet id that does not exist.",
+        r#"relations
+  rel to="docs.auth" type=described_by  // an existing snippet id
+end"#,
+    ),
+    // Extern errors
+    ErrorCode::error(
+        "E-EXTERN-001",
+        "Unknown Extern Abstract",
+        "An `extern-impl` implements an `extern-abstract` that does not exist.",
+        r#"snippet id="fs.read.deno" kind="extern-impl"
+  implements="fs.read" platform="deno"  // define extern-abstract fs.read first
+end"#,
+    ),
+    ErrorCode::error(
+        "E-EXTERN-002",
+        "No Binding for Target",
+        "An `extern-abstract` has no `extern-impl` for the platform being compiled for.",
+        r#"snippet id="fs.read.node" kind="extern-impl"
+  implements="fs.read" platform="node"  // one impl per target platform
+end"#,
+    ),
+    ErrorCode::error(
+        "E-EXTERN-003",
+        "Invalid Extern ID",
+        "An extern snippet id has no namespace. Extern ids use a dot to separate \
+         the namespace from the name.",
+        r#"snippet id="console.println" kind="extern"  // not id="println"
+end"#,
+    ),
+    // Query errors
+    ErrorCode::error(
+        "E-QUERY-001",
+        "Query Exceeds Cost Budget",
+        "Static analysis estimates a query will cost more than its snippet's declared cost hint.",
+        r#"metadata
+  cost_hint=expensive  // or rewrite the query to avoid the join
+end"#,
+    ),
+    ErrorCode::error(
+        "E-QUERY-002",
+        "Invalid Query Target",
+        "A query's target is neither `project` nor a declared database binding.",
+        r#"step id="s1" kind="query"
+  target="app_db"  // a declared database snippet
+  select all
+  from="users"
+  as="users"
+end"#,
+    ),
+    ErrorCode::error(
+        "E-QUERY-012",
+        "SQL Runtime Error",
+        "The database rejected the SQL of a dialect query at runtime.",
+        r#"body
+  SELECT id, name FROM users WHERE id = :id  -- valid SQL for the dialect
+end"#,
+    ),
+    ErrorCode::error(
+        "E-QUERY-013",
+        "Return Type Mismatch",
+        "A SQL query returned columns that do not match its declared return type.",
+        r#"returns collection of="User"  // select exactly the columns of User"#,
+    ),
+    ErrorCode::error(
+        "E-QUERY-020",
+        "Unmatched Placeholder",
+        "A placeholder in the SQL body has no matching `param` declaration.",
+        r#"params
+  param name="user_id" from="id"  // one param per :placeholder
+end"#,
+    ),
+    ErrorCode::error(
+        "E-QUERY-021",
+        "Missing Placeholder",
+        "A `param` is declared but the SQL body has no matching placeholder.",
+        r#"body
+  SELECT * FROM users WHERE id = :user_id  -- use every declared param
+end"#,
+    ),
+    ErrorCode::error(
+        "E-QUERY-022",
+        "Missing Returns Annotation",
+        "A SQL dialect query has no `returns` type annotation.",
+        r#"returns collection of="User""#,
+    ),
+    // Runtime errors
+    ErrorCode::error(
+        "E-RT-001",
+        "Symbol Not Found",
+        "The runtime was asked for a symbol its symbol table does not contain.",
+        "// look up an id the loaded program defines",
+    ),
+    ErrorCode::error(
+        "E-RT-002",
+        "Invalid Query",
+        "A query sent to the runtime could not be executed as written.",
+        r#"target="project" select all from="functions" where equals field="is_pure" lit=true"#,
+    ),
+    ErrorCode::error(
+        "E-RT-003",
+        "Query Cancelled",
+        "A runtime query was cancelled before it completed.",
+        "// rerun the query; nothing in the source needs to change",
+    ),
+    ErrorCode::error(
+        "E-RT-004",
+        "Query Timeout",
+        "A runtime query ran past its time limit.",
+        "// narrow the query with a `where` clause or raise the timeout",
+    ),
+    ErrorCode::error(
+        "E-RT-005",
+        "Runtime Parse Error",
+        "Source handed to the runtime, for example by a metaprogram, does not parse.",
+        "// fix the generated source as for the E-PARSE code in the message",
+    ),
+    ErrorCode::error(
+        "E-RT-006",
+        "Validation Error",
+        "A snippet added or changed at runtime failed validation.",
+        "// fix the snippet as for the check error in the message",
+    ),
+    ErrorCode::error(
+        "E-RT-007",
+        "Compilation Error",
+        "A snippet added or changed at runtime failed to compile.",
+        "// fix the snippet as for the compile error in the message",
+    ),
+    ErrorCode::error(
+        "E-RT-008",
+        "Deserialization Failed",
+        "Data read by the runtime, such as a stored symbol table, is not in the expected format.",
+        "// rebuild the stored data with the current compiler",
+    ),
+    ErrorCode::error(
+        "E-RT-999",
+        "Internal Error",
+        "The runtime reached a state it does not expect. This is a bug in Covenant.",
+        "// report it together with the program that triggers it",
+    ),
+    // Warnings
+    ErrorCode::warning(
+        "W-DEAD-001",
+        "Unused Binding",
+        "A step binds a name nothing uses.",
+        r#"step id="s1" kind="call"
+  fn="io.print"
+  arg name="msg" lit="hi"
+  as="_"  // bind to `_` when the result is not needed
+end"#,
+    ),
+    ErrorCode::warning(
+        "W-DEAD-002",
+        "Unreachable Code",
+        "A step can never run, for example because it follows a `return`.",
+        r#"step id="s1" kind="return"  // the last step of its block
+  lit=42
+  as="_"
+end"#,
+    ),
+    ErrorCode::warning(
+        "W-DEAD-003",
+        "Uncalled Function",
+        "A function is not called by any other function and is not an entry point.",
+        "// call it, export it, or delete the snippet",
+    ),
+    ErrorCode::warning(
+        "W-PERF-001",
+        "Inefficient Query Pattern",
+        "A query uses a pattern that could be written more efficiently.",
+        r#"step id="s1" kind="query"
+  target="project"
+  select all
+  from="functions"
+  where equals field="module" lit="auth"  // filter in the query, not afterwards
+  as="fns"
+end"#,
+    ),
+];
+
+/// Look up a registered code, ignoring case
+pub fn lookup_code(code: &str) -> Option<&'static ErrorCode> {
+    ERROR_CODES.iter().find(|c| c.code.eq_ignore_ascii_case(code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_and_anchor() {
+        let code = lookup_code("e-effect-004").unwrap();
+        assert_eq!(code.title, "Effectful Function Passed Where Pure Expected");
+        assert_eq!(
+            code.doc_anchor(),
+            "e-effect-004-effectful-function-passed-where-pure-expected"
+        );
+        assert_eq!(lookup_code("W-DEAD-001").unwrap().severity, Severity::Warning);
+        // A code in the registry's format that nothing emits
+        assert!(lookup_code("E-PARSE-999").is_none());
+    }
+
+    #[test]
+    fn test_codes_are_unique() {
+        for (i, code) in ERROR_CODES.iter().enumerate() {
+            assert!(
+                ERROR_CODES[i + 1..].iter().all(|other| other.code != code.code),
+                "{} registered twice",
+                code.code
+            );
+        }
+    }
+
+    #[test]
+    fn test_every_code_is_documented() {
+        let docs = include_str!("../../../docs/design/ERROR_CODES.md");
+        for code in ERROR_CODES {
+            let heading = format!("### {}: {}\n", code.code, code.title);
+            assert!(docs.contains(&heading), "ERROR_CODES.md has no `{}`", heading.trim());
+        }
+    }
+}
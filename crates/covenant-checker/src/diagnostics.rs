@@ -4,6 +4,9 @@
 //! and effect violation explanations for the Covenant compiler.
 
 use covenant_ast::Span;
+use covenant_parser::ParseError;
+use covenant_symbols::SymbolError;
+use serde::Serialize;
 use crate::CheckError;
 
 /// A diagnostic with context, suggestions, and explanations
@@ -23,6 +26,10 @@ pub struct Diagnostic {
     pub severity: Severity,
     /// Error code (e.g., "E-EFFECT-001")
     pub code: String,
+    /// Snippet the diagnostic is about, if known
+    pub snippet_id: Option<String>,
+    /// Step the diagnostic is about, if known
+    pub step_id: Option<String>,
 }
 
 /// Kind of diagnostic
@@ -34,6 +41,9 @@ pub enum DiagnosticKind {
     Type(TypeDiagnostic),
     /// Symbol-related error
     Symbol(SymbolDiagnostic),
+    /// Diagnostic described by its message alone (parse errors, symbol graph
+    /// errors, requirement errors, optimizer warnings)
+    Message(String),
 }
 
 /// Effect-specific diagnostic information
@@ -109,6 +119,13 @@ pub enum FixSuggestion {
         /// Snippet ID
         snippet_id: String,
     },
+    /// Pass the arguments the callee declares
+    MatchParameters {
+        /// The function being called
+        function: String,
+        /// Declared parameters, as `name: Type`
+        params: Vec<String>,
+    },
 }
 
 /// A related source location with context
@@ -116,16 +133,15 @@ pub enum FixSuggestion {
 pub struct RelatedLocation {
     /// Message explaining the relation
     pub message: String,
-    /// Source span
+    /// Source span, in the file given by its file id
     pub span: Span,
-    /// File path (if different from main error)
-    pub file: Option<String>,
     /// Label for the location (e.g., "defined here", "called from here")
     pub label: String,
 }
 
 /// Severity level of a diagnostic
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Severity {
     Error,
     Warning,
@@ -133,6 +149,18 @@ pub enum Severity {
     Hint,
 }
 
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+            Severity::Hint => "hint",
+        };
+        write!(f, "{}", label)
+    }
+}
+
 impl Diagnostic {
     /// Create a new diagnostic
     pub fn new(
@@ -149,6 +177,8 @@ impl Diagnostic {
             span,
             severity: Severity::Error,
             code: code.into(),
+            snippet_id: None,
+            step_id: None,
         }
     }
 
@@ -176,11 +206,42 @@ impl Diagnostic {
         self
     }
 
+    /// Set the snippet the diagnostic is about
+    pub fn with_snippet(mut self, snippet_id: impl Into<String>) -> Self {
+        self.snippet_id = Some(snippet_id.into());
+        self
+    }
+
+    /// Set the step the diagnostic is about
+    pub fn with_step(mut self, step_id: impl Into<String>) -> Self {
+        self.step_id = Some(step_id.into());
+        self
+    }
+
     /// Get the primary message for this diagnostic
     pub fn message(&self) -> String {
         match &self.error {
             DiagnosticKind::Effect(e) => {
-                if let Some(ref callee) = e.callee {
+                if self.code == "E-EFFECT-004" {
+                    format!(
+                        "function `{}` passes effectful function `{}` where a pure function is expected (effects: {})",
+                        e.function,
+                        e.callee.as_deref().unwrap_or("unknown"),
+                        e.effects.join(", ")
+                    )
+                } else if self.code == "E-EFFECT-005" {
+                    format!(
+                        "function `{}` passes a function parameter to `{}` without declaring its effects",
+                        e.function,
+                        e.callee.as_deref().unwrap_or("unknown")
+                    )
+                } else if self.code == "E-EFFECT-007" {
+                    format!(
+                        "effect policy forbids function `{}` from using {}",
+                        e.function,
+                        e.effects.join(", ")
+                    )
+                } else if let Some(ref callee) = e.callee {
                     format!(
                         "pure function `{}` calls effectful function `{}` (effects: {})",
                         e.function,
@@ -201,6 +262,7 @@ impl Diagnostic {
             DiagnosticKind::Symbol(s) => {
                 format!("undefined symbol: `{}` ({})", s.name, s.context)
             }
+            DiagnosticKind::Message(message) => message.clone(),
         }
     }
 
@@ -258,6 +320,13 @@ impl FixSuggestion {
                     effects.join(", ")
                 )
             }
+            FixSuggestion::MatchParameters { function, params } => {
+                if params.is_empty() {
+                    format!("Call `{}` without arguments", function)
+                } else {
+                    format!("Call `{}` with its parameters: {}", function, params.join(", "))
+                }
+            }
         }
     }
 
@@ -275,6 +344,15 @@ impl FixSuggestion {
                 code.push_str("end");
                 Some(code)
             }
+            FixSuggestion::MatchParameters { params, .. } if !params.is_empty() => {
+                let args: Vec<String> = params.iter()
+                    .map(|p| {
+                        let name = p.split(':').next().unwrap_or(p).trim();
+                        format!("arg name=\"{}\" from=\"...\"", name)
+                    })
+                    .collect();
+                Some(args.join("\n"))
+            }
             _ => None,
         }
     }
@@ -282,6 +360,7 @@ impl FixSuggestion {
 
 impl From<CheckError> for Diagnostic {
     fn from(error: CheckError) -> Self {
+        let code = error.code();
         match error {
             CheckError::UndefinedSymbol { name } => {
                 Diagnostic::new(
@@ -290,7 +369,7 @@ impl From<CheckError> for Diagnostic {
                         context: "not found in scope".to_string(),
                     }),
                     Span::dummy(),
-                    "E-SYMBOL-001",
+                    code,
                     format!("The symbol `{}` was used but not defined. Check for typos or missing imports.", name),
                 )
             }
@@ -301,7 +380,7 @@ impl From<CheckError> for Diagnostic {
                         found: found.clone(),
                     }),
                     Span::dummy(),
-                    "E-TYPE-001",
+                    code,
                     format!(
                         "Expected type `{}` but found `{}`. Ensure the value matches the expected type.",
                         expected, found
@@ -317,7 +396,7 @@ impl From<CheckError> for Diagnostic {
                         call_chain: Vec::new(),
                     }),
                     Span::dummy(),
-                    "E-EFFECT-001",
+                    code,
                     format!(
                         "The effect `{}` is not allowed in this context. Either declare it in the effects section or extract effectful code.",
                         effect
@@ -331,7 +410,7 @@ impl From<CheckError> for Diagnostic {
                         context: "already defined".to_string(),
                     }),
                     Span::dummy(),
-                    "E-SYMBOL-002",
+                    code,
                     format!("The name `{}` is defined multiple times. Each name must be unique in its scope.", name),
                 )
             }
@@ -342,7 +421,7 @@ impl From<CheckError> for Diagnostic {
                         found: value_type.clone(),
                     }),
                     Span::dummy(),
-                    "E-TYPE-002",
+                    code,
                     format!(
                         "Type `{}` is not a member of union `{}`. Check that the value matches one of the union variants.",
                         value_type, union_type
@@ -356,7 +435,7 @@ impl From<CheckError> for Diagnostic {
                         found: format!("missing: {}", missing.join(", ")),
                     }),
                     Span::dummy(),
-                    "E-TYPE-003",
+                    code,
                     format!(
                         "Match on `{}` is not exhaustive. Missing variants: {}. Add arms for all cases or use a wildcard pattern.",
                         matched_type,
@@ -371,27 +450,55 @@ impl From<CheckError> for Diagnostic {
                         context: "unknown query target".to_string(),
                     }),
                     Span::dummy(),
-                    "E-QUERY-001",
+                    code,
                     format!(
                         "Query target `{}` is not recognized. Valid targets include: project, collections, or database bindings.",
                         target
                     ),
                 )
             }
-            CheckError::UnknownField { field, type_name } => {
+            CheckError::UnknownField { field, type_name, span } => {
                 Diagnostic::new(
                     DiagnosticKind::Type(TypeDiagnostic {
                         expected: format!("field of {}", type_name),
                         found: field.clone(),
                     }),
-                    Span::dummy(),
-                    "E-TYPE-004",
+                    span,
+                    code,
                     format!(
                         "Field `{}` does not exist on type `{}`. Check the field name for typos.",
                         field, type_name
                     ),
                 )
             }
+            CheckError::MissingField { field, type_name, span } => {
+                Diagnostic::new(
+                    DiagnosticKind::Type(TypeDiagnostic {
+                        expected: format!("field `{}` of {}", field, type_name),
+                        found: "no value".to_string(),
+                    }),
+                    span,
+                    code,
+                    format!(
+                        "Construction of `{}` does not set required field `{}`. Only `optional` and `auto` fields may be omitted.",
+                        type_name, field
+                    ),
+                )
+            }
+            CheckError::FieldTypeMismatch { field, type_name, expected, found, span } => {
+                Diagnostic::new(
+                    DiagnosticKind::Type(TypeDiagnostic {
+                        expected: expected.clone(),
+                        found: found.clone(),
+                    }),
+                    span,
+                    code,
+                    format!(
+                        "Field `{}` of `{}` has type `{}` but was given `{}`.",
+                        field, type_name, expected, found
+                    ),
+                )
+            }
             CheckError::UnknownExternAbstract { impl_id, abstract_id } => {
                 Diagnostic::new(
                     DiagnosticKind::Symbol(SymbolDiagnostic {
@@ -399,7 +506,7 @@ impl From<CheckError> for Diagnostic {
                         context: format!("referenced by extern-impl `{}`", impl_id),
                     }),
                     Span::dummy(),
-                    "E-EXTERN-001",
+                    code,
                     format!(
                         "Extern implementation `{}` references unknown abstract `{}`. Define the extern-abstract first.",
                         impl_id, abstract_id
@@ -413,7 +520,7 @@ impl From<CheckError> for Diagnostic {
                         context: format!("no binding for target `{}`", target),
                     }),
                     Span::dummy(),
-                    "E-EXTERN-002",
+                    code,
                     format!(
                         "No extern-impl binding for `{}` targeting platform `{}`. Create an extern-impl snippet for this target.",
                         extern_id, target
@@ -427,13 +534,284 @@ impl From<CheckError> for Diagnostic {
                         context: "invalid extern ID format".to_string(),
                     }),
                     Span::dummy(),
-                    "E-EXTERN-003",
+                    code,
                     format!(
                         "Extern snippet ID `{}` must be namespaced (e.g., 'module.function'). Use a dot to separate namespace from name.",
                         id
                     ),
                 )
             }
+            CheckError::ConflictingTypeArgument { function, param, first, second } => {
+                Diagnostic::new(
+                    DiagnosticKind::Type(TypeDiagnostic {
+                        expected: first.clone(),
+                        found: second.clone(),
+                    }),
+                    Span::dummy(),
+                    code,
+                    format!(
+                        "Type parameter `{}` of `{}` is bound to both `{}` and `{}` by the call arguments. All uses of a type parameter must agree.",
+                        param, function, first, second
+                    ),
+                )
+            }
+            CheckError::UninferredTypeParameter { function, param } => {
+                Diagnostic::new(
+                    DiagnosticKind::Symbol(SymbolDiagnostic {
+                        name: param.clone(),
+                        context: format!("type parameter of `{}`", function),
+                    }),
+                    Span::dummy(),
+                    code,
+                    format!(
+                        "Type parameter `{}` of `{}` does not appear in any argument type, so it cannot be inferred at this call site.",
+                        param, function
+                    ),
+                )
+            }
+            CheckError::MissingArgument { function, param, expected, caller, span } => {
+                Diagnostic::new(
+                    DiagnosticKind::Symbol(SymbolDiagnostic {
+                        name: param.clone(),
+                        context: format!("missing argument to `{}`", function),
+                    }),
+                    span,
+                    code,
+                    format!(
+                        "The call to `{}` does not pass parameter `{}`. Every declared parameter must be given an `arg`.",
+                        function, param
+                    ),
+                )
+                .with_suggestion(FixSuggestion::MatchParameters { function, params: expected })
+                .with_snippet(caller)
+            }
+            CheckError::UnknownArgument { function, arg, expected, caller, span } => {
+                Diagnostic::new(
+                    DiagnosticKind::Symbol(SymbolDiagnostic {
+                        name: arg.clone(),
+                        context: format!("not a parameter of `{}`", function),
+                    }),
+                    span,
+                    code,
+                    format!(
+                        "`{}` has no parameter named `{}`. Check the argument name for typos.",
+                        function, arg
+                    ),
+                )
+                .with_suggestion(FixSuggestion::MatchParameters { function, params: expected })
+                .with_snippet(caller)
+            }
+            CheckError::DuplicateArgument { function, arg, caller, span } => {
+                Diagnostic::new(
+                    DiagnosticKind::Symbol(SymbolDiagnostic {
+                        name: arg.clone(),
+                        context: format!("argument to `{}` passed twice", function),
+                    }),
+                    span,
+                    code,
+                    format!(
+                        "The call to `{}` passes `{}` more than once. Each parameter takes exactly one `arg`.",
+                        function, arg
+                    ),
+                )
+                .with_snippet(caller)
+            }
+            CheckError::ArgumentTypeMismatch { function, param, expected, found, caller, span } => {
+                Diagnostic::new(
+                    DiagnosticKind::Type(TypeDiagnostic {
+                        expected: expected.clone(),
+                        found: found.clone(),
+                    }),
+                    span,
+                    code,
+                    format!(
+                        "Argument `{}` of `{}` expects type `{}` but was given `{}`.",
+                        param, function, expected, found
+                    ),
+                )
+                .with_snippet(caller)
+            }
+            CheckError::ArgumentCountMismatch { function, expected, found, caller, span } => {
+                Diagnostic::new(
+                    DiagnosticKind::Type(TypeDiagnostic {
+                        expected: format!("{} arguments", expected),
+                        found: format!("{} arguments", found),
+                    }),
+                    span,
+                    code,
+                    format!(
+                        "`{}` is a function value taking {} arguments, but the call passes {}. Arguments to a function value are matched by position.",
+                        function, expected, found
+                    ),
+                )
+                .with_snippet(caller)
+            }
+            CheckError::InvalidEffectVariable { function, param, span } => {
+                Diagnostic::new(
+                    DiagnosticKind::Symbol(SymbolDiagnostic {
+                        name: param.clone(),
+                        context: format!("not a function-typed parameter of `{}`", function),
+                    }),
+                    span,
+                    code,
+                    format!(
+                        "`effect of=\"{}\"` in `{}` must name a parameter with a function type such as `(Int) -> Int`.",
+                        param, function
+                    ),
+                )
+            }
+            CheckError::DuplicateBinding { name, step_id, first, span } => {
+                let first = match first {
+                    Some(step) => format!("step `{}`", step),
+                    None => "a parameter".to_string(),
+                };
+                Diagnostic::new(
+                    DiagnosticKind::Symbol(SymbolDiagnostic {
+                        name: name.clone(),
+                        context: format!("already bound by {}", first),
+                    }),
+                    span,
+                    code,
+                    format!(
+                        "Step `{}` binds `{}`, which is already bound by {}. Each step must bind a new name; choose a different `as` name.",
+                        step_id, name, first
+                    ),
+                )
+            }
+            CheckError::UseBeforeDefinition { name, step_id, defined_by, span } => {
+                Diagnostic::new(
+                    DiagnosticKind::Symbol(SymbolDiagnostic {
+                        name: name.clone(),
+                        context: format!("defined later by step `{}`", defined_by),
+                    }),
+                    span,
+                    code,
+                    format!(
+                        "Step `{}` uses `{}` before step `{}` defines it. Move the defining step earlier.",
+                        step_id, name, defined_by
+                    ),
+                )
+            }
+            CheckError::BranchLocalBinding { name, step_id, branch_step, span } => {
+                Diagnostic::new(
+                    DiagnosticKind::Symbol(SymbolDiagnostic {
+                        name: name.clone(),
+                        context: format!("local to step `{}`", branch_step),
+                    }),
+                    span,
+                    code,
+                    format!(
+                        "Step `{}` uses `{}`, which is only bound inside a branch of step `{}`. Use the output binding of `{}` instead.",
+                        step_id, name, branch_step, branch_step
+                    ),
+                )
+            }
+            CheckError::DuplicateStepId { id, snippet, span } => {
+                Diagnostic::new(
+                    DiagnosticKind::Symbol(SymbolDiagnostic {
+                        name: id.clone(),
+                        context: format!("duplicate step id in `{}`", snippet),
+                    }),
+                    span,
+                    code,
+                    format!(
+                        "Step id `{}` is used more than once in snippet `{}`. Step ids must be unique within a snippet.",
+                        id, snippet
+                    ),
+                )
+            }
+            CheckError::TooManyPatternBindings { variant, expected, found, span } => {
+                Diagnostic::new(
+                    DiagnosticKind::Type(TypeDiagnostic {
+                        expected: format!("at most {} bindings", expected),
+                        found: format!("{} bindings", found),
+                    }),
+                    span,
+                    code,
+                    format!(
+                        "Pattern `{}` binds {} values but the variant has only {} fields. Bindings are matched to fields by position.",
+                        variant, found, expected
+                    ),
+                )
+            }
+            CheckError::UnhandleableErrorType { function, error_type, errors, span } => {
+                let message = if errors.is_empty() {
+                    format!(
+                        "`{}` does not return an error union, so it cannot fail with `{}`. Remove the `handle` case.",
+                        function, error_type
+                    )
+                } else {
+                    format!(
+                        "`{}` cannot fail with `{}`. Its error types are: {}.",
+                        function, error_type, errors.join(", ")
+                    )
+                };
+                Diagnostic::new(
+                    DiagnosticKind::Type(TypeDiagnostic {
+                        expected: if errors.is_empty() {
+                            "no handle cases".to_string()
+                        } else {
+                            errors.join(" | ")
+                        },
+                        found: error_type.clone(),
+                    }),
+                    span,
+                    code,
+                    message,
+                )
+            }
+        }
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(error: ParseError) -> Self {
+        let explanation = match &error {
+            ParseError::InvalidOrdering { .. } => {
+                "Run `covenant format` to put sections and attributes in canonical order.".to_string()
+            }
+            _ => String::new(),
+        };
+        Diagnostic::new(
+            DiagnosticKind::Message(error.to_string()),
+            error.span(),
+            error.code(),
+            explanation,
+        )
+    }
+}
+
+impl From<SymbolError> for Diagnostic {
+    fn from(error: SymbolError) -> Self {
+        let (snippet_id, explanation) = match &error {
+            SymbolError::UndefinedReference { name, referrer, .. } => (
+                Some(referrer.clone()),
+                format!("`{}` references `{}`, which no snippet defines. Check for typos or missing snippets.", referrer, name),
+            ),
+            SymbolError::DuplicateId { id, .. } => (
+                Some(id.clone()),
+                format!("More than one snippet has the ID `{}`. Snippet IDs must be unique.", id),
+            ),
+            SymbolError::CircularImport { cycle, .. } => (
+                None,
+                format!("The imports form a cycle: {}. Break the cycle by moving shared code into its own snippet.", cycle),
+            ),
+            SymbolError::RelationTargetNotFound { target, from_symbol, .. } => (
+                Some(from_symbol.clone()),
+                format!("`{}` declares a relation to `{}`, which does not exist.", from_symbol, target),
+            ),
+        };
+        let severity = if error.is_hard_error() { Severity::Error } else { Severity::Warning };
+        let diagnostic = Diagnostic::new(
+            DiagnosticKind::Message(error.to_string()),
+            error.span(),
+            error.code(),
+            explanation,
+        )
+        .with_severity(severity);
+        match snippet_id {
+            Some(id) => diagnostic.with_snippet(id),
+            None => diagnostic,
         }
     }
 }
@@ -500,6 +878,33 @@ impl EffectDiagnosticBuilder {
         Self { diagnostic }
     }
 
+    /// Create a builder for an effect forbidden by the project effect policy
+    pub fn policy_violation(
+        function: String,
+        effect: String,
+        reason: String,
+        span: Span,
+    ) -> Self {
+        let explanation = format!(
+            "Function `{}` uses effect `{}`, which the project effect policy forbids: {}.",
+            function, effect, reason
+        );
+
+        let diagnostic = Diagnostic::new(
+            DiagnosticKind::Effect(EffectDiagnostic {
+                function,
+                callee: None,
+                effects: vec![effect],
+                call_chain: Vec::new(),
+            }),
+            span,
+            "E-EFFECT-007",
+            explanation,
+        );
+
+        Self { diagnostic }
+    }
+
     /// Add the call chain for detailed explanation
     pub fn with_call_chain(mut self, chain: Vec<CallChainEntry>) -> Self {
         if let DiagnosticKind::Effect(ref mut effect_diag) = self.diagnostic.error {
@@ -592,4 +997,65 @@ mod tests {
         assert_eq!(diag.code, "E-SYMBOL-001");
         assert!(diag.message().contains("foo"));
     }
+
+    #[test]
+    fn test_scope_error_codes() {
+        let span = Span::new(10, 20);
+        let errors = vec![
+            CheckError::DuplicateBinding {
+                name: "x".to_string(),
+                step_id: "s2".to_string(),
+                first: None,
+                span,
+            },
+            CheckError::UseBeforeDefinition {
+                name: "x".to_string(),
+                step_id: "s1".to_string(),
+                defined_by: "s2".to_string(),
+                span,
+            },
+            CheckError::BranchLocalBinding {
+                name: "x".to_string(),
+                step_id: "s3".to_string(),
+                branch_step: "s2".to_string(),
+                span,
+            },
+            CheckError::DuplicateStepId {
+                id: "s1".to_string(),
+                snippet: "app.main".to_string(),
+                span,
+            },
+        ];
+        let codes: Vec<String> = errors.into_iter()
+            .map(|e| Diagnostic::from(e))
+            .inspect(|d| assert_eq!(d.span, span))
+            .map(|d| d.code)
+            .collect();
+        assert_eq!(codes, ["E-SCOPE-001", "E-SCOPE-002", "E-SCOPE-003", "E-SCOPE-004"]);
+    }
+
+    #[test]
+    fn test_argument_error_suggests_parameters() {
+        let error = CheckError::UnknownArgument {
+            function: "math.add".to_string(),
+            arg: "c".to_string(),
+            expected: vec!["a: Int".to_string(), "b: Int".to_string()],
+            caller: "app.main".to_string(),
+            span: Span::new(10, 20),
+        };
+        let diag: Diagnostic = error.into();
+
+        assert_eq!(diag.code, "E-CALL-002");
+        assert_eq!(diag.span, Span::new(10, 20));
+        assert_eq!(diag.snippet_id.as_deref(), Some("app.main"));
+        assert_eq!(diag.suggestions.len(), 1);
+        assert_eq!(
+            diag.suggestions[0].description(),
+            "Call `math.add` with its parameters: a: Int, b: Int"
+        );
+        assert_eq!(
+            diag.suggestions[0].code_snippet().as_deref(),
+            Some("arg name=\"a\" from=\"...\"\narg name=\"b\" from=\"...\"")
+        );
+    }
 }
@@ -5,12 +5,18 @@
 //! - Transitive effect closure computation
 //! - Effect declaration validation (I2 invariant)
 //! - Parameterized effect validation (effect subsumption)
+//! - Effect variables of higher-order functions (`effect of="f"`)
+//! - Effect hierarchy (declaring a parent effect covers its children)
+//! - Project effect policy (allow/deny rules by snippet id)
 //! - Rich diagnostic generation for effect violations
 
 use std::collections::{HashMap, HashSet};
 use covenant_ast::{EffectDecl, Literal, SymbolId, EffectId, Span};
-use covenant_symbols::{SymbolGraph, SymbolInfo};
+use covenant_symbols::{FunctionArg, FunctionValue, SymbolGraph, SymbolInfo};
 
+use crate::hierarchy::EffectHierarchy;
+use crate::policy::{format_effect, EffectPolicy};
+use crate::subsumption::SubsumptionRules;
 use crate::diagnostics::{
     Diagnostic, EffectDiagnosticBuilder, FixSuggestion, RelatedLocation, CallChainEntry,
 };
@@ -138,6 +144,8 @@ pub struct EffectClosure {
     pub computed_full: Vec<EffectDecl>,
     /// True if declared is empty (pure function)
     pub is_pure: bool,
+    /// Function-typed parameters whose effects are added at each call site
+    pub effect_vars: Vec<String>,
 }
 
 /// Result of effect checking phase
@@ -147,6 +155,8 @@ pub struct EffectCheckResult {
     pub closures: HashMap<String, EffectClosure>,
     /// Effect violations found
     pub violations: Vec<EffectError>,
+    /// Effect hierarchy declared by effect snippets
+    pub hierarchy: EffectHierarchy,
 }
 
 /// Effect validation errors
@@ -174,7 +184,7 @@ pub enum EffectError {
         /// Source span of the function
         span: Span,
     },
-    /// Effect parameter not covered (E-EFFECT-003)
+    /// Effect parameter not covered (E-EFFECT-008)
     ParameterNotCovered {
         /// Name of the function
         function: String,
@@ -191,6 +201,86 @@ pub enum EffectError {
         /// Source span of the function
         span: Span,
     },
+    /// Effectful function passed to a parameter without an effect variable (E-EFFECT-004)
+    ImpureFunctionArgument {
+        /// Name of the calling function
+        function: String,
+        /// The higher-order function being called
+        callee: String,
+        /// The callee's parameter receiving the function
+        param: String,
+        /// The effectful function passed
+        argument: String,
+        /// Effects of the function passed
+        effects: Vec<String>,
+        /// Source span of the function
+        span: Span,
+    },
+    /// Function parameter passed on to an effect variable that the caller does
+    /// not declare itself (E-EFFECT-005)
+    UndeclaredEffectVariable {
+        /// Name of the calling function
+        function: String,
+        /// The higher-order function being called
+        callee: String,
+        /// The callee's parameter with the effect variable
+        param: String,
+        /// The caller's own parameter passed on
+        argument: String,
+        /// Source span of the function
+        span: Span,
+    },
+    /// Effect forbidden by the project effect policy (E-EFFECT-007)
+    PolicyViolation {
+        /// Name of the function
+        function: String,
+        /// The forbidden effect as used, e.g. `filesystem(path="/etc")`
+        effect: String,
+        /// Why the policy forbids it
+        reason: String,
+        /// Calls from the function to the snippet declaring the effect
+        call_chain: Vec<String>,
+        /// Source span of the function
+        span: Span,
+    },
+}
+
+impl EffectError {
+    /// Error code, e.g. "E-EFFECT-002"
+    pub fn code(&self) -> &'static str {
+        match self {
+            EffectError::PureCallsEffectful { .. } => "E-EFFECT-001",
+            EffectError::MissingEffect { .. } => "E-EFFECT-002",
+            EffectError::ImpureFunctionArgument { .. } => "E-EFFECT-004",
+            EffectError::UndeclaredEffectVariable { .. } => "E-EFFECT-005",
+            EffectError::PolicyViolation { .. } => "E-EFFECT-007",
+            EffectError::ParameterNotCovered { .. } => "E-EFFECT-008",
+        }
+    }
+
+    /// The function the violation is reported on
+    pub fn function(&self) -> &str {
+        match self {
+            EffectError::PureCallsEffectful { function, .. }
+            | EffectError::MissingEffect { function, .. }
+            | EffectError::ParameterNotCovered { function, .. }
+            | EffectError::ImpureFunctionArgument { function, .. }
+            | EffectError::UndeclaredEffectVariable { function, .. }
+            | EffectError::PolicyViolation { function, .. } => function,
+        }
+    }
+
+    /// Source span of the function
+    pub fn span(&self) -> Span {
+        match self {
+            EffectError::PureCallsEffectful { span, .. }
+            | EffectError::MissingEffect { span, .. }
+            | EffectError::ParameterNotCovered { span, .. }
+            | EffectError::ImpureFunctionArgument { span, .. }
+            | EffectError::UndeclaredEffectVariable { span, .. }
+            | EffectError::PolicyViolation { span, .. } => *span,
+        }
+    }
 }
 
 /// Compute effect closures for all symbols in the graph and validate I2 invariant.
@@ -199,22 +289,33 @@ pub enum EffectError {
 /// - `declared_effects ⊇ computed_effects`
 /// - If a function declares no effects (pure), it cannot call effectful code
 pub fn check_effects(graph: &SymbolGraph) -> EffectCheckResult {
+    check_effects_with_policy(graph, &EffectPolicy::new())
+}
+
+/// Compute effect closures, validate the I2 invariant, and check every
+/// callable's effects against a project effect policy
+pub fn check_effects_with_policy(graph: &SymbolGraph, policy: &EffectPolicy) -> EffectCheckResult {
     let mut closures = HashMap::new();
     let mut violations = Vec::new();
+    let rules = SubsumptionRules::from_graph(graph);
+    let hierarchy = EffectHierarchy::from_graph(graph);
 
     // Process all callable symbols (functions and externs)
     for symbol in graph.iter().filter(|s| s.is_callable()) {
         let closure = compute_closure_for_symbol(symbol, graph);
 
         // Validate: declared must cover computed
-        if let Some(error) = validate_closure(symbol, &closure, graph) {
+        if let Some(error) = validate_closure(symbol, &closure, graph, &rules, &hierarchy) {
             violations.push(error);
         }
+        if !policy.is_empty() {
+            violations.extend(check_policy(symbol, &closure, graph, policy, &rules, &hierarchy));
+        }
 
         closures.insert(symbol.name.clone(), closure);
     }
 
-    EffectCheckResult { closures, violations }
+    EffectCheckResult { closures, violations, hierarchy }
 }
 
 /// Compute transitive effect closure for a single symbol
@@ -222,6 +323,7 @@ fn compute_closure_for_symbol(symbol: &SymbolInfo, graph: &SymbolGraph) -> Effec
     let declared: HashSet<String> = symbol.declared_effects.iter().map(|e| e.name.clone()).collect();
     let declared_full = symbol.declared_effects.clone();
     let is_pure = declared.is_empty();
+    let effect_vars = symbol.effect_vars.clone();
 
     let mut computed = HashSet::new();
     let mut computed_full = Vec::new();
@@ -229,7 +331,7 @@ fn compute_closure_for_symbol(symbol: &SymbolInfo, graph: &SymbolGraph) -> Effec
 
     collect_transitive_effects(symbol, graph, &mut visited, &mut computed, &mut computed_full);
 
-    EffectClosure { declared, declared_full, computed, computed_full, is_pure }
+    EffectClosure { declared, declared_full, computed, computed_full, is_pure, effect_vars }
 }
 
 /// Recursively collect effects from callees
@@ -274,6 +376,41 @@ fn collect_transitive_effects(
         }
         // Note: unresolved calls are ignored here (handled in Phase 4)
     }
+
+    // Instantiate callee effect variables with the functions passed to them
+    for arg in instantiated_function_args(symbol, graph) {
+        if let FunctionValue::Symbol(name) = &arg.value {
+            if let Some(function) = graph.get_by_name(name) {
+                collect_transitive_effects(function, graph, visited, effects, effects_full);
+            }
+        }
+    }
+}
+
+/// Function arguments passed to a parameter the callee declares as an effect
+/// variable: the callee has the effects of whatever is passed there
+fn instantiated_function_args<'a>(
+    symbol: &'a SymbolInfo,
+    graph: &SymbolGraph,
+) -> Vec<&'a FunctionArg> {
+    symbol
+        .function_args
+        .iter()
+        .filter(|arg| {
+            graph
+                .get_by_name(&arg.callee)
+                .is_some_and(|callee| callee.effect_vars.contains(&arg.param))
+        })
+        .collect()
+}
+
+/// Names of all effects a symbol has, including transitively
+fn transitive_effect_names(symbol: &SymbolInfo, graph: &SymbolGraph) -> Vec<String> {
+    let mut effects = HashSet::new();
+    collect_transitive_effects(symbol, graph, &mut HashSet::new(), &mut effects, &mut Vec::new());
+    let mut effects: Vec<String> = effects.into_iter().collect();
+    effects.sort();
+    effects
 }
 
 /// Validate that declared effects cover computed effects
@@ -281,12 +418,15 @@ fn validate_closure(
     symbol: &SymbolInfo,
     closure: &EffectClosure,
     graph: &SymbolGraph,
+    rules: &SubsumptionRules,
+    hierarchy: &EffectHierarchy,
 ) -> Option<EffectError> {
-    // First check: find effects by name that are in computed but not in declared
-    let missing: Vec<String> = closure.computed
-        .difference(&closure.declared)
+    // First check: find computed effects that no declared effect covers
+    let mut missing: Vec<String> = closure.computed.iter()
+        .filter(|required| !closure.declared.iter().any(|d| hierarchy.covers(d, required)))
         .cloned()
         .collect();
+    missing.sort();
 
     if !missing.is_empty() {
         // Find which callee introduced these effects (for error message)
@@ -329,15 +469,68 @@ fn validate_closure(
             continue;
         }
 
-        // Find the declared effect with matching name
-        if let Some(declared) = closure.declared_full.iter().find(|d| d.name == required.name) {
-            // Check if declared subsumes required
-            if let Some(error) = check_effect_subsumption(symbol, declared, required, graph) {
+        // Any declared effect with a matching name, or above it in the hierarchy, may cover it
+        let candidates: Vec<&EffectDecl> = closure.declared_full.iter()
+            .filter(|d| hierarchy.covers(&d.name, &required.name))
+            .collect();
+        if candidates.iter().any(|declared| check_effect_subsumption(symbol, declared, required, graph, rules).is_none()) {
+            continue;
+        }
+        if let Some(declared) = candidates.first() {
+            // Report against the first declaration
+            if let Some(error) = check_effect_subsumption(symbol, declared, required, graph, rules) {
                 return Some(error);
             }
         }
     }
 
+    // Third check: functions passed as arguments
+    check_function_args(symbol, graph)
+}
+
+/// Check functions passed to higher-order callees.
+///
+/// A parameter with an effect variable accepts any function, since its effects
+/// are added at the call site. Any other function-typed parameter is assumed
+/// pure, so only pure functions may be passed to it.
+fn check_function_args(symbol: &SymbolInfo, graph: &SymbolGraph) -> Option<EffectError> {
+    for arg in &symbol.function_args {
+        let Some(callee) = graph.get_by_name(&arg.callee) else {
+            continue; // Unresolved callee (handled in Phase 4)
+        };
+
+        match &arg.value {
+            FunctionValue::Param(name)
+                if callee.effect_vars.contains(&arg.param) && !symbol.effect_vars.contains(name) =>
+            {
+                return Some(EffectError::UndeclaredEffectVariable {
+                    function: symbol.name.clone(),
+                    callee: arg.callee.clone(),
+                    param: arg.param.clone(),
+                    argument: name.clone(),
+                    span: symbol.span,
+                });
+            }
+            FunctionValue::Symbol(name) if !callee.effect_vars.contains(&arg.param) => {
+                let Some(function) = graph.get_by_name(name) else {
+                    continue;
+                };
+                let effects = transitive_effect_names(function, graph);
+                if !effects.is_empty() {
+                    return Some(EffectError::ImpureFunctionArgument {
+                        function: symbol.name.clone(),
+                        callee: arg.callee.clone(),
+                        param: arg.param.clone(),
+                        argument: name.clone(),
+                        effects,
+                        span: symbol.span,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
     None
 }
 
@@ -346,14 +539,14 @@ fn validate_closure(
 /// Rules:
 /// - If required has no parameters, any declaration with same name is fine
 /// - If declared has no parameters, it covers any parameterized version (wildcard)
-/// - If both have parameters, declared must subsume required for each param:
-///   - For path parameters: declared path must be a prefix of required path
-///   - For other parameters: declared must equal required (exact match)
+/// - If both have parameters, declared must subsume required for each param,
+///   using the parameter's subsumption rule (see [`SubsumptionRules`])
 fn check_effect_subsumption(
     symbol: &SymbolInfo,
     declared: &EffectDecl,
     required: &EffectDecl,
     graph: &SymbolGraph,
+    rules: &SubsumptionRules,
 ) -> Option<EffectError> {
     // If declared has no parameters, it's a wildcard - covers everything
     if !declared.has_params() {
@@ -368,24 +561,25 @@ fn check_effect_subsumption(
     // Both have parameters - check each required parameter
     for req_param in &required.params {
         match declared.get_param(&req_param.name) {
-            Some(decl_param) => {
-                // Check if declared value subsumes required value
-                if !param_value_subsumes(&decl_param.value, &req_param.value, &req_param.name) {
-                    let source_callee = find_effect_source_for_param(
-                        symbol, &required.name, &req_param.name, graph
-                    ).unwrap_or_else(|| "unknown".to_string());
-
-                    return Some(EffectError::ParameterNotCovered {
-                        function: symbol.name.clone(),
-                        effect_name: required.name.clone(),
-                        param_name: req_param.name.clone(),
-                        required_value: literal_to_string(&req_param.value),
-                        declared_value: Some(literal_to_string(&decl_param.value)),
-                        source_callee,
-                        span: symbol.span,
-                    });
-                }
+            // Check if declared value subsumes required value
+            Some(decl_param)
+                if !rules.subsumes(&required.name, &req_param.name, &decl_param.value, &req_param.value) =>
+            {
+                let source_callee = find_effect_source_for_param(
+                    symbol, &required.name, &req_param.name, graph
+                ).unwrap_or_else(|| "unknown".to_string());
+
+                return Some(EffectError::ParameterNotCovered {
+                    function: symbol.name.clone(),
+                    effect_name: required.name.clone(),
+                    param_name: req_param.name.clone(),
+                    required_value: literal_to_string(&req_param.value),
+                    declared_value: Some(literal_to_string(&decl_param.value)),
+                    source_callee,
+                    span: symbol.span,
+                });
             }
+            Some(_) => {}
             None => {
                 // Declared doesn't have this parameter - that's OK, it means unrestricted
             }
@@ -395,33 +589,81 @@ fn check_effect_subsumption(
     None
 }
 
-/// Check if a declared parameter value subsumes a required parameter value
-///
-/// For "path" parameters: declared must be a prefix of required (path="/data" subsumes path="/data/users")
-/// For other parameters: exact match required
-fn param_value_subsumes(declared: &Literal, required: &Literal, param_name: &str) -> bool {
-    // Special handling for path parameters
-    if param_name == "path" {
-        if let (Literal::String(decl_path), Literal::String(req_path)) = (declared, required) {
-            // Declared path must be a prefix of required path
-            // "/data" subsumes "/data/users" and "/data" but not "/other"
-            return req_path.starts_with(decl_path.as_str());
+/// Check the effects a symbol uses against the project effect policy
+fn check_policy(
+    symbol: &SymbolInfo,
+    closure: &EffectClosure,
+    graph: &SymbolGraph,
+    policy: &EffectPolicy,
+    rules: &SubsumptionRules,
+    hierarchy: &EffectHierarchy,
+) -> Vec<EffectError> {
+    let mut violations = Vec::new();
+    let mut reported = HashSet::new();
+    for effect in &closure.computed_full {
+        let Some(denial) = policy.check(&symbol.name, effect, hierarchy, rules) else {
+            continue;
+        };
+        let effect_str = format_effect(effect);
+        if reported.insert(effect_str.clone()) {
+            violations.push(EffectError::PolicyViolation {
+                function: symbol.name.clone(),
+                call_chain: find_effect_chain(symbol, effect, graph),
+                effect: effect_str,
+                reason: denial.reason,
+                span: symbol.span,
+            });
+        }
+    }
+    violations
+}
+
+/// Path of calls from `symbol` to where `effect` originates: the farthest
+/// snippet declaring it, following functions passed to effect variables as
+/// well as direct calls
+fn find_effect_chain(symbol: &SymbolInfo, effect: &EffectDecl, graph: &SymbolGraph) -> Vec<String> {
+    let mut previous: HashMap<String, String> = HashMap::new();
+    let mut visited = HashSet::from([symbol.name.clone()]);
+    let mut queue = std::collections::VecDeque::from([symbol]);
+    let mut origin = symbol.name.clone();
+
+    while let Some(current) = queue.pop_front() {
+        if current.declared_effects.iter().any(|e| same_effect(e, effect)) {
+            origin = current.name.clone();
+        }
+        let passed = instantiated_function_args(current, graph)
+            .into_iter()
+            .filter_map(|arg| match &arg.value {
+                FunctionValue::Symbol(name) => Some(name),
+                FunctionValue::Param(_) => None,
+            });
+        for next in current.calls.iter().chain(passed) {
+            if let Some(next_symbol) = graph.get_by_name(next) {
+                if visited.insert(next.clone()) {
+                    previous.insert(next.clone(), current.name.clone());
+                    queue.push_back(next_symbol);
+                }
+            }
         }
     }
 
-    // For all other parameters, require exact match
-    match (declared, required) {
-        (Literal::Int(d), Literal::Int(r)) => d == r,
-        (Literal::Float(d), Literal::Float(r)) => (d - r).abs() < f64::EPSILON,
-        (Literal::String(d), Literal::String(r)) => d == r,
-        (Literal::Bool(d), Literal::Bool(r)) => d == r,
-        (Literal::None, Literal::None) => true,
-        _ => false,
+    let mut chain = vec![origin];
+    while let Some(prev) = previous.get(chain.last().unwrap()) {
+        chain.push(prev.clone());
     }
+    chain.reverse();
+    chain
+}
+
+/// Check if two effect declarations name the same effect with the same parameters
+fn same_effect(a: &EffectDecl, b: &EffectDecl) -> bool {
+    a.name == b.name
+        && a.params.len() == b.params.len()
+        && a.params.iter().zip(&b.params).all(|(x, y)| x.name == y.name && x.value == y.value)
 }
 
 /// Convert a Literal to a displayable string
-fn literal_to_string(lit: &Literal) -> String {
+pub(crate) fn literal_to_string(lit: &Literal) -> String {
     match lit {
         Literal::Int(i) => i.to_string(),
         Literal::Float(f) => f.to_string(),
@@ -442,6 +684,17 @@ fn find_effect_source(symbol: &SymbolInfo, missing: &[String], graph: &SymbolGra
             }
         }
     }
+    // Functions passed to effect variables bring their effects to this call site
+    for arg in instantiated_function_args(symbol, graph) {
+        if let FunctionValue::Symbol(name) = &arg.value {
+            if let Some(function) = graph.get_by_name(name) {
+                let effects = transitive_effect_names(function, graph);
+                if effects.iter().any(|e| missing.contains(e)) {
+                    return Some(name.clone());
+                }
+            }
+        }
+    }
     // If not found in direct callees, it might be transitive
     // In that case, we could do a deeper search, but for now return None
     None
@@ -481,13 +734,17 @@ pub fn check_effects_with_diagnostics(graph: &SymbolGraph) -> (EffectCheckResult
 
 /// Generate a rich diagnostic from an effect error, including call chain explanation
 pub fn explain_effect_violation(error: &EffectError, graph: &SymbolGraph) -> Diagnostic {
-    match error {
+    let diagnostic = match error {
         EffectError::PureCallsEffectful { function, callee, effects, span } => {
-            let call_chain = build_call_chain(function, callee, effects, graph);
+            let instantiation = find_instantiation(function, callee, graph);
+            let call_chain = match instantiation {
+                Some(arg) => build_instantiated_call_chain(function, arg, effects, graph),
+                None => build_call_chain(function, callee, effects, graph),
+            };
             let related = build_related_locations(&call_chain);
             let suggestions = build_pure_calls_effectful_suggestions(function, effects, *span);
 
-            EffectDiagnosticBuilder::pure_calls_effectful(
+            let mut diagnostic = EffectDiagnosticBuilder::pure_calls_effectful(
                 function.clone(),
                 callee.clone(),
                 effects.clone(),
@@ -496,14 +753,22 @@ pub fn explain_effect_violation(error: &EffectError, graph: &SymbolGraph) -> Dia
             .with_call_chain(call_chain)
             .with_suggestions(suggestions)
             .with_related(related)
-            .build()
+            .build();
+            if let Some(arg) = instantiation {
+                diagnostic.explanation.push_str(&explain_instantiation(arg));
+            }
+            diagnostic
         }
         EffectError::MissingEffect { function, missing, source_callee, span } => {
-            let call_chain = build_call_chain(function, source_callee, missing, graph);
+            let instantiation = find_instantiation(function, source_callee, graph);
+            let call_chain = match instantiation {
+                Some(arg) => build_instantiated_call_chain(function, arg, missing, graph),
+                None => build_call_chain(function, source_callee, missing, graph),
+            };
             let related = build_related_locations(&call_chain);
             let suggestions = build_missing_effect_suggestions(function, missing, *span);
 
-            EffectDiagnosticBuilder::missing_effect(
+            let mut diagnostic = EffectDiagnosticBuilder::missing_effect(
                 function.clone(),
                 missing.clone(),
                 source_callee.clone(),
@@ -512,7 +777,11 @@ pub fn explain_effect_violation(error: &EffectError, graph: &SymbolGraph) -> Dia
             .with_call_chain(call_chain)
             .with_suggestions(suggestions)
             .with_related(related)
-            .build()
+            .build();
+            if let Some(arg) = instantiation {
+                diagnostic.explanation.push_str(&explain_instantiation(arg));
+            }
+            diagnostic
         }
         EffectError::ParameterNotCovered {
             function, effect_name, param_name, required_value,
@@ -539,12 +808,148 @@ pub fn explain_effect_violation(error: &EffectError, graph: &SymbolGraph) -> Dia
                     call_chain: Vec::new(),
                 }),
                 *span,
-                "E-EFFECT-003",
+                error.code(),
                 explanation,
             )
             .with_suggestion(suggestion)
         }
+        EffectError::ImpureFunctionArgument { function, callee, param, argument, effects, span } => {
+            let explanation = format!(
+                "Function `{}` passes `{}` to parameter `{}` of `{}`, but `{}` requires effects: {}. \
+                 `{}` does not declare `effect of=\"{}\"`, so `{}` may only receive pure functions.",
+                function, argument, param, callee, argument, effects.join(", "), callee, param, param
+            );
+
+            let mut call_chain = Vec::new();
+            call_chain.extend(chain_entry(function, graph));
+            call_chain.extend(chain_entry(callee, graph));
+            call_chain.extend(chain_entry(argument, graph));
+            let related = build_related_locations(&call_chain);
+
+            let suggestion = FixSuggestion::AddEffect {
+                effect: format!("of=\"{}\"", param),
+                snippet_id: callee.clone(),
+                location: graph.get_by_name(callee).map(|s| s.span).unwrap_or(*span),
+                code_snippet: format!("effect of=\"{}\"", param),
+            };
+
+            let mut diagnostic = Diagnostic::new(
+                crate::diagnostics::DiagnosticKind::Effect(crate::diagnostics::EffectDiagnostic {
+                    function: function.clone(),
+                    callee: Some(argument.clone()),
+                    effects: effects.clone(),
+                    call_chain,
+                }),
+                *span,
+                error.code(),
+                explanation,
+            )
+            .with_suggestion(suggestion);
+            diagnostic.related = related;
+            diagnostic
+        }
+        EffectError::UndeclaredEffectVariable { function, callee, param, argument, span } => {
+            let explanation = format!(
+                "Function `{}` passes its parameter `{}` to parameter `{}` of `{}`, which has the \
+                 effects of `{}`. Declare `effect of=\"{}\"` so the effects of the function passed \
+                 for `{}` reach the callers of `{}`.",
+                function, argument, param, callee, param, argument, argument, function
+            );
+
+            let suggestion = FixSuggestion::AddEffect {
+                effect: format!("of=\"{}\"", argument),
+                snippet_id: function.clone(),
+                location: *span,
+                code_snippet: format!("effect of=\"{}\"", argument),
+            };
+
+            Diagnostic::new(
+                crate::diagnostics::DiagnosticKind::Effect(crate::diagnostics::EffectDiagnostic {
+                    function: function.clone(),
+                    callee: Some(callee.clone()),
+                    effects: Vec::new(),
+                    call_chain: Vec::new(),
+                }),
+                *span,
+                error.code(),
+                explanation,
+            )
+            .with_suggestion(suggestion)
+        }
+        EffectError::PolicyViolation { function, effect, reason, call_chain, span } => {
+            let call_chain: Vec<CallChainEntry> = call_chain
+                .iter()
+                .filter_map(|name| chain_entry(name, graph))
+                .collect();
+            // The function itself is the subject of the diagnostic
+            let related = build_related_locations(&call_chain).into_iter().skip(1).collect();
+
+            EffectDiagnosticBuilder::policy_violation(
+                function.clone(),
+                effect.clone(),
+                reason.clone(),
+                *span,
+            )
+            .with_call_chain(call_chain)
+            .with_related(related)
+            .build()
+        }
+    };
+    diagnostic.with_snippet(error.function())
+}
+
+/// Find the function argument through which `value` reached `function`'s
+/// effects by instantiating a callee's effect variable
+fn find_instantiation<'a>(
+    function: &str,
+    value: &str,
+    graph: &'a SymbolGraph,
+) -> Option<&'a FunctionArg> {
+    let symbol = graph.get_by_name(function)?;
+    if symbol.calls.contains(value) {
+        return None; // Called directly
+    }
+    instantiated_function_args(symbol, graph)
+        .into_iter()
+        .find(|arg| matches!(&arg.value, FunctionValue::Symbol(name) if name == value))
+}
+
+/// Explain how an effect variable was instantiated at a call site
+fn explain_instantiation(arg: &FunctionArg) -> String {
+    let FunctionValue::Symbol(value) = &arg.value else {
+        return String::new();
+    };
+    format!(
+        " `{}` is passed to parameter `{}` of `{}`, which has the effects of `{}`.",
+        value, arg.param, arg.callee, arg.param
+    )
+}
+
+/// Call chain through a higher-order callee whose effect variable was
+/// instantiated with the effectful function
+fn build_instantiated_call_chain(
+    function: &str,
+    arg: &FunctionArg,
+    effects: &[String],
+    graph: &SymbolGraph,
+) -> Vec<CallChainEntry> {
+    let mut chain = Vec::new();
+    chain.extend(chain_entry(function, graph));
+    chain.extend(chain_entry(&arg.callee, graph));
+    if let FunctionValue::Symbol(value) = &arg.value {
+        // Continue from the passed function towards the effect source
+        chain.extend(build_call_chain(value, value, effects, graph).into_iter().skip(1));
     }
+    chain
+}
+
+/// A single call chain entry for a symbol
+fn chain_entry(name: &str, graph: &SymbolGraph) -> Option<CallChainEntry> {
+    graph.get_by_name(name).map(|sym| CallChainEntry {
+        name: name.to_string(),
+        effects: sym.declared_effects.iter().map(|e| e.name.clone()).collect(),
+        span: sym.span,
+    })
 }
 
 /// Build the call chain that introduces the effect violation
@@ -637,7 +1042,6 @@ fn build_related_locations(call_chain: &[CallChainEntry]) -> Vec<RelatedLocation
         RelatedLocation {
             message,
             span: entry.span,
-            file: None,
             label,
         }
     }).collect()
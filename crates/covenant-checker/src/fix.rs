@@ -0,0 +1,460 @@
+//! Applying fix suggestions
+//!
+//! Diagnostics carry `FixSuggestion`s; this module turns them into concrete
+//! edits on the AST and applies them transactionally: each fix is applied to
+//! a copy of the program, printed, re-parsed and re-checked, and rolled back
+//! if the result no longer parses or has more errors than before.
+//! [`splice_fixes`] then writes only the added text into the original source.
+
+use covenant_ast::printer::{to_cov, ToCov};
+use covenant_ast::{EffectVar, EffectsSection, Program, Section, Span};
+use covenant_symbols::build_symbol_graph;
+use thiserror::Error;
+
+use crate::diagnostics::{Diagnostic, FixSuggestion};
+use crate::effects::{check_effects, explain_effect_violation};
+
+/// Minimum confidence applied without review (see ERROR_CODES.md)
+pub const DEFAULT_FIX_CONFIDENCE: f64 = 0.9;
+
+/// A single source edit a fix performs
+#[derive(Debug, Clone, PartialEq)]
+pub enum FixEdit {
+    /// Declare an effect, creating the effects section if needed
+    AddEffect { snippet_id: String, effect: String },
+    /// Declare an effect variable `effect of="param"`
+    AddEffectVar { snippet_id: String, param: String },
+}
+
+/// Edits resolving one diagnostic
+#[derive(Debug, Clone)]
+pub struct Fix {
+    /// Error code of the diagnostic being fixed
+    pub code: String,
+    pub description: String,
+    /// Confidence that the fix is what the author intended, 0.0 to 1.0
+    pub confidence: f64,
+    pub edits: Vec<FixEdit>,
+}
+
+/// Errors applying a fix
+#[derive(Debug, Error)]
+pub enum FixError {
+    #[error("snippet `{snippet_id}` not found")]
+    UnknownSnippet { snippet_id: String },
+
+    #[error("invalid effect `{effect}`: {message}")]
+    InvalidEffect { effect: String, message: String },
+
+    #[error("fix changes nothing")]
+    NoChange,
+
+    #[error("fixed source does not parse: {message}")]
+    Unparseable { message: String },
+
+    #[error("fix increases errors from {before} to {after}")]
+    MoreErrors { before: usize, after: usize },
+}
+
+impl FixSuggestion {
+    /// Confidence that applying this suggestion is correct, following the
+    /// levels in ERROR_CODES.md
+    pub fn confidence(&self) -> f64 {
+        match self {
+            FixSuggestion::DeclareEffectsSection { .. } => 1.0,
+            FixSuggestion::AddEffect { effect, .. } => {
+                if effect_var_param(effect).is_some() {
+                    0.7 // Changes which functions the snippet accepts
+                } else if effect.contains('(') {
+                    0.8 // Widens a parameterized capability
+                } else {
+                    1.0
+                }
+            }
+            FixSuggestion::RemoveCall { .. } | FixSuggestion::MatchParameters { .. } => 0.5,
+            FixSuggestion::WrapInEffectfulFunction { .. } => 0.3,
+        }
+    }
+
+    /// Concrete edits for the suggestion; empty when it needs a human to
+    /// write code
+    pub fn edits(&self) -> Vec<FixEdit> {
+        match self {
+            FixSuggestion::AddEffect { effect, snippet_id, .. } => {
+                let edit = match effect_var_param(effect) {
+                    Some(param) => FixEdit::AddEffectVar {
+                        snippet_id: snippet_id.clone(),
+                        param: param.to_string(),
+                    },
+                    None => FixEdit::AddEffect {
+                        snippet_id: snippet_id.clone(),
+                        effect: effect.clone(),
+                    },
+                };
+                vec![edit]
+            }
+            FixSuggestion::DeclareEffectsSection { effects, snippet_id } => effects
+                .iter()
+                .map(|effect| FixEdit::AddEffect {
+                    snippet_id: snippet_id.clone(),
+                    effect: effect.clone(),
+                })
+                .collect(),
+            FixSuggestion::RemoveCall { .. }
+            | FixSuggestion::WrapInEffectfulFunction { .. }
+            | FixSuggestion::MatchParameters { .. } => Vec::new(),
+        }
+    }
+}
+
+/// The parameter of an effect variable written as `of="f"`
+fn effect_var_param(effect: &str) -> Option<&str> {
+    effect.strip_prefix("of=\"")?.strip_suffix('"')
+}
+
+/// Type errors and effect violations of a program, with fix suggestions
+pub fn program_diagnostics(program: &Program) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = match crate::check(program) {
+        Ok(_) => Vec::new(),
+        Err(errors) => errors.into_iter().map(Diagnostic::from).collect(),
+    };
+    if let Ok(symbol_result) = build_symbol_graph(program) {
+        let result = check_effects(&symbol_result.graph);
+        diagnostics.extend(
+            result
+                .violations
+                .iter()
+                .map(|error| explain_effect_violation(error, &symbol_result.graph)),
+        );
+    }
+    diagnostics
+}
+
+/// One fix per diagnostic that has applicable suggestions: the edits of its
+/// most confident suggestions
+pub fn plan_fixes(diagnostics: &[Diagnostic]) -> Vec<Fix> {
+    let mut fixes = Vec::new();
+    for diagnostic in diagnostics {
+        let applicable: Vec<&FixSuggestion> = diagnostic
+            .suggestions
+            .iter()
+            .filter(|s| !s.edits().is_empty())
+            .collect();
+        let Some(confidence) = applicable.iter().map(|s| s.confidence()).reduce(f64::max) else {
+            continue;
+        };
+
+        let mut edits = Vec::new();
+        let mut descriptions = Vec::new();
+        for suggestion in applicable.iter().filter(|s| s.confidence() == confidence) {
+            let new: Vec<FixEdit> = suggestion.edits().into_iter().filter(|e| !edits.contains(e)).collect();
+            if !new.is_empty() {
+                edits.extend(new);
+                descriptions.push(suggestion.description());
+            }
+        }
+        fixes.push(Fix {
+            code: diagnostic.code.clone(),
+            description: descriptions.join("; "),
+            confidence,
+            edits,
+        });
+    }
+    fixes
+}
+
+/// Apply a fix's edits to a program, returning whether anything changed
+pub fn apply_fix(program: &mut Program, fix: &Fix) -> Result<bool, FixError> {
+    let mut changed = false;
+    for edit in &fix.edits {
+        changed |= apply_edit(program, edit)?;
+    }
+    Ok(changed)
+}
+
+/// Apply a fix to a copy of the program and re-check it. Returns the fixed
+/// program and its diagnostics, or why the fix was rolled back.
+pub fn try_fix(
+    program: &Program,
+    fix: &Fix,
+    errors_before: usize,
+) -> Result<(Program, Vec<Diagnostic>), FixError> {
+    let mut fixed = program.clone();
+    if !apply_fix(&mut fixed, fix)? {
+        return Err(FixError::NoChange);
+    }
+
+    // Re-parse the printed source so the fix round-trips through the text form
+    let reparsed = covenant_parser::parse(&to_cov(&fixed)).map_err(|e| FixError::Unparseable {
+        message: e.to_string(),
+    })?;
+    let diagnostics = program_diagnostics(&reparsed);
+    if diagnostics.len() > errors_before {
+        return Err(FixError::MoreErrors {
+            before: errors_before,
+            after: diagnostics.len(),
+        });
+    }
+    Ok((reparsed, diagnostics))
+}
+
+/// Insert the effects `fixed` declares beyond `original` into `source`, the
+/// text `original` was parsed from. Everything else in the source, comments
+/// and layout included, is left as written.
+pub fn splice_fixes(source: &str, original: &Program, fixed: &Program) -> String {
+    let (Program::Snippets { snippets: before, .. }, Program::Snippets { snippets: after, .. }) = (original, fixed)
+    else {
+        return source.to_string();
+    };
+
+    let mut inserts: Vec<(usize, String)> = Vec::new();
+    for snippet in before {
+        let Some(fixed_snippet) = after.iter().find(|s| s.id == snippet.id) else {
+            continue;
+        };
+        let Some(fixed_effects) = effects_section(&fixed_snippet.sections) else {
+            continue;
+        };
+        let existing = effects_section(&snippet.sections);
+        let declared: Vec<String> = existing.map(effect_lines).unwrap_or_default();
+        let added: Vec<String> = effect_lines(fixed_effects)
+            .into_iter()
+            .filter(|line| !declared.contains(line))
+            .collect();
+        if added.is_empty() {
+            continue;
+        }
+
+        match existing {
+            // Before the section's closing `end`
+            Some(section) => {
+                let end_line = line_start(source, section.span.end.saturating_sub("end".len()));
+                let indent = format!("{}  ", line_indent(source, section.span.start));
+                let text: String = added.iter().map(|line| format!("{}{}\n", indent, line)).collect();
+                inserts.push((end_line, text));
+            }
+            // A new section after the snippet header and its notes, ahead of
+            // any comment on the first section
+            None => {
+                let Some(first) = snippet.sections.iter().map(|s| s.span().start).min() else {
+                    continue;
+                };
+                let header_end = snippet.notes.iter().map(|n| n.span.end).fold(snippet.span.start, usize::max);
+                let offset = source[header_end..].find('\n').map_or(source.len(), |i| header_end + i + 1);
+                let indent = line_indent(source, first);
+                let mut text = format!("\n{}effects\n", indent);
+                for line in &added {
+                    text.push_str(&format!("{}  {}\n", indent, line));
+                }
+                text.push_str(&format!("{}end\n", indent));
+                inserts.push((offset, text));
+            }
+        }
+    }
+
+    let mut spliced = source.to_string();
+    inserts.sort_by_key(|(offset, _)| std::cmp::Reverse(*offset));
+    for (offset, text) in inserts {
+        spliced.insert_str(offset, &text);
+    }
+    spliced
+}
+
+fn effects_section(sections: &[Section]) -> Option<&EffectsSection> {
+    sections.iter().find_map(|s| match s {
+        Section::Effects(effects) => Some(effects),
+        _ => None,
+    })
+}
+
+/// The section's declarations as unindented lines
+fn effect_lines(section: &EffectsSection) -> Vec<String> {
+    section
+        .effects
+        .iter()
+        .map(|e| e.to_cov(0))
+        .chain(section.vars.iter().map(|v| format!("effect of=\"{}\"", v.param)))
+        .collect()
+}
+
+fn line_start(source: &str, offset: usize) -> usize {
+    source[..offset].rfind('\n').map_or(0, |i| i + 1)
+}
+
+fn line_indent(source: &str, offset: usize) -> &str {
+    let line = &source[line_start(source, offset)..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+fn apply_edit(program: &mut Program, edit: &FixEdit) -> Result<bool, FixError> {
+    let snippet_id = match edit {
+        FixEdit::AddEffect { snippet_id, .. } | FixEdit::AddEffectVar { snippet_id, .. } => snippet_id,
+    };
+    let Program::Snippets { snippets, .. } = program else {
+        return Err(FixError::UnknownSnippet { snippet_id: snippet_id.clone() });
+    };
+    let snippet = snippets
+        .iter_mut()
+        .find(|s| &s.id == snippet_id)
+        .ok_or_else(|| FixError::UnknownSnippet { snippet_id: snippet_id.clone() })?;
+
+    // The effects section comes first in canonical order
+    if !snippet.sections.iter().any(|s| matches!(s, Section::Effects(_))) {
+        snippet.sections.insert(
+            0,
+            Section::Effects(EffectsSection {
+                effects: Vec::new(),
+                vars: Vec::new(),
+                span: Span::dummy(),
+            }),
+        );
+    }
+    let Some(Section::Effects(effects)) = snippet
+        .sections
+        .iter_mut()
+        .find(|s| matches!(s, Section::Effects(_)))
+    else {
+        unreachable!("effects section was just ensured");
+    };
+
+    match edit {
+        FixEdit::AddEffect { effect, .. } => {
+            let mut decl = covenant_parser::parse_effect(effect).map_err(|e| FixError::InvalidEffect {
+                effect: effect.clone(),
+                message: e.to_string(),
+            })?;
+            decl.span = Span::dummy();
+            let exists = effects.effects.iter().any(|e| {
+                e.name == decl.name
+                    && e.params.len() == decl.params.len()
+                    && e.params.iter().zip(&decl.params).all(|(a, b)| a.name == b.name && a.value == b.value)
+            });
+            if exists {
+                return Ok(false);
+            }
+            effects.effects.push(decl);
+        }
+        FixEdit::AddEffectVar { param, .. } => {
+            if effects.vars.iter().any(|v| &v.param == param) {
+                return Ok(false);
+            }
+            effects.vars.push(EffectVar {
+                param: param.clone(),
+                span: Span::dummy(),
+            });
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"
+snippet id="io.print" kind="extern"
+
+effects
+  effect console
+end
+
+signature
+  fn name="print"
+    param name="msg" type="String"
+    returns type="Unit"
+  end
+end
+
+end
+
+snippet id="app.greet" kind="fn"
+
+signature
+  fn name="greet"
+    returns type="Unit"
+  end
+end
+
+body
+  step id="s1" kind="call"
+    fn="io.print"
+    arg name="msg" lit="hello"
+    as="_"
+  end
+end
+
+end
+"#;
+
+    #[test]
+    fn test_fix_declares_missing_effect() {
+        let program = covenant_parser::parse(SOURCE).unwrap();
+        let diagnostics = program_diagnostics(&program);
+        let fixes = plan_fixes(&diagnostics);
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(fixes[0].code, "E-EFFECT-001");
+        assert_eq!(fixes[0].confidence, 1.0);
+        assert_eq!(
+            fixes[0].edits,
+            vec![FixEdit::AddEffect { snippet_id: "app.greet".to_string(), effect: "console".to_string() }]
+        );
+
+        let (fixed, remaining) = try_fix(&program, &fixes[0], diagnostics.len()).unwrap();
+        assert!(remaining.is_empty(), "{:?}", remaining);
+        assert!(to_cov(&fixed).contains("effects\n  effect console\nend"));
+
+        // Applying it again changes nothing
+        assert!(matches!(try_fix(&fixed, &fixes[0], 0), Err(FixError::NoChange)));
+    }
+
+    #[test]
+    fn test_splice_keeps_source_text() {
+        let source = SOURCE.replace(
+            "snippet id=\"app.greet\" kind=\"fn\"\n",
+            "snippet id=\"app.greet\" kind=\"fn\"\n// Greets the user\n",
+        );
+        let program = covenant_parser::parse(&source).unwrap();
+        let fixes = plan_fixes(&program_diagnostics(&program));
+        let (fixed, _) = try_fix(&program, &fixes[0], 1).unwrap();
+
+        let spliced = splice_fixes(&source, &program, &fixed);
+        assert_eq!(
+            spliced,
+            source.replace("kind=\"fn\"\n", "kind=\"fn\"\n\neffects\n  effect console\nend\n")
+        );
+
+        // A second effect goes into the now existing section
+        let reparsed = covenant_parser::parse(&spliced).unwrap();
+        let mut more = reparsed.clone();
+        let fix = Fix {
+            code: "E-EFFECT-001".to_string(),
+            description: String::new(),
+            confidence: 1.0,
+            edits: vec![FixEdit::AddEffect { snippet_id: "app.greet".to_string(), effect: "network".to_string() }],
+        };
+        assert!(apply_fix(&mut more, &fix).unwrap());
+        assert!(splice_fixes(&spliced, &reparsed, &more)
+            .contains("kind=\"fn\"\n\neffects\n  effect console\n  effect network\nend\n// Greets the user\n"));
+    }
+
+    #[test]
+    fn test_suggestions_without_edits_are_not_planned() {
+        let suggestion = FixSuggestion::WrapInEffectfulFunction {
+            effects: vec!["console".to_string()],
+            suggested_name: None,
+        };
+        assert!(suggestion.edits().is_empty());
+
+        let var = FixSuggestion::AddEffect {
+            effect: "of=\"f\"".to_string(),
+            snippet_id: "app.each".to_string(),
+            location: Span::dummy(),
+            code_snippet: String::new(),
+        };
+        assert_eq!(var.confidence(), 0.7);
+        assert_eq!(
+            var.edits(),
+            vec![FixEdit::AddEffectVar { snippet_id: "app.each".to_string(), param: "f".to_string() }]
+        );
+    }
+}
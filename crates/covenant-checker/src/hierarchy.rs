@@ -0,0 +1,151 @@
+//! Effect hierarchy
+//!
+//! Effect names are otherwise unrelated strings: `database` and
+//! `database.read` are separate effects. Effect snippets relate them by
+//! declaring parents or implications, and declaring an effect then covers
+//! every effect beneath it:
+//!
+//! ```text
+//! snippet id="database.read" kind="effect"
+//! signature
+//!   effect name="read"
+//!     parent effect="database"
+//!   end
+//! end
+//! end
+//! ```
+//!
+//! `parent effect="database"` on `database.read` and `implies
+//! effect="database.read"` on `database` are equivalent.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use covenant_symbols::{SymbolGraph, SymbolKind};
+
+/// Which effects each effect directly covers
+#[derive(Debug, Clone, Default)]
+pub struct EffectHierarchy {
+    children: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl EffectHierarchy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build the hierarchy from the parents and implications of effect snippets
+    pub fn from_graph(graph: &SymbolGraph) -> Self {
+        let mut hierarchy = Self::new();
+        for symbol in graph.iter().filter(|s| s.kind == SymbolKind::Effect) {
+            for parent in &symbol.effect_parents {
+                hierarchy.add(parent, &symbol.name);
+            }
+            for implied in &symbol.effect_implies {
+                hierarchy.add(&symbol.name, implied);
+            }
+        }
+        hierarchy
+    }
+
+    /// Record that declaring `parent` covers `child`
+    pub fn add(&mut self, parent: &str, child: &str) {
+        self.children
+            .entry(parent.to_string())
+            .or_default()
+            .insert(child.to_string());
+    }
+
+    /// True if no effect covers another
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Check if declaring `declared` covers `required`: they are the same
+    /// effect, or `required` is beneath `declared` in the hierarchy
+    pub fn covers(&self, declared: &str, required: &str) -> bool {
+        if declared == required {
+            return true;
+        }
+        let mut visited = HashSet::new();
+        let mut stack = vec![declared];
+        while let Some(effect) = stack.pop() {
+            if !visited.insert(effect) {
+                continue; // Cycles are allowed; each effect is visited once
+            }
+            for child in self.children(effect) {
+                if child == required {
+                    return true;
+                }
+                stack.push(child);
+            }
+        }
+        false
+    }
+
+    /// Effects directly covered by `effect`, in name order
+    pub fn children(&self, effect: &str) -> impl Iterator<Item = &str> {
+        self.children
+            .get(effect)
+            .into_iter()
+            .flat_map(|children| children.iter().map(String::as_str))
+    }
+
+    /// Effects with children but no parent, in name order
+    pub fn roots(&self) -> Vec<&str> {
+        let covered: HashSet<&str> = self.children.values().flatten().map(String::as_str).collect();
+        let mut roots: Vec<&str> = self
+            .children
+            .keys()
+            .map(String::as_str)
+            .filter(|effect| !covered.contains(effect))
+            .collect();
+        // Effects that only appear in cycles have no root; list them once
+        let mut reachable = HashSet::new();
+        for root in &roots {
+            self.collect_reachable(root, &mut reachable);
+        }
+        for effect in self.children.keys() {
+            if !reachable.contains(effect.as_str()) {
+                roots.push(effect);
+                self.collect_reachable(effect, &mut reachable);
+            }
+        }
+        roots
+    }
+
+    fn collect_reachable<'a>(&'a self, effect: &'a str, reachable: &mut HashSet<&'a str>) {
+        if reachable.insert(effect) {
+            for child in self.children(effect) {
+                self.collect_reachable(child, reachable);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_covers_is_transitive() {
+        let mut hierarchy = EffectHierarchy::new();
+        hierarchy.add("storage", "database");
+        hierarchy.add("database", "database.read");
+
+        assert!(hierarchy.covers("storage", "database.read"));
+        assert!(hierarchy.covers("database", "database"));
+        assert!(!hierarchy.covers("database.read", "database"));
+        assert!(!hierarchy.covers("database", "network"));
+        assert_eq!(hierarchy.roots(), vec!["storage"]);
+    }
+
+    #[test]
+    fn test_cycles_terminate() {
+        let mut hierarchy = EffectHierarchy::new();
+        hierarchy.add("a", "b");
+        hierarchy.add("b", "a");
+
+        assert!(hierarchy.covers("a", "b"));
+        assert!(!hierarchy.covers("a", "c"));
+        assert_eq!(hierarchy.roots(), vec!["a"]);
+    }
+}
@@ -0,0 +1,306 @@
+//! Machine-readable diagnostics
+//!
+//! Renders diagnostics from every phase in the JSON form documented in
+//! ERROR_CODES.md, either as one JSON array or as JSON Lines (one object per
+//! line). Spans are resolved through the [`SourceMap`] to their file and
+//! given both as byte offsets and as 1-based line/column.
+
+use covenant_ast::{FileId, SourceMap, Span};
+use serde::Serialize;
+
+use crate::diagnostics::{Diagnostic, DiagnosticKind, FixSuggestion, RelatedLocation, Severity};
+use crate::fix::FixEdit;
+
+pub use covenant_ast::line_column;
+
+/// Output format for diagnostics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiagnosticFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// A single JSON array
+    Json,
+    /// One JSON object per line
+    JsonLines,
+    /// A SARIF 2.1.0 log
+    Sarif,
+}
+
+impl std::str::FromStr for DiagnosticFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" | "txt" => Ok(DiagnosticFormat::Text),
+            "json" => Ok(DiagnosticFormat::Json),
+            "jsonl" | "json-lines" | "ndjson" => Ok(DiagnosticFormat::JsonLines),
+            "sarif" => Ok(DiagnosticFormat::Sarif),
+            _ => Err(format!("Unknown format: {}. Expected: text, json, jsonl, or sarif", s)),
+        }
+    }
+}
+
+/// A diagnostic as emitted in JSON
+#[derive(Debug, Clone, Serialize)]
+pub struct JsonDiagnostic {
+    pub code: String,
+    pub severity: Severity,
+    pub message: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub explanation: String,
+    pub source_location: SourceLocation,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub related: Vec<JsonRelated>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<JsonSuggestion>,
+}
+
+/// Where a diagnostic points. Position fields are absent for diagnostics
+/// without a source span.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SourceLocation {
+    pub file: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step_id: Option<String>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub span: Option<JsonSpan>,
+}
+
+/// A span as byte offsets and 1-based line/column positions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct JsonSpan {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+/// A related location, e.g. a step in an effect's call chain
+#[derive(Debug, Clone, Serialize)]
+pub struct JsonRelated {
+    pub message: String,
+    pub label: String,
+    pub file: String,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub span: Option<JsonSpan>,
+}
+
+/// A fix suggestion. `auto_fix` suggestions carry edits `covenant fix` can
+/// apply; `refactor` suggestions need code written by hand.
+#[derive(Debug, Clone, Serialize)]
+pub struct JsonSuggestion {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub description: String,
+    pub confidence: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub edits: Vec<JsonEdit>,
+}
+
+/// An edit with an XPath-like target, as in ERROR_CODES.md
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JsonEdit {
+    pub operation: &'static str,
+    pub target: String,
+    pub content: String,
+}
+
+impl Diagnostic {
+    /// Convert to the JSON form, resolving spans against the files of
+    /// `sources`. Diagnostics and related locations without a span are
+    /// attributed to `file`.
+    pub fn to_json(&self, sources: &SourceMap, file: FileId) -> JsonDiagnostic {
+        let snippet_id = self.snippet_id.clone().or_else(|| match &self.error {
+            DiagnosticKind::Effect(effect) => Some(effect.function.clone()),
+            _ => None,
+        });
+        let (file_name, span) = resolve(sources, self.span, file);
+        JsonDiagnostic {
+            code: self.code.clone(),
+            severity: self.severity,
+            message: self.message(),
+            explanation: self.explanation.clone(),
+            source_location: SourceLocation {
+                file: file_name,
+                snippet_id,
+                step_id: self.step_id.clone(),
+                span,
+            },
+            related: self
+                .related
+                .iter()
+                .map(|related| JsonRelated::new(related, sources, file))
+                .collect(),
+            suggestions: self.suggestions.iter().map(JsonSuggestion::from).collect(),
+        }
+    }
+}
+
+/// File name and position of a span; the span's own file unless it is a dummy
+fn resolve(sources: &SourceMap, span: Span, file: FileId) -> (String, Option<JsonSpan>) {
+    let file = if span.is_dummy() { file } else { span.file };
+    let position = sources.get(file).and_then(|f| JsonSpan::resolve(span, &f.source));
+    (sources.name(file).to_string(), position)
+}
+
+impl JsonSpan {
+    /// Resolve a span against source text; `None` for dummy spans
+    pub fn resolve(span: Span, source: &str) -> Option<Self> {
+        if span.is_dummy() {
+            return None;
+        }
+        let (line, column) = line_column(source, span.start);
+        let (end_line, end_column) = line_column(source, span.end);
+        Some(Self {
+            start: span.start,
+            end: span.end,
+            line,
+            column,
+            end_line,
+            end_column,
+        })
+    }
+}
+
+impl JsonRelated {
+    fn new(related: &RelatedLocation, sources: &SourceMap, file: FileId) -> Self {
+        let (file, span) = resolve(sources, related.span, file);
+        Self {
+            message: related.message.clone(),
+            label: related.label.clone(),
+            file,
+            span,
+        }
+    }
+}
+
+impl From<&FixSuggestion> for JsonSuggestion {
+    fn from(suggestion: &FixSuggestion) -> Self {
+        let edits: Vec<JsonEdit> = suggestion.edits().iter().map(JsonEdit::from).collect();
+        Self {
+            kind: if edits.is_empty() { "refactor" } else { "auto_fix" },
+            description: suggestion.description(),
+            confidence: suggestion.confidence(),
+            code: suggestion.code_snippet(),
+            edits,
+        }
+    }
+}
+
+impl From<&FixEdit> for JsonEdit {
+    fn from(edit: &FixEdit) -> Self {
+        let (snippet_id, content) = match edit {
+            FixEdit::AddEffect { snippet_id, effect } => (snippet_id, format!("effect {}", effect)),
+            FixEdit::AddEffectVar { snippet_id, param } => {
+                (snippet_id, format!("effect of=\"{}\"", param))
+            }
+        };
+        Self {
+            operation: "insert_after",
+            target: format!("snippet[@id='{}']/effects/effect[last()]", snippet_id),
+            content,
+        }
+    }
+}
+
+/// Render diagnostics as a JSON array, as JSON Lines or as a SARIF log.
+/// Returns an empty string for [`DiagnosticFormat::Text`].
+pub fn render_json(diagnostics: &[JsonDiagnostic], format: DiagnosticFormat) -> String {
+    match format {
+        DiagnosticFormat::Text => String::new(),
+        DiagnosticFormat::Json => {
+            serde_json::to_string_pretty(diagnostics).expect("diagnostics serialize to JSON")
+        }
+        DiagnosticFormat::JsonLines => diagnostics
+            .iter()
+            .map(|d| serde_json::to_string(d).expect("diagnostics serialize to JSON"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        DiagnosticFormat::Sarif => crate::sarif::render_sarif(diagnostics),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::{EffectDiagnostic, EffectDiagnosticBuilder};
+
+    #[test]
+    fn test_effect_diagnostic_json() {
+        let mut sources = SourceMap::new();
+        let main = sources.add("main.cov", "snippet id=\"app.main\" kind=\"fn\"\nend\n");
+        let io = sources.add("io.cov", "\nsnippet id=\"io.print\" kind=\"extern\"\nend\n");
+        let diagnostic = EffectDiagnosticBuilder::missing_effect(
+            "app.main".to_string(),
+            vec!["console".to_string()],
+            "io.print".to_string(),
+            Span::new(0, 7).in_file(main),
+        )
+        .with_related(vec![RelatedLocation {
+            message: "`io.print` requires effects: console".to_string(),
+            span: Span::new(1, 8).in_file(io),
+            label: "declares effects: console".to_string(),
+        }])
+        .with_suggestions(vec![FixSuggestion::AddEffect {
+            effect: "console".to_string(),
+            snippet_id: "app.main".to_string(),
+            location: Span::dummy(),
+            code_snippet: "effect console".to_string(),
+        }])
+        .build();
+
+        let json = serde_json::to_value(diagnostic.to_json(&sources, main)).unwrap();
+        assert_eq!(json["code"], "E-EFFECT-002");
+        assert_eq!(json["severity"], "error");
+        assert_eq!(json["source_location"]["file"], "main.cov");
+        assert_eq!(json["source_location"]["snippet_id"], "app.main");
+        assert_eq!(json["source_location"]["line"], 1);
+        assert_eq!(json["source_location"]["end_column"], 8);
+        assert_eq!(json["related"][0]["file"], "io.cov");
+        assert_eq!(json["related"][0]["line"], 2);
+        assert_eq!(json["related"][0]["column"], 1);
+        assert_eq!(json["suggestions"][0]["type"], "auto_fix");
+        assert_eq!(json["suggestions"][0]["confidence"], 1.0);
+        assert_eq!(
+            json["suggestions"][0]["edits"][0]["target"],
+            "snippet[@id='app.main']/effects/effect[last()]"
+        );
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let diagnostic = Diagnostic::new(
+            DiagnosticKind::Effect(EffectDiagnostic {
+                function: "f".to_string(),
+                callee: None,
+                effects: vec!["network".to_string()],
+                call_chain: Vec::new(),
+            }),
+            Span::dummy(),
+            "E-EFFECT-002",
+            "",
+        )
+        .with_severity(Severity::Warning);
+        let mut sources = SourceMap::new();
+        let a = sources.add("a.cov", "");
+        let b = sources.add("b.cov", "");
+        let json = vec![diagnostic.to_json(&sources, a), diagnostic.to_json(&sources, b)];
+
+        let lines = render_json(&json, DiagnosticFormat::JsonLines);
+        assert_eq!(lines.lines().count(), 2);
+        let first: serde_json::Value = serde_json::from_str(lines.lines().next().unwrap()).unwrap();
+        assert_eq!(first["severity"], "warning");
+        assert!(first["source_location"].get("line").is_none());
+        assert_eq!(json[1].source_location.file, "b.cov");
+
+        let array: serde_json::Value = serde_json::from_str(&render_json(&json, DiagnosticFormat::Json)).unwrap();
+        assert_eq!(array.as_array().unwrap().len(), 2);
+    }
+}
@@ -6,17 +6,38 @@ mod types;
 mod symbols;
 mod checker;
 mod effects;
+mod hierarchy;
+mod policy;
+mod subsumption;
 mod snippet_checker;
+mod scope;
 mod diagnostics;
+mod fix;
+mod json;
+mod codes;
+mod sarif;
 
 pub use types::*;
 pub use symbols::*;
 pub use checker::*;
 pub use effects::*;
+pub use hierarchy::EffectHierarchy;
+pub use policy::{EffectPolicy, PolicyDenial, PolicyError, PolicyRule, POLICY_FILE_NAME};
+pub use subsumption::{rule_subsumes, SubsumptionRules};
 pub use snippet_checker::SnippetChecker;
 pub use diagnostics::*;
+pub use json::{
+    line_column, render_json, DiagnosticFormat, JsonDiagnostic, JsonEdit, JsonRelated, JsonSpan,
+    JsonSuggestion, SourceLocation,
+};
+pub use codes::{lookup_code, ErrorCode, ERROR_CODES, ERROR_CODES_DOC};
+pub use sarif::{render_sarif, sarif_log};
+pub use fix::{
+    apply_fix, plan_fixes, program_diagnostics, splice_fixes, try_fix, Fix, FixEdit, FixError,
+    DEFAULT_FIX_CONFIDENCE,
+};
 
-use covenant_ast::Program;
+use covenant_ast::{Program, Span};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -49,7 +70,27 @@ pub enum CheckError {
     UnknownQueryTarget { target: String },
 
     #[error("unknown field '{field}' in type '{type_name}'")]
-    UnknownField { field: String, type_name: String },
+    UnknownField {
+        field: String,
+        type_name: String,
+        span: Span,
+    },
+
+    #[error("missing field '{field}' in construction of '{type_name}'")]
+    MissingField {
+        field: String,
+        type_name: String,
+        span: Span,
+    },
+
+    #[error("field '{field}' of '{type_name}' expects {expected}, found {found}")]
+    FieldTypeMismatch {
+        field: String,
+        type_name: String,
+        expected: String,
+        found: String,
+        span: Span,
+    },
 
     #[error("extern-impl '{impl_id}' references unknown extern-abstract '{abstract_id}'")]
     UnknownExternAbstract {
@@ -65,6 +106,157 @@ pub enum CheckError {
 
     #[error("extern snippet '{id}' must have a namespaced ID (e.g., 'module.function')")]
     InvalidExternId { id: String },
+
+    #[error("conflicting types for type parameter '{param}' of '{function}': {first} and {second}")]
+    ConflictingTypeArgument {
+        function: String,
+        param: String,
+        first: String,
+        second: String,
+    },
+
+    #[error("cannot infer type parameter '{param}' of '{function}' from the call arguments")]
+    UninferredTypeParameter { function: String, param: String },
+
+    #[error("call to '{function}' is missing argument '{param}'")]
+    MissingArgument {
+        function: String,
+        param: String,
+        expected: Vec<String>,
+        /// Snippet ID of the calling function
+        caller: String,
+        span: Span,
+    },
+
+    #[error("'{function}' has no parameter named '{arg}'")]
+    UnknownArgument {
+        function: String,
+        arg: String,
+        expected: Vec<String>,
+        /// Snippet ID of the calling function
+        caller: String,
+        span: Span,
+    },
+
+    #[error("argument '{arg}' is passed to '{function}' more than once")]
+    DuplicateArgument {
+        function: String,
+        arg: String,
+        /// Snippet ID of the calling function
+        caller: String,
+        span: Span,
+    },
+
+    #[error("argument '{param}' of '{function}' expects {expected}, found {found}")]
+    ArgumentTypeMismatch {
+        function: String,
+        param: String,
+        expected: String,
+        found: String,
+        /// Snippet ID of the calling function
+        caller: String,
+        span: Span,
+    },
+
+    #[error("'{function}' takes {expected} arguments, but {found} were passed")]
+    ArgumentCountMismatch {
+        function: String,
+        expected: usize,
+        found: usize,
+        /// Snippet ID of the calling function
+        caller: String,
+        span: Span,
+    },
+
+    #[error("effect variable '{param}' of '{function}' is not a function-typed parameter")]
+    InvalidEffectVariable {
+        function: String,
+        param: String,
+        span: Span,
+    },
+
+    #[error("pattern '{variant}' binds {found} values, but the variant has {expected}")]
+    TooManyPatternBindings {
+        variant: String,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+
+    #[error("'{name}' is bound more than once (again by step '{step_id}')")]
+    DuplicateBinding {
+        name: String,
+        step_id: String,
+        /// Step that first bound the name, or None for a parameter
+        first: Option<String>,
+        span: Span,
+    },
+
+    #[error("step '{step_id}' uses '{name}' before step '{defined_by}' defines it")]
+    UseBeforeDefinition {
+        name: String,
+        step_id: String,
+        defined_by: String,
+        span: Span,
+    },
+
+    #[error("step '{step_id}' uses '{name}', which is only bound inside step '{branch_step}'")]
+    BranchLocalBinding {
+        name: String,
+        step_id: String,
+        branch_step: String,
+        span: Span,
+    },
+
+    #[error("duplicate step id '{id}' in snippet '{snippet}'")]
+    DuplicateStepId {
+        id: String,
+        snippet: String,
+        span: Span,
+    },
+
+    #[error("'{function}' cannot fail with '{error_type}'")]
+    UnhandleableErrorType {
+        function: String,
+        error_type: String,
+        errors: Vec<String>,
+        span: Span,
+    },
+}
+
+impl CheckError {
+    /// Error code for machine-readable output
+    pub fn code(&self) -> &'static str {
+        match self {
+            CheckError::UndefinedSymbol { .. } => "E-SYMBOL-001",
+            CheckError::TypeMismatch { .. } => "E-TYPE-001",
+            CheckError::EffectNotAllowed { .. } => "E-EFFECT-001",
+            CheckError::DuplicateDefinition { .. } => "E-SYMBOL-002",
+            CheckError::IncompatibleUnion { .. } => "E-TYPE-003",
+            CheckError::NonExhaustiveMatch { .. } => "E-TYPE-004",
+            CheckError::UnknownQueryTarget { .. } => "E-QUERY-002",
+            CheckError::UnknownField { .. } => "E-TYPE-010",
+            CheckError::MissingField { .. } => "E-TYPE-007",
+            CheckError::FieldTypeMismatch { .. } => "E-TYPE-008",
+            CheckError::UnknownExternAbstract { .. } => "E-EXTERN-001",
+            CheckError::NoBindingForTarget { .. } => "E-EXTERN-002",
+            CheckError::InvalidExternId { .. } => "E-EXTERN-003",
+            CheckError::ConflictingTypeArgument { .. } => "E-TYPE-005",
+            CheckError::UninferredTypeParameter { .. } => "E-TYPE-006",
+            CheckError::MissingArgument { .. } => "E-CALL-001",
+            CheckError::UnknownArgument { .. } => "E-CALL-002",
+            CheckError::DuplicateArgument { .. } => "E-CALL-006",
+            CheckError::ArgumentTypeMismatch { .. } => "E-CALL-003",
+            CheckError::ArgumentCountMismatch { .. } => "E-CALL-005",
+            CheckError::InvalidEffectVariable { .. } => "E-EFFECT-006",
+            CheckError::DuplicateBinding { .. } => "E-SCOPE-001",
+            CheckError::UseBeforeDefinition { .. } => "E-SCOPE-002",
+            CheckError::BranchLocalBinding { .. } => "E-SCOPE-003",
+            CheckError::DuplicateStepId { .. } => "E-SCOPE-004",
+            CheckError::TooManyPatternBindings { .. } => "E-TYPE-009",
+            CheckError::UnhandleableErrorType { .. } => "E-CALL-004",
+        }
+    }
 }
 
 /// Check a program and return the typed/annotated version
@@ -0,0 +1,339 @@
+//! Project effect policy
+//!
+//! Each snippet declares its own effects; a policy file restricts which
+//! snippets may use which effects at all. `covenant-policy.toml` lists rules
+//! by snippet id glob:
+//!
+//! ```toml
+//! # Nothing under ui.* may touch the database
+//! [[rule]]
+//! snippets = "ui.*"
+//! deny = ["database"]
+//!
+//! # Only infra.* may spawn processes
+//! [[rule]]
+//! snippets = "*"
+//! deny = ["process"]
+//!
+//! [[rule]]
+//! snippets = "infra.*"
+//! allow = ["process"]
+//!
+//! # Handlers may only read files under /data
+//! [[rule]]
+//! snippets = "app.handlers.*"
+//! allow = ['filesystem(path="/data")']
+//! ```
+//!
+//! Rules apply in order and a later matching rule overrides an earlier one.
+//! Within a rule, a parameterized `allow` permits only uses its parameters
+//! subsume and forbids other uses of that effect; a parameterized `deny`
+//! forbids uses its parameters subsume, and uses without the parameter.
+//! Effects no rule mentions are allowed.
+
+use std::path::{Path, PathBuf};
+use covenant_ast::{EffectDecl, Literal, SubsumptionRule};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::hierarchy::EffectHierarchy;
+use crate::subsumption::{rule_subsumes, SubsumptionRules};
+
+/// File name looked up by [`EffectPolicy::discover`]
+pub const POLICY_FILE_NAME: &str = "covenant-policy.toml";
+
+/// Errors loading a policy file
+#[derive(Debug, Error)]
+pub enum PolicyError {
+    #[error("cannot read policy file {path}: {message}")]
+    Io { path: String, message: String },
+
+    #[error("invalid policy file: {message}")]
+    Toml { message: String },
+
+    #[error("invalid effect `{entry}` in policy rule for `{snippets}`: {message}")]
+    InvalidEffect {
+        snippets: String,
+        entry: String,
+        message: String,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default)]
+    rule: Vec<RuleEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleEntry {
+    snippets: String,
+    #[serde(default)]
+    allow: Vec<String>,
+    #[serde(default)]
+    deny: Vec<String>,
+}
+
+/// A single `[[rule]]` of a policy file
+#[derive(Debug, Clone)]
+pub struct PolicyRule {
+    /// Glob over snippet ids, where `*` also matches `.`
+    pub snippets: String,
+    /// Effects the matching snippets may use
+    pub allow: Vec<EffectDecl>,
+    /// Effects the matching snippets may not use
+    pub deny: Vec<EffectDecl>,
+}
+
+impl PolicyRule {
+    /// Check if the rule applies to a snippet
+    pub fn matches(&self, snippet: &str) -> bool {
+        let pattern = Literal::String(self.snippets.clone());
+        rule_subsumes(SubsumptionRule::Glob, &pattern, &Literal::String(snippet.to_string()))
+    }
+}
+
+/// Allow/deny rules for effects by snippet id
+#[derive(Debug, Clone, Default)]
+pub struct EffectPolicy {
+    pub rules: Vec<PolicyRule>,
+}
+
+/// Why a policy forbids an effect
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyDenial {
+    /// Snippet glob of the deciding rule
+    pub snippets: String,
+    /// Human-readable reason, e.g. "rule for `ui.*` denies `database`"
+    pub reason: String,
+}
+
+/// How a policy entry relates to a used effect
+enum Coverage {
+    /// The entry is about a different effect
+    Unrelated,
+    /// The entry's parameters subsume the use
+    Within,
+    /// Same effect, but the use has no value for a parameter the entry constrains
+    Unconstrained,
+    /// Same effect with parameter values outside the entry's
+    Outside,
+}
+
+impl EffectPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// True if the policy has no rules
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Parse a policy from TOML
+    pub fn from_toml_str(source: &str) -> Result<Self, PolicyError> {
+        let file: PolicyFile = toml::from_str(source).map_err(|e| PolicyError::Toml {
+            message: e.to_string(),
+        })?;
+
+        let mut rules = Vec::new();
+        for entry in file.rule {
+            let allow = parse_entries(&entry.snippets, &entry.allow)?;
+            let deny = parse_entries(&entry.snippets, &entry.deny)?;
+            rules.push(PolicyRule { snippets: entry.snippets, allow, deny });
+        }
+        Ok(Self { rules })
+    }
+
+    /// Load a policy file
+    pub fn load(path: &Path) -> Result<Self, PolicyError> {
+        let source = std::fs::read_to_string(path).map_err(|e| PolicyError::Io {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+        Self::from_toml_str(&source)
+    }
+
+    /// Find the policy file for sources in `start`: the nearest
+    /// `covenant-policy.toml` in it or any parent directory
+    pub fn discover(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(POLICY_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// Check whether `snippet` may use `effect`, returning why not if it may not
+    pub fn check(
+        &self,
+        snippet: &str,
+        effect: &EffectDecl,
+        hierarchy: &EffectHierarchy,
+        rules: &SubsumptionRules,
+    ) -> Option<PolicyDenial> {
+        let mut verdict = None;
+        for rule in self.rules.iter().filter(|r| r.matches(snippet)) {
+            let coverage = |entry: &EffectDecl| entry_coverage(entry, effect, hierarchy, rules);
+
+            let allowed: Vec<&EffectDecl> = rule
+                .allow
+                .iter()
+                .filter(|e| !matches!(coverage(e), Coverage::Unrelated))
+                .collect();
+            if allowed.iter().any(|e| matches!(coverage(e), Coverage::Within)) {
+                verdict = None;
+                continue;
+            }
+
+            let denied = rule
+                .deny
+                .iter()
+                .find(|e| matches!(coverage(e), Coverage::Within | Coverage::Unconstrained));
+            if let Some(entry) = denied {
+                verdict = Some(PolicyDenial {
+                    snippets: rule.snippets.clone(),
+                    reason: format!(
+                        "rule for `{}` denies `{}`",
+                        rule.snippets,
+                        format_effect(entry)
+                    ),
+                });
+            } else if !allowed.is_empty() {
+                let allowed: Vec<String> = allowed
+                    .iter()
+                    .map(|e| format!("`{}`", format_effect(e)))
+                    .collect();
+                verdict = Some(PolicyDenial {
+                    snippets: rule.snippets.clone(),
+                    reason: format!(
+                        "rule for `{}` only allows {}",
+                        rule.snippets,
+                        allowed.join(", ")
+                    ),
+                });
+            }
+        }
+        verdict
+    }
+}
+
+fn parse_entries(snippets: &str, entries: &[String]) -> Result<Vec<EffectDecl>, PolicyError> {
+    entries
+        .iter()
+        .map(|entry| {
+            covenant_parser::parse_effect(entry).map_err(|e| PolicyError::InvalidEffect {
+                snippets: snippets.to_string(),
+                entry: entry.clone(),
+                message: e.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn entry_coverage(
+    entry: &EffectDecl,
+    effect: &EffectDecl,
+    hierarchy: &EffectHierarchy,
+    rules: &SubsumptionRules,
+) -> Coverage {
+    if !hierarchy.covers(&entry.name, &effect.name) {
+        return Coverage::Unrelated;
+    }
+    let mut coverage = Coverage::Within;
+    for param in &entry.params {
+        match effect.get_param(&param.name) {
+            None => coverage = Coverage::Unconstrained,
+            Some(used) => {
+                if !rules.subsumes(&effect.name, &param.name, &param.value, &used.value) {
+                    return Coverage::Outside;
+                }
+            }
+        }
+    }
+    coverage
+}
+
+/// Format an effect as written in a policy file, e.g. `filesystem(path="/data")`
+pub fn format_effect(effect: &EffectDecl) -> String {
+    if !effect.has_params() {
+        return effect.name.clone();
+    }
+    let params: Vec<String> = effect
+        .params
+        .iter()
+        .map(|p| format!("{}={}", p.name, crate::effects::literal_to_string(&p.value)))
+        .collect();
+    format!("{}({})", effect.name, params.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effect(source: &str) -> EffectDecl {
+        covenant_parser::parse_effect(source).unwrap()
+    }
+
+    fn check(policy: &EffectPolicy, snippet: &str, used: &str) -> Option<PolicyDenial> {
+        policy.check(snippet, &effect(used), &EffectHierarchy::new(), &SubsumptionRules::builtin())
+    }
+
+    #[test]
+    fn test_later_rules_override_earlier() {
+        let policy = EffectPolicy::from_toml_str(
+            r#"
+            [[rule]]
+            snippets = "*"
+            deny = ["process"]
+
+            [[rule]]
+            snippets = "infra.*"
+            allow = ["process"]
+            "#,
+        )
+        .unwrap();
+
+        assert!(check(&policy, "infra.deploy.run", "process").is_none());
+        let denial = check(&policy, "app.main", "process").unwrap();
+        assert_eq!(denial.reason, "rule for `*` denies `process`");
+        assert!(check(&policy, "app.main", "network").is_none());
+    }
+
+    #[test]
+    fn test_parameterized_entries() {
+        let policy = EffectPolicy::from_toml_str(
+            r#"
+            [[rule]]
+            snippets = "app.*"
+            allow = ['filesystem(path="/data")']
+            deny = ['network(host="*.internal")']
+            "#,
+        )
+        .unwrap();
+
+        assert!(check(&policy, "app.load", r#"filesystem(path="/data/users")"#).is_none());
+        assert!(check(&policy, "app.load", r#"filesystem(path="/etc")"#).is_some());
+        assert!(check(&policy, "app.load", "filesystem").is_some());
+        assert!(check(&policy, "app.load", r#"network(host="api.example.com")"#).is_none());
+        assert!(check(&policy, "app.load", r#"network(host="db.internal")"#).is_some());
+        assert!(check(&policy, "app.load", "network").is_some());
+    }
+
+    #[test]
+    fn test_invalid_entries_are_rejected() {
+        let err = EffectPolicy::from_toml_str(
+            r#"
+            [[rule]]
+            snippets = "ui.*"
+            deny = ["database("]
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(err, PolicyError::InvalidEffect { .. }));
+
+        let err = EffectPolicy::from_toml_str("[[rule]]\nsnippet = \"ui.*\"\n").unwrap_err();
+        assert!(matches!(err, PolicyError::Toml { .. }));
+    }
+}
@@ -0,0 +1,248 @@
+//! SARIF 2.1.0 output
+//!
+//! Renders diagnostics as a SARIF log for code-scanning tools. Rules come
+//! from the error code registry, and a diagnostic's related locations (the
+//! call chain of an effect violation) become a code flow.
+
+use serde_json::{json, Value};
+
+use crate::codes::lookup_code;
+use crate::diagnostics::Severity;
+use crate::json::{JsonDiagnostic, JsonSpan};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Render diagnostics as a SARIF 2.1.0 log with a single run
+pub fn render_sarif(diagnostics: &[JsonDiagnostic]) -> String {
+    serde_json::to_string_pretty(&sarif_log(diagnostics)).expect("SARIF log serializes to JSON")
+}
+
+/// Build the SARIF log
+pub fn sarif_log(diagnostics: &[JsonDiagnostic]) -> Value {
+    // Rules for the codes used, in order of first use
+    let mut codes: Vec<&str> = Vec::new();
+    for diagnostic in diagnostics {
+        if !codes.contains(&diagnostic.code.as_str()) {
+            codes.push(&diagnostic.code);
+        }
+    }
+    let rules: Vec<Value> = codes.iter().map(|code| rule(code)).collect();
+
+    let results: Vec<Value> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let rule_index = codes.iter().position(|c| *c == diagnostic.code).unwrap_or_default();
+            result(diagnostic, rule_index)
+        })
+        .collect();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "covenant",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+fn rule(code: &str) -> Value {
+    let Some(registered) = lookup_code(code) else {
+        return json!({ "id": code });
+    };
+    let help = registered.doc_link();
+    json!({
+        "id": code,
+        "name": registered.title.replace(|c: char| !c.is_alphanumeric(), ""),
+        "shortDescription": { "text": registered.title },
+        "help": {
+            "text": format!("See {}", help),
+            "markdown": format!("See [{}]({})", code, help),
+        },
+        "defaultConfiguration": { "level": level(registered.severity) },
+    })
+}
+
+fn result(diagnostic: &JsonDiagnostic, rule_index: usize) -> Value {
+    let location = &diagnostic.source_location;
+    // A diagnostic that belongs to no file has only a logical location
+    let mut primary = json!({});
+    if !location.file.is_empty() {
+        primary["physicalLocation"] = physical_location(&location.file, location.span);
+    }
+    if let Some(snippet_id) = &location.snippet_id {
+        let name = match &location.step_id {
+            Some(step_id) => format!("{}/{}", snippet_id, step_id),
+            None => snippet_id.clone(),
+        };
+        primary["logicalLocations"] = json!([{ "fullyQualifiedName": name, "kind": "function" }]);
+    }
+    let locations = if primary.as_object().is_some_and(serde_json::Map::is_empty) { Vec::new() } else { vec![primary] };
+
+    let mut result = json!({
+        "ruleId": diagnostic.code,
+        "ruleIndex": rule_index,
+        "level": level(diagnostic.severity),
+        "message": { "text": diagnostic.message },
+        "locations": locations,
+    });
+
+    if !diagnostic.related.is_empty() {
+        let related: Vec<Value> = diagnostic
+            .related
+            .iter()
+            .enumerate()
+            .map(|(id, related)| {
+                json!({
+                    "id": id,
+                    "physicalLocation": physical_location(&related.file, related.span),
+                    "message": { "text": related.message },
+                })
+            })
+            .collect();
+        let flow: Vec<Value> = diagnostic
+            .related
+            .iter()
+            .map(|related| {
+                json!({
+                    "location": {
+                        "physicalLocation": physical_location(&related.file, related.span),
+                        "message": { "text": related.message },
+                    }
+                })
+            })
+            .collect();
+        result["relatedLocations"] = json!(related);
+        result["codeFlows"] = json!([{ "threadFlows": [{ "locations": flow }] }]);
+    }
+
+    let mut properties = serde_json::Map::new();
+    if !diagnostic.explanation.is_empty() {
+        properties.insert("explanation".to_string(), json!(diagnostic.explanation));
+    }
+    if !diagnostic.suggestions.is_empty() {
+        properties.insert("suggestions".to_string(), json!(diagnostic.suggestions));
+    }
+    if !properties.is_empty() {
+        result["properties"] = Value::Object(properties);
+    }
+    result
+}
+
+fn physical_location(file: &str, span: Option<JsonSpan>) -> Value {
+    let mut location = json!({ "artifactLocation": artifact_location(file) });
+    if let Some(span) = span {
+        location["region"] = json!({
+            "startLine": span.line,
+            "startColumn": span.column,
+            "endLine": span.end_line,
+            "endColumn": span.end_column,
+            "byteOffset": span.start,
+            "byteLength": span.end - span.start,
+        });
+    }
+    location
+}
+
+/// Absolute paths become `file://` URIs; relative ones stay relative to the
+/// source root
+fn artifact_location(file: &str) -> Value {
+    let path = file.replace('\\', "/").replace(' ', "%20");
+    if path.starts_with('/') {
+        json!({ "uri": format!("file://{}", path) })
+    } else {
+        let path = path.strip_prefix("./").unwrap_or(&path);
+        json!({ "uri": path, "uriBaseId": "%SRCROOT%" })
+    }
+}
+
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info | Severity::Hint => "note",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use covenant_ast::{SourceMap, Span};
+    use crate::diagnostics::{EffectDiagnosticBuilder, RelatedLocation};
+
+    #[test]
+    fn test_sarif_rules_and_code_flows() {
+        let source = "snippet id=\"app.main\" kind=\"fn\"\nend\nsnippet id=\"db.query\" kind=\"extern\"\nend\n";
+        let diagnostic = EffectDiagnosticBuilder::missing_effect(
+            "app.main".to_string(),
+            vec!["database".to_string()],
+            "db.query".to_string(),
+            Span::new(0, 36),
+        )
+        .with_related(vec![
+            RelatedLocation {
+                message: "`app.main` calls `db.query`".to_string(),
+                span: Span::new(0, 36),
+                label: "called from here".to_string(),
+            },
+            RelatedLocation {
+                message: "`db.query` declares `database`".to_string(),
+                span: Span::new(37, 78),
+                label: "declares effects: database".to_string(),
+            },
+        ])
+        .build();
+
+        let mut sources = SourceMap::new();
+        let file = sources.add("src/main.cov", source);
+        let log = sarif_log(&[diagnostic.to_json(&sources, file)]);
+        assert_eq!(log["version"], "2.1.0");
+
+        let run = &log["runs"][0];
+        let rule = &run["tool"]["driver"]["rules"][0];
+        assert_eq!(rule["id"], "E-EFFECT-002");
+        assert_eq!(rule["name"], "MissingEffectDeclaration");
+        assert!(rule["help"]["text"]
+            .as_str()
+            .unwrap()
+            .ends_with("ERROR_CODES.md#e-effect-002-missing-effect-declaration"));
+
+        let result = &run["results"][0];
+        assert_eq!(result["ruleId"], "E-EFFECT-002");
+        assert_eq!(result["level"], "error");
+        let location = &result["locations"][0];
+        assert_eq!(location["physicalLocation"]["artifactLocation"]["uri"], "src/main.cov");
+        assert_eq!(location["physicalLocation"]["region"]["startLine"], 1);
+        assert_eq!(location["logicalLocations"][0]["fullyQualifiedName"], "app.main");
+
+        let flow = &result["codeFlows"][0]["threadFlows"][0]["locations"];
+        assert_eq!(flow.as_array().unwrap().len(), 2);
+        assert_eq!(flow[1]["location"]["physicalLocation"]["region"]["startLine"], 3);
+    }
+
+    #[test]
+    fn test_unregistered_code_and_note_level() {
+        let diagnostic = crate::Diagnostic::new(
+            crate::DiagnosticKind::Message("custom".to_string()),
+            Span::dummy(),
+            "E-CUSTOM-001",
+            "",
+        )
+        .with_severity(Severity::Info);
+        let mut sources = SourceMap::new();
+        let file = sources.add("/abs/file.cov", "");
+        let log = sarif_log(&[diagnostic.to_json(&sources, file)]);
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][0], json!({ "id": "E-CUSTOM-001" }));
+        assert_eq!(run["results"][0]["level"], "note");
+        let location = &run["results"][0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "file:///abs/file.cov");
+        assert!(location.get("region").is_none());
+    }
+}
@@ -0,0 +1,197 @@
+//! Scoping of step bindings within a function body
+//!
+//! Bindings follow SSA discipline: each name is bound once. The steps of an
+//! `if` branch, `for` body, `match` or `handle` case and concurrent branch form
+//! a scope whose bindings end with it. The one exception is a loop-carried
+//! value: a step inside a `for` body may rebind a name bound outside the loop,
+//! updating it for the next iteration.
+
+use std::collections::{HashMap, HashSet};
+
+use covenant_ast::{walk_steps, MatchPattern, Step, StepKind};
+
+/// Where a name was bound
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum BindingSite {
+    Param,
+    Step(String),
+}
+
+/// Outcome of binding a name in the current scope
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Rebinding {
+    /// The name was not in scope
+    New,
+    /// The name was bound outside an enclosing loop and is updated by it
+    LoopCarried,
+    /// The name is already bound in scope
+    Duplicate(BindingSite),
+}
+
+/// Why a name used by a step is not in scope
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Unbound {
+    /// Bound only inside the branch owned by this step, which has ended
+    BranchLocal(String),
+    /// Bound by this later step
+    NotYetDefined(String),
+    /// Not bound anywhere in the body
+    Undefined,
+}
+
+#[derive(Debug)]
+struct Frame {
+    /// Step owning the scope (the `if`, `for`, `match` or call step)
+    owner: String,
+    is_loop: bool,
+    /// Names first bound in this scope, dropped when it ends
+    introduced: Vec<String>,
+}
+
+/// Tracks which bindings are in scope while checking a function body
+#[derive(Debug, Default)]
+pub(crate) struct BindingScopes {
+    frames: Vec<Frame>,
+    /// Names in scope, with their binding site and the depth they were bound at
+    bound: HashMap<String, (BindingSite, usize)>,
+    /// Names whose scope has ended, with the step that owned it
+    expired: HashMap<String, String>,
+    /// Every name bound anywhere in the body, with the first step binding it
+    declared: HashMap<String, String>,
+}
+
+impl BindingScopes {
+    /// Start a function body with its parameters in scope
+    pub fn new<'a>(params: impl IntoIterator<Item = &'a str>, steps: &[Step]) -> Self {
+        let mut scopes = Self::default();
+        for param in params {
+            scopes.bound.insert(param.to_string(), (BindingSite::Param, 0));
+        }
+        walk_steps(steps, &mut |step| {
+            for name in step_bindings(step) {
+                scopes.declared.entry(name.to_string()).or_insert_with(|| step.id.clone());
+            }
+        });
+        scopes
+    }
+
+    /// Open a nested scope owned by step `owner`
+    pub fn enter(&mut self, owner: &str, is_loop: bool) {
+        self.frames.push(Frame {
+            owner: owner.to_string(),
+            is_loop,
+            introduced: Vec::new(),
+        });
+    }
+
+    /// Close the innermost scope, returning the names that went out of scope
+    pub fn exit(&mut self) -> Vec<String> {
+        let Some(frame) = self.frames.pop() else {
+            return Vec::new();
+        };
+        for name in &frame.introduced {
+            self.bound.remove(name);
+            self.expired.insert(name.clone(), frame.owner.clone());
+        }
+        frame.introduced
+    }
+
+    /// Bind a name in the current scope
+    pub fn bind(&mut self, name: &str, site: BindingSite) -> Rebinding {
+        let depth = self.frames.len();
+        if let Some((first, first_depth)) = self.bound.get(name) {
+            let crosses_loop = self.frames[*first_depth..].iter().any(|f| f.is_loop);
+            return if crosses_loop {
+                Rebinding::LoopCarried
+            } else {
+                Rebinding::Duplicate(first.clone())
+            };
+        }
+        self.bound.insert(name.to_string(), (site, depth));
+        self.expired.remove(name);
+        if let Some(frame) = self.frames.last_mut() {
+            frame.introduced.push(name.to_string());
+        }
+        Rebinding::New
+    }
+
+    /// Explain why a name is not in scope
+    pub fn unbound(&self, name: &str) -> Unbound {
+        if let Some(owner) = self.expired.get(name) {
+            Unbound::BranchLocal(owner.clone())
+        } else if let Some(step) = self.declared.get(name) {
+            Unbound::NotYetDefined(step.clone())
+        } else {
+            Unbound::Undefined
+        }
+    }
+}
+
+/// Names a step binds: its output, a loop variable, or match bindings
+fn step_bindings(step: &Step) -> Vec<&str> {
+    let mut names = Vec::new();
+    if step.output_binding != "_" {
+        names.push(step.output_binding.as_str());
+    }
+    match &step.kind {
+        StepKind::For(for_step) => names.push(for_step.var.as_str()),
+        StepKind::Match(match_step) => {
+            for case in &match_step.cases {
+                if let MatchPattern::Variant { bindings, .. } = &case.pattern {
+                    names.extend(bindings.iter().map(String::as_str));
+                }
+            }
+        }
+        _ => {}
+    }
+    names
+}
+
+/// Steps whose ID repeats an earlier step's ID in the same body
+pub(crate) fn duplicate_step_ids(steps: &[Step]) -> Vec<&Step> {
+    let mut seen = HashSet::new();
+    let mut duplicates = Vec::new();
+    walk_steps(steps, &mut |step| {
+        if !seen.insert(step.id.as_str()) {
+            duplicates.push(step);
+        }
+    });
+    duplicates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rebinding_outside_loop_is_duplicate() {
+        let mut scopes = BindingScopes::new(["n"], &[]);
+        assert_eq!(scopes.bind("n", BindingSite::Step("s1".into())), Rebinding::Duplicate(BindingSite::Param));
+        assert_eq!(scopes.bind("total", BindingSite::Step("s1".into())), Rebinding::New);
+
+        scopes.enter("s2", false);
+        assert_eq!(
+            scopes.bind("total", BindingSite::Step("s2a".into())),
+            Rebinding::Duplicate(BindingSite::Step("s1".into()))
+        );
+        scopes.exit();
+
+        scopes.enter("s3", true);
+        scopes.enter("s3a", false);
+        assert_eq!(scopes.bind("total", BindingSite::Step("s3b".into())), Rebinding::LoopCarried);
+    }
+
+    #[test]
+    fn test_branch_bindings_expire() {
+        let mut scopes = BindingScopes::new([], &[]);
+        scopes.enter("s1", false);
+        assert_eq!(scopes.bind("x", BindingSite::Step("s1a".into())), Rebinding::New);
+        assert_eq!(scopes.exit(), vec!["x".to_string()]);
+        assert_eq!(scopes.unbound("x"), Unbound::BranchLocal("s1".into()));
+        assert_eq!(scopes.unbound("y"), Unbound::Undefined);
+
+        // A sibling branch may bind the same name again
+        scopes.enter("s2", false);
+        assert_eq!(scopes.bind("x", BindingSite::Step("s2a".into())), Rebinding::New);
+    }
+}
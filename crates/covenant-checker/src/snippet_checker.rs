@@ -5,12 +5,13 @@
 use std::collections::{HashMap, HashSet};
 use covenant_ast::{
     Snippet, SnippetKind, Section, SignatureKind, BodySection,
-    Step, StepKind, ComputeStep, Operation, Input, InputSource, CallStep,
+    Step, StepKind, ComputeStep, Operation, Input, InputSource, CallStep, HandleBlock,
     ReturnStep, ReturnValue, IfStep, ForStep, BindStep, BindSource, MatchStep, MatchPattern,
     FunctionSignature, ReturnType, Type, TypeKind, Literal, QueryStep, QueryContent,
-    StructSignature, EnumSignature, StructConstruction,
+    StructSignature, EnumSignature, StructConstruction, VariantConstruction, FieldAssignment, Span,
 };
-use crate::{CheckError, CheckResult, ResolvedType, SymbolTable, SymbolKind, EffectTable, TypeRegistry, VariantDef};
+use crate::{CheckError, CheckResult, ResolvedType, Symbol, SymbolTable, SymbolKind, EffectTable, TypeRegistry, VariantDef};
+use crate::scope::{self, BindingScopes, BindingSite, Rebinding, Unbound};
 
 /// Checker for snippet-mode programs
 pub struct SnippetChecker {
@@ -25,6 +26,16 @@ pub struct SnippetChecker {
     type_registry: TypeRegistry,
     /// Expected return type for current function being checked
     current_return_type: Option<ResolvedType>,
+    /// Type parameters of generic functions, by function name and snippet ID
+    generic_functions: HashMap<String, Vec<String>>,
+    /// Type parameters in scope while resolving a signature or body
+    type_params: Vec<String>,
+    /// Snippet ID of the function being checked
+    current_function: Option<String>,
+    /// Bindings in scope in the function body being checked
+    scopes: BindingScopes,
+    /// ID and span of the step being checked
+    current_step: Option<(String, Span)>,
 }
 
 impl SnippetChecker {
@@ -37,6 +48,11 @@ impl SnippetChecker {
             function_returns: HashMap::new(),
             type_registry: TypeRegistry::new(),
             current_return_type: None,
+            generic_functions: HashMap::new(),
+            type_params: Vec::new(),
+            current_function: None,
+            scopes: BindingScopes::default(),
+            current_step: None,
         };
         checker.register_stdlib();
         checker
@@ -115,12 +131,15 @@ impl SnippetChecker {
     /// Register a function's signature (first pass)
     fn register_function_signature(&mut self, snippet: &Snippet) {
         // Extract signature info without holding references
-        let (fn_name, snippet_id, params, return_type, effects) = {
+        let (fn_name, snippet_id, params, return_type, effects, generics) = {
             let sig = match find_function_signature(snippet) {
                 Some(s) => s,
                 None => return,
             };
 
+            let generics: Vec<String> = sig.generics.iter().map(|g| g.name.clone()).collect();
+            self.type_params = generics.clone();
+
             let return_type = sig.returns.as_ref()
                 .map(|r| self.resolve_return_type(r))
                 .unwrap_or(ResolvedType::None);
@@ -130,10 +149,16 @@ impl SnippetChecker {
                 .collect();
 
             let effects = collect_snippet_effects(snippet);
+            self.type_params.clear();
 
-            (sig.name.clone(), snippet.id.clone(), params, return_type, effects)
+            (sig.name.clone(), snippet.id.clone(), params, return_type, effects, generics)
         };
 
+        if !generics.is_empty() {
+            self.generic_functions.insert(fn_name.clone(), generics.clone());
+            self.generic_functions.insert(snippet_id.clone(), generics);
+        }
+
         // Register by short name (for backwards compatibility)
         self.symbols.define(
             fn_name.clone(),
@@ -172,6 +197,8 @@ impl SnippetChecker {
                 None => return, // External functions may not have bodies
             };
 
+            self.type_params = sig.generics.iter().map(|g| g.name.clone()).collect();
+
             let params_info: Vec<(String, ResolvedType)> = sig.params.iter()
                 .map(|p| (p.name.clone(), self.resolve_type(&p.ty)))
                 .collect();
@@ -182,7 +209,33 @@ impl SnippetChecker {
             (params_info, body.steps.clone(), expected_return)
         };
 
+        // Step IDs must be unique within the snippet
+        for step in scope::duplicate_step_ids(&steps_cloned) {
+            self.errors.push(CheckError::DuplicateStepId {
+                id: step.id.clone(),
+                snippet: snippet.id.clone(),
+                span: step.span,
+            });
+        }
+
+        // Effect variables must name function-typed parameters
+        for var in snippet.sections.iter().filter_map(|s| match s {
+            Section::Effects(effects) => Some(&effects.vars),
+            _ => None,
+        }).flatten() {
+            let is_function_param = params_info.iter()
+                .any(|(name, ty)| *name == var.param && matches!(ty, ResolvedType::Function { .. }));
+            if !is_function_param {
+                self.errors.push(CheckError::InvalidEffectVariable {
+                    function: snippet.id.clone(),
+                    param: var.param.clone(),
+                    span: var.span,
+                });
+            }
+        }
+
         // Set up local scope with parameters
+        self.scopes = BindingScopes::new(params_info.iter().map(|(name, _)| name.as_str()), &steps_cloned);
         self.locals.clear();
         for (name, ty) in params_info {
             self.locals.insert(name, ty);
@@ -190,31 +243,119 @@ impl SnippetChecker {
 
         // Set expected return type for this function
         self.current_return_type = expected_return;
+        self.current_function = Some(snippet.id.clone());
 
         // Check each step
         for step in &steps_cloned {
             self.check_step(step);
         }
 
-        // Clear expected return type after checking
+        // Clear per-function state after checking
         self.current_return_type = None;
+        self.current_function = None;
+        self.type_params.clear();
     }
 
     /// Check a single step and add its binding to locals
     fn check_step(&mut self, step: &Step) {
+        let outer_step = self.current_step.replace((step.id.clone(), step.span));
         let step_type = self.infer_step_type(step);
+        if let Some(function) = &self.current_function {
+            self.symbols.record_step_type(function, &step.id, step_type.clone());
+        }
 
         // Add binding to locals if not discarded
         if step.output_binding != "_" {
-            self.locals.insert(step.output_binding.clone(), step_type);
+            self.bind_local(&step.output_binding, step_type, &step.id, step.span);
+        }
+        self.current_step = outer_step;
+    }
+
+    /// Bind a name in the current scope, reporting it if already bound
+    fn bind_local(&mut self, name: &str, ty: ResolvedType, step_id: &str, span: Span) {
+        match self.scopes.bind(name, BindingSite::Step(step_id.to_string())) {
+            Rebinding::New | Rebinding::LoopCarried => {
+                self.locals.insert(name.to_string(), ty);
+            }
+            Rebinding::Duplicate(first) => {
+                self.errors.push(CheckError::DuplicateBinding {
+                    name: name.to_string(),
+                    step_id: step_id.to_string(),
+                    first: match first {
+                        BindingSite::Param => None,
+                        BindingSite::Step(id) => Some(id),
+                    },
+                    span,
+                });
+            }
         }
     }
 
+    /// Snippet ID of the function being checked
+    fn caller(&self) -> String {
+        self.current_function.clone().unwrap_or_default()
+    }
+
+    /// Span of the step being checked
+    fn step_span(&self) -> Span {
+        self.current_step.as_ref().map(|(_, span)| *span).unwrap_or_else(Span::dummy)
+    }
+
+    /// Report a use of a name that is not in scope in the current step
+    fn report_unbound(&mut self, name: &str) {
+        let (step_id, span) = self.current_step.clone()
+            .unwrap_or_else(|| (String::new(), Span::dummy()));
+        let error = match self.scopes.unbound(name) {
+            Unbound::BranchLocal(branch_step) => CheckError::BranchLocalBinding {
+                name: name.to_string(),
+                step_id,
+                branch_step,
+                span,
+            },
+            Unbound::NotYetDefined(defined_by) => CheckError::UseBeforeDefinition {
+                name: name.to_string(),
+                step_id,
+                defined_by,
+                span,
+            },
+            Unbound::Undefined => CheckError::UndefinedSymbol { name: name.to_string() },
+        };
+        self.errors.push(error);
+    }
+
+    /// Check steps nested in the current step (a branch, loop body or case)
+    /// in their own scope, with `bindings` bound on entry. Returns the type
+    /// of the last step's output binding, if it has one.
+    fn check_nested_steps(
+        &mut self,
+        is_loop: bool,
+        bindings: Vec<(String, ResolvedType)>,
+        steps: &[Step],
+    ) -> Option<ResolvedType> {
+        let (owner, span) = self.current_step.clone()
+            .unwrap_or_else(|| (String::new(), Span::dummy()));
+        self.scopes.enter(&owner, is_loop);
+        for (name, ty) in bindings {
+            self.bind_local(&name, ty, &owner, span);
+        }
+
+        let mut last_type = None;
+        for step in steps {
+            self.check_step(step);
+            last_type = self.locals.get(&step.output_binding).cloned();
+        }
+
+        for name in self.scopes.exit() {
+            self.locals.remove(&name);
+        }
+        last_type
+    }
+
     /// Infer the type of a step
     fn infer_step_type(&mut self, step: &Step) -> ResolvedType {
         match &step.kind {
             StepKind::Compute(compute) => self.infer_compute_step(compute),
-            StepKind::Call(call) => self.infer_call_step(call),
+            StepKind::Call(call) => self.infer_call_step(&step.id, call),
             StepKind::Return(ret) => self.infer_return_step(ret),
             StepKind::If(if_step) => self.infer_if_step(if_step),
             StepKind::Bind(bind) => self.infer_bind_step(bind),
@@ -230,18 +371,14 @@ impl SnippetChecker {
             StepKind::Parallel(parallel) => {
                 // For parallel, check all branches and return a collection of their results
                 for branch in &parallel.branches {
-                    for step in &branch.steps {
-                        self.check_step(step);
-                    }
+                    self.check_nested_steps(false, Vec::new(), &branch.steps);
                 }
                 ResolvedType::Unknown // TODO: infer tuple/struct of branch results
             }
             StepKind::Race(race) => {
                 // For race, the result is whichever branch finishes first
                 for branch in &race.branches {
-                    for step in &branch.steps {
-                        self.check_step(step);
-                    }
+                    self.check_nested_steps(false, Vec::new(), &branch.steps);
                 }
                 ResolvedType::Unknown // TODO: infer union of branch result types
             }
@@ -430,18 +567,304 @@ impl SnippetChecker {
         }
     }
 
-    /// Infer type of a call step
-    fn infer_call_step(&mut self, call: &CallStep) -> ResolvedType {
-        // Look up function return type
-        if let Some(return_type) = self.function_returns.get(&call.fn_name) {
-            return_type.clone()
-        } else if let Some(symbol) = self.symbols.lookup(&call.fn_name) {
-            symbol.ty.clone()
-        } else {
-            self.errors.push(CheckError::UndefinedSymbol {
-                name: call.fn_name.clone(),
+    /// Infer type of a call step, checking its arguments against the callee's parameters
+    fn infer_call_step(&mut self, step_id: &str, call: &CallStep) -> ResolvedType {
+        if let Some(ResolvedType::Function { params, ret }) = self.locals.get(&call.fn_name).cloned() {
+            return self.infer_indirect_call(call, &params, *ret);
+        }
+
+        let (params, return_type) = match self.symbols.lookup(&call.fn_name) {
+            Some(Symbol { kind: SymbolKind::Function { params, .. }, ty, .. }) => {
+                (params.clone(), ty.clone())
+            }
+            Some(symbol) => return symbol.ty.clone(),
+            None => {
+                self.errors.push(CheckError::UndefinedSymbol {
+                    name: call.fn_name.clone(),
+                });
+                return ResolvedType::Error;
+            }
+        };
+        let return_type = self.function_returns.get(&call.fn_name).cloned().unwrap_or(return_type);
+        let return_type = match &call.handle {
+            Some(handle) => self.check_handle_block(&call.fn_name, handle, return_type),
+            None => return_type,
+        };
+        let return_type = self.strip_propagated_errors(return_type);
+
+        let arg_types = self.match_call_args(call, &params);
+
+        // Bind type parameters of generic callees from the argument types
+        let mut bindings = HashMap::new();
+        if let Some(generics) = self.generic_functions.get(&call.fn_name).cloned() {
+            for (_, param_type, arg_type, _) in &arg_types {
+                self.bind_type_params(&call.fn_name, param_type, arg_type, &mut bindings);
+            }
+            let type_args = self.collect_type_args(&call.fn_name, &generics, &bindings);
+            if let Some(caller) = &self.current_function {
+                self.symbols.record_type_args(caller, step_id, type_args);
+            }
+        }
+
+        for (param, param_type, arg_type, span) in arg_types {
+            let expected = param_type.substitute(&bindings);
+            if !self.types_compatible(&expected, &arg_type) {
+                self.errors.push(CheckError::ArgumentTypeMismatch {
+                    function: call.fn_name.clone(),
+                    param,
+                    expected: expected.display(),
+                    found: arg_type.display(),
+                    caller: self.caller(),
+                    span,
+                });
+            }
+        }
+
+        return_type.substitute(&bindings)
+    }
+
+    /// Infer type of a call through a function-typed local. Function types
+    /// have no parameter names, so arguments are matched by position.
+    fn infer_indirect_call(
+        &mut self,
+        call: &CallStep,
+        params: &[ResolvedType],
+        return_type: ResolvedType,
+    ) -> ResolvedType {
+        if call.args.len() != params.len() {
+            self.errors.push(CheckError::ArgumentCountMismatch {
+                function: call.fn_name.clone(),
+                expected: params.len(),
+                found: call.args.len(),
+                caller: self.caller(),
+                span: self.step_span(),
             });
-            ResolvedType::Error
+        }
+        for (arg, expected) in call.args.iter().zip(params) {
+            let arg_type = self.resolve_source_type(&arg.source);
+            if !self.types_compatible(expected, &arg_type) {
+                self.errors.push(CheckError::ArgumentTypeMismatch {
+                    function: call.fn_name.clone(),
+                    param: arg.name.clone(),
+                    expected: expected.display(),
+                    found: arg_type.display(),
+                    caller: self.caller(),
+                    span: arg.span,
+                });
+            }
+        }
+        let return_type = match &call.handle {
+            Some(handle) => self.check_handle_block(&call.fn_name, handle, return_type),
+            None => return_type,
+        };
+        self.strip_propagated_errors(return_type)
+    }
+
+    /// Check the cases of a call's handle block against the callee's error
+    /// members and return the call result with the handled members removed.
+    /// A case may name a whole error type or a single variant of one; only
+    /// whole types narrow the result.
+    fn check_handle_block(
+        &mut self,
+        fn_name: &str,
+        handle: &HandleBlock,
+        return_type: ResolvedType,
+    ) -> ResolvedType {
+        let errors: Vec<ResolvedType> = match &return_type {
+            ResolvedType::Union(members) => members.iter().skip(1).cloned().collect(),
+            _ => Vec::new(),
+        };
+        let error_names: Vec<String> = errors.iter().map(|e| e.display()).collect();
+
+        let mut handled = HashSet::new();
+        for case in &handle.cases {
+            let enum_name = case.error_type.split("::").next().unwrap_or(&case.error_type);
+            if error_names.contains(&case.error_type) {
+                handled.insert(case.error_type.clone());
+            } else if !error_names.iter().any(|name| name == enum_name) {
+                self.errors.push(CheckError::UnhandleableErrorType {
+                    function: fn_name.to_string(),
+                    error_type: case.error_type.clone(),
+                    errors: error_names.clone(),
+                    span: case.span,
+                });
+            }
+            self.check_nested_steps(false, Vec::new(), &case.steps);
+        }
+
+        let ResolvedType::Union(members) = return_type else {
+            return return_type;
+        };
+        let mut remaining: Vec<ResolvedType> = members.iter().skip(1)
+            .filter(|member| !handled.contains(&member.display()))
+            .cloned()
+            .collect();
+        if remaining.is_empty() {
+            members[0].clone()
+        } else {
+            remaining.insert(0, members[0].clone());
+            ResolvedType::Union(remaining)
+        }
+    }
+
+    /// Remove the error members of a union call result that propagate to the
+    /// caller. The first member of a union is the success value; any later
+    /// member the caller's own return type accepts is returned early.
+    fn strip_propagated_errors(&self, ty: ResolvedType) -> ResolvedType {
+        let (ResolvedType::Union(members), Some(caller_return)) = (&ty, &self.current_return_type) else {
+            return ty;
+        };
+        let Some((success, errors)) = members.split_first() else {
+            return ty;
+        };
+        let mut remaining: Vec<ResolvedType> = errors.iter()
+            .filter(|e| !self.types_compatible(caller_return, e))
+            .cloned()
+            .collect();
+        if remaining.is_empty() {
+            success.clone()
+        } else {
+            remaining.insert(0, success.clone());
+            ResolvedType::Union(remaining)
+        }
+    }
+
+    /// Match a call's named arguments to the callee's parameters, reporting
+    /// unknown and missing arguments. Returns (param name, param type,
+    /// argument type) for each matched argument.
+    fn match_call_args(
+        &mut self,
+        call: &CallStep,
+        params: &[(String, ResolvedType)],
+    ) -> Vec<(String, ResolvedType, ResolvedType, Span)> {
+        let expected: Vec<String> = params.iter()
+            .map(|(name, ty)| format!("{}: {}", name, ty.display()))
+            .collect();
+
+        let mut matched = Vec::new();
+        for (i, arg) in call.args.iter().enumerate() {
+            if call.args[..i].iter().any(|earlier| earlier.name == arg.name) {
+                self.errors.push(CheckError::DuplicateArgument {
+                    function: call.fn_name.clone(),
+                    arg: arg.name.clone(),
+                    caller: self.caller(),
+                    span: arg.span,
+                });
+                continue;
+            }
+            let arg_type = self.resolve_source_type(&arg.source);
+            match params.iter().find(|(name, _)| *name == arg.name) {
+                Some((name, param_type)) => {
+                    matched.push((name.clone(), param_type.clone(), arg_type, arg.span));
+                }
+                None => self.errors.push(CheckError::UnknownArgument {
+                    function: call.fn_name.clone(),
+                    arg: arg.name.clone(),
+                    expected: expected.clone(),
+                    caller: self.caller(),
+                    span: arg.span,
+                }),
+            }
+        }
+
+        for (name, _) in params {
+            if !call.args.iter().any(|arg| arg.name == *name) {
+                self.errors.push(CheckError::MissingArgument {
+                    function: call.fn_name.clone(),
+                    param: name.clone(),
+                    expected: expected.clone(),
+                    caller: self.caller(),
+                    span: self.step_span(),
+                });
+            }
+        }
+
+        matched
+    }
+
+    /// Type arguments of a generic call, in declaration order. Parameters
+    /// the arguments did not determine are reported and left as errors.
+    fn collect_type_args(
+        &mut self,
+        function: &str,
+        generics: &[String],
+        bindings: &HashMap<String, ResolvedType>,
+    ) -> Vec<ResolvedType> {
+        let mut type_args = Vec::new();
+        for param in generics {
+            match bindings.get(param) {
+                Some(ty) => type_args.push(ty.clone()),
+                None => {
+                    self.errors.push(CheckError::UninferredTypeParameter {
+                        function: function.to_string(),
+                        param: param.clone(),
+                    });
+                    type_args.push(ResolvedType::Error);
+                }
+            }
+        }
+        type_args
+    }
+
+    /// Bind type parameters appearing in `param` to the matching parts of `arg`
+    fn bind_type_params(
+        &mut self,
+        function: &str,
+        param: &ResolvedType,
+        arg: &ResolvedType,
+        bindings: &mut HashMap<String, ResolvedType>,
+    ) {
+        match (param, arg) {
+            // Nothing to learn from unknown or erroneous arguments
+            (_, ResolvedType::Unknown) | (_, ResolvedType::Error) => {}
+
+            (ResolvedType::TypeParam(name), _) => match bindings.get(name) {
+                Some(bound) if !self.types_compatible(bound, arg) => {
+                    self.errors.push(CheckError::ConflictingTypeArgument {
+                        function: function.to_string(),
+                        param: name.clone(),
+                        first: bound.display(),
+                        second: arg.display(),
+                    });
+                }
+                Some(_) => {}
+                None => {
+                    bindings.insert(name.clone(), arg.clone());
+                }
+            },
+
+            (ResolvedType::List(p), ResolvedType::List(a))
+            | (ResolvedType::Set(p), ResolvedType::Set(a))
+            | (ResolvedType::Optional(p), ResolvedType::Optional(a)) => {
+                self.bind_type_params(function, p, a, bindings);
+            }
+            (ResolvedType::Optional(_), ResolvedType::None) => {}
+            (ResolvedType::Optional(p), a) => self.bind_type_params(function, p, a, bindings),
+
+            (
+                ResolvedType::Named { name: n1, args: a1, .. },
+                ResolvedType::Named { name: n2, args: a2, .. },
+            ) if n1 == n2 && a1.len() == a2.len() => {
+                for (p, a) in a1.iter().zip(a2) {
+                    self.bind_type_params(function, p, a, bindings);
+                }
+            }
+            (ResolvedType::Tuple(p), ResolvedType::Tuple(a)) if p.len() == a.len() => {
+                for (p, a) in p.iter().zip(a) {
+                    self.bind_type_params(function, p, a, bindings);
+                }
+            }
+            (
+                ResolvedType::Function { params: p1, ret: r1 },
+                ResolvedType::Function { params: p2, ret: r2 },
+            ) if p1.len() == p2.len() => {
+                for (p, a) in p1.iter().zip(p2) {
+                    self.bind_type_params(function, p, a, bindings);
+                }
+                self.bind_type_params(function, r1, r2, bindings);
+            }
+
+            _ => {}
         }
     }
 
@@ -450,16 +873,14 @@ impl SnippetChecker {
         let inferred = match &ret.value {
             ReturnValue::Var(name) => {
                 self.locals.get(name).cloned().unwrap_or_else(|| {
-                    self.errors.push(CheckError::UndefinedSymbol { name: name.clone() });
+                    self.report_unbound(name);
                     ResolvedType::Error
                 })
             }
             ReturnValue::Lit(lit) => self.literal_type(lit),
-            ReturnValue::Struct(s) => {
-                // Return the struct type
-                self.resolve_type(&s.ty)
-            }
+            ReturnValue::Struct(s) => self.infer_construct_step(s),
             ReturnValue::Variant(v) => {
+                self.check_variant_construction(v);
                 // Return a named type for the variant
                 ResolvedType::Named {
                     name: v.ty.clone(),
@@ -492,20 +913,13 @@ impl SnippetChecker {
             _ => ResolvedType::Unknown,
         };
 
-        // Register the iteration variable
-        self.locals.insert(for_step.var.clone(), element_type);
-
-        // Clone steps to avoid borrow issues
-        let body_steps = for_step.steps.clone();
-
-        // Type-check body steps and track the last step's type
-        let mut body_type = ResolvedType::None;
-        for step in &body_steps {
-            self.check_step(step);
-            body_type = self.locals.get(&step.output_binding)
-                .cloned()
-                .unwrap_or(ResolvedType::None);
-        }
+        // Type-check body steps with the iteration variable in scope, and
+        // track the last step's type
+        let body_type = self.check_nested_steps(
+            true,
+            vec![(for_step.var.clone(), element_type)],
+            &for_step.steps,
+        ).unwrap_or(ResolvedType::None);
 
         // For-loop collects body results into a List
         if matches!(body_type, ResolvedType::None) {
@@ -527,48 +941,38 @@ impl SnippetChecker {
                         });
                     }
                 } else {
-                    self.errors.push(CheckError::UndefinedSymbol {
-                        name: name.clone(),
-                    });
+                    self.report_unbound(name);
                 }
             }
             InputSource::Field { of, .. } => {
                 // Field access on a variable (e.g. "entry.is_directory") — check the base exists
                 if self.locals.get(of).is_none() {
-                    self.errors.push(CheckError::UndefinedSymbol {
-                        name: of.clone(),
-                    });
+                    self.report_unbound(of);
                 }
                 // Assume field access produces Bool (used as condition)
             }
             InputSource::Lit(_) => {
                 // Literal conditions are valid (e.g., lit=true)
             }
+            InputSource::FnRef(name) => {
+                let found = self.function_ref_type(name);
+                self.errors.push(CheckError::TypeMismatch {
+                    expected: "Bool".to_string(),
+                    found: found.display(),
+                });
+            }
         }
 
-        // Clone steps to avoid borrow issues
-        let then_steps = if_step.then_steps.clone();
-        let else_steps = if_step.else_steps.clone();
-
         // Check then branch and get last step's type
-        let mut then_type = ResolvedType::None;
-        for step in &then_steps {
-            self.check_step(step);
-            then_type = self.locals.get(&step.output_binding)
-                .cloned()
-                .unwrap_or(ResolvedType::None);
-        }
+        let then_type = self.check_nested_steps(false, Vec::new(), &if_step.then_steps)
+            .unwrap_or(ResolvedType::None);
 
         // Check else branch if present
-        let mut else_type = ResolvedType::None;
-        if let Some(else_steps) = &else_steps {
-            for step in else_steps {
-                self.check_step(step);
-                else_type = self.locals.get(&step.output_binding)
-                    .cloned()
-                    .unwrap_or(ResolvedType::None);
-            }
-        }
+        let else_type = match &if_step.else_steps {
+            Some(else_steps) => self.check_nested_steps(false, Vec::new(), else_steps)
+                .unwrap_or(ResolvedType::None),
+            None => ResolvedType::None,
+        };
 
         // Unify branch types if both produce values
         match (&then_type, &else_type) {
@@ -585,7 +989,7 @@ impl SnippetChecker {
         match &bind.source {
             BindSource::Var(name) => {
                 self.locals.get(name).cloned().unwrap_or_else(|| {
-                    self.errors.push(CheckError::UndefinedSymbol { name: name.clone() });
+                    self.report_unbound(name);
                     ResolvedType::Error
                 })
             }
@@ -602,7 +1006,7 @@ impl SnippetChecker {
                         ResolvedType::Unknown
                     }
                 } else {
-                    self.errors.push(CheckError::UndefinedSymbol { name: of.clone() });
+                    self.report_unbound(of);
                     ResolvedType::Error
                 }
             }
@@ -615,9 +1019,7 @@ impl SnippetChecker {
         let matched_type = if let Some(ty) = self.locals.get(&match_step.on) {
             ty.clone()
         } else {
-            self.errors.push(CheckError::UndefinedSymbol {
-                name: match_step.on.clone(),
-            });
+            self.report_unbound(&match_step.on);
             return ResolvedType::Error;
         };
 
@@ -632,27 +1034,34 @@ impl SnippetChecker {
 
         // Check each case
         for case in &cases {
-            // Add pattern bindings to scope with appropriate types
+            // Add pattern bindings to the case's scope with appropriate types
+            let mut case_bindings = Vec::new();
             if let MatchPattern::Variant { variant, bindings } = &case.pattern {
                 // Try to get the variant's field types from the matched type
                 let variant_name = extract_variant_name(variant);
-                let binding_type = self.get_variant_binding_type(&matched_type, &variant_name);
-
-                for binding in bindings {
-                    self.locals.insert(binding.clone(), binding_type.clone());
+                let binding_types = self.get_variant_binding_types(&matched_type, &variant_name);
+
+                if let Some(types) = &binding_types {
+                    if bindings.len() > types.len() {
+                        self.errors.push(CheckError::TooManyPatternBindings {
+                            variant: variant.clone(),
+                            expected: types.len(),
+                            found: bindings.len(),
+                            span: case.span,
+                        });
+                    }
+                }
+                for (i, binding) in bindings.iter().enumerate() {
+                    let ty = binding_types.as_ref()
+                        .and_then(|types| types.get(i).cloned())
+                        .unwrap_or(ResolvedType::Unknown);
+                    case_bindings.push((binding.clone(), ty));
                 }
             }
 
-            // Check case steps
-            for step in &case.steps {
-                self.check_step(step);
-            }
-
-            // Get the type of the last step (if any) as the case result
-            if let Some(last_step) = case.steps.last() {
-                if let Some(ty) = self.locals.get(&last_step.output_binding) {
-                    case_types.push(ty.clone());
-                }
+            // Check case steps, taking the type of the last step (if any) as the case result
+            if let Some(ty) = self.check_nested_steps(false, case_bindings, &case.steps) {
+                case_types.push(ty);
             }
         }
 
@@ -670,61 +1079,144 @@ impl SnippetChecker {
 
     /// Infer type of a construct step
     fn infer_construct_step(&mut self, construct: &StructConstruction) -> ResolvedType {
+        // Constructing `Enum::Variant` yields a value of the enum
+        if let TypeKind::Named(path) = &construct.ty.kind {
+            if let Some((enum_name, _)) = path.name().split_once("::") {
+                if self.type_registry.get_enum(enum_name).is_some() {
+                    self.check_variant_construction(&VariantConstruction {
+                        ty: path.name().to_string(),
+                        fields: construct.fields.clone(),
+                        span: construct.span,
+                    });
+                    return self.resolve_type_name(enum_name);
+                }
+            }
+        }
+
         // The type of a construct step is the struct type being constructed
-        self.resolve_type(&construct.ty)
+        let ty = self.resolve_type(&construct.ty);
+        if let ResolvedType::Named { name, .. } = &ty {
+            if let Some(def) = self.type_registry.get_struct(name).cloned() {
+                self.check_construction_fields(name, &def.fields, &def.optional_fields, &construct.fields, construct.span);
+            }
+        }
+        ty
     }
 
-    /// Get the binding type for a variant pattern
-    fn get_variant_binding_type(&self, matched_type: &ResolvedType, variant_name: &str) -> ResolvedType {
+    /// Check the fields of a variant construction (`Enum::Variant`, or a
+    /// struct member of a union) against its declaration
+    fn check_variant_construction(&mut self, variant: &VariantConstruction) {
+        if let Some((enum_name, variant_name)) = variant.ty.split_once("::") {
+            let def = self.type_registry.get_enum(enum_name)
+                .and_then(|e| e.variants.iter().find(|v| v.name == variant_name))
+                .cloned();
+            if let Some(def) = def {
+                let declared = def.fields.unwrap_or_default();
+                self.check_construction_fields(&variant.ty, &declared, &def.optional_fields, &variant.fields, variant.span);
+            }
+        } else if let Some(def) = self.type_registry.get_struct(&variant.ty).cloned() {
+            self.check_construction_fields(&variant.ty, &def.fields, &def.optional_fields, &variant.fields, variant.span);
+        }
+    }
+
+    /// Check field assignments against the declared fields of a type:
+    /// every assigned field must exist and have a compatible value, and
+    /// every field that is not `optional` or `auto` must be assigned.
+    fn check_construction_fields(
+        &mut self,
+        type_name: &str,
+        declared: &[(String, ResolvedType)],
+        optional_fields: &[String],
+        fields: &[FieldAssignment],
+        span: Span,
+    ) {
+        for field in fields {
+            let Some((_, field_type)) = declared.iter().find(|(name, _)| *name == field.name) else {
+                self.errors.push(CheckError::UnknownField {
+                    field: field.name.clone(),
+                    type_name: type_name.to_string(),
+                    span: field.span,
+                });
+                continue;
+            };
+            let expected = if optional_fields.contains(&field.name) {
+                ResolvedType::Optional(Box::new(field_type.clone()))
+            } else {
+                field_type.clone()
+            };
+            let found = self.resolve_source_type(&field.value);
+            if !self.types_compatible(&expected, &found) {
+                self.errors.push(CheckError::FieldTypeMismatch {
+                    field: field.name.clone(),
+                    type_name: type_name.to_string(),
+                    expected: expected.display(),
+                    found: found.display(),
+                    span: field.span,
+                });
+            }
+        }
+
+        for (name, _) in declared {
+            if !optional_fields.contains(name) && !fields.iter().any(|f| f.name == *name) {
+                self.errors.push(CheckError::MissingField {
+                    field: name.clone(),
+                    type_name: type_name.to_string(),
+                    span,
+                });
+            }
+        }
+    }
+
+    /// Get the types a variant pattern can bind, by position: one per field
+    /// of an enum variant, or the value itself for a union member or `Some`.
+    /// Returns `None` when the matched type is not known well enough to say.
+    fn get_variant_binding_types(&self, matched_type: &ResolvedType, variant_name: &str) -> Option<Vec<ResolvedType>> {
         match matched_type {
             ResolvedType::Named { name, .. } => {
-                // Look up enum variant in type registry
-                if let Some(enum_def) = self.type_registry.get_enum(name) {
-                    for variant in &enum_def.variants {
-                        if variant.name == variant_name {
-                            // If variant has fields, return the first field's type
-                            // (simplified - real implementation would handle multiple fields)
-                            if let Some(fields) = &variant.fields {
-                                if let Some((_, ty)) = fields.first() {
-                                    return ty.clone();
-                                }
-                            }
-                            return ResolvedType::None;
+                let enum_def = self.type_registry.get_enum(name)?;
+                let variant = enum_def.variants.iter().find(|v| v.name == variant_name)?;
+                Some(variant.fields.iter()
+                    .flatten()
+                    .map(|(field, ty)| {
+                        if variant.optional_fields.contains(field) {
+                            ResolvedType::Optional(Box::new(ty.clone()))
+                        } else {
+                            ty.clone()
                         }
-                    }
-                }
-                ResolvedType::Unknown
+                    })
+                    .collect())
             }
             ResolvedType::Union(types) => {
                 // Find the matching type in the union
-                for ty in types {
-                    if ty.display() == variant_name {
-                        return ty.clone();
-                    }
-                }
-                ResolvedType::Unknown
+                types.iter()
+                    .find(|ty| ty.display() == variant_name)
+                    .map(|ty| vec![ty.clone()])
             }
             ResolvedType::Optional(inner) => {
-                if variant_name == "some" || variant_name == inner.display() {
-                    (**inner).clone()
+                // Examples spell the present case both `some` and `Some`
+                if variant_name.eq_ignore_ascii_case("some") || variant_name == inner.display() {
+                    Some(vec![(**inner).clone()])
                 } else {
-                    ResolvedType::None
+                    Some(Vec::new())
                 }
             }
-            _ => ResolvedType::Unknown,
+            _ => None,
         }
     }
 
     /// Resolve an input's type
     fn resolve_input_type(&mut self, input: &Input) -> ResolvedType {
-        match &input.source {
+        self.resolve_source_type(&input.source)
+    }
+
+    /// Resolve the type of a value source (variable, literal, or field access)
+    fn resolve_source_type(&mut self, source: &InputSource) -> ResolvedType {
+        match source {
             InputSource::Var(name) => {
                 match self.locals.get(name) {
                     Some(ty) => ty.clone(),
                     None => {
-                        self.errors.push(CheckError::UndefinedSymbol {
-                            name: name.clone(),
-                        });
+                        self.report_unbound(name);
                         ResolvedType::Error
                     }
                 }
@@ -744,6 +1236,26 @@ impl SnippetChecker {
                     ResolvedType::Unknown
                 }
             }
+            InputSource::FnRef(name) => self.function_ref_type(name),
+        }
+    }
+
+    /// Type of a function used as a value
+    fn function_ref_type(&mut self, name: &str) -> ResolvedType {
+        match self.symbols.lookup(name) {
+            Some(Symbol { kind: SymbolKind::Function { params, .. }, ty, .. }) => {
+                let ret = self.function_returns.get(name).cloned().unwrap_or_else(|| ty.clone());
+                ResolvedType::Function {
+                    params: params.iter().map(|(_, ty)| ty.clone()).collect(),
+                    ret: Box::new(ret),
+                }
+            }
+            _ => {
+                self.errors.push(CheckError::UndefinedSymbol {
+                    name: name.to_string(),
+                });
+                ResolvedType::Error
+            }
         }
     }
 
@@ -769,6 +1281,9 @@ impl SnippetChecker {
             (ResolvedType::Named { name, .. }, _) if name == "Any" => true,
             (_, ResolvedType::Named { name, .. }) if name == "Any" => true,
 
+            // Type parameters are only compatible with themselves
+            (ResolvedType::TypeParam(a), ResolvedType::TypeParam(b)) => a == b,
+
             // Primitive types
             (ResolvedType::Int, ResolvedType::Int) => true,
             (ResolvedType::Float, ResolvedType::Float) => true,
@@ -791,6 +1306,11 @@ impl SnippetChecker {
             // Set types
             (ResolvedType::Set(e), ResolvedType::Set(f)) => self.types_compatible(e, f),
 
+            // Union to union - every member of the value's union must fit
+            (ResolvedType::Union(_), ResolvedType::Union(members)) => {
+                members.iter().all(|m| self.types_compatible(expected, m))
+            }
+
             // Union types - value must be compatible with at least one member
             (ResolvedType::Union(members), found) => {
                 members.iter().any(|m| self.types_compatible(m, found))
@@ -857,10 +1377,16 @@ impl SnippetChecker {
             "Bytes" => ResolvedType::Bytes,
             "DateTime" => ResolvedType::DateTime,
             _ => {
-                if let Some(bracket_pos) = name.find('<') {
-                    let base_name = &name[..bracket_pos];
+                if let Some(item) = name.strip_suffix("[]") {
+                    ResolvedType::List(Box::new(self.resolve_type_name(item.trim())))
+                } else if let Some(bracket_pos) = name.find('<') {
+                    let base_name = name[..bracket_pos].trim();
                     let args_str = &name[bracket_pos + 1..name.len() - 1];
-                    let inner_type = self.resolve_type_name(args_str);
+                    let args: Vec<ResolvedType> = split_type_args(args_str)
+                        .into_iter()
+                        .map(|arg| self.resolve_type_name(arg))
+                        .collect();
+                    let inner_type = args.first().cloned().unwrap_or(ResolvedType::Unknown);
                     match base_name {
                         "List" => ResolvedType::List(Box::new(inner_type)),
                         "Optional" => ResolvedType::Optional(Box::new(inner_type)),
@@ -868,9 +1394,11 @@ impl SnippetChecker {
                         _ => ResolvedType::Named {
                             name: base_name.to_string(),
                             id: covenant_ast::SymbolId(0),
-                            args: vec![inner_type],
+                            args,
                         },
                     }
+                } else if self.type_params.iter().any(|p| p == name) {
+                    ResolvedType::TypeParam(name.to_string())
                 } else {
                     ResolvedType::Named {
                         name: name.to_string(),
@@ -914,21 +1442,12 @@ impl SnippetChecker {
                         ResolvedType::Set(Box::new(inner))
                     }
                     _ => {
-                        // Handle inline generic syntax (e.g. "List<String>" from attribute parsing)
-                        if let Some(bracket_pos) = name.find('<') {
-                            let base_name = &name[..bracket_pos];
-                            let args_str = &name[bracket_pos + 1..name.len() - 1]; // strip < and >
-                            let inner_type = self.resolve_type_name(args_str);
-                            match base_name {
-                                "List" => ResolvedType::List(Box::new(inner_type)),
-                                "Optional" => ResolvedType::Optional(Box::new(inner_type)),
-                                "Set" => ResolvedType::Set(Box::new(inner_type)),
-                                _ => ResolvedType::Named {
-                                    name: base_name.to_string(),
-                                    id: covenant_ast::SymbolId(0),
-                                    args: vec![inner_type],
-                                },
-                            }
+                        // Handle inline generic and list syntax (e.g. "List<String>" or
+                        // "Json[]" from attribute parsing)
+                        if name.contains('<') || name.ends_with("[]") {
+                            self.resolve_type_name(name)
+                        } else if path.generics.is_empty() && self.type_params.iter().any(|p| p == name) {
+                            ResolvedType::TypeParam(name.to_string())
                         } else {
                             ResolvedType::Named {
                                 name: name.to_string(),
@@ -1012,8 +1531,11 @@ impl SnippetChecker {
             .collect();
 
         // Register in type registry
-        self.type_registry
-            .register_struct(struct_sig.name.clone(), fields.clone());
+        self.type_registry.register_struct(
+            struct_sig.name.clone(),
+            fields.clone(),
+            omittable_fields(&struct_sig.fields),
+        );
 
         // Also register as a symbol
         self.symbols.define(
@@ -1041,6 +1563,7 @@ impl SnippetChecker {
                         .map(|f| (f.name.clone(), self.resolve_type(&f.ty)))
                         .collect()
                 }),
+                optional_fields: v.fields.as_deref().map(omittable_fields).unwrap_or_default(),
             })
             .collect();
 
@@ -1236,6 +1759,35 @@ fn find_function_signature(snippet: &Snippet) -> Option<&FunctionSignature> {
     None
 }
 
+/// Names of declared fields that a construction may leave out
+fn omittable_fields(fields: &[covenant_ast::SnippetFieldDecl]) -> Vec<String> {
+    fields.iter()
+        .filter(|f| f.optional || f.auto)
+        .map(|f| f.name.clone())
+        .collect()
+}
+
+/// Split the arguments of an inline generic type (`String, List<Int>`) at
+/// top-level commas
+fn split_type_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts
+}
+
 /// Find the body section in a snippet
 fn find_body_section(snippet: &Snippet) -> Option<&BodySection> {
     for section in &snippet.sections {
@@ -1310,6 +1862,7 @@ mod tests {
                 ("id".to_string(), ResolvedType::Int),
                 ("name".to_string(), ResolvedType::String),
             ],
+            vec![],
         );
 
         let user = registry.get_struct("User");
@@ -1332,10 +1885,12 @@ mod tests {
                 VariantDef {
                     name: "Ok".to_string(),
                     fields: Some(vec![("value".to_string(), ResolvedType::Int)]),
+                    optional_fields: vec![],
                 },
                 VariantDef {
                     name: "Err".to_string(),
                     fields: Some(vec![("error".to_string(), ResolvedType::String)]),
+                    optional_fields: vec![],
                 },
             ],
         );
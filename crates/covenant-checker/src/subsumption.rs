@@ -0,0 +1,366 @@
+//! Effect parameter subsumption
+//!
+//! A declared effect parameter covers a required one when the declared value
+//! subsumes the required value under the parameter's rule. Built-in effects
+//! have fixed rules (e.g. `filesystem(path=...)` compares whole path segments);
+//! custom effects declare theirs on `kind="effect"` snippets:
+//!
+//! ```text
+//! snippet id="queue" kind="effect"
+//! signature
+//!   effect name="queue"
+//!     param name="topic" rule="glob"
+//!   end
+//! end
+//! end
+//! ```
+//!
+//! Parameters without a rule require exact equality.
+
+use std::collections::HashMap;
+use covenant_ast::{Literal, SubsumptionRule};
+use covenant_symbols::{SymbolGraph, SymbolKind};
+
+/// Rules for the parameters of built-in effects
+const BUILTIN_RULES: &[(&str, &str, SubsumptionRule)] = &[
+    ("filesystem", "path", SubsumptionRule::Path),
+    ("network", "host", SubsumptionRule::Url),
+    ("network", "url", SubsumptionRule::Url),
+    ("network", "port", SubsumptionRule::Range),
+];
+
+/// Subsumption rules by effect and parameter name
+#[derive(Debug, Clone)]
+pub struct SubsumptionRules {
+    rules: HashMap<(String, String), SubsumptionRule>,
+}
+
+impl Default for SubsumptionRules {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl SubsumptionRules {
+    /// Rules for the built-in effects only
+    pub fn builtin() -> Self {
+        let rules = BUILTIN_RULES
+            .iter()
+            .map(|(effect, param, rule)| ((effect.to_string(), param.to_string()), *rule))
+            .collect();
+        Self { rules }
+    }
+
+    /// Built-in rules plus those declared by effect snippets in the graph.
+    /// A declared rule overrides the built-in one for the same parameter.
+    pub fn from_graph(graph: &SymbolGraph) -> Self {
+        let mut rules = Self::builtin();
+        for symbol in graph.iter().filter(|s| s.kind == SymbolKind::Effect) {
+            for param in &symbol.effect_params {
+                rules.rules.insert((symbol.name.clone(), param.name.clone()), param.rule);
+            }
+        }
+        rules
+    }
+
+    /// The rule for a parameter of an effect
+    pub fn rule_for(&self, effect: &str, param: &str) -> SubsumptionRule {
+        self.rules
+            .get(&(effect.to_string(), param.to_string()))
+            .copied()
+            .unwrap_or(SubsumptionRule::Exact)
+    }
+
+    /// Check if a declared parameter value subsumes a required one
+    pub fn subsumes(&self, effect: &str, param: &str, declared: &Literal, required: &Literal) -> bool {
+        rule_subsumes(self.rule_for(effect, param), declared, required)
+    }
+}
+
+/// Check if `declared` subsumes `required` under a rule
+pub fn rule_subsumes(rule: SubsumptionRule, declared: &Literal, required: &Literal) -> bool {
+    match rule {
+        SubsumptionRule::Exact => literals_equal(declared, required),
+        SubsumptionRule::Range => match (parse_range(declared), parse_range(required)) {
+            (Some((d_min, d_max)), Some((r_min, r_max))) => d_min <= r_min && r_max <= d_max,
+            _ => literals_equal(declared, required),
+        },
+        SubsumptionRule::Set => match (literal_text(declared), literal_text(required)) {
+            (Some(d), Some(r)) => {
+                let allowed: Vec<&str> = d.split(',').map(str::trim).collect();
+                r.split(',').map(str::trim).all(|v| allowed.contains(&v))
+            }
+            _ => literals_equal(declared, required),
+        },
+        SubsumptionRule::Path | SubsumptionRule::Glob | SubsumptionRule::Url => {
+            match (declared, required) {
+                (Literal::String(d), Literal::String(r)) => match rule {
+                    SubsumptionRule::Path => path_subsumes(d, r),
+                    SubsumptionRule::Glob => glob_match(d, r),
+                    _ => url_subsumes(d, r),
+                },
+                _ => literals_equal(declared, required),
+            }
+        }
+    }
+}
+
+fn literals_equal(declared: &Literal, required: &Literal) -> bool {
+    match (declared, required) {
+        (Literal::Int(d), Literal::Int(r)) => d == r,
+        (Literal::Float(d), Literal::Float(r)) => (d - r).abs() < f64::EPSILON,
+        (Literal::String(d), Literal::String(r)) => d == r,
+        (Literal::Bool(d), Literal::Bool(r)) => d == r,
+        (Literal::None, Literal::None) => true,
+        _ => false,
+    }
+}
+
+fn literal_text(lit: &Literal) -> Option<String> {
+    match lit {
+        Literal::String(s) => Some(s.clone()),
+        Literal::Int(i) => Some(i.to_string()),
+        Literal::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Parse a number or an inclusive range `min..max`; either bound may be omitted
+fn parse_range(lit: &Literal) -> Option<(f64, f64)> {
+    match lit {
+        Literal::Int(i) => Some((*i as f64, *i as f64)),
+        Literal::Float(f) => Some((*f, *f)),
+        Literal::String(s) => match s.split_once("..") {
+            Some((min, max)) => {
+                let min = if min.trim().is_empty() { f64::NEG_INFINITY } else { min.trim().parse().ok()? };
+                let max = if max.trim().is_empty() { f64::INFINITY } else { max.trim().parse().ok()? };
+                Some((min, max))
+            }
+            None => {
+                let value: f64 = s.trim().parse().ok()?;
+                Some((value, value))
+            }
+        },
+        _ => None,
+    }
+}
+
+/// Path prefix by whole segments: `/data` covers `/data/users` but not
+/// `/database`. Declared segments may use `*` and `?` within a segment, and
+/// `**` for any number of segments. `.` and `..` are resolved first.
+fn path_subsumes(declared: &str, required: &str) -> bool {
+    if declared.starts_with('/') != required.starts_with('/') {
+        return false;
+    }
+    let declared = normalize_segments(declared);
+    let required = normalize_segments(required);
+    segments_subsume(&declared, &required)
+}
+
+fn normalize_segments(path: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." if segments.last().is_some_and(|s| *s != "..") => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    segments
+}
+
+fn segments_subsume(declared: &[&str], required: &[&str]) -> bool {
+    match declared.split_first() {
+        // Declared prefix exhausted: everything beneath is covered
+        None => true,
+        Some((&"**", rest)) => (0..=required.len()).any(|i| segments_subsume(rest, &required[i..])),
+        Some((segment, rest)) => match required.split_first() {
+            Some((req, req_rest)) => glob_match(segment, req) && segments_subsume(rest, req_rest),
+            None => false,
+        },
+    }
+}
+
+/// Match a whole string against a glob where `*` matches any characters and
+/// `?` a single character
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // Let the last `*` absorb one more character
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Parts of a URL or bare host, e.g. `https://api.example.com:8443/v1`
+struct UrlParts<'a> {
+    scheme: Option<&'a str>,
+    host: &'a str,
+    port: Option<&'a str>,
+    path: &'a str,
+}
+
+fn parse_url(url: &str) -> UrlParts<'_> {
+    let (scheme, rest) = match url.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, url),
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, ""),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (authority, None),
+    };
+    UrlParts { scheme, host, port, path }
+}
+
+fn default_port(scheme: Option<&str>) -> Option<&'static str> {
+    match scheme {
+        Some("http") | Some("ws") => Some("80"),
+        Some("https") | Some("wss") => Some("443"),
+        _ => None,
+    }
+}
+
+/// URL or host matching. The declared scheme and port, when present, must
+/// match (ports default from the scheme); `*.example.com` covers any subdomain
+/// of `example.com` and `*` any host; a declared path covers paths beneath it.
+fn url_subsumes(declared: &str, required: &str) -> bool {
+    let d = parse_url(declared);
+    let r = parse_url(required);
+
+    if let Some(scheme) = d.scheme {
+        if !r.scheme.is_some_and(|s| s.eq_ignore_ascii_case(scheme)) {
+            return false;
+        }
+    }
+    if !host_matches(d.host, r.host) {
+        return false;
+    }
+    if let Some(port) = d.port {
+        if r.port.or(default_port(r.scheme)) != Some(port) {
+            return false;
+        }
+    }
+    if d.path.is_empty() || d.path == "/" {
+        return true;
+    }
+    path_subsumes(d.path, if r.path.is_empty() { "/" } else { r.path })
+}
+
+fn host_matches(declared: &str, required: &str) -> bool {
+    let declared = declared.to_ascii_lowercase();
+    let required = required.to_ascii_lowercase();
+    if declared == "*" || declared == required {
+        return true;
+    }
+    match declared.strip_prefix("*.") {
+        Some(domain) => required.ends_with(&format!(".{}", domain)),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(value: &str) -> Literal {
+        Literal::String(value.to_string())
+    }
+
+    #[test]
+    fn test_path_compares_whole_segments() {
+        let rule = SubsumptionRule::Path;
+        assert!(rule_subsumes(rule, &s("/data"), &s("/data")));
+        assert!(rule_subsumes(rule, &s("/data"), &s("/data/users/1.json")));
+        assert!(rule_subsumes(rule, &s("/data/"), &s("/data/users")));
+        assert!(rule_subsumes(rule, &s("/"), &s("/etc/passwd")));
+        assert!(!rule_subsumes(rule, &s("/data"), &s("/database")));
+        assert!(!rule_subsumes(rule, &s("/data"), &s("/data/../etc")));
+        assert!(!rule_subsumes(rule, &s("/data/users"), &s("/data")));
+        assert!(!rule_subsumes(rule, &s("/data"), &s("data/users")));
+    }
+
+    #[test]
+    fn test_path_globs() {
+        let rule = SubsumptionRule::Path;
+        assert!(rule_subsumes(rule, &s("/logs/*.log"), &s("/logs/app.log")));
+        assert!(!rule_subsumes(rule, &s("/logs/*.log"), &s("/logs/app.txt")));
+        assert!(rule_subsumes(rule, &s("/home/*/cache"), &s("/home/ana/cache/x")));
+        assert!(!rule_subsumes(rule, &s("/home/*/cache"), &s("/home/ana/docs")));
+        assert!(rule_subsumes(rule, &s("/srv/**/tmp"), &s("/srv/a/b/tmp/f")));
+        assert!(rule_subsumes(rule, &s("/srv/**/tmp"), &s("/srv/tmp")));
+        assert!(!rule_subsumes(rule, &s("/srv/**/tmp"), &s("/srv/a/b")));
+    }
+
+    #[test]
+    fn test_glob() {
+        let rule = SubsumptionRule::Glob;
+        assert!(rule_subsumes(rule, &s("orders.*"), &s("orders.created")));
+        assert!(rule_subsumes(rule, &s("*"), &s("")));
+        assert!(rule_subsumes(rule, &s("job-??"), &s("job-42")));
+        assert!(!rule_subsumes(rule, &s("job-??"), &s("job-420")));
+        assert!(!rule_subsumes(rule, &s("orders.*"), &s("users.created")));
+    }
+
+    #[test]
+    fn test_url_hosts_schemes_and_ports() {
+        let rule = SubsumptionRule::Url;
+        assert!(rule_subsumes(rule, &s("api.example.com"), &s("api.example.com")));
+        assert!(rule_subsumes(rule, &s("api.example.com"), &s("https://api.example.com/v1")));
+        assert!(rule_subsumes(rule, &s("*.example.com"), &s("api.example.com")));
+        assert!(!rule_subsumes(rule, &s("*.example.com"), &s("example.com")));
+        assert!(!rule_subsumes(rule, &s("*.example.com"), &s("api.example.org")));
+        assert!(rule_subsumes(rule, &s("https://example.com"), &s("https://example.com:443/x")));
+        assert!(!rule_subsumes(rule, &s("https://example.com"), &s("http://example.com")));
+        assert!(rule_subsumes(rule, &s("example.com:8080"), &s("example.com:8080")));
+        assert!(!rule_subsumes(rule, &s("example.com:8080"), &s("example.com:9090")));
+        assert!(rule_subsumes(rule, &s("https://example.com:443"), &s("https://example.com")));
+        assert!(rule_subsumes(rule, &s("example.com/api"), &s("https://example.com/api/users")));
+        assert!(!rule_subsumes(rule, &s("example.com/api"), &s("https://example.com/apis")));
+    }
+
+    #[test]
+    fn test_range_and_set() {
+        let range = SubsumptionRule::Range;
+        assert!(rule_subsumes(range, &s("1..100"), &Literal::Int(50)));
+        assert!(rule_subsumes(range, &s("1..100"), &s("10..20")));
+        assert!(rule_subsumes(range, &s("..100"), &Literal::Int(-5)));
+        assert!(rule_subsumes(range, &Literal::Int(100), &Literal::Int(100)));
+        assert!(!rule_subsumes(range, &s("1..100"), &Literal::Int(101)));
+        assert!(!rule_subsumes(range, &s("1..100"), &s("50..")));
+
+        let set = SubsumptionRule::Set;
+        assert!(rule_subsumes(set, &s("GET, HEAD"), &s("GET")));
+        assert!(rule_subsumes(set, &s("GET,HEAD,POST"), &s("GET,POST")));
+        assert!(!rule_subsumes(set, &s("GET,HEAD"), &s("GET,DELETE")));
+    }
+
+    #[test]
+    fn test_builtin_rules() {
+        let rules = SubsumptionRules::builtin();
+        assert_eq!(rules.rule_for("filesystem", "path"), SubsumptionRule::Path);
+        assert_eq!(rules.rule_for("network", "host"), SubsumptionRule::Url);
+        assert_eq!(rules.rule_for("ratelimit", "max"), SubsumptionRule::Exact);
+    }
+}
@@ -10,6 +10,10 @@ pub struct SymbolTable {
     symbols: Vec<Symbol>,
     by_name: HashMap<String, SymbolId>,
     scopes: Vec<Scope>,
+    /// Type arguments of generic calls, keyed by (caller snippet ID, step ID)
+    type_args: HashMap<(String, String), Vec<ResolvedType>>,
+    /// Types of step output bindings, keyed by (snippet ID, step ID)
+    step_types: HashMap<(String, String), ResolvedType>,
 }
 
 /// A symbol definition
@@ -96,6 +100,30 @@ impl SymbolTable {
         self.symbols.iter()
     }
 
+    /// Record the type arguments inferred for a call to a generic function.
+    /// They are in the order of the callee's `generic` declarations and may
+    /// refer to type parameters of the caller.
+    pub fn record_type_args(&mut self, caller: &str, step_id: &str, args: Vec<ResolvedType>) {
+        self.type_args.insert((caller.to_string(), step_id.to_string()), args);
+    }
+
+    /// Type arguments of the generic call in step `step_id` of snippet `caller`
+    pub fn type_args(&self, caller: &str, step_id: &str) -> Option<&[ResolvedType]> {
+        self.type_args
+            .get(&(caller.to_string(), step_id.to_string()))
+            .map(|args| args.as_slice())
+    }
+
+    /// Record the type of the value step `step_id` of snippet `snippet` binds
+    pub fn record_step_type(&mut self, snippet: &str, step_id: &str, ty: ResolvedType) {
+        self.step_types.insert((snippet.to_string(), step_id.to_string()), ty);
+    }
+
+    /// Type of the value bound by step `step_id` of snippet `snippet`
+    pub fn step_type(&self, snippet: &str, step_id: &str) -> Option<&ResolvedType> {
+        self.step_types.get(&(snippet.to_string(), step_id.to_string()))
+    }
+
     /// Get all function symbols
     pub fn functions(&self) -> impl Iterator<Item = &Symbol> {
         self.symbols.iter().filter(|s| matches!(s.kind, SymbolKind::Function { .. }))
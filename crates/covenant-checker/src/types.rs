@@ -47,6 +47,9 @@ pub enum ResolvedType {
     /// Struct type
     Struct(Vec<(String, ResolvedType)>),
 
+    /// Type parameter of a generic function (bound at each call site)
+    TypeParam(String),
+
     /// Unknown (for inference)
     Unknown,
 
@@ -113,10 +116,42 @@ impl ResolvedType {
                         .join(", ")
                 )
             }
+            ResolvedType::TypeParam(name) => name.clone(),
             ResolvedType::Unknown => "?".to_string(),
             ResolvedType::Error => "<error>".to_string(),
         }
     }
+
+    /// Replace type parameters with their bound types. Unbound parameters
+    /// are left in place.
+    pub fn substitute(&self, bindings: &HashMap<String, ResolvedType>) -> ResolvedType {
+        let subst_all = |types: &[ResolvedType]| -> Vec<ResolvedType> {
+            types.iter().map(|t| t.substitute(bindings)).collect()
+        };
+        match self {
+            ResolvedType::TypeParam(name) => {
+                bindings.get(name).cloned().unwrap_or_else(|| self.clone())
+            }
+            ResolvedType::Named { name, id, args } => ResolvedType::Named {
+                name: name.clone(),
+                id: *id,
+                args: subst_all(args),
+            },
+            ResolvedType::Optional(inner) => ResolvedType::Optional(Box::new(inner.substitute(bindings))),
+            ResolvedType::List(inner) => ResolvedType::List(Box::new(inner.substitute(bindings))),
+            ResolvedType::Set(inner) => ResolvedType::Set(Box::new(inner.substitute(bindings))),
+            ResolvedType::Union(types) => ResolvedType::Union(subst_all(types)),
+            ResolvedType::Tuple(types) => ResolvedType::Tuple(subst_all(types)),
+            ResolvedType::Function { params, ret } => ResolvedType::Function {
+                params: subst_all(params),
+                ret: Box::new(ret.substitute(bindings)),
+            },
+            ResolvedType::Struct(fields) => ResolvedType::Struct(
+                fields.iter().map(|(n, t)| (n.clone(), t.substitute(bindings))).collect(),
+            ),
+            _ => self.clone(),
+        }
+    }
 }
 
 // ============================================================================
@@ -137,6 +172,8 @@ pub struct TypeRegistry {
 pub struct StructDef {
     pub name: String,
     pub fields: Vec<(String, ResolvedType)>,
+    /// Fields a construction may omit (declared `optional` or `auto`)
+    pub optional_fields: Vec<String>,
 }
 
 /// Definition of an enum type
@@ -152,6 +189,8 @@ pub struct VariantDef {
     pub name: String,
     /// None for unit variants, Some for variants with fields
     pub fields: Option<Vec<(String, ResolvedType)>>,
+    /// Fields a construction may omit (declared `optional` or `auto`)
+    pub optional_fields: Vec<String>,
 }
 
 impl TypeRegistry {
@@ -160,8 +199,13 @@ impl TypeRegistry {
     }
 
     /// Register a struct type definition
-    pub fn register_struct(&mut self, name: String, fields: Vec<(String, ResolvedType)>) {
-        self.structs.insert(name.clone(), StructDef { name, fields });
+    pub fn register_struct(
+        &mut self,
+        name: String,
+        fields: Vec<(String, ResolvedType)>,
+        optional_fields: Vec<String>,
+    ) {
+        self.structs.insert(name.clone(), StructDef { name, fields, optional_fields });
     }
 
     /// Register an enum type definition
@@ -0,0 +1,23 @@
+/// Build a `kind="fn"` snippet whose function is named after the last
+/// segment of `id`. `effects` and `signature` are the lines inside the
+/// `effects` and `fn` blocks (no `effects` block when empty); `body` is the
+/// step text.
+pub fn fn_snippet(id: &str, effects: &[&str], signature: &[&str], body: &str) -> String {
+    let name = id.rsplit('.').next().unwrap_or(id);
+    let mut source = format!("\nsnippet id=\"{}\" kind=\"fn\"\n", id);
+    if !effects.is_empty() {
+        source.push_str("effects\n");
+        for line in effects {
+            source.push_str(&format!("  {}\n", line));
+        }
+        source.push_str("end\n");
+    }
+    source.push_str(&format!("signature\n  fn name=\"{}\"\n", name));
+    for line in signature {
+        source.push_str(&format!("    {}\n", line));
+    }
+    source.push_str("  end\nend\nbody\n");
+    source.push_str(body);
+    source.push_str("end\nend\n");
+    source
+}
@@ -1,6 +1,12 @@
 //! Tests for effect checking (Phase 3)
 
-use covenant_checker::{check_effects, EffectError};
+mod common;
+
+use common::fn_snippet;
+use covenant_checker::{
+    check_effects, check_effects_with_policy, explain_effect_violation, DiagnosticKind, EffectError,
+    EffectPolicy,
+};
 use covenant_symbols::build_symbol_graph;
 use covenant_parser::parse;
 
@@ -1325,6 +1331,81 @@ end
     assert!(closure.computed.contains("network.http.get"));
 }
 
+/// `database.read` under `database` (declared by the child) and
+/// `database.write` under `database` (declared by the parent)
+const DATABASE_EFFECTS: &str = r#"
+snippet id="database.read" kind="effect"
+
+signature
+  effect name="read"
+    parent effect="database"
+    param name="resource" rule="glob"
+  end
+end
+
+end
+
+snippet id="database" kind="effect"
+
+signature
+  effect name="database"
+    implies effect="database.write"
+  end
+end
+
+end
+"#;
+
+#[test]
+fn declaring_parent_effect_covers_children() {
+    for child in ["database.read", "database.write"] {
+        let source = format!("{}{}", DATABASE_EFFECTS, subsumption_source(child, "database"));
+        let result = check_effects_for_source(&source);
+        assert!(result.violations.is_empty(), "`database` should cover `{}`: {:?}", child, result.violations);
+    }
+    let result = check_effects_for_source(DATABASE_EFFECTS);
+    assert!(result.hierarchy.covers("database", "database.read"));
+    assert_eq!(result.hierarchy.roots(), vec!["database"]);
+}
+
+#[test]
+fn declaring_child_effect_does_not_cover_parent() {
+    let source = format!("{}{}", DATABASE_EFFECTS, subsumption_source("database", "database.read"));
+    let result = check_effects_for_source(&source);
+    assert_eq!(result.violations.len(), 1);
+    match &result.violations[0] {
+        EffectError::MissingEffect { missing, .. } => assert_eq!(missing, &vec!["database".to_string()]),
+        other => panic!("Expected MissingEffect error, got {:?}", other),
+    }
+
+    // Without effect snippets, dotted names stay unrelated
+    let result = check_effects_for_source(&subsumption_source("database.read", "database"));
+    assert_eq!(result.violations.len(), 1, "`database` alone should not cover `database.read`");
+}
+
+#[test]
+fn parent_effect_parameters_constrain_children() {
+    let source = format!(
+        "{}{}",
+        DATABASE_EFFECTS,
+        subsumption_source(r#"database.read(resource="orders")"#, r#"database(resource="order*")"#)
+    );
+    let result = check_effects_for_source(&source);
+    assert!(result.violations.is_empty(), "Child rules should apply: {:?}", result.violations);
+
+    let source = format!(
+        "{}{}",
+        DATABASE_EFFECTS,
+        subsumption_source(r#"database.read(resource="users")"#, r#"database(resource="order*")"#)
+    );
+    let result = check_effects_for_source(&source);
+    assert!(matches!(
+        result.violations.as_slice(),
+        [EffectError::ParameterNotCovered { effect_name, param_name, .. }]
+            if effect_name == "database.read" && param_name == "resource"
+    ), "Expected ParameterNotCovered: {:?}", result.violations);
+}
+
 // ==========================================================================
 // PARAMETERIZED EFFECTS - Priority 2 Feature
 // ==========================================================================
@@ -1810,3 +1891,515 @@ end
     assert!(result.violations.is_empty(),
         "Exact path match should work");
 }
+
+/// `app.call` declares `declared` and calls an extern requiring `required`
+fn subsumption_source(required: &str, declared: &str) -> String {
+    let effects: Vec<String> = declared.split(';').map(|d| format!("effect {}", d.trim())).collect();
+    let effects: Vec<&str> = effects.iter().map(String::as_str).collect();
+    let body = r#"  step id="s1" kind="call"
+    fn="ext.op"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+"#;
+    format!(
+        r#"
+snippet id="ext.op" kind="extern"
+
+effects
+  effect {}
+end
+
+signature
+  fn name="op"
+    returns type="Int"
+  end
+end
+
+end
+{}"#,
+        required,
+        fn_snippet("app.call", &effects, &[r#"returns type="Int""#], body)
+    )
+}
+
+/// A custom `queue` effect whose parameters use declared rules
+const QUEUE_EFFECT: &str = r#"
+snippet id="queue" kind="effect"
+
+signature
+  effect name="queue"
+    param name="topic" rule="glob"
+    param name="priority" rule="range"
+    param name="mode" rule="set"
+  end
+end
+
+end
+"#;
+
+#[test]
+fn path_parameter_compares_whole_segments() {
+    let result = check_effects_for_source(&subsumption_source(
+        r#"filesystem(path="/database")"#,
+        r#"filesystem(path="/data")"#,
+    ));
+    assert_eq!(result.violations.len(), 1, "`/data` must not cover `/database`");
+    assert!(matches!(
+        &result.violations[0],
+        EffectError::ParameterNotCovered { param_name, .. } if param_name == "path"
+    ));
+
+    let result = check_effects_for_source(&subsumption_source(
+        r#"filesystem(path="/data/app/cache/blob")"#,
+        r#"filesystem(path="/data/*/cache")"#,
+    ));
+    assert!(result.violations.is_empty(), "Segment glob should cover: {:?}", result.violations);
+}
+
+#[test]
+fn network_host_parameter_matches_urls() {
+    let result = check_effects_for_source(&subsumption_source(
+        r#"network(host="https://api.example.com/v1")"#,
+        r#"network(host="*.example.com")"#,
+    ));
+    assert!(result.violations.is_empty(), "Host wildcard should cover: {:?}", result.violations);
+
+    let result = check_effects_for_source(&subsumption_source(
+        r#"network(host="http://api.example.com")"#,
+        r#"network(host="https://*.example.com")"#,
+    ));
+    assert_eq!(result.violations.len(), 1, "A declared scheme must match");
+}
+
+#[test]
+fn any_matching_declaration_covers_required_effect() {
+    let result = check_effects_for_source(&subsumption_source(
+        r#"filesystem(path="/tmp/x")"#,
+        r#"filesystem(path="/data"); filesystem(path="/tmp")"#,
+    ));
+    assert!(result.violations.is_empty(), "Second declaration should cover: {:?}", result.violations);
+}
+
+#[test]
+fn effect_snippet_declares_parameter_rules() {
+    let covered = subsumption_source(
+        r#"queue(topic="orders.created", priority=3, mode="read")"#,
+        r#"queue(topic="orders.*", priority="1..5", mode="read,write")"#,
+    );
+    let result = check_effects_for_source(&format!("{}{}", QUEUE_EFFECT, covered));
+    assert!(result.violations.is_empty(), "Declared rules should cover: {:?}", result.violations);
+
+    let out_of_range = subsumption_source(
+        r#"queue(topic="orders.created", priority=9)"#,
+        r#"queue(topic="orders.*", priority="1..5")"#,
+    );
+    let result = check_effects_for_source(&format!("{}{}", QUEUE_EFFECT, out_of_range));
+    assert_eq!(result.violations.len(), 1);
+    match &result.violations[0] {
+        EffectError::ParameterNotCovered { param_name, required_value, .. } => {
+            assert_eq!(param_name, "priority");
+            assert_eq!(required_value, "9");
+        }
+        other => panic!("Expected ParameterNotCovered error, got {:?}", other),
+    }
+
+    // Without the effect snippet, the same parameters need exact equality
+    let result = check_effects_for_source(&subsumption_source(
+        r#"queue(topic="orders.created")"#,
+        r#"queue(topic="orders.*")"#,
+    ));
+    assert_eq!(result.violations.len(), 1, "Undeclared rules default to exact");
+}
+
+// ==========================================================================
+// FUNCTION VALUES - Passed functions carry their effects to the caller
+// ==========================================================================
+
+/// `each(f)` calls the function it is given and has its effects; `main`
+/// passes it `io.print`
+const PASS_PRINT_SOURCE: &str = r#"
+snippet id="io.print" kind="extern"
+
+effects
+  effect console
+end
+
+signature
+  fn name="print"
+    param name="msg" type="String"
+    returns type="Unit"
+  end
+end
+
+end
+
+snippet id="app.each" kind="fn"
+
+effects
+  effect of="f"
+end
+
+signature
+  fn name="each"
+    param name="f" type="(String) -> Unit"
+    returns type="Unit"
+  end
+end
+
+body
+  step id="s1" kind="call"
+    fn="f"
+    arg name="msg" lit="hello"
+    as="_"
+  end
+end
+
+end
+
+snippet id="app.main" kind="fn"
+
+signature
+  fn name="main"
+    returns type="Unit"
+  end
+end
+
+body
+  step id="s1" kind="call"
+    fn="app.each"
+    arg name="f" fn="io.print"
+    as="_"
+  end
+end
+
+end
+"#;
+
+/// `run(g)` passes its own function parameter on to `app.each`
+const PASS_ON_SNIPPET: &str = r#"
+snippet id="app.run" kind="fn"
+
+signature
+  fn name="run"
+    param name="g" type="(String) -> Unit"
+    returns type="Unit"
+  end
+end
+
+body
+  step id="s1" kind="call"
+    fn="app.each"
+    arg name="f" from="g"
+    as="_"
+  end
+end
+
+end
+"#;
+
+#[test]
+fn passed_function_effects_flow_into_caller() {
+    let result = check_effects_for_source(PASS_PRINT_SOURCE);
+
+    // Calling a function-typed parameter is not a call to a symbol
+    let each = result.closures.get("app.each").expect("closure not found");
+    assert!(each.computed.is_empty(), "Expected app.each to be pure: {:?}", each.computed);
+    assert_eq!(each.effect_vars, vec!["f".to_string()]);
+
+    // The effect variable is instantiated with `io.print` at the call site
+    let main = result.closures.get("app.main").expect("closure not found");
+    assert!(main.computed.contains("console"));
+    assert_eq!(result.violations.len(), 1, "Expected 1 violation");
+    match &result.violations[0] {
+        EffectError::PureCallsEffectful { function, callee, .. } => {
+            assert_eq!(function, "app.main");
+            assert_eq!(callee, "io.print");
+        }
+        other => panic!("Expected PureCallsEffectful error, got {:?}", other),
+    }
+}
+
+#[test]
+fn passed_function_diagnostic_explains_instantiation() {
+    let program = parse(PASS_PRINT_SOURCE).expect("parse failed");
+    let graph = build_symbol_graph(&program).expect("symbol graph failed").graph;
+    let result = check_effects(&graph);
+    let diagnostic = explain_effect_violation(&result.violations[0], &graph);
+
+    assert!(
+        diagnostic.explanation.contains("`io.print` is passed to parameter `f` of `app.each`"),
+        "Explanation should mention the instantiation: {}",
+        diagnostic.explanation
+    );
+    match &diagnostic.error {
+        DiagnosticKind::Effect(effect) => {
+            let chain: Vec<&str> = effect.call_chain.iter().map(|e| e.name.as_str()).collect();
+            assert_eq!(chain, vec!["app.main", "app.each", "io.print"]);
+        }
+        other => panic!("Expected effect diagnostic, got {:?}", other),
+    }
+}
+
+#[test]
+fn passed_function_effects_declared_by_caller_is_valid() {
+    let source = PASS_PRINT_SOURCE.replace(
+        "snippet id=\"app.main\" kind=\"fn\"\n",
+        "snippet id=\"app.main\" kind=\"fn\"\n\neffects\n  effect console\nend\n",
+    );
+    let result = check_effects_for_source(&source);
+    assert!(result.violations.is_empty(), "Expected no violations: {:?}", result.violations);
+}
+
+#[test]
+fn effectful_function_passed_without_effect_variable() {
+    let source = PASS_PRINT_SOURCE.replace("effects\n  effect of=\"f\"\nend\n", "");
+    let result = check_effects_for_source(&source);
+
+    // Without the effect variable, `app.each` only accepts pure functions
+    let main = result.closures.get("app.main").expect("closure not found");
+    assert!(main.computed.is_empty(), "Expected app.main to be pure: {:?}", main.computed);
+    assert_eq!(result.violations.len(), 1, "Expected 1 violation: {:?}", result.violations);
+    match &result.violations[0] {
+        EffectError::ImpureFunctionArgument { function, callee, param, argument, effects, .. } => {
+            assert_eq!(function, "app.main");
+            assert_eq!(callee, "app.each");
+            assert_eq!(param, "f");
+            assert_eq!(argument, "io.print");
+            assert_eq!(effects, &vec!["console".to_string()]);
+        }
+        other => panic!("Expected ImpureFunctionArgument error, got {:?}", other),
+    }
+}
+
+#[test]
+fn function_parameter_passed_on_requires_effect_variable() {
+    let source = format!("{}{}", PASS_PRINT_SOURCE, PASS_ON_SNIPPET);
+    let result = check_effects_for_source(&source);
+
+    assert!(
+        result.violations.iter().any(|v| matches!(
+            v,
+            EffectError::UndeclaredEffectVariable { function, callee, param, argument, .. }
+                if function == "app.run" && callee == "app.each" && param == "f" && argument == "g"
+        )),
+        "Expected UndeclaredEffectVariable: {:?}",
+        result.violations
+    );
+}
+
+#[test]
+fn effect_variables_instantiate_through_wrappers() {
+    let run = PASS_ON_SNIPPET.replace(
+        "snippet id=\"app.run\" kind=\"fn\"\n",
+        "snippet id=\"app.run\" kind=\"fn\"\n\neffects\n  effect of=\"g\"\nend\n",
+    );
+    let main = PASS_PRINT_SOURCE
+        .replace("fn=\"app.each\"", "fn=\"app.run\"")
+        .replace("arg name=\"f\" fn=\"io.print\"", "arg name=\"g\" fn=\"io.print\"")
+        .replace(
+            "snippet id=\"app.main\" kind=\"fn\"\n",
+            "snippet id=\"app.main\" kind=\"fn\"\n\neffects\n  effect console\nend\n",
+        );
+    let result = check_effects_for_source(&format!("{}{}", main, run));
+
+    assert!(result.violations.is_empty(), "Expected no violations: {:?}", result.violations);
+    let run = result.closures.get("app.run").expect("closure not found");
+    assert!(run.computed.is_empty(), "Expected app.run to be pure: {:?}", run.computed);
+    let main = result.closures.get("app.main").expect("closure not found");
+    assert!(main.computed.contains("console"));
+}
+
+// ==========================================================================
+// PROJECT EFFECT POLICY - Per-project rules on which snippets may use an effect
+// ==========================================================================
+
+/// `ui.page.render` reaches `database` through `app.store.save`; the
+/// `*.shell` functions declare `process`
+const POLICY_SOURCE: &str = r#"
+snippet id="db.write" kind="extern"
+
+effects
+  effect database
+end
+
+signature
+  fn name="write"
+    returns type="Int"
+  end
+end
+
+end
+
+snippet id="app.store.save" kind="fn"
+
+effects
+  effect database
+end
+
+signature
+  fn name="save"
+    returns type="Int"
+  end
+end
+
+body
+  step id="s1" kind="call"
+    fn="db.write"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+end
+
+end
+
+snippet id="ui.page.render" kind="fn"
+
+effects
+  effect database
+end
+
+signature
+  fn name="render"
+    returns type="Int"
+  end
+end
+
+body
+  step id="s1" kind="call"
+    fn="app.store.save"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+end
+
+end
+
+snippet id="infra.deploy.shell" kind="extern"
+
+effects
+  effect process
+end
+
+signature
+  fn name="shell"
+    returns type="Int"
+  end
+end
+
+end
+
+snippet id="app.tools.shell" kind="extern"
+
+effects
+  effect process
+end
+
+signature
+  fn name="shell"
+    returns type="Int"
+  end
+end
+
+end
+"#;
+
+fn check_effects_with_policy_toml(source: &str, policy: &str) -> covenant_checker::EffectCheckResult {
+    let program = parse(source).expect("parse failed");
+    let symbol_result = build_symbol_graph(&program).expect("symbol graph failed");
+    let policy = EffectPolicy::from_toml_str(policy).expect("invalid policy");
+    check_effects_with_policy(&symbol_result.graph, &policy)
+}
+
+fn policy_violations(result: &covenant_checker::EffectCheckResult) -> Vec<(&str, &str)> {
+    result
+        .violations
+        .iter()
+        .filter_map(|e| match e {
+            EffectError::PolicyViolation { function, effect, .. } => Some((function.as_str(), effect.as_str())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn policy_denies_effect_for_matching_snippets() {
+    let policy = r#"
+[[rule]]
+snippets = "ui.*"
+deny = ["database"]
+"#;
+    let result = check_effects_with_policy_toml(POLICY_SOURCE, policy);
+    assert_eq!(policy_violations(&result), vec![("ui.page.render", "database")]);
+
+    // Without a policy the same source is valid
+    assert!(check_effects_for_source(POLICY_SOURCE).violations.is_empty());
+}
+
+#[test]
+fn policy_later_rules_override_earlier() {
+    let policy = r#"
+[[rule]]
+snippets = "*"
+deny = ["process"]
+
+[[rule]]
+snippets = "infra.*"
+allow = ["process"]
+"#;
+    let result = check_effects_with_policy_toml(POLICY_SOURCE, policy);
+    assert_eq!(policy_violations(&result), vec![("app.tools.shell", "process")]);
+}
+
+#[test]
+fn policy_allow_constrains_effect_parameters() {
+    let policy = r#"
+[[rule]]
+snippets = "app.*"
+allow = ['filesystem(path="/data")']
+"#;
+    let source = subsumption_source(r#"filesystem(path="/data/users")"#, r#"filesystem(path="/data")"#);
+    let result = check_effects_with_policy_toml(&source, policy);
+    assert!(result.violations.is_empty(), "Expected no violations: {:?}", result.violations);
+
+    let source = subsumption_source(r#"filesystem(path="/etc/passwd")"#, r#"filesystem(path="/etc")"#);
+    let result = check_effects_with_policy_toml(&source, policy);
+    assert_eq!(
+        policy_violations(&result),
+        vec![("app.call", r#"filesystem(path="/etc")"#), ("app.call", r#"filesystem(path="/etc/passwd")"#)]
+    );
+}
+
+#[test]
+fn policy_violation_diagnostic_shows_call_chain() {
+    let policy = r#"
+[[rule]]
+snippets = "ui.*"
+deny = ["database"]
+"#;
+    let program = parse(POLICY_SOURCE).expect("parse failed");
+    let symbol_result = build_symbol_graph(&program).expect("symbol graph failed");
+    let policy = EffectPolicy::from_toml_str(policy).unwrap();
+    let result = check_effects_with_policy(&symbol_result.graph, &policy);
+    assert_eq!(result.violations.len(), 1);
+
+    let diagnostic = explain_effect_violation(&result.violations[0], &symbol_result.graph);
+    assert_eq!(diagnostic.code, "E-EFFECT-007");
+    assert!(diagnostic.explanation.contains("rule for `ui.*` denies `database`"), "{}", diagnostic.explanation);
+    match &diagnostic.error {
+        DiagnosticKind::Effect(effect) => {
+            let chain: Vec<&str> = effect.call_chain.iter().map(|e| e.name.as_str()).collect();
+            assert_eq!(chain, vec!["ui.page.render", "app.store.save", "db.write"]);
+        }
+        other => panic!("Expected effect diagnostic, got {:?}", other),
+    }
+}
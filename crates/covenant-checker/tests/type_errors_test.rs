@@ -1,5 +1,8 @@
 //! Integration tests for type error detection (Phase 4)
 
+mod common;
+
+use common::fn_snippet;
 use covenant_checker::check;
 use covenant_parser::parse;
 
@@ -114,6 +117,39 @@ end
     assert!(!errors.is_empty(), "Expected undefined variable 'y' error");
 }
 
+/// A function `f(n: Int) -> Int` with the given body steps
+fn body_source(steps: &str) -> String {
+    fn_snippet("app.f", &[], &[r#"param name="n" type="Int""#, r#"returns type="Int""#], steps)
+}
+
+#[test]
+fn test_use_before_definition() {
+    let errors = check_source_has_errors(&body_source(r#"
+  step id="s1" kind="compute"
+    op=add
+    input var="later"
+    input lit=1
+    as="x"
+  end
+  step id="s2" kind="bind"
+    from="n"
+    as="later"
+  end
+  step id="s3" kind="return"
+    from="x"
+    as="_"
+  end
+"#));
+    assert!(
+        errors.iter().any(|e| matches!(
+            e,
+            covenant_checker::CheckError::UseBeforeDefinition { name, step_id, defined_by, span }
+                if name == "later" && step_id == "s1" && defined_by == "s2" && span.start > 0
+        )),
+        "Expected use before definition: {:?}", errors
+    );
+}
+
 // === Compute Operation Type Tests ===
 
 #[test]
@@ -389,6 +425,186 @@ end
     check_source_ok(source);
 }
 
+#[test]
+fn test_duplicate_binding() {
+    let errors = check_source_has_errors(&body_source(r#"
+  step id="s1" kind="compute"
+    op=add
+    input var="n"
+    input lit=1
+    as="x"
+  end
+  step id="s2" kind="compute"
+    op=add
+    input var="x"
+    input lit=1
+    as="x"
+  end
+  step id="s3" kind="compute"
+    op=add
+    input var="x"
+    input lit=1
+    as="n"
+  end
+  step id="s4" kind="return"
+    from="x"
+    as="_"
+  end
+"#));
+    assert!(
+        errors.iter().any(|e| matches!(
+            e,
+            covenant_checker::CheckError::DuplicateBinding { name, step_id, first: Some(first), .. }
+                if name == "x" && step_id == "s2" && first == "s1"
+        )),
+        "Expected x to be bound twice: {:?}", errors
+    );
+    assert!(
+        errors.iter().any(|e| matches!(
+            e,
+            covenant_checker::CheckError::DuplicateBinding { name, first: None, .. } if name == "n"
+        )),
+        "Expected the parameter n to be rebound: {:?}", errors
+    );
+}
+
+#[test]
+fn test_branch_local_binding_used_after_branch() {
+    let errors = check_source_has_errors(&body_source(r#"
+  step id="s1" kind="compute"
+    op=greater
+    input var="n"
+    input lit=0
+    as="positive"
+  end
+  step id="s2" kind="if"
+    condition="positive"
+    then
+      step id="s2a" kind="bind"
+        from="n"
+        as="result"
+      end
+    end
+    else
+      step id="s2b" kind="bind"
+        lit=0
+        as="result"
+      end
+    end
+    as="_"
+  end
+  step id="s3" kind="return"
+    from="result"
+    as="_"
+  end
+"#));
+    assert_eq!(errors.len(), 1, "Expected only the leaked binding: {:?}", errors);
+    assert!(matches!(
+        &errors[0],
+        covenant_checker::CheckError::BranchLocalBinding { name, step_id, branch_step, .. }
+            if name == "result" && step_id == "s3" && branch_step == "s2"
+    ));
+}
+
+#[test]
+fn test_branch_result_through_step_binding() {
+    check_source_ok(&body_source(r#"
+  step id="s1" kind="compute"
+    op=greater
+    input var="n"
+    input lit=0
+    as="positive"
+  end
+  step id="s2" kind="if"
+    condition="positive"
+    then
+      step id="s2a" kind="bind"
+        from="n"
+        as="result"
+      end
+    end
+    else
+      step id="s2b" kind="bind"
+        lit=0
+        as="result"
+      end
+    end
+    as="clamped"
+  end
+  step id="s3" kind="return"
+    from="clamped"
+    as="_"
+  end
+"#));
+}
+
+#[test]
+fn test_loop_may_update_outer_binding() {
+    let source = r#"
+snippet id="app.sum" kind="fn"
+signature
+  fn name="sum"
+    param name="items" type="List<Int>"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="bind"
+    lit=0
+    as="total"
+  end
+  step id="s2" kind="for"
+    var="item" in="items"
+    step id="s2a" kind="compute"
+      op=add
+      input var="total"
+      input var="item"
+      as="total"
+    end
+    as="_"
+  end
+  step id="s3" kind="return"
+    from="total"
+    as="_"
+  end
+end
+end
+"#;
+    check_source_ok(source);
+}
+
+#[test]
+fn test_duplicate_step_id() {
+    let errors = check_source_has_errors(&body_source(r#"
+  step id="s1" kind="compute"
+    op=greater
+    input var="n"
+    input lit=0
+    as="positive"
+  end
+  step id="s2" kind="if"
+    condition="positive"
+    then
+      step id="s1" kind="return"
+        lit=1
+        as="_"
+      end
+    end
+    as="_"
+  end
+  step id="s3" kind="return"
+    lit=0
+    as="_"
+  end
+"#));
+    assert_eq!(errors.len(), 1, "Expected only the duplicate step id: {:?}", errors);
+    assert!(matches!(
+        &errors[0],
+        covenant_checker::CheckError::DuplicateStepId { id, snippet, .. }
+            if id == "s1" && snippet == "app.f"
+    ));
+}
+
 // ==========================================================================
 // COMPREHENSIVE PHASE 4 TESTS - Operators, Queries, Unions, Exhaustiveness
 // ==========================================================================
@@ -819,7 +1035,6 @@ end
 // === Function Call Type Tests ===
 
 #[test]
-#[ignore = "Cross-snippet argument type checking not yet implemented"]
 fn test_call_wrong_arg_type() {
     let source = r#"
 snippet id="math.add" kind="fn"
@@ -893,56 +1108,154 @@ end
     assert!(!errors.is_empty(), "Should error when calling undefined function");
 }
 
-// === If Condition Type Tests ===
-
-#[test]
-fn test_if_condition_not_bool_is_error() {
-    let source = r#"
-snippet id="test.fn" kind="fn"
+const MATH_ADD: &str = r#"
+snippet id="math.add" kind="fn"
 signature
-  fn name="test"
-    param name="x" type="Int"
+  fn name="add"
+    param name="a" type="Int"
+    param name="b" type="Int"
     returns type="Int"
   end
 end
 body
-  step id="s1" kind="if"
-    condition="x"
-    then
-      step id="s1a" kind="return"
-        lit=1
-        as="_"
-      end
-    end
-    else
-      step id="s1b" kind="return"
-        lit=0
-        as="_"
-      end
-    end
+  step id="s1" kind="compute"
+    op=add
+    input var="a"
+    input var="b"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
     as="_"
   end
 end
 end
 "#;
-    let errors = check_source_has_errors(source);
-    assert!(!errors.is_empty(), "Should error when if condition is Int, not Bool");
+
+/// Build a caller of math.add with the given `arg` lines
+fn call_add_source(args: &str) -> String {
+    let body = format!(r#"  step id="s1" kind="call"
+    fn="math.add"
+{}
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+"#, args);
+    format!("{}{}", MATH_ADD, fn_snippet("test.fn", &[], &[r#"returns type="Int""#], &body))
 }
 
-// === Optional Type Tests ===
+#[test]
+fn test_call_correct_args() {
+    check_source_ok(&call_add_source("    arg name=\"a\" lit=1\n    arg name=\"b\" lit=2"));
+}
 
 #[test]
-fn test_optional_return_none() {
+fn test_call_missing_arg() {
+    let errors = check_source_has_errors(&call_add_source("    arg name=\"a\" lit=1"));
+    assert!(
+        errors.iter().any(|e| matches!(
+            e,
+            covenant_checker::CheckError::MissingArgument { param, expected, .. }
+                if param == "b" && expected == &["a: Int", "b: Int"]
+        )),
+        "Expected missing argument 'b': {:?}", errors
+    );
+}
+
+#[test]
+fn test_call_misspelled_arg() {
+    let errors = check_source_has_errors(&call_add_source("    arg name=\"a\" lit=1\n    arg name=\"bb\" lit=2"));
+    assert!(
+        errors.iter().any(|e| matches!(
+            e,
+            covenant_checker::CheckError::UnknownArgument { arg, .. } if arg == "bb"
+        )),
+        "Expected unknown argument 'bb': {:?}", errors
+    );
+    assert!(
+        errors.iter().any(|e| matches!(
+            e,
+            covenant_checker::CheckError::MissingArgument { param, .. } if param == "b"
+        )),
+        "Expected missing argument 'b': {:?}", errors
+    );
+}
+
+#[test]
+fn test_call_extra_arg() {
+    let errors = check_source_has_errors(&call_add_source(
+        "    arg name=\"a\" lit=1\n    arg name=\"b\" lit=2\n    arg name=\"c\" lit=3",
+    ));
+    assert_eq!(errors.len(), 1, "Expected only the extra argument error: {:?}", errors);
+    assert!(matches!(
+        &errors[0],
+        covenant_checker::CheckError::UnknownArgument { arg, .. } if arg == "c"
+    ));
+}
+
+#[test]
+fn test_call_args_in_any_order() {
+    check_source_ok(&call_add_source("    arg name=\"b\" lit=2\n    arg name=\"a\" lit=1"));
+}
+
+#[test]
+fn test_call_duplicate_arg() {
+    let errors = check_source_has_errors(&call_add_source(
+        "    arg name=\"a\" lit=1\n    arg name=\"b\" lit=2\n    arg name=\"a\" lit=3",
+    ));
+    assert_eq!(errors.len(), 1, "Expected only the duplicate argument error: {:?}", errors);
+    assert!(matches!(
+        &errors[0],
+        covenant_checker::CheckError::DuplicateArgument { arg, caller, span, .. }
+            if arg == "a" && caller == "test.fn" && !span.is_dummy()
+    ));
+}
+
+#[test]
+fn test_call_arg_type_mismatch_names_param() {
+    let errors = check_source_has_errors(&call_add_source("    arg name=\"a\" lit=1\n    arg name=\"b\" lit=true"));
+    assert!(
+        errors.iter().any(|e| matches!(
+            e,
+            covenant_checker::CheckError::ArgumentTypeMismatch { param, expected, found, caller, span, .. }
+                if param == "b" && expected == "Int" && found == "Bool"
+                    && caller == "test.fn" && !span.is_dummy()
+        )),
+        "Expected mismatch on 'b' located in the caller: {:?}", errors
+    );
+}
+
+#[test]
+fn test_call_any_list_param_accepts_suffix_list_type() {
+    // `Item[]` is a list of `Item`, so it fits list.get's `List<Any>`
     let source = r#"
-snippet id="test.fn" kind="fn"
+snippet id="app.Item" kind="struct"
 signature
-  fn name="test"
-    returns type="Int" optional
+  struct name="Item"
+    field name="id" type="Int"
+  end
+end
+end
+
+snippet id="app.first" kind="fn"
+signature
+  fn name="first"
+    param name="items" type="Item[]"
+    returns type="Item"
   end
 end
 body
-  step id="s1" kind="return"
-    lit=none
+  step id="s1" kind="call"
+    fn="list.get"
+    arg name="list" from="items"
+    arg name="index" lit=0
+    as="item"
+  end
+  step id="s2" kind="return"
+    from="item"
     as="_"
   end
 end
@@ -951,72 +1264,435 @@ end
     check_source_ok(source);
 }
 
-#[test]
-fn test_optional_return_value() {
-    let source = r#"
-snippet id="test.fn" kind="fn"
+const GENERIC_FIRST: &str = r#"
+snippet id="util.first_or" kind="fn"
 signature
-  fn name="test"
-    returns type="Int" optional
+  fn name="first_or"
+    generic name="T"
+    param name="items" type="List<T>"
+    param name="fallback" type="T"
+    returns type="T"
   end
 end
 body
   step id="s1" kind="return"
-    lit=42
+    from="fallback"
     as="_"
   end
 end
 end
 "#;
-    check_source_ok(source);
-}
-
-// === Union Type Tests ===
 
 #[test]
-fn test_union_return_first_member() {
-    let source = r#"
-snippet id="test.fn" kind="fn"
+fn test_generic_call_infers_return_type() {
+    let source = format!(r#"{}
+snippet id="app.main" kind="fn"
 signature
-  fn name="test"
-    returns union
-      type="Int"
-      type="String"
-    end
+  fn name="main"
+    param name="xs" type="List<Int>"
+    returns type="Int"
   end
 end
 body
-  step id="s1" kind="return"
-    lit=42
+  step id="s1" kind="call"
+    fn="util.first_or"
+    arg name="items" from="xs"
+    arg name="fallback" lit=0
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
     as="_"
   end
 end
 end
-"#;
-    check_source_ok(source);
+"#, GENERIC_FIRST);
+    let program = parse(&source).expect("parse failed");
+    let result = check(&program).expect("generic call should check");
+    let type_args = result.symbols.type_args("app.main", "s1").expect("type args recorded");
+    assert_eq!(type_args, &[covenant_checker::ResolvedType::Int]);
+    assert_eq!(
+        result.symbols.step_type("app.main", "s1"),
+        Some(&covenant_checker::ResolvedType::Int)
+    );
 }
 
 #[test]
-fn test_union_return_second_member() {
-    let source = r#"
-snippet id="test.fn" kind="fn"
+fn test_generic_call_wrong_return_use() {
+    let source = format!(r#"{}
+snippet id="app.main" kind="fn"
 signature
-  fn name="test"
-    returns union
-      type="Int"
-      type="String"
-    end
+  fn name="main"
+    param name="xs" type="List<String>"
+    returns type="Int"
   end
 end
 body
-  step id="s1" kind="return"
-    lit="hello"
+  step id="s1" kind="call"
+    fn="util.first_or"
+    arg name="items" from="xs"
+    arg name="fallback" lit="none"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
     as="_"
   end
 end
 end
-"#;
-    check_source_ok(source);
+"#, GENERIC_FIRST);
+    let errors = check_source_has_errors(&source);
+    assert!(
+        errors.iter().any(|e| matches!(e, covenant_checker::CheckError::TypeMismatch { .. })),
+        "Returning a String-instantiated T as Int should fail: {:?}", errors
+    );
+}
+
+#[test]
+fn test_generic_conflicting_type_arguments() {
+    let source = format!(r#"{}
+snippet id="app.main" kind="fn"
+signature
+  fn name="main"
+    param name="xs" type="List<Int>"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="call"
+    fn="util.first_or"
+    arg name="items" from="xs"
+    arg name="fallback" lit="zero"
+    as="result"
+  end
+  step id="s2" kind="return"
+    lit=0
+    as="_"
+  end
+end
+end
+"#, GENERIC_FIRST);
+    let errors = check_source_has_errors(&source);
+    assert!(
+        errors.iter().any(|e| matches!(
+            e,
+            covenant_checker::CheckError::ConflictingTypeArgument { param, .. } if param == "T"
+        )),
+        "Expected conflicting binding for T: {:?}", errors
+    );
+}
+
+#[test]
+fn test_generic_uninferred_type_parameter() {
+    let source = r#"
+snippet id="util.make" kind="fn"
+signature
+  fn name="make"
+    generic name="T"
+    param name="n" type="Int"
+    returns type="List<T>"
+  end
+end
+body
+  step id="s1" kind="return"
+    lit=none
+    as="_"
+  end
+end
+end
+
+snippet id="app.main" kind="fn"
+signature
+  fn name="main"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="call"
+    fn="util.make"
+    arg name="n" lit=3
+    as="xs"
+  end
+  step id="s2" kind="return"
+    lit=0
+    as="_"
+  end
+end
+end
+"#;
+    let errors = check_source_has_errors(source);
+    assert!(
+        errors.iter().any(|e| matches!(
+            e,
+            covenant_checker::CheckError::UninferredTypeParameter { param, .. } if param == "T"
+        )),
+        "Expected uninferred T: {:?}", errors
+    );
+}
+
+/// Functions `apply` may be given
+const DOUBLE_AND_SHOUT: &str = r#"
+snippet id="math.double" kind="fn"
+signature
+  fn name="double"
+    param name="x" type="Int"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="compute"
+    op=mul
+    input var="x"
+    input lit=2
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+end
+end
+
+snippet id="text.shout" kind="fn"
+signature
+  fn name="shout"
+    param name="s" type="String"
+    returns type="String"
+  end
+end
+body
+  step id="s1" kind="return"
+    from="s"
+    as="_"
+  end
+end
+end
+"#;
+
+/// `apply(f, x)` calls `f` on `x`; `main` passes it `callee`
+fn apply_source(f_type: &str, call_args: &str, callee: &str) -> String {
+    let f_param = format!(r#"param name="f" type="{}""#, f_type);
+    let apply_body = format!(r#"  step id="s1" kind="call"
+    fn="f"
+{}    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+"#, call_args);
+    let main_body = format!(r#"  step id="s1" kind="call"
+    fn="fn.apply"
+    arg name="f" fn="{}"
+    arg name="x" lit=21
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+"#, callee);
+    format!(
+        "{}{}{}",
+        DOUBLE_AND_SHOUT,
+        fn_snippet("fn.apply", &[], &[&f_param, r#"param name="x" type="Int""#, r#"returns type="Int""#], &apply_body),
+        fn_snippet("app.main", &[], &[r#"returns type="Int""#], &main_body)
+    )
+}
+
+const APPLY_ARGS: &str = "    arg name=\"x\" from=\"x\"\n";
+
+#[test]
+fn test_function_value_argument() {
+    check_source_ok(&apply_source("(Int) -> Int", APPLY_ARGS, "math.double"));
+}
+
+#[test]
+fn test_function_value_signature_mismatch() {
+    let errors = check_source_has_errors(&apply_source("(Int) -> Int", APPLY_ARGS, "text.shout"));
+    assert_eq!(errors.len(), 1, "Expected only the mismatched argument: {:?}", errors);
+    assert!(matches!(
+        &errors[0],
+        covenant_checker::CheckError::ArgumentTypeMismatch { param, expected, found, .. }
+            if param == "f" && expected == "(Int) -> Int" && found == "(String) -> String"
+    ));
+}
+
+#[test]
+fn test_undefined_function_value() {
+    let errors = check_source_has_errors(&apply_source("(Int) -> Int", APPLY_ARGS, "math.triple"));
+    assert!(
+        errors.iter().any(|e| matches!(
+            e,
+            covenant_checker::CheckError::UndefinedSymbol { name } if name == "math.triple"
+        )),
+        "Expected undefined function: {:?}", errors
+    );
+}
+
+#[test]
+fn test_indirect_call_argument_count() {
+    let args = "    arg name=\"x\" from=\"x\"\n    arg name=\"y\" from=\"x\"\n";
+    let errors = check_source_has_errors(&apply_source("(Int) -> Int", args, "math.double"));
+    assert_eq!(errors.len(), 1, "Expected only the argument count: {:?}", errors);
+    assert!(matches!(
+        &errors[0],
+        covenant_checker::CheckError::ArgumentCountMismatch { function, expected: 1, found: 2, .. }
+            if function == "f"
+    ));
+}
+
+#[test]
+fn test_indirect_call_argument_type() {
+    let args = "    arg name=\"x\" lit=\"twenty-one\"\n";
+    let errors = check_source_has_errors(&apply_source("(Int) -> Int", args, "math.double"));
+    assert_eq!(errors.len(), 1, "Expected only the argument type: {:?}", errors);
+    assert!(matches!(
+        &errors[0],
+        covenant_checker::CheckError::ArgumentTypeMismatch { function, expected, found, .. }
+            if function == "f" && expected == "Int" && found == "String"
+    ));
+}
+
+#[test]
+fn test_effect_variable_must_name_function_parameter() {
+    let source = apply_source("(Int) -> Int", APPLY_ARGS, "math.double").replace(
+        "snippet id=\"fn.apply\" kind=\"fn\"\n",
+        "snippet id=\"fn.apply\" kind=\"fn\"\neffects\n  effect of=\"f\"\n  effect of=\"x\"\nend\n",
+    );
+    let errors = check_source_has_errors(&source);
+    assert_eq!(errors.len(), 1, "Expected only the non-function parameter: {:?}", errors);
+    assert!(matches!(
+        &errors[0],
+        covenant_checker::CheckError::InvalidEffectVariable { function, param, .. }
+            if function == "fn.apply" && param == "x"
+    ));
+}
+
+// === If Condition Type Tests ===
+
+#[test]
+fn test_if_condition_not_bool_is_error() {
+    let source = r#"
+snippet id="test.fn" kind="fn"
+signature
+  fn name="test"
+    param name="x" type="Int"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="if"
+    condition="x"
+    then
+      step id="s1a" kind="return"
+        lit=1
+        as="_"
+      end
+    end
+    else
+      step id="s1b" kind="return"
+        lit=0
+        as="_"
+      end
+    end
+    as="_"
+  end
+end
+end
+"#;
+    let errors = check_source_has_errors(source);
+    assert!(!errors.is_empty(), "Should error when if condition is Int, not Bool");
+}
+
+// === Optional Type Tests ===
+
+#[test]
+fn test_optional_return_none() {
+    let source = r#"
+snippet id="test.fn" kind="fn"
+signature
+  fn name="test"
+    returns type="Int" optional
+  end
+end
+body
+  step id="s1" kind="return"
+    lit=none
+    as="_"
+  end
+end
+end
+"#;
+    check_source_ok(source);
+}
+
+#[test]
+fn test_optional_return_value() {
+    let source = r#"
+snippet id="test.fn" kind="fn"
+signature
+  fn name="test"
+    returns type="Int" optional
+  end
+end
+body
+  step id="s1" kind="return"
+    lit=42
+    as="_"
+  end
+end
+end
+"#;
+    check_source_ok(source);
+}
+
+// === Union Type Tests ===
+
+#[test]
+fn test_union_return_first_member() {
+    let source = r#"
+snippet id="test.fn" kind="fn"
+signature
+  fn name="test"
+    returns union
+      type="Int"
+      type="String"
+    end
+  end
+end
+body
+  step id="s1" kind="return"
+    lit=42
+    as="_"
+  end
+end
+end
+"#;
+    check_source_ok(source);
+}
+
+#[test]
+fn test_union_return_second_member() {
+    let source = r#"
+snippet id="test.fn" kind="fn"
+signature
+  fn name="test"
+    returns union
+      type="Int"
+      type="String"
+    end
+  end
+end
+body
+  step id="s1" kind="return"
+    lit="hello"
+    as="_"
+  end
+end
+end
+"#;
+    check_source_ok(source);
 }
 
 #[test]
@@ -1044,6 +1720,198 @@ end
     assert!(!errors.is_empty(), "Should error when returning Bool for union of Int|String");
 }
 
+const SAFE_DIV: &str = r#"
+snippet id="math.MathError" kind="enum"
+signature
+  enum name="MathError"
+    variant name="DivByZero"
+    end
+  end
+end
+end
+
+snippet id="math.safe_div" kind="fn"
+signature
+  fn name="safe_div"
+    param name="a" type="Int"
+    param name="b" type="Int"
+    returns union
+      type="Int"
+      type="MathError"
+    end
+  end
+end
+body
+  step id="s1" kind="compute"
+    op=div
+    input var="a"
+    input var="b"
+    as="q"
+  end
+  step id="s2" kind="return"
+    from="q"
+    as="_"
+  end
+end
+end
+"#;
+
+/// A caller returning `Int` that divides with `safe_div`, with the given
+/// handle block (possibly empty) on the call
+fn div_caller_source(handle: &str) -> String {
+    let body = format!(r#"  step id="s1" kind="call"
+    fn="math.safe_div"
+    arg name="a" lit=6
+    arg name="b" lit=3
+    as="result"
+{}  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+"#, handle);
+    format!("{}{}", SAFE_DIV, fn_snippet("app.div", &[], &[r#"returns type="Int""#], &body))
+}
+
+#[test]
+fn test_handle_block_narrows_call_result() {
+    check_source_ok(&div_caller_source(
+        r#"    handle
+      case type="MathError"
+        step id="h1" kind="return"
+          lit=0
+          as="_"
+        end
+      end
+    end
+"#,
+    ));
+}
+
+#[test]
+fn test_unhandled_error_keeps_union_result() {
+    let errors = check_source_has_errors(&div_caller_source(""));
+    assert!(
+        errors.iter().any(|e| matches!(e, covenant_checker::CheckError::TypeMismatch { .. })),
+        "Expected the unhandled error to remain in the result: {:?}", errors
+    );
+}
+
+#[test]
+fn test_handle_case_not_in_callee_union() {
+    let errors = check_source_has_errors(&div_caller_source(
+        r#"    handle
+      case type="MathError"
+        step id="h1" kind="return"
+          lit=0
+          as="_"
+        end
+      end
+      case type="IoError"
+        step id="h2" kind="return"
+          lit=1
+          as="_"
+        end
+      end
+    end
+"#,
+    ));
+    assert_eq!(errors.len(), 1, "Expected only the IoError case to fail: {:?}", errors);
+    match &errors[0] {
+        covenant_checker::CheckError::UnhandleableErrorType { function, error_type, errors, span } => {
+            assert_eq!(function, "math.safe_div");
+            assert_eq!(error_type, "IoError");
+            assert_eq!(errors, &vec!["MathError".to_string()]);
+            assert!(span.start > 0);
+        }
+        other => panic!("Expected UnhandleableErrorType, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_handle_variant_case_does_not_narrow() {
+    let errors = check_source_has_errors(&div_caller_source(
+        r#"    handle
+      case type="MathError::DivByZero"
+        step id="h1" kind="return"
+          lit=0
+          as="_"
+        end
+      end
+    end
+"#,
+    ));
+    assert!(
+        !errors.iter().any(|e| matches!(e, covenant_checker::CheckError::UnhandleableErrorType { .. })),
+        "A variant of a member error type may be handled: {:?}", errors
+    );
+    assert!(
+        errors.iter().any(|e| matches!(e, covenant_checker::CheckError::TypeMismatch { .. })),
+        "Other variants still reach the result: {:?}", errors
+    );
+}
+
+#[test]
+fn test_call_propagated_error_not_passed_as_arg() {
+    // IoError propagates to the caller, so `content` is a plain String
+    let source = r#"
+snippet id="fs.read" kind="extern"
+signature
+  fn name="read"
+    param name="path" type="String"
+    returns union
+      type="String"
+      type="IoError"
+    end
+  end
+end
+end
+
+snippet id="text.shout" kind="fn"
+signature
+  fn name="shout"
+    param name="s" type="String"
+    returns type="String"
+  end
+end
+body
+  step id="s1" kind="return"
+    from="s"
+    as="_"
+  end
+end
+end
+
+snippet id="app.load" kind="fn"
+signature
+  fn name="load"
+    returns union
+      type="String"
+      type="IoError"
+    end
+  end
+end
+body
+  step id="s1" kind="call"
+    fn="fs.read"
+    arg name="path" lit="a.txt"
+    as="content"
+  end
+  step id="s2" kind="call"
+    fn="text.shout"
+    arg name="s" from="content"
+    as="loud"
+  end
+  step id="s3" kind="return"
+    from="loud"
+    as="_"
+  end
+end
+end
+"#;
+    check_source_ok(source);
+}
+
 // === List Type Tests ===
 
 #[test]
@@ -1099,6 +1967,135 @@ end
     check_source_ok(source);
 }
 
+const USER_STRUCT: &str = r#"
+snippet id="types.User" kind="struct"
+signature
+  struct name="User"
+    field name="id" type="Int" primary auto
+    field name="name" type="String"
+    field name="age" type="Int"
+    field name="nickname" type="String" optional
+  end
+end
+end
+"#;
+
+/// Build a function that constructs a User from the given `field` lines
+fn construct_user_source(fields: &str) -> String {
+    let body = format!(r#"  step id="s1" kind="construct"
+    type="User"
+{}
+    as="user"
+  end
+  step id="s2" kind="return"
+    from="user"
+    as="_"
+  end
+"#, fields);
+    format!("{}{}", USER_STRUCT, fn_snippet("test.fn", &[], &[r#"returns type="User""#], &body))
+}
+
+#[test]
+fn test_struct_construction_ok() {
+    // `id` is auto and `nickname` is optional, so both may be omitted
+    check_source_ok(&construct_user_source(
+        "    field name=\"name\" lit=\"Ada\"\n    field name=\"age\" lit=36",
+    ));
+    check_source_ok(&construct_user_source(
+        "    field name=\"name\" lit=\"Ada\"\n    field name=\"age\" lit=36\n    field name=\"nickname\" lit=none",
+    ));
+}
+
+#[test]
+fn test_struct_construction_missing_field() {
+    let source = construct_user_source("    field name=\"name\" lit=\"Ada\"");
+    let errors = check_source_has_errors(&source);
+    assert_eq!(errors.len(), 1, "Expected one error: {:?}", errors);
+    assert!(matches!(
+        &errors[0],
+        covenant_checker::CheckError::MissingField { field, type_name, .. }
+            if field == "age" && type_name == "User"
+    ));
+}
+
+#[test]
+fn test_struct_construction_unknown_field_has_span() {
+    let source = construct_user_source(
+        "    field name=\"name\" lit=\"Ada\"\n    field name=\"age\" lit=36\n    field name=\"email\" lit=\"a@b.c\"",
+    );
+    let errors = check_source_has_errors(&source);
+    let span = errors.iter().find_map(|e| match e {
+        covenant_checker::CheckError::UnknownField { field, span, .. } if field == "email" => Some(*span),
+        _ => None,
+    });
+    let span = span.unwrap_or_else(|| panic!("Expected unknown field 'email': {:?}", errors));
+    assert!(source[span.start..span.end].contains("email"));
+}
+
+#[test]
+fn test_struct_construction_field_type_mismatch() {
+    let source = construct_user_source(
+        "    field name=\"name\" lit=\"Ada\"\n    field name=\"age\" lit=\"old\"",
+    );
+    let errors = check_source_has_errors(&source);
+    assert!(
+        errors.iter().any(|e| matches!(
+            e,
+            covenant_checker::CheckError::FieldTypeMismatch { field, expected, found, .. }
+                if field == "age" && expected == "Int" && found == "String"
+        )),
+        "Expected mismatch on 'age': {:?}", errors
+    );
+}
+
+#[test]
+fn test_variant_construction_fields() {
+    let source = r#"
+snippet id="types.ParseError" kind="enum"
+signature
+  enum name="ParseError"
+    variant name="OutOfRange"
+      field name="field" type="String"
+      field name="value" type="Int"
+    end
+  end
+end
+end
+
+snippet id="test.fn" kind="fn"
+signature
+  fn name="test"
+    returns type="ParseError"
+  end
+end
+body
+  step id="s1" kind="return"
+    variant type="ParseError::OutOfRange"
+      field name="field" lit="port"
+      field name="valu" lit=true
+    end
+    as="_"
+  end
+end
+end
+"#;
+    let errors = check_source_has_errors(source);
+    assert!(
+        errors.iter().any(|e| matches!(
+            e,
+            covenant_checker::CheckError::UnknownField { field, .. } if field == "valu"
+        )),
+        "Expected unknown field 'valu': {:?}", errors
+    );
+    assert!(
+        errors.iter().any(|e| matches!(
+            e,
+            covenant_checker::CheckError::MissingField { field, .. } if field == "value"
+        )),
+        "Expected missing field 'value': {:?}", errors
+    );
+}
+
 // === Enum Type Tests ===
 
 #[test]
@@ -1133,6 +2130,150 @@ end
     check_source_ok(source);
 }
 
+const SHAPE_ENUM: &str = r#"
+snippet id="geo.Shape" kind="enum"
+signature
+  enum name="Shape"
+    variant name="Empty"
+    end
+    variant name="Rect"
+      field name="width" type="Int"
+      field name="label" type="String"
+    end
+  end
+end
+end
+"#;
+
+/// A function matching on `Shape` whose `Rect` case has the given bindings
+/// and returns the given binding
+fn shape_match_source(bindings: &str, returned: &str) -> String {
+    let body = format!(
+        r#"  step id="s1" kind="match"
+    on="shape"
+    case variant type="Shape::Empty"
+      step id="c1" kind="return"
+        lit=0
+        as="_"
+      end
+    end
+    case variant type="Shape::Rect" bindings=({})
+      step id="c2" kind="return"
+        from="{}"
+        as="_"
+      end
+    end
+    as="_"
+  end
+"#,
+        bindings, returned
+    );
+    let signature = [r#"param name="shape" type="Shape""#, r#"returns type="Int""#];
+    format!("{}{}", SHAPE_ENUM, fn_snippet("geo.width", &[], &signature, &body))
+}
+
+#[test]
+fn test_variant_bindings_take_field_types_by_position() {
+    check_source_ok(&shape_match_source(r#""w", "l""#, "w"));
+
+    let errors = check_source_has_errors(&shape_match_source(r#""w", "l""#, "l"));
+    assert!(
+        errors.iter().any(|e| matches!(
+            e,
+            covenant_checker::CheckError::TypeMismatch { found, .. } if found == "String"
+        )),
+        "Expected the second binding to be the String field: {:?}", errors
+    );
+}
+
+#[test]
+fn test_variant_pattern_too_many_bindings() {
+    let errors = check_source_has_errors(&shape_match_source(r#""w", "l", "extra""#, "w"));
+    assert!(
+        errors.iter().any(|e| matches!(
+            e,
+            covenant_checker::CheckError::TooManyPatternBindings { expected: 2, found: 3, .. }
+        )),
+        "Expected too many bindings: {:?}", errors
+    );
+}
+
+#[test]
+fn test_optional_some_pattern_binds_value() {
+    let source = r#"
+snippet id="app.or_zero" kind="fn"
+signature
+  fn name="or_zero"
+    param name="x" type="Optional<Int>"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="match"
+    on="x"
+    case variant type="Some" bindings=("value")
+      step id="c1" kind="return"
+        from="value"
+        as="_"
+      end
+    end
+    case variant type="None"
+      step id="c2" kind="return"
+        lit=0
+        as="_"
+      end
+    end
+    as="_"
+  end
+end
+end
+"#;
+    check_source_ok(source);
+}
+
+#[test]
+fn test_construct_step_builds_enum_variant() {
+    let source = r#"
+snippet id="geo.Shape" kind="enum"
+signature
+  enum name="Shape"
+    variant name="Rect"
+      field name="width" type="Int"
+      field name="height" type="Int"
+    end
+  end
+end
+end
+
+snippet id="geo.square" kind="fn"
+signature
+  fn name="square"
+    param name="side" type="Int"
+    returns type="Shape"
+  end
+end
+body
+  step id="s1" kind="construct"
+    type="Shape::Rect"
+    field name="width" from="side"
+    as="shape"
+  end
+  step id="s2" kind="return"
+    from="shape"
+    as="_"
+  end
+end
+end
+"#;
+    let errors = check_source_has_errors(source);
+    assert_eq!(errors.len(), 1, "Expected only the missing field: {:?}", errors);
+    assert!(matches!(
+        &errors[0],
+        covenant_checker::CheckError::MissingField { field, type_name, .. }
+            if field == "height" && type_name == "Shape::Rect"
+    ));
+}
+
 // === Chain of Operations ===
 
 #[test]
@@ -0,0 +1,581 @@
+//! Package dependencies
+//!
+//! Dependencies in `covenant.toml` are local directories or vendored,
+//! uncompressed tarballs. Each owns a snippet id prefix: its snippets must
+//! be under it, and no other package may define ids under it.
+//! `covenant.lock` records the content hash of every dependency snippet, so
+//! an updated dependency is noticed.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use covenant_ast::SourceMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::manifest::{Dependency, DependencySource, Manifest, LOCK_FILE_NAME};
+use crate::project::Project;
+
+/// Lockfile format version
+const LOCK_VERSION: u32 = 1;
+
+/// Errors resolving dependencies
+#[derive(Debug, Error)]
+pub enum DependencyError {
+    #[error("cannot read dependency `{name}` from {path}: {message}")]
+    Io { name: String, path: String, message: String },
+
+    #[error("invalid dependency name `{name}`: use letters, digits, `_` and `-`")]
+    InvalidName { name: String },
+
+    #[error("dependency `{name}` is not unpacked to {dir}; run `covenant build`")]
+    NotUnpacked { name: String, dir: String },
+
+    #[error("invalid tarball {path}: {message}")]
+    Tarball { path: String, message: String },
+
+    #[error("snippet `{id}` of dependency `{dependency}` is outside its prefix `{prefix}`")]
+    OutsidePrefix { dependency: String, id: String, prefix: String },
+
+    #[error("snippet `{id}` is defined by both {first} and {second}")]
+    ConflictingId { id: String, first: String, second: String },
+
+    #[error("invalid lockfile {path}: {message}")]
+    Lockfile { path: String, message: String },
+}
+
+/// A dependency with its sources on disk
+#[derive(Debug, Clone)]
+pub struct ResolvedDependency {
+    pub name: String,
+    pub prefix: String,
+    pub source: DependencySource,
+    /// Directory of the sources: the path itself, or the unpacked tarball
+    pub dir: PathBuf,
+}
+
+/// Whether [`fetch`] may write tarball contents to disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchMode {
+    /// Unpack each tarball, replacing its earlier copy
+    Unpack,
+    /// Use the copies the last build unpacked, leaving the disk untouched
+    Existing,
+}
+
+/// Make the sources of each dependency available. Path dependencies are
+/// used in place; tarballs are unpacked to `<output>/deps/<name>`.
+pub fn fetch(manifest: &Manifest, mode: FetchMode) -> Result<Vec<ResolvedDependency>, DependencyError> {
+    let mut resolved = Vec::new();
+    for (name, dependency) in &manifest.dependencies {
+        let source = dependency.source();
+        let dir = match &source {
+            DependencySource::Path(path) => {
+                let dir = manifest.dir.join(path);
+                if !dir.is_dir() {
+                    return Err(DependencyError::Io {
+                        name: name.clone(),
+                        path: dir.display().to_string(),
+                        message: "not a directory".to_string(),
+                    });
+                }
+                dir
+            }
+            DependencySource::Tarball(path) => {
+                let dir = unpack_dir(manifest, name)?;
+                if mode == FetchMode::Existing {
+                    if !dir.is_dir() {
+                        return Err(DependencyError::NotUnpacked {
+                            name: name.clone(),
+                            dir: dir.display().to_string(),
+                        });
+                    }
+                    resolved.push(ResolvedDependency::new(name, dependency, source, dir));
+                    continue;
+                }
+                let archive = manifest.dir.join(path);
+                let data = fs::read(&archive).map_err(|e| DependencyError::Io {
+                    name: name.clone(),
+                    path: archive.display().to_string(),
+                    message: e.to_string(),
+                })?;
+                unpack_tarball(&data, &dir).map_err(|message| DependencyError::Tarball {
+                    path: archive.display().to_string(),
+                    message,
+                })?;
+                dir
+            }
+        };
+        resolved.push(ResolvedDependency::new(name, dependency, source, dir));
+    }
+    Ok(resolved)
+}
+
+impl ResolvedDependency {
+    fn new(name: &str, dependency: &Dependency, source: DependencySource, dir: PathBuf) -> Self {
+        Self {
+            name: name.to_string(),
+            prefix: dependency.prefix.clone().unwrap_or_else(|| name.to_string()),
+            source,
+            dir,
+        }
+    }
+}
+
+/// Where a tarball dependency is unpacked: `<output>/deps/<name>`. The
+/// name must be a plain identifier, so the directory cannot be elsewhere.
+fn unpack_dir(manifest: &Manifest, name: &str) -> Result<PathBuf, DependencyError> {
+    let invalid = || DependencyError::InvalidName { name: name.to_string() };
+    let plain = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !plain {
+        return Err(invalid());
+    }
+    let deps = manifest.output_dir().join("deps");
+    let dir = deps.join(name);
+    if dir.parent() != Some(deps.as_path()) {
+        return Err(invalid());
+    }
+    Ok(dir)
+}
+
+/// Unpack the `.cov` files of an uncompressed tar archive into `dest`,
+/// replacing what was there
+fn unpack_tarball(data: &[u8], dest: &Path) -> Result<(), String> {
+    if data.starts_with(&[0x1f, 0x8b]) {
+        return Err("compressed tarballs are not supported; vendor an uncompressed .tar".to_string());
+    }
+    if dest.exists() {
+        fs::remove_dir_all(dest).map_err(|e| e.to_string())?;
+    }
+    fs::create_dir_all(dest).map_err(|e| e.to_string())?;
+
+    let mut offset = 0;
+    let mut long_name = None;
+    while offset + 512 <= data.len() {
+        let header = &data[offset..offset + 512];
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let size = parse_octal(&header[124..136]).ok_or("invalid entry size")?;
+        let body_start = offset + 512;
+        let body = data.get(body_start..body_start + size).ok_or("truncated entry")?;
+        let name = match long_name.take() {
+            Some(name) => name,
+            None => {
+                let name = c_string(&header[0..100]);
+                let prefix = if &header[257..262] == b"ustar" { c_string(&header[345..500]) } else { String::new() };
+                if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) }
+            }
+        };
+
+        match header[156] {
+            // GNU long name for the next entry
+            b'L' => long_name = Some(c_string(body)),
+            b'0' | 0 if name.ends_with(".cov") => {
+                let path = Path::new(&name);
+                if !path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+                    return Err(format!("unsafe path `{}`", name));
+                }
+                let target = dest.join(path);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                fs::write(&target, body).map_err(|e| e.to_string())?;
+            }
+            // Directories, links, other files and extended headers
+            _ => {}
+        }
+        offset = body_start + size.div_ceil(512) * 512;
+    }
+    Ok(())
+}
+
+fn parse_octal(field: &[u8]) -> Option<usize> {
+    let text = c_string(field);
+    let text = text.trim();
+    if text.is_empty() {
+        return Some(0);
+    }
+    usize::from_str_radix(text, 8).ok()
+}
+
+fn c_string(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).to_string()
+}
+
+/// SHA-256 of a snippet's source text, as in `Node.content_hash`
+pub fn content_hash(text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(text.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// `covenant.lock`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: u32,
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+/// A dependency as locked: its source and the hash of each snippet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub name: String,
+    pub source: String,
+    pub prefix: String,
+    /// Content hash by snippet id
+    pub snippets: BTreeMap<String, String>,
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Self { version: LOCK_VERSION, packages: Vec::new() }
+    }
+}
+
+impl Lockfile {
+    /// Load a lockfile; `None` if there is none
+    pub fn load(path: &Path) -> Result<Option<Self>, DependencyError> {
+        let error = |message: String| DependencyError::Lockfile { path: path.display().to_string(), message };
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let source = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+        let lockfile: Lockfile = toml::from_str(&source).map_err(|e| error(e.to_string()))?;
+        if lockfile.version != LOCK_VERSION {
+            return Err(error(format!("unsupported version {}", lockfile.version)));
+        }
+        Ok(Some(lockfile))
+    }
+
+    pub fn to_toml(&self) -> String {
+        let body = toml::to_string(self).expect("lockfile serializes to TOML");
+        format!("# {} is generated by covenant. Do not edit.\n\n{}", LOCK_FILE_NAME, body)
+    }
+
+    fn package(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|package| package.name == name)
+    }
+
+    /// How the packages of `newer` differ from these
+    pub fn updates(&self, newer: &Lockfile) -> Vec<PackageUpdate> {
+        let mut updates = Vec::new();
+        for package in &newer.packages {
+            let Some(old) = self.package(&package.name) else {
+                updates.push(PackageUpdate::new(&package.name, UpdateKind::Added));
+                continue;
+            };
+            let mut changes = SnippetChanges {
+                source: (old.source != package.source).then(|| old.source.clone()),
+                ..SnippetChanges::default()
+            };
+            for (id, hash) in &package.snippets {
+                match old.snippets.get(id) {
+                    None => changes.added.push(id.clone()),
+                    Some(old_hash) if old_hash != hash => changes.changed.push(id.clone()),
+                    Some(_) => {}
+                }
+            }
+            changes.removed = old.snippets.keys().filter(|id| !package.snippets.contains_key(*id)).cloned().collect();
+            if !changes.is_empty() {
+                updates.push(PackageUpdate::new(&package.name, UpdateKind::Changed(changes)));
+            }
+        }
+        for package in &self.packages {
+            if newer.package(&package.name).is_none() {
+                updates.push(PackageUpdate::new(&package.name, UpdateKind::Removed));
+            }
+        }
+        updates
+    }
+}
+
+/// A dependency that differs from the lockfile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageUpdate {
+    pub name: String,
+    pub kind: UpdateKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateKind {
+    Added,
+    Removed,
+    Changed(SnippetChanges),
+}
+
+/// Snippet ids added, removed and changed in an updated dependency
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnippetChanges {
+    /// The previous source, if the source moved
+    pub source: Option<String>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl SnippetChanges {
+    fn is_empty(&self) -> bool {
+        self.source.is_none() && self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl PackageUpdate {
+    fn new(name: &str, kind: UpdateKind) -> Self {
+        Self { name: name.to_string(), kind }
+    }
+}
+
+impl fmt::Display for PackageUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            UpdateKind::Added => write!(f, "added dependency `{}`", self.name),
+            UpdateKind::Removed => write!(f, "removed dependency `{}`", self.name),
+            UpdateKind::Changed(changes) => {
+                write!(
+                    f,
+                    "updated dependency `{}`: {} changed, {} added, {} removed",
+                    self.name,
+                    changes.changed.len(),
+                    changes.added.len(),
+                    changes.removed.len()
+                )?;
+                if let Some(source) = &changes.source {
+                    write!(f, " (was {})", source)?;
+                }
+                for (label, ids) in [("changed", &changes.changed), ("added", &changes.added), ("removed", &changes.removed)] {
+                    for id in ids {
+                        write!(f, "\n    {} {}", label, id)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Check that each dependency's snippets are under its prefix and that no
+/// snippet id is defined twice, then lock the dependencies
+pub fn lock(
+    project: &Project,
+    dependencies: &[ResolvedDependency],
+    sources: &SourceMap,
+) -> Result<Lockfile, Vec<DependencyError>> {
+    let mut errors = Vec::new();
+    let mut owners: HashMap<String, String> = HashMap::new();
+    let mut claim = |id: &str, owner: String, errors: &mut Vec<DependencyError>| {
+        if let Some(first) = owners.get(id) {
+            errors.push(DependencyError::ConflictingId {
+                id: id.to_string(),
+                first: first.clone(),
+                second: owner,
+            });
+        } else {
+            owners.insert(id.to_string(), owner);
+        }
+    };
+
+    // Project snippets may not take ids a dependency owns
+    for snippet in project.snippets().iter().filter(|s| project.files.contains(&s.span.file)) {
+        match dependencies.iter().find(|d| has_prefix(&snippet.id, &d.prefix)) {
+            // Reported here, so the dependency's own snippet claims the id
+            Some(dependency) => errors.push(DependencyError::ConflictingId {
+                id: snippet.id.clone(),
+                first: format!("dependency `{}` (prefix `{}`)", dependency.name, dependency.prefix),
+                second: "the project".to_string(),
+            }),
+            None => claim(&snippet.id, "the project".to_string(), &mut errors),
+        }
+    }
+
+    let mut lockfile = Lockfile::default();
+    for dependency in dependencies {
+        let files = project
+            .libraries
+            .iter()
+            .find(|library| library.dir == dependency.dir)
+            .map(|library| library.files.as_slice())
+            .unwrap_or_default();
+        let mut snippets = BTreeMap::new();
+        for snippet in project.snippets().iter().filter(|s| files.contains(&s.span.file)) {
+            if !has_prefix(&snippet.id, &dependency.prefix) {
+                errors.push(DependencyError::OutsidePrefix {
+                    dependency: dependency.name.clone(),
+                    id: snippet.id.clone(),
+                    prefix: dependency.prefix.clone(),
+                });
+            }
+            claim(&snippet.id, format!("dependency `{}`", dependency.name), &mut errors);
+            let text = sources
+                .file_of(snippet.span)
+                .and_then(|file| file.source.get(snippet.span.start..snippet.span.end))
+                .unwrap_or_default();
+            snippets.insert(snippet.id.clone(), content_hash(text));
+        }
+        lockfile.packages.push(LockedPackage {
+            name: dependency.name.clone(),
+            source: dependency.source.to_string(),
+            prefix: dependency.prefix.clone(),
+            snippets,
+        });
+    }
+
+    if errors.is_empty() {
+        Ok(lockfile)
+    } else {
+        Err(errors)
+    }
+}
+
+/// True if `id` is `prefix` or under `prefix.`
+fn has_prefix(id: &str, prefix: &str) -> bool {
+    id.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippet(id: &str) -> String {
+        format!("snippet id=\"{}\" kind=\"fn\"\nsignature\n  fn name=\"f\"\n    returns type=\"Int\"\n  end\nend\nend\n", id)
+    }
+
+    /// A ustar archive of `files`
+    fn tarball(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for (name, content) in files {
+            let mut header = [0u8; 512];
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            header[100..107].copy_from_slice(b"0000644");
+            header[124..135].copy_from_slice(format!("{:011o}", content.len()).as_bytes());
+            header[156] = b'0';
+            header[257..263].copy_from_slice(b"ustar\0");
+            header[148..156].copy_from_slice(b"        ");
+            let checksum: u32 = header.iter().map(|&b| b as u32).sum();
+            header[148..155].copy_from_slice(format!("{:06o}\0", checksum).as_bytes());
+            data.extend_from_slice(&header);
+            data.extend_from_slice(content.as_bytes());
+            data.resize(data.len().div_ceil(512) * 512, 0);
+        }
+        data.resize(data.len() + 1024, 0);
+        data
+    }
+
+    fn load(dir: &Path) -> (Manifest, Project, Vec<ResolvedDependency>, SourceMap) {
+        let manifest = Manifest::load(&dir.join("covenant.toml")).unwrap();
+        let dependencies = fetch(&manifest, FetchMode::Unpack).unwrap();
+        let libraries: Vec<PathBuf> = dependencies.iter().map(|d| d.dir.clone()).collect();
+        let mut sources = SourceMap::new();
+        let project = Project::load_all(&manifest.source_roots(), &libraries, &mut sources).unwrap();
+        (manifest, project, dependencies, sources)
+    }
+
+    #[test]
+    fn test_lock_and_detect_updates() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("app/src")).unwrap();
+        fs::create_dir_all(root.join("billing")).unwrap();
+        fs::create_dir_all(root.join("app/vendor")).unwrap();
+        fs::write(
+            root.join("app/covenant.toml"),
+            "[project]\nname = \"app\"\n\n[dependencies]\nbilling = { path = \"../billing\" }\ntax = { tarball = \"vendor/tax.tar\", prefix = \"acme.tax\" }\n",
+        )
+        .unwrap();
+        fs::write(root.join("app/src/main.cov"), snippet("app.main")).unwrap();
+        fs::write(root.join("billing/charge.cov"), snippet("billing.charge")).unwrap();
+        let tax = snippet("acme.tax.rate");
+        fs::write(root.join("app/vendor/tax.tar"), tarball(&[("tax/rate.cov", &tax), ("tax/README", "docs")])).unwrap();
+
+        let (manifest, project, dependencies, sources) = load(&root.join("app"));
+        assert!(manifest.output_dir().join("deps/tax/tax/rate.cov").is_file());
+        let locked = lock(&project, &dependencies, &sources).unwrap();
+        let tax_package = &locked.packages[1];
+        assert_eq!(tax_package.source, "tarball:vendor/tax.tar");
+        assert_eq!(tax_package.snippets["acme.tax.rate"], content_hash(tax.trim_end()));
+
+        let parsed: Lockfile = toml::from_str(&locked.to_toml()).unwrap();
+        assert_eq!(parsed, locked);
+        assert!(locked.updates(&parsed).is_empty());
+
+        // Change one snippet and add another
+        fs::write(
+            root.join("billing/charge.cov"),
+            snippet("billing.charge").replace("Int", "Float") + &snippet("billing.refund"),
+        )
+        .unwrap();
+        let (_, project, dependencies, sources) = load(&root.join("app"));
+        let updates = locked.updates(&lock(&project, &dependencies, &sources).unwrap());
+        assert_eq!(updates.len(), 1);
+        assert_eq!(
+            updates[0].to_string(),
+            "updated dependency `billing`: 1 changed, 1 added, 0 removed\n    changed billing.charge\n    added billing.refund"
+        );
+        assert_eq!(Lockfile::default().updates(&locked)[0].kind, UpdateKind::Added);
+    }
+
+    #[test]
+    fn test_conflicting_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("billing")).unwrap();
+        fs::write(root.join("covenant.toml"), "[project]\nname = \"app\"\n\n[dependencies]\nbilling = { path = \"billing\" }\n").unwrap();
+        fs::write(root.join("src/main.cov"), snippet("billing.charge")).unwrap();
+        fs::write(root.join("billing/charge.cov"), snippet("billing.charge") + &snippet("util.helper")).unwrap();
+
+        let (_, project, dependencies, sources) = load(root);
+        let errors: Vec<String> = lock(&project, &dependencies, &sources).unwrap_err().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "snippet `billing.charge` is defined by both dependency `billing` (prefix `billing`) and the project",
+                "snippet `util.helper` of dependency `billing` is outside its prefix `billing`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fetch_rejects_unsafe_names_and_existing_leaves_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("vendor")).unwrap();
+        fs::write(root.join("vendor/tax.tar"), tarball(&[("rate.cov", &snippet("tax.rate"))])).unwrap();
+        let manifest = |name: &str| {
+            Manifest::from_toml_str(
+                &format!("[project]\nname = \"app\"\n\n[dependencies]\n\"{}\" = {{ tarball = \"vendor/tax.tar\" }}\n", name),
+                root,
+            )
+            .unwrap()
+        };
+
+        for name in ["..", "../src", "a/b", ""] {
+            assert!(matches!(fetch(&manifest(name), FetchMode::Unpack), Err(DependencyError::InvalidName { .. })));
+        }
+
+        let manifest = manifest("tax");
+        let unpacked = manifest.output_dir().join("deps/tax");
+        assert!(matches!(fetch(&manifest, FetchMode::Existing), Err(DependencyError::NotUnpacked { .. })));
+        assert!(!unpacked.exists());
+        fetch(&manifest, FetchMode::Unpack).unwrap();
+        fs::write(unpacked.join("local.cov"), "").unwrap();
+        assert_eq!(fetch(&manifest, FetchMode::Existing).unwrap()[0].dir, unpacked);
+        assert!(unpacked.join("local.cov").is_file());
+    }
+
+    #[test]
+    fn test_unpack_rejects_unsafe_and_compressed() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("out");
+        assert!(unpack_tarball(&tarball(&[("../escape.cov", "x")]), &dest).unwrap_err().contains("unsafe path"));
+        assert!(unpack_tarball(&[0x1f, 0x8b, 8, 0], &dest).unwrap_err().contains("compressed"));
+        assert!(has_prefix("acme.tax", "acme.tax") && has_prefix("acme.tax.rate", "acme.tax"));
+        assert!(!has_prefix("acme.taxes", "acme.tax"));
+    }
+}
@@ -1,20 +1,22 @@
 //! Covenant CLI - Command line interface for the Covenant compiler
 
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::{Parser, Subcommand};
 use ariadne::{Color, Label, Report, ReportKind, Source};
 
-use covenant_parser::parse;
+use covenant_parser::{parse, parse_file, parse_file_with_recovery, parse_with_recovery, ParseError, ParseOutput};
+use covenant_ast::{FileId, Program, SourceMap};
 use covenant_ast::printer::to_cov;
-use covenant_symbols::build_symbol_graph;
+use covenant_symbols::{build_from_snippets, build_symbol_graph};
 use covenant_checker::{
-    check, check_effects, check_effects_with_diagnostics, EffectError,
-    Diagnostic,
+    check, check_effects, check_effects_with_policy, CheckResult, Severity, explain_effect_violation, EffectError, EffectHierarchy,
+    EffectPolicy, Diagnostic, DiagnosticFormat, JsonDiagnostic, plan_fixes, program_diagnostics,
+    render_json, splice_fixes, try_fix, FixError, DEFAULT_FIX_CONFIDENCE, lookup_code, ERROR_CODES,
 };
 use covenant_graph::{GraphBuilder, execute_query, parse_query};
-use covenant_codegen::{compile_pure, compile_with_symbols};
+use covenant_codegen::{compile_pure, compile_with_embedding, compile_with_symbols, node_permission_flag, CapabilityManifest};
 use covenant_llm::{
     ExplainGenerator, ExplanationCache, LlmClient,
     Verbosity, ExplainFormat, format_explanation,
@@ -22,6 +24,14 @@ use covenant_llm::{
 use covenant_requirements::{validate_program, format_report, ReportFormat, filter_uncovered, has_coverage_errors};
 use covenant_optimizer::{optimize, OptSettings, OptLevel};
 
+mod deps;
+mod manifest;
+mod project;
+
+use deps::{FetchMode, Lockfile, ResolvedDependency};
+use manifest::{Manifest, ManifestError, LOCK_FILE_NAME, MANIFEST_FILE_NAME};
+use project::Project;
+
 #[derive(Parser)]
 #[command(name = "covenant")]
 #[command(about = "Covenant programming language compiler", long_about = None)]
@@ -42,11 +52,26 @@ enum Commands {
     },
     /// Type check a file
     Check {
-        /// Input file(s)
+        /// Input file(s), or project root(s) with --project
         files: Vec<PathBuf>,
+        /// Check all .cov files under each root (default: .) as one project
+        #[arg(long)]
+        project: bool,
+        /// Standard library directory (default: $COVENANT_STD or the nearest runtime/std)
+        #[arg(long, requires = "project")]
+        std: Option<PathBuf>,
+        /// Do not load the standard library
+        #[arg(long, requires = "project", conflicts_with = "std")]
+        no_std: bool,
         /// Also validate requirement coverage
         #[arg(long)]
         requirements: bool,
+        /// Effect policy file (default: nearest covenant-policy.toml)
+        #[arg(long)]
+        policy: Option<PathBuf>,
+        /// Diagnostic output format (text, json, jsonl, sarif)
+        #[arg(long, default_value = "text")]
+        format: String,
     },
     /// Compile a file to WASM
     Compile {
@@ -61,11 +86,46 @@ enum Commands {
         /// Optimization level (0=none, 1=basic, 2=standard, 3=aggressive)
         #[arg(long, default_value = "0")]
         optimize: u8,
+        /// Diagnostic output format (text, json, jsonl, sarif)
+        #[arg(long, default_value = "text")]
+        format: String,
+    },
+    /// Create a project: covenant.toml and src/main.cov
+    Init {
+        /// Project directory (default: .)
+        path: Option<PathBuf>,
+        /// Project name (default: the directory name)
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Check and compile the project to WASM in its output directory
+    Build {
+        /// Manifest (default: the nearest covenant.toml)
+        #[arg(long)]
+        manifest_path: Option<PathBuf>,
+        /// Target platform, overriding the manifest (deno, node, browser, wasi)
+        #[arg(long)]
+        target: Option<String>,
+        /// Optimization level, overriding the manifest (0-3)
+        #[arg(long)]
+        optimize: Option<u8>,
+        /// Fail instead of updating covenant.lock
+        #[arg(long)]
+        locked: bool,
     },
     /// Query the codebase
     Query {
-        /// Input file(s) to analyze
+        /// Input file(s) to analyze, or project root(s) with --project
         files: Vec<PathBuf>,
+        /// Query all .cov files under each root (default: .) as one project
+        #[arg(long)]
+        project: bool,
+        /// Standard library directory (default: $COVENANT_STD or the nearest runtime/std)
+        #[arg(long, requires = "project")]
+        std: Option<PathBuf>,
+        /// Do not load the standard library
+        #[arg(long, requires = "project", conflicts_with = "std")]
+        no_std: bool,
         /// Query string (e.g., "select * from functions where is_pure = true")
         #[arg(short, long)]
         query: String,
@@ -89,6 +149,11 @@ enum Commands {
         #[arg(long)]
         no_cache: bool,
     },
+    /// Explain an error code, e.g. E-EFFECT-002
+    ExplainError {
+        /// Error code
+        code: String,
+    },
     /// Analyze effect declarations and compute transitive closures
     Effects {
         /// Input file(s) to analyze
@@ -99,12 +164,18 @@ enum Commands {
         /// Show verbose diagnostics with call chains and fix suggestions
         #[arg(long)]
         explain: bool,
+        /// Effect policy file (default: nearest covenant-policy.toml)
+        #[arg(long)]
+        policy: Option<PathBuf>,
+        /// Diagnostic output format (text, json, jsonl, sarif)
+        #[arg(long, default_value = "text")]
+        format: String,
     },
     /// Analyze requirement coverage
     Requirements {
         /// Input file(s) to analyze
         files: Vec<PathBuf>,
-        /// Output format (text, json, markdown)
+        /// Output format (text, json, markdown, sarif)
         #[arg(long, default_value = "text")]
         report: String,
         /// Show only uncovered requirements
@@ -128,6 +199,20 @@ enum Commands {
         #[arg(long)]
         check: bool,
     },
+    /// Apply suggested fixes for errors
+    Fix {
+        /// Input file(s)
+        files: Vec<PathBuf>,
+        /// Apply fixes at or above this confidence (0.0-1.0)
+        #[arg(long, default_value_t = DEFAULT_FIX_CONFIDENCE)]
+        min_confidence: f64,
+        /// Ask before applying each fix, whatever its confidence
+        #[arg(short, long)]
+        interactive: bool,
+        /// Print the fixed source instead of writing it back
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Interactive REPL
     Repl,
     /// Compile and run a file
@@ -137,6 +222,9 @@ enum Commands {
         /// Optimization level (0=none, 1=basic, 2=standard, 3=aggressive)
         #[arg(long, default_value = "0")]
         optimize: u8,
+        /// Diagnostic output format (text, json, jsonl, sarif)
+        #[arg(long, default_value = "text")]
+        format: String,
     },
 }
 
@@ -146,20 +234,48 @@ async fn main() {
 
     match cli.command {
         Commands::Parse { file, pretty } => cmd_parse(&file, pretty),
-        Commands::Check { files, requirements } => cmd_check(&files, requirements),
-        Commands::Compile { file, output, target, optimize: opt_level } => cmd_compile(&file, output, &target, opt_level),
-        Commands::Query { files, query } => cmd_query(&files, &query),
+        Commands::Check { files, project, std, no_std, requirements, policy, format } => {
+            let format = parse_diagnostic_format(&format);
+            if project {
+                let std = StdOption { path: std, disabled: no_std };
+                cmd_check_project(&project_roots(files), &std, requirements, policy.as_deref(), format);
+            } else {
+                cmd_check(&files, requirements, policy.as_deref(), format);
+            }
+        }
+        Commands::Compile { file, output, target, optimize: opt_level, format } => {
+            cmd_compile(&file, output, &target, opt_level, parse_diagnostic_format(&format))
+        }
+        Commands::Init { path, name } => cmd_init(path.as_deref(), name.as_deref()),
+        Commands::Build { manifest_path, target, optimize: opt_level, locked } => {
+            cmd_build(manifest_path.as_deref(), target, opt_level, locked);
+        }
+        Commands::Query { files, project, std, no_std, query } => {
+            if project {
+                let std = StdOption { path: std, disabled: no_std };
+                cmd_query_project(&project_roots(files), &std, &query);
+            } else {
+                cmd_query(&files, &query);
+            }
+        }
         Commands::Info { file } => cmd_info(&file),
         Commands::Explain { file, format, verbosity, no_cache } => {
             cmd_explain(&file, &format, &verbosity, no_cache).await;
         }
-        Commands::Effects { files, violations_only, explain } => cmd_effects(&files, violations_only, explain),
+        Commands::ExplainError { code } => cmd_explain_error(&code),
+        Commands::Effects { files, violations_only, explain, policy, format } => {
+            let format = parse_diagnostic_format(&format);
+            cmd_effects(&files, violations_only, explain, policy.as_deref(), format);
+        }
         Commands::Requirements { files, report, uncovered_only, min_coverage, strict } => {
             cmd_requirements(&files, &report, uncovered_only, min_coverage, strict);
         }
         Commands::Format { file, output, check } => cmd_format(&file, output, check),
+        Commands::Fix { files, min_confidence, interactive, dry_run } => {
+            cmd_fix(&files, min_confidence, interactive, dry_run);
+        }
         Commands::Repl => cmd_repl(),
-        Commands::Run { file, optimize: opt_level } => cmd_run(&file, opt_level),
+        Commands::Run { file, optimize: opt_level, format } => cmd_run(&file, opt_level, parse_diagnostic_format(&format)),
     }
 }
 
@@ -188,8 +304,110 @@ fn cmd_parse(file: &PathBuf, pretty: bool) {
     }
 }
 
-fn cmd_check(files: &[PathBuf], validate_requirements: bool) {
+/// Load the effect policy given with `--policy`, or else the nearest
+/// `covenant-policy.toml` above `file` (or in it, for a project root)
+fn load_policy(explicit: Option<&Path>, file: &Path) -> Result<EffectPolicy, String> {
+    let path = match explicit {
+        Some(path) => Some(path.to_path_buf()),
+        None => {
+            let dir = if file.is_dir() {
+                file
+            } else {
+                file.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."))
+            };
+            let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+            EffectPolicy::discover(&dir)
+        }
+    };
+    match path {
+        Some(path) => EffectPolicy::load(&path).map_err(|e| e.to_string()),
+        None => Ok(EffectPolicy::new()),
+    }
+}
+
+fn parse_diagnostic_format(format: &str) -> DiagnosticFormat {
+    format.parse().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    })
+}
+
+/// Diagnostics for `--format json`, `jsonl` and `sarif`. JSON Lines are
+/// printed as diagnostics are reported; a JSON array or SARIF log once all
+/// files are done.
+struct DiagnosticOutput {
+    format: DiagnosticFormat,
+    sources: SourceMap,
+    diagnostics: Vec<JsonDiagnostic>,
+}
+
+impl DiagnosticOutput {
+    fn new(format: DiagnosticFormat) -> Self {
+        Self { format, sources: SourceMap::new(), diagnostics: Vec::new() }
+    }
+
+    fn add_file(&mut self, file: &Path, source: &str) -> FileId {
+        self.sources.add(file.display().to_string(), source)
+    }
+
+    fn is_text(&self) -> bool {
+        self.format == DiagnosticFormat::Text
+    }
+
+    fn report(&mut self, file: FileId, diagnostic: Diagnostic) {
+        let json = diagnostic.to_json(&self.sources, file);
+        if self.format == DiagnosticFormat::JsonLines {
+            println!("{}", render_json(std::slice::from_ref(&json), self.format));
+        } else {
+            self.diagnostics.push(json);
+        }
+    }
+
+    /// Report a diagnostic; in text mode print it on one line with its
+    /// location
+    fn report_line(&mut self, file: FileId, diagnostic: Diagnostic) {
+        if !self.is_text() {
+            self.report(file, diagnostic);
+            return;
+        }
+        let json = diagnostic.to_json(&self.sources, file);
+        let location = &json.source_location;
+        let location = if location.file.is_empty() {
+            String::new()
+        } else {
+            let position = location.span.map(|span| format!(":{}:{}", span.line, span.column)).unwrap_or_default();
+            format!("{}{}: ", location.file, position)
+        };
+        eprintln!("  {}[{}]: {}{}", json.severity, json.code, location, json.message);
+    }
+
+    /// Report a diagnostic that belongs to no file: one with neither a span
+    /// nor a snippet of a loaded file. Its location's file is left empty.
+    fn report_unlocated(&mut self, diagnostic: Diagnostic) {
+        // No file has this ID yet
+        let nowhere = FileId(self.sources.len() as u32);
+        self.report_line(nowhere, diagnostic);
+    }
+
+    /// Print a progress message; on stderr when stdout carries diagnostics
+    fn status(&self, message: impl std::fmt::Display) {
+        if self.is_text() {
+            println!("{}", message);
+        } else {
+            eprintln!("{}", message);
+        }
+    }
+
+    fn finish(&self) {
+        if matches!(self.format, DiagnosticFormat::Json | DiagnosticFormat::Sarif) {
+            println!("{}", render_json(&self.diagnostics, self.format));
+        }
+    }
+}
+
+fn cmd_check(files: &[PathBuf], validate_requirements: bool, policy: Option<&Path>, format: DiagnosticFormat) {
     let mut all_ok = true;
+    let mut output = DiagnosticOutput::new(format);
 
     for file in files {
         let source = match fs::read_to_string(file) {
@@ -200,167 +418,761 @@ fn cmd_check(files: &[PathBuf], validate_requirements: bool) {
                 continue;
             }
         };
+        let policy = match load_policy(policy, file) {
+            Ok(policy) => policy,
+            Err(e) => {
+                eprintln!("Error loading effect policy: {}", e);
+                all_ok = false;
+                continue;
+            }
+        };
+        let file_id = output.add_file(file, &source);
 
-        match parse(&source) {
-            Ok(program) => {
-                // Phase 2: Symbol graph building
-                let symbol_result = match build_symbol_graph(&program) {
-                    Ok(result) => {
-                        // Report deferred errors (undefined references) as warnings
-                        for err in &result.deferred_errors {
-                            eprintln!("  warning: {}", err);
-                        }
-                        result
+        // Phase 1: Parsing. The snippets that parsed are still checked.
+        let ParseOutput { program, errors: parse_errors } = parse_file_with_recovery(&source, file_id);
+        let parsed = parse_errors.is_empty();
+        for e in parse_errors {
+            if output.is_text() {
+                report_parse_error(&source, file, &e);
+            } else {
+                output.report(file_id, Diagnostic::from(e));
+            }
+        }
+        if !parsed {
+            all_ok = false;
+            let nothing_parsed = match &program {
+                Program::Snippets { snippets, .. } => snippets.is_empty(),
+                Program::Legacy { declarations, .. } => declarations.is_empty(),
+            };
+            if nothing_parsed {
+                continue;
+            }
+        }
+
+        // Phase 2: Symbol graph building
+        let symbol_result = match build_symbol_graph(&program) {
+            Ok(result) => {
+                // Report deferred errors (undefined references) as warnings
+                for err in &result.deferred_errors {
+                    if output.is_text() {
+                        eprintln!("  warning: {}", err);
+                    } else {
+                        output.report(file_id, Diagnostic::from(err.clone()));
                     }
-                    Err(errors) => {
-                        eprintln!("✗ {} - {} symbol errors:", file.display(), errors.len());
-                        for err in &errors {
-                            eprintln!("  {}: {}", err.code(), err);
-                        }
-                        all_ok = false;
-                        continue;
+                }
+                result
+            }
+            Err(errors) => {
+                if output.is_text() {
+                    eprintln!("✗ {} - {} symbol errors:", file.display(), errors.len());
+                    for err in &errors {
+                        eprintln!("  {}: {}", err.code(), err);
                     }
-                };
+                } else {
+                    for err in errors {
+                        output.report(file_id, Diagnostic::from(err));
+                    }
+                }
+                all_ok = false;
+                continue;
+            }
+        };
 
-                // Phase 3-4: Type checking
-                match check(&program) {
-                    Ok(result) => {
-                        let fn_count = result.symbols.functions().count();
-                        let pure_count = result.symbols.functions()
-                            .filter(|s| result.effects.is_pure(s.id))
-                            .count();
-                        let symbol_count = symbol_result.graph.len();
-
-                        // Phase 5: Requirement validation (optional)
-                        let req_info = if validate_requirements {
-                            let req_report = validate_program(&program, None);
-                            let has_errors = has_coverage_errors(&req_report);
-                            if has_errors {
-                                all_ok = false;
+        // Phase 3-4: Type checking
+        match check(&program) {
+            Ok(result) => {
+                let fn_count = result.symbols.functions().count();
+                let pure_count = result.symbols.functions()
+                    .filter(|s| result.effects.is_pure(s.id))
+                    .count();
+                let symbol_count = symbol_result.graph.len();
+
+                // Effect policy (only when the project has one)
+                if !policy.is_empty() {
+                    let effects = check_effects_with_policy(&symbol_result.graph, &policy);
+                    let policy_violations: Vec<&EffectError> = effects.violations.iter()
+                        .filter(|e| matches!(e, EffectError::PolicyViolation { .. }))
+                        .collect();
+                    if !policy_violations.is_empty() {
+                        if output.is_text() {
+                            eprintln!(
+                                "✗ {} - {} effect policy violations:",
+                                file.display(),
+                                policy_violations.len()
+                            );
+                            for error in policy_violations {
+                                report_effect_error_concise(error);
                             }
-                            Some((req_report.summary.coverage_percent, has_errors))
                         } else {
-                            None
-                        };
-
-                        // Print status line
-                        if let Some((coverage, has_errors)) = req_info {
-                            if has_errors {
-                                eprintln!(
-                                    "✗ {} - {} symbols, {} functions ({} pure), requirements: {:.0}% coverage (errors)",
-                                    file.display(),
-                                    symbol_count,
-                                    fn_count,
-                                    pure_count,
-                                    coverage
-                                );
-                            } else {
-                                println!(
-                                    "✓ {} - {} symbols, {} functions ({} pure), requirements: {:.0}% coverage",
-                                    file.display(),
-                                    symbol_count,
-                                    fn_count,
-                                    pure_count,
-                                    coverage
-                                );
+                            for error in policy_violations {
+                                let diagnostic = explain_effect_violation(error, &symbol_result.graph);
+                                output.report(file_id, diagnostic);
                             }
-                        } else {
-                            println!(
-                                "✓ {} - {} symbols, {} functions ({} pure)",
-                                file.display(),
-                                symbol_count,
-                                fn_count,
-                                pure_count
-                            );
                         }
+                        all_ok = false;
+                        continue;
                     }
-                    Err(errors) => {
-                        eprintln!("✗ {} - {} type errors:", file.display(), errors.len());
-                        for err in errors {
-                            eprintln!("  {}", err);
-                        }
+                }
+
+                // Phase 5: Requirement validation (optional)
+                let req_info = if validate_requirements {
+                    let req_report = validate_program(&program, None);
+                    let has_errors = has_coverage_errors(&req_report);
+                    if has_errors {
                         all_ok = false;
                     }
+                    if !output.is_text() {
+                        for err in &req_report.errors {
+                            output.report(file_id, Diagnostic::from(err.clone()));
+                        }
+                    }
+                    Some((req_report.summary.coverage_percent, has_errors))
+                } else {
+                    None
+                };
+                if !output.is_text() {
+                    continue;
+                }
+
+                // Print status line
+                if !parsed {
+                    eprintln!(
+                        "✗ {} - syntax errors; the rest has {} symbols, {} functions ({} pure)",
+                        file.display(),
+                        symbol_count,
+                        fn_count,
+                        pure_count
+                    );
+                } else if let Some((coverage, has_errors)) = req_info {
+                    if has_errors {
+                        eprintln!(
+                            "✗ {} - {} symbols, {} functions ({} pure), requirements: {:.0}% coverage (errors)",
+                            file.display(),
+                            symbol_count,
+                            fn_count,
+                            pure_count,
+                            coverage
+                        );
+                    } else {
+                        println!(
+                            "✓ {} - {} symbols, {} functions ({} pure), requirements: {:.0}% coverage",
+                            file.display(),
+                            symbol_count,
+                            fn_count,
+                            pure_count,
+                            coverage
+                        );
+                    }
+                } else {
+                    println!(
+                        "✓ {} - {} symbols, {} functions ({} pure)",
+                        file.display(),
+                        symbol_count,
+                        fn_count,
+                        pure_count
+                    );
+                }
+            }
+            Err(errors) => {
+                if output.is_text() {
+                    eprintln!("✗ {} - {} type errors:", file.display(), errors.len());
+                    for err in errors {
+                        eprintln!("  {}", err);
+                    }
+                } else {
+                    for err in errors {
+                        output.report(file_id, Diagnostic::from(err));
+                    }
                 }
+                all_ok = false;
+            }
+        }
+    }
+
+    output.finish();
+    if !all_ok {
+        std::process::exit(1);
+    }
+}
+
+/// Roots given to `--project`, or the current directory
+fn project_roots(roots: Vec<PathBuf>) -> Vec<PathBuf> {
+    if roots.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        roots
+    }
+}
+
+/// The `--std` and `--no-std` options of project mode
+struct StdOption {
+    path: Option<PathBuf>,
+    disabled: bool,
+}
+
+impl StdOption {
+    /// The standard library of a project: `--std`, else the manifest's, else
+    /// discovered
+    fn find(&self, root: &Path, manifest: Option<&Manifest>) -> Option<PathBuf> {
+        if self.disabled {
+            return None;
+        }
+        let explicit = self.path.clone().or_else(|| manifest.and_then(Manifest::std_dir));
+        project::find_std(explicit.as_deref(), root)
+    }
+}
+
+/// The manifest in a project root, if it has one
+fn root_manifest(root: &Path) -> Result<Option<Manifest>, ManifestError> {
+    let path = root.join(MANIFEST_FILE_NAME);
+    if path.is_file() {
+        Manifest::load(&path).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Load a project: the manifest's source roots and dependencies, or else
+/// every `.cov` file under `root`, with the standard library
+fn load_project(
+    root: &Path,
+    manifest: Option<&Manifest>,
+    dependencies: &[ResolvedDependency],
+    std: &StdOption,
+    sources: &mut SourceMap,
+) -> std::io::Result<Project> {
+    let roots = manifest.map_or_else(|| vec![root.to_path_buf()], Manifest::source_roots);
+    let mut libraries: Vec<PathBuf> = std.find(root, manifest).into_iter().collect();
+    libraries.extend(dependencies.iter().map(|dependency| dependency.dir.clone()));
+    Project::load_all(&roots, &libraries, sources)
+}
+
+/// The dependencies of a manifest as the last build left them on disk; none
+/// without one
+fn fetch_dependencies(manifest: Option<&Manifest>) -> Result<Vec<ResolvedDependency>, deps::DependencyError> {
+    manifest.map_or(Ok(Vec::new()), |manifest| deps::fetch(manifest, FetchMode::Existing))
+}
+
+/// What to do when dependencies differ from `covenant.lock`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LockMode {
+    /// Report the differences
+    Report,
+    /// Report them and rewrite the lockfile
+    Update,
+    /// Fail
+    Locked,
+}
+
+/// Check that dependency snippets are under their prefixes and no id is
+/// defined twice, then compare the dependencies with `covenant.lock`.
+/// Returns false if resolution failed or, when locked, the lockfile is out
+/// of date.
+fn lock_dependencies(
+    manifest: &Manifest,
+    project: &Project,
+    dependencies: &[ResolvedDependency],
+    sources: &SourceMap,
+    mode: LockMode,
+) -> bool {
+    let lockfile = match deps::lock(project, dependencies, sources) {
+        Ok(lockfile) => lockfile,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("  error: {}", error);
             }
+            return false;
+        }
+    };
+    let path = manifest.lock_file();
+    let previous = match Lockfile::load(&path) {
+        Ok(previous) => previous,
+        Err(e) => {
+            eprintln!("  error: {}", e);
+            return false;
+        }
+    };
+    // A project without dependencies needs no lockfile
+    if previous.is_none() && lockfile.packages.is_empty() {
+        return true;
+    }
+
+    let updates = previous.unwrap_or_default().updates(&lockfile);
+    for update in &updates {
+        eprintln!("  {}", update);
+    }
+    if updates.is_empty() {
+        return true;
+    }
+    match mode {
+        LockMode::Report => true,
+        LockMode::Locked => {
+            eprintln!("  error: {} is out of date; build without --locked to update it", LOCK_FILE_NAME);
+            false
+        }
+        LockMode::Update => match fs::write(&path, lockfile.to_toml()) {
+            Ok(()) => true,
             Err(e) => {
-                report_parse_error(&source, file, &e);
+                eprintln!("Error writing {}: {}", path.display(), e);
+                false
+            }
+        },
+    }
+}
+
+/// Outcome of checking a project
+struct ProjectCheck {
+    /// Errors reported
+    errors: usize,
+    /// Symbols, functions and pure functions, if type checking succeeded
+    summary: Option<(usize, usize, usize)>,
+    /// Requirement coverage fell short
+    coverage_errors: bool,
+}
+
+impl ProjectCheck {
+    fn is_ok(&self) -> bool {
+        self.errors == 0 && self.summary.is_some() && !self.coverage_errors
+    }
+}
+
+/// Check each root as one project: every `.cov` file under it, with the
+/// standard library. Diagnostics in the standard library are not reported.
+fn cmd_check_project(
+    roots: &[PathBuf],
+    std: &StdOption,
+    validate_requirements: bool,
+    policy: Option<&Path>,
+    format: DiagnosticFormat,
+) {
+    let mut all_ok = true;
+    let mut output = DiagnosticOutput::new(format);
+
+    for root in roots {
+        let manifest = match root_manifest(root) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                eprintln!("Error loading {}: {}", root.join(MANIFEST_FILE_NAME).display(), e);
+                all_ok = false;
+                continue;
+            }
+        };
+        let dependencies = match fetch_dependencies(manifest.as_ref()) {
+            Ok(dependencies) => dependencies,
+            Err(e) => {
+                eprintln!("Error resolving dependencies of {}: {}", root.display(), e);
+                all_ok = false;
+                continue;
+            }
+        };
+        let mut project = match load_project(root, manifest.as_ref(), &dependencies, std, &mut output.sources) {
+            Ok(project) => project,
+            Err(e) => {
+                eprintln!("Error reading {}: {}", root.display(), e);
                 all_ok = false;
+                continue;
+            }
+        };
+        let policy_file = policy.map(Path::to_path_buf).or_else(|| manifest.as_ref().and_then(Manifest::policy_file));
+        let policy = match load_policy(policy_file.as_deref(), root) {
+            Ok(policy) => policy,
+            Err(e) => {
+                eprintln!("Error loading effect policy: {}", e);
+                all_ok = false;
+                continue;
+            }
+        };
+
+        let result = check_project(&mut project, &policy, validate_requirements, &mut output);
+        all_ok &= result.is_ok();
+        if let Some(manifest) = &manifest {
+            all_ok &= lock_dependencies(manifest, &project, &dependencies, &output.sources, LockMode::Report);
+        }
+
+        if output.is_text() {
+            let files = project.files.len();
+            match result.summary {
+                Some((symbols, fns, pure)) if result.errors == 0 => println!(
+                    "✓ {} - {} files, {} symbols, {} functions ({} pure)",
+                    root.display(), files, symbols, fns, pure
+                ),
+                _ => eprintln!("✗ {} - {} files, {} errors", root.display(), files, result.errors),
+            }
+        }
+    }
+
+    output.finish();
+    if !all_ok {
+        std::process::exit(1);
+    }
+}
+
+/// Run every phase over a project and report its diagnostics, leaving out
+/// those in library files
+fn check_project(
+    project: &mut Project,
+    policy: &EffectPolicy,
+    validate_requirements: bool,
+    output: &mut DiagnosticOutput,
+) -> ProjectCheck {
+    let mut diagnostics = Vec::new();
+    let mut coverage_errors = false;
+
+    // Phase 1: Parsing. The snippets that parsed are still checked; a
+    // library snippet that failed to parse is left out.
+    for (file, error) in std::mem::take(&mut project.parse_errors) {
+        if !project.is_library(file) {
+            diagnostics.push(Diagnostic::from(error));
+        } else if output.is_text() {
+            let name = output.sources.name(file);
+            match error {
+                // Blocks out of order are parsed anyway, so the snippet is kept
+                ParseError::InvalidOrdering { .. } => eprintln!("  warning: in {}: {}", name, error),
+                _ => eprintln!("  warning: skipping a snippet of {}: {}", name, error),
             }
         }
     }
 
-    if !all_ok {
+    // Phase 2: One symbol graph for the whole project
+    let symbol_result = match build_from_snippets(project.snippets()) {
+        Ok(result) => {
+            diagnostics.extend(result.deferred_errors.iter().cloned().map(Diagnostic::from));
+            Some(result)
+        }
+        Err(errors) => {
+            diagnostics.extend(errors.into_iter().map(Diagnostic::from));
+            None
+        }
+    };
+
+    // Phases 3-5: Types, effects and requirements
+    let mut summary = None;
+    if let Some(symbol_result) = &symbol_result {
+        match check(&project.program) {
+            Ok(result) => {
+                let fn_count = result.symbols.functions().count();
+                let pure_count = result.symbols.functions()
+                    .filter(|s| result.effects.is_pure(s.id))
+                    .count();
+                summary = Some((symbol_result.graph.len(), fn_count, pure_count));
+            }
+            Err(errors) => diagnostics.extend(errors.into_iter().map(Diagnostic::from)),
+        }
+
+        let effects = check_effects_with_policy(&symbol_result.graph, policy);
+        diagnostics.extend(
+            effects.violations.iter().map(|error| explain_effect_violation(error, &symbol_result.graph)),
+        );
+
+        if validate_requirements {
+            let config = covenant_requirements::ValidatorConfig::default_config();
+            let req_report = validate_program(&project.program, Some(config.clone()));
+            coverage_errors = has_coverage_errors(&req_report);
+            diagnostics.extend(req_report.errors.iter().map(|error| {
+                let severity = error.severity_with_config(&config).into();
+                Diagnostic::from(error.clone()).with_severity(severity)
+            }));
+        }
+    }
+
+    let mut errors = 0;
+    for diagnostic in diagnostics {
+        let file = project.file_of(&diagnostic);
+        if file.is_some_and(|file| project.is_library(file)) {
+            continue;
+        }
+        if diagnostic.severity == Severity::Error {
+            errors += 1;
+        }
+        match file {
+            Some(file) => output.report_line(file, diagnostic),
+            None => output.report_unlocated(diagnostic),
+        }
+    }
+
+    ProjectCheck { errors, summary, coverage_errors }
+}
+
+/// Map an optimization level flag
+fn map_opt_level(opt_level: u8) -> OptLevel {
+    match opt_level {
+        0 => OptLevel::O0,
+        1 => OptLevel::O1,
+        2 => OptLevel::O2,
+        _ => OptLevel::O3,
+    }
+}
+
+/// Run the optimizer over each snippet's body if the level is above 0,
+/// returning its warnings located at the steps they are about
+fn optimize_program(program: &mut Program, opt_level: OptLevel) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
+    if opt_level == OptLevel::O0 {
+        return warnings;
+    }
+    let settings = OptSettings {
+        level: opt_level,
+        emit_warnings: true,
+    };
+
+    // Optimize each snippet's body
+    if let Program::Snippets { ref mut snippets, .. } = program {
+        for snippet in snippets.iter_mut() {
+            // Find the body section and optimize its steps
+            for section in snippet.sections.iter_mut() {
+                if let covenant_ast::Section::Body(ref mut body) = section {
+                    // Step spans before passes remove any steps
+                    let mut spans = std::collections::HashMap::new();
+                    covenant_ast::walk_steps(&body.steps, &mut |step| {
+                        spans.insert(step.id.clone(), step.span);
+                    });
+
+                    let opt_result = optimize(&mut body.steps, &settings);
+                    for warning in opt_result.warnings {
+                        let span = warning.step_id.as_ref().and_then(|id| spans.get(id)).copied();
+                        let mut diagnostic = Diagnostic::from(warning).with_snippet(snippet.id.clone());
+                        if let Some(span) = span {
+                            diagnostic.span = span;
+                        }
+                        warnings.push(diagnostic);
+                    }
+                }
+            }
+        }
+    }
+    warnings
+}
+
+/// Parse and type check the single file of `compile` and `run`, exiting on
+/// errors
+fn check_file(file: &PathBuf, output: &mut DiagnosticOutput) -> (Program, FileId, CheckResult) {
+    let source = match fs::read_to_string(file) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Error reading file: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let file_id = output.add_file(file, &source);
+
+    let program = match parse(&source) {
+        Ok(p) => p,
+        Err(e) if output.is_text() => {
+            report_parse_error(&source, file, &e);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            output.report(file_id, Diagnostic::from(e));
+            output.finish();
+            std::process::exit(1);
+        }
+    };
+
+    match check(&program) {
+        Ok(result) => (program, file_id, result),
+        Err(errors) if output.is_text() => {
+            eprintln!("Type check errors:");
+            for err in errors {
+                eprintln!("  {}", err);
+            }
+            std::process::exit(1);
+        }
+        Err(errors) => {
+            for err in errors {
+                output.report(file_id, Diagnostic::from(err));
+            }
+            output.finish();
+            std::process::exit(1);
+        }
+    }
+}
+
+fn cmd_init(path: Option<&Path>, name: Option<&str>) {
+    let dir = path.unwrap_or(Path::new("."));
+    let manifest_path = dir.join(MANIFEST_FILE_NAME);
+    if manifest_path.exists() {
+        eprintln!("{} already exists", manifest_path.display());
         std::process::exit(1);
     }
-}
 
-fn cmd_compile(file: &PathBuf, output: Option<PathBuf>, target: &str, opt_level: u8) {
-    // Validate target platform
-    let valid_targets = ["deno", "node", "browser", "wasi"];
-    if !valid_targets.contains(&target) {
-        eprintln!("Invalid target '{}'. Valid targets: deno, node, browser, wasi", target);
+    if let Err(e) = fs::create_dir_all(dir.join("src")) {
+        eprintln!("Error creating project: {}", e);
         std::process::exit(1);
     }
 
-    // Map optimization level
-    let opt_level = match opt_level {
-        0 => OptLevel::O0,
-        1 => OptLevel::O1,
-        2 => OptLevel::O2,
-        _ => OptLevel::O3,
+    // The name prefixes snippet ids, so it must be an identifier
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => dir
+            .canonicalize()
+            .ok()
+            .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "app".to_string()),
     };
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+
+    let main_path = dir.join("src").join("main.cov");
+    let result = fs::write(&manifest_path, manifest::scaffold_manifest(&name))
+        .and_then(|_| {
+            if main_path.exists() {
+                Ok(())
+            } else {
+                fs::write(&main_path, manifest::scaffold_main(&name))
+            }
+        })
+        .and_then(|_| {
+            let gitignore = dir.join(".gitignore");
+            if gitignore.exists() {
+                Ok(())
+            } else {
+                fs::write(gitignore, "/target\n")
+            }
+        });
+    if let Err(e) = result {
+        eprintln!("Error creating project: {}", e);
+        std::process::exit(1);
+    }
+    println!("Created project `{}` in {}", name, dir.display());
+}
 
-    let source = match fs::read_to_string(file) {
-        Ok(s) => s,
+/// Check the project of a manifest and compile it to
+/// `<output>/<target>/<name>.wasm`, with its capability manifest alongside.
+/// `covenant.lock` is updated unless `locked`.
+fn cmd_build(manifest_path: Option<&Path>, target: Option<String>, opt_level: Option<u8>, locked: bool) {
+    let manifest_path = match manifest_path {
+        Some(path) => path.to_path_buf(),
+        None => match Manifest::discover(&std::env::current_dir().unwrap_or_default()) {
+            Some(path) => path,
+            None => {
+                eprintln!("No {} found; run `covenant init` to create one", MANIFEST_FILE_NAME);
+                std::process::exit(1);
+            }
+        },
+    };
+    let manifest = match Manifest::load(&manifest_path) {
+        Ok(manifest) => manifest,
         Err(e) => {
-            eprintln!("Error reading file: {}", e);
+            eprintln!("Error loading {}: {}", manifest_path.display(), e);
             std::process::exit(1);
         }
     };
+    let target = target.unwrap_or_else(|| manifest.build.target.clone());
+    if !manifest::TARGETS.contains(&target.as_str()) {
+        eprintln!("Invalid target '{}'. Valid targets: {}", target, manifest::TARGETS.join(", "));
+        std::process::exit(1);
+    }
+    let opt_level = map_opt_level(opt_level.unwrap_or(manifest.build.optimize));
 
-    let mut program = match parse(&source) {
-        Ok(p) => p,
+    let root = manifest.dir.clone();
+    let std = StdOption { path: None, disabled: false };
+    let mut output = DiagnosticOutput::new(DiagnosticFormat::Text);
+    let dependencies = match deps::fetch(&manifest, FetchMode::Unpack) {
+        Ok(dependencies) => dependencies,
         Err(e) => {
-            report_parse_error(&source, file, &e);
+            eprintln!("Error resolving dependencies: {}", e);
             std::process::exit(1);
         }
     };
-
-    let result = match check(&program) {
-        Ok(r) => r,
-        Err(errors) => {
-            eprintln!("Type check errors:");
-            for err in errors {
-                eprintln!("  {}", err);
-            }
+    let mut project = match load_project(&root, Some(&manifest), &dependencies, &std, &mut output.sources) {
+        Ok(project) => project,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", root.display(), e);
+            std::process::exit(1);
+        }
+    };
+    let policy = match load_policy(manifest.policy_file().as_deref(), &root) {
+        Ok(policy) => policy,
+        Err(e) => {
+            eprintln!("Error loading effect policy: {}", e);
             std::process::exit(1);
         }
     };
 
-    // Run optimizer if level > 0
-    if opt_level != OptLevel::O0 {
-        let settings = OptSettings {
-            level: opt_level,
-            emit_warnings: true,
-        };
+    let result = check_project(&mut project, &policy, false, &mut output);
+    if !result.is_ok() {
+        eprintln!("✗ {} - {} errors", manifest.project.name, result.errors);
+        std::process::exit(1);
+    }
+    let mode = if locked { LockMode::Locked } else { LockMode::Update };
+    if !lock_dependencies(&manifest, &project, &dependencies, &output.sources, mode) {
+        std::process::exit(1);
+    }
+    let missing: Vec<&String> = manifest.project.entry.iter()
+        .filter(|entry| !project.snippets().iter().any(|snippet| &snippet.id == *entry))
+        .collect();
+    if !missing.is_empty() {
+        for entry in missing {
+            eprintln!("  error: entry snippet `{}` is not defined", entry);
+        }
+        std::process::exit(1);
+    }
 
-        // Optimize each snippet's body
-        if let covenant_ast::Program::Snippets { ref mut snippets, .. } = program {
-            for snippet in snippets.iter_mut() {
-                // Find the body section and optimize its steps
-                for section in snippet.sections.iter_mut() {
-                    if let covenant_ast::Section::Body(ref mut body) = section {
-                        let opt_result = optimize(&mut body.steps, &settings);
-
-                        // Report warnings
-                        for warning in &opt_result.warnings {
-                            eprintln!("{}: {}", warning.code, warning.message);
-                        }
-                    }
-                }
-            }
+    // The checks passed, so each phase succeeds again here
+    let Ok(result) = check(&project.program) else { std::process::exit(1) };
+    for warning in optimize_program(&mut project.program, opt_level) {
+        match project.file_of(&warning) {
+            Some(file) if project.is_library(file) => {}
+            Some(file) => output.report_line(file, warning),
+            None => output.report_unlocated(warning),
+        }
+    }
+    let Ok(symbol_result) = build_from_snippets(project.snippets()) else { std::process::exit(1) };
+    let symbol_graph = symbol_result.graph;
+    let symbols = result.symbols;
+    let effect_result = check_effects_with_policy(&symbol_graph, &policy);
+
+    let wasm = match compile_with_embedding(
+        &project.program,
+        &symbols,
+        &symbol_graph,
+        &effect_result,
+        manifest.build.embed,
+        &manifest.project.entry,
+    ) {
+        Ok(wasm) => wasm,
+        Err(e) => {
+            eprintln!("Compilation error: {}", e);
+            std::process::exit(1);
         }
+    };
+
+    let out_dir = manifest.output_dir().join(&target);
+    let out_path = out_dir.join(format!("{}.wasm", manifest.project.name));
+    let manifest_json = CapabilityManifest::from_effects(&effect_result).to_json();
+    let written = fs::create_dir_all(&out_dir)
+        .and_then(|_| fs::write(&out_path, &wasm))
+        .and_then(|_| fs::write(out_path.with_extension("capabilities.json"), manifest_json));
+    if let Err(e) = written {
+        eprintln!("Error writing {}: {}", out_dir.display(), e);
+        std::process::exit(1);
+    }
+    println!(
+        "Built {} v{} ({}) to {} ({} bytes)",
+        manifest.project.name,
+        manifest.project.version,
+        target,
+        out_path.display(),
+        wasm.len()
+    );
+}
+
+fn cmd_compile(file: &PathBuf, output: Option<PathBuf>, target: &str, opt_level: u8, format: DiagnosticFormat) {
+    // Validate target platform
+    let valid_targets = ["deno", "node", "browser", "wasi"];
+    if !valid_targets.contains(&target) {
+        eprintln!("Invalid target '{}'. Valid targets: deno, node, browser, wasi", target);
+        std::process::exit(1);
+    }
+
+    let opt_level = map_opt_level(opt_level);
+
+    let mut diagnostics = DiagnosticOutput::new(format);
+    let (mut program, file_id, result) = check_file(file, &mut diagnostics);
+    for warning in optimize_program(&mut program, opt_level) {
+        diagnostics.report_line(file_id, warning);
     }
 
     // Build symbol graph and run effect checking for symbol metadata embedding
@@ -382,9 +1194,17 @@ fn cmd_compile(file: &PathBuf, output: Option<PathBuf>, target: &str, opt_level:
                 p
             });
             fs::write(&out_path, &wasm).expect("Failed to write output");
-            println!("Compiled to {} ({} bytes)", out_path.display(), wasm.len());
+            diagnostics.status(format!("Compiled to {} ({} bytes)", out_path.display(), wasm.len()));
+
+            // Sidecar capability manifest next to the module
+            let manifest = CapabilityManifest::from_effects(&effect_result);
+            let manifest_path = out_path.with_extension("capabilities.json");
+            fs::write(&manifest_path, manifest.to_json()).expect("Failed to write capability manifest");
+            diagnostics.status(format!("Capability manifest: {}", manifest_path.display()));
+            diagnostics.finish();
         }
         Err(e) => {
+            diagnostics.finish();
             eprintln!("Compilation error: {}", e);
             std::process::exit(1);
         }
@@ -421,6 +1241,51 @@ fn cmd_query(files: &[PathBuf], query_str: &str) {
         }
     }
 
+    run_query(&all_programs, query_str);
+}
+
+/// Query one project per root; files that fail to parse are left out
+fn cmd_query_project(roots: &[PathBuf], std: &StdOption, query_str: &str) {
+    let mut all_programs = Vec::new();
+
+    for root in roots {
+        let manifest = match root_manifest(root) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                eprintln!("Error loading {}: {}", root.join(MANIFEST_FILE_NAME).display(), e);
+                continue;
+            }
+        };
+        let dependencies = match fetch_dependencies(manifest.as_ref()) {
+            Ok(dependencies) => dependencies,
+            Err(e) => {
+                eprintln!("Error resolving dependencies of {}: {}", root.display(), e);
+                continue;
+            }
+        };
+        let mut sources = SourceMap::new();
+        let project = match load_project(root, manifest.as_ref(), &dependencies, std, &mut sources) {
+            Ok(project) => project,
+            Err(e) => {
+                eprintln!("Error reading {}: {}", root.display(), e);
+                continue;
+            }
+        };
+        let mut skipped: Vec<FileId> = project.parse_errors.iter().map(|(file, _)| *file).collect();
+        skipped.dedup();
+        for file in skipped {
+            eprintln!("Skipping snippets of {} due to parse errors", sources.name(file));
+        }
+        match check(&project.program) {
+            Ok(result) => all_programs.push((project.program, result)),
+            Err(_) => eprintln!("Skipping {} due to type errors", root.display()),
+        }
+    }
+
+    run_query(&all_programs, query_str);
+}
+
+fn run_query(all_programs: &[(Program, CheckResult)], query_str: &str) {
     // Parse the query
     let query = match parse_query(query_str) {
         Some(q) => q,
@@ -431,7 +1296,7 @@ fn cmd_query(files: &[PathBuf], query_str: &str) {
     };
 
     // Execute query against each program
-    for (program, result) in &all_programs {
+    for (program, result) in all_programs {
         let graph_builder = GraphBuilder::new(&result.symbols);
         let graph = graph_builder.build(program);
 
@@ -511,9 +1376,16 @@ fn cmd_info(file: &PathBuf) {
     println!("Legend: ○ = pure, ● = effectful");
 }
 
-fn cmd_effects(files: &[PathBuf], violations_only: bool, explain: bool) {
+fn cmd_effects(
+    files: &[PathBuf],
+    violations_only: bool,
+    explain: bool,
+    policy: Option<&Path>,
+    format: DiagnosticFormat,
+) {
     let mut all_ok = true;
     let mut total_violations = 0;
+    let mut output = DiagnosticOutput::new(format);
 
     for file in files {
         let source = match fs::read_to_string(file) {
@@ -524,11 +1396,24 @@ fn cmd_effects(files: &[PathBuf], violations_only: bool, explain: bool) {
                 continue;
             }
         };
+        let policy = match load_policy(policy, file) {
+            Ok(policy) => policy,
+            Err(e) => {
+                eprintln!("Error loading effect policy: {}", e);
+                all_ok = false;
+                continue;
+            }
+        };
+        let file_id = output.add_file(file, &source);
 
-        let program = match parse(&source) {
+        let program = match parse_file(&source, file_id) {
             Ok(p) => p,
             Err(e) => {
-                report_parse_error(&source, file, &e);
+                if output.is_text() {
+                    report_parse_error(&source, file, &e);
+                } else {
+                    output.report(file_id, Diagnostic::from(e));
+                }
                 all_ok = false;
                 continue;
             }
@@ -538,9 +1423,15 @@ fn cmd_effects(files: &[PathBuf], violations_only: bool, explain: bool) {
         let symbol_result = match build_symbol_graph(&program) {
             Ok(result) => result,
             Err(errors) => {
-                eprintln!("✗ {} - {} symbol errors:", file.display(), errors.len());
-                for err in &errors {
-                    eprintln!("  {}: {}", err.code(), err);
+                if output.is_text() {
+                    eprintln!("✗ {} - {} symbol errors:", file.display(), errors.len());
+                    for err in &errors {
+                        eprintln!("  {}: {}", err.code(), err);
+                    }
+                } else {
+                    for err in errors {
+                        output.report(file_id, Diagnostic::from(err));
+                    }
                 }
                 all_ok = false;
                 continue;
@@ -548,13 +1439,26 @@ fn cmd_effects(files: &[PathBuf], violations_only: bool, explain: bool) {
         };
 
         // Run effect checking (Phase 3)
-        let (result, diagnostics) = if explain {
-            check_effects_with_diagnostics(&symbol_result.graph)
+        let result = check_effects_with_policy(&symbol_result.graph, &policy);
+        let diagnostics: Vec<Diagnostic> = if explain || !output.is_text() {
+            result.violations.iter()
+                .map(|err| explain_effect_violation(err, &symbol_result.graph))
+                .collect()
         } else {
-            (check_effects(&symbol_result.graph), Vec::new())
+            Vec::new()
         };
         total_violations += result.violations.len();
 
+        if !output.is_text() {
+            if !result.violations.is_empty() {
+                all_ok = false;
+            }
+            for diagnostic in diagnostics {
+                output.report(file_id, diagnostic);
+            }
+            continue;
+        }
+
         if !violations_only {
             println!("File: {}", file.display());
             println!();
@@ -575,7 +1479,11 @@ fn cmd_effects(files: &[PathBuf], violations_only: bool, explain: bool) {
                 println!("Pure functions:");
                 for (name, closure) in &pure_fns {
                     let status = if closure.computed.is_empty() { "✓" } else { "✗" };
-                    println!("  {} {}", status, name);
+                    if closure.effect_vars.is_empty() {
+                        println!("  {} {}", status, name);
+                    } else {
+                        println!("  {} {} [effects of: {}]", status, name, closure.effect_vars.join(", "));
+                    }
                 }
                 println!();
             }
@@ -585,11 +1493,21 @@ fn cmd_effects(files: &[PathBuf], violations_only: bool, explain: bool) {
                 for (name, closure) in &effectful_fns {
                     let declared: Vec<_> = closure.declared.iter().collect();
                     let computed: Vec<_> = closure.computed.iter().collect();
-                    let status = if closure.declared == closure.computed { "✓" } else { "✗" };
+                    let covered = closure.computed.iter()
+                        .all(|c| closure.declared.iter().any(|d| result.hierarchy.covers(d, c)));
+                    let status = if covered { "✓" } else { "✗" };
                     println!("  {} {} [declared: {:?}, computed: {:?}]", status, name, declared, computed);
                 }
                 println!();
             }
+
+            if !result.hierarchy.is_empty() {
+                println!("Effect hierarchy:");
+                for root in result.hierarchy.roots() {
+                    print_effect_tree(&result.hierarchy, root, &mut Vec::new());
+                }
+                println!();
+            }
         }
 
         // Report violations
@@ -599,7 +1517,7 @@ fn cmd_effects(files: &[PathBuf], violations_only: bool, explain: bool) {
             if explain {
                 // Use rich diagnostics with ariadne
                 for (error, diagnostic) in result.violations.iter().zip(diagnostics.iter()) {
-                    report_effect_error_rich(&source, file, error, diagnostic);
+                    report_effect_error_rich(&output.sources, error, diagnostic);
                 }
             } else {
                 // Use concise format
@@ -612,8 +1530,10 @@ fn cmd_effects(files: &[PathBuf], violations_only: bool, explain: bool) {
         }
     }
 
+    output.finish();
+
     // Summary
-    if violations_only {
+    if violations_only && output.is_text() {
         if total_violations == 0 {
             println!("No effect violations found");
         } else {
@@ -626,40 +1546,69 @@ fn cmd_effects(files: &[PathBuf], violations_only: bool, explain: bool) {
     }
 }
 
+/// Print an effect and the effects its declaration covers, indented by depth
+fn print_effect_tree<'a>(hierarchy: &'a EffectHierarchy, effect: &'a str, path: &mut Vec<&'a str>) {
+    let indent = "  ".repeat(path.len() + 1);
+    if path.contains(&effect) {
+        println!("{}{} (cycle)", indent, effect);
+        return;
+    }
+    println!("{}{}", indent, effect);
+    path.push(effect);
+    for child in hierarchy.children(effect) {
+        print_effect_tree(hierarchy, child, path);
+    }
+    path.pop();
+}
+
 /// Report an effect error in concise format
 fn report_effect_error_concise(error: &EffectError) {
     match error {
         EffectError::PureCallsEffectful { function, callee, effects, span } => {
             eprintln!(
-                "  E-EFFECT-001 [{}:{}]: pure function `{}` calls effectful `{}` (effects: {:?})",
-                span.start, span.end, function, callee, effects
+                "  {} [{}:{}]: pure function `{}` calls effectful `{}` (effects: {:?})",
+                error.code(), span.start, span.end, function, callee, effects
             );
         }
         EffectError::MissingEffect { function, missing, source_callee, span } => {
             eprintln!(
-                "  E-EFFECT-002 [{}:{}]: function `{}` missing effect declarations {:?} (from `{}`)",
-                span.start, span.end, function, missing, source_callee
+                "  {} [{}:{}]: function `{}` missing effect declarations {:?} (from `{}`)",
+                error.code(), span.start, span.end, function, missing, source_callee
             );
         }
         EffectError::ParameterNotCovered {
             function, effect_name, param_name, required_value, declared_value, source_callee, span
         } => {
             eprintln!(
-                "  E-EFFECT-003 [{}:{}]: function `{}` effect `{}` parameter `{}` not covered (required: {}, declared: {:?}, from `{}`)",
-                span.start, span.end, function, effect_name, param_name, required_value, declared_value, source_callee
+                "  {} [{}:{}]: function `{}` effect `{}` parameter `{}` not covered (required: {}, declared: {:?}, from `{}`)",
+                error.code(), span.start, span.end, function, effect_name, param_name, required_value, declared_value, source_callee
+            );
+        }
+        EffectError::ImpureFunctionArgument { function, callee, param, argument, effects, span } => {
+            eprintln!(
+                "  {} [{}:{}]: function `{}` passes effectful `{}` (effects: {:?}) to parameter `{}` of `{}` without an effect variable",
+                error.code(), span.start, span.end, function, argument, effects, param, callee
+            );
+        }
+        EffectError::UndeclaredEffectVariable { function, callee, param, argument, span } => {
+            eprintln!(
+                "  {} [{}:{}]: function `{}` passes parameter `{}` to effect variable `{}` of `{}` without declaring `effect of=\"{}\"`",
+                error.code(), span.start, span.end, function, argument, param, callee, argument
+            );
+        }
+        EffectError::PolicyViolation { function, effect, reason, call_chain, span } => {
+            eprintln!(
+                "  {} [{}:{}]: function `{}` uses `{}`, forbidden by effect policy ({}); via {}",
+                error.code(), span.start, span.end, function, effect, reason, call_chain.join(" -> ")
             );
         }
     }
 }
 
 /// Report an effect error with rich diagnostics using ariadne
-fn report_effect_error_rich(source: &str, file: &PathBuf, error: &EffectError, diagnostic: &Diagnostic) {
-    let file_name = file.to_string_lossy().to_string();
-    let span = match error {
-        EffectError::PureCallsEffectful { span, .. } => span,
-        EffectError::MissingEffect { span, .. } => span,
-        EffectError::ParameterNotCovered { span, .. } => span,
-    };
+fn report_effect_error_rich(sources: &SourceMap, error: &EffectError, diagnostic: &Diagnostic) {
+    let span = error.span();
+    let file_name = sources.name(span.file).to_string();
 
     // Build the main report
     let mut report = Report::build(ReportKind::Error, file_name.clone(), span.start)
@@ -676,7 +1625,7 @@ fn report_effect_error_rich(source: &str, file: &PathBuf, error: &EffectError, d
     // Add related locations as labels
     for related in &diagnostic.related {
         report = report.with_label(
-            Label::new((file_name.clone(), related.span.start..related.span.end))
+            Label::new((sources.name(related.span.file).to_string(), related.span.start..related.span.end))
                 .with_message(&related.message)
                 .with_color(Color::Blue),
         );
@@ -698,10 +1647,10 @@ fn report_effect_error_rich(source: &str, file: &PathBuf, error: &EffectError, d
         report = report.with_note(&diagnostic.explanation);
     }
 
-    // Print the report
+    // Print the report; related locations may be in other files
     report
         .finish()
-        .eprint((file_name, Source::from(source)))
+        .eprint(ariadne::sources(sources.iter().map(|f| (f.name.clone(), f.source.clone()))))
         .unwrap();
 
     eprintln!(); // Add blank line between errors
@@ -716,6 +1665,10 @@ fn cmd_requirements(
 ) {
     use covenant_requirements::ValidatorConfig;
 
+    // SARIF is rendered by the checker, from the report's errors
+    let mut sarif = format_str
+        .eq_ignore_ascii_case("sarif")
+        .then(|| DiagnosticOutput::new(DiagnosticFormat::Sarif));
     let format: ReportFormat = format_str.parse().unwrap_or(ReportFormat::Text);
     let mut all_ok = true;
 
@@ -736,10 +1689,15 @@ fn cmd_requirements(
             }
         };
 
-        let program = match parse(&source) {
+        let file_id = sarif.as_mut().map_or(FileId::default(), |output| output.add_file(file, &source));
+
+        let program = match parse_file(&source, file_id) {
             Ok(p) => p,
             Err(e) => {
-                report_parse_error(&source, file, &e);
+                match &mut sarif {
+                    Some(output) => output.report(file_id, Diagnostic::from(e)),
+                    None => report_parse_error(&source, file, &e),
+                }
                 all_ok = false;
                 continue;
             }
@@ -755,7 +1713,15 @@ fn cmd_requirements(
         };
 
         // Output the report
-        println!("{}", format_report(&report, format));
+        match &mut sarif {
+            Some(output) => {
+                for error in &report.errors {
+                    let severity = error.severity_with_config(&config).into();
+                    output.report(file_id, Diagnostic::from(error.clone()).with_severity(severity));
+                }
+            }
+            None => println!("{}", format_report(&report, format)),
+        }
 
         // Check for errors
         if has_coverage_errors(&report) {
@@ -775,6 +1741,10 @@ fn cmd_requirements(
         }
     }
 
+    if let Some(output) = &sarif {
+        output.finish();
+    }
+
     if !all_ok {
         std::process::exit(1);
     }
@@ -789,18 +1759,22 @@ fn cmd_format(file: &PathBuf, output: Option<PathBuf>, check: bool) {
         }
     };
 
-    let program = match parse(&source) {
-        Ok(p) => p,
-        Err(e) => {
-            report_parse_error(&source, file, &e);
-            std::process::exit(1);
-        }
-    };
+    // Blocks out of canonical order parse, and printing reorders them
+    let ParseOutput { program, errors } = parse_with_recovery(&source);
+    let (misordered, syntax_errors): (Vec<_>, Vec<_>) =
+        errors.into_iter().partition(|e| matches!(e, ParseError::InvalidOrdering { .. }));
+    if let Some(e) = syntax_errors.first() {
+        report_parse_error(&source, file, e);
+        std::process::exit(1);
+    }
 
     // Convert to canonical form
     let canonical = to_cov(&program);
 
     if check {
+        for e in &misordered {
+            report_parse_error(&source, file, e);
+        }
         // Check mode - compare with original
         // Normalize both for comparison (trim trailing whitespace from lines)
         let normalize = |s: &str| -> String {
@@ -825,12 +1799,109 @@ fn cmd_format(file: &PathBuf, output: Option<PathBuf>, check: bool) {
             std::process::exit(1);
         }
         println!("Formatted {} -> {}", file.display(), out_path.display());
+        if !misordered.is_empty() {
+            println!("Reordered {} block(s) to canonical order", misordered.len());
+        }
     } else {
         // Print to stdout
         println!("{}", canonical);
     }
 }
 
+fn cmd_fix(files: &[PathBuf], min_confidence: f64, interactive: bool, dry_run: bool) {
+    use std::io::{BufRead, Write};
+
+    let mut all_ok = true;
+
+    for file in files {
+        let source = match fs::read_to_string(file) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("Error reading {}: {}", file.display(), e);
+                all_ok = false;
+                continue;
+            }
+        };
+
+        let mut program = match parse(&source) {
+            Ok(p) => p,
+            Err(e) => {
+                report_parse_error(&source, file, &e);
+                all_ok = false;
+                continue;
+            }
+        };
+
+        let original = program.clone();
+        let mut diagnostics = program_diagnostics(&program);
+        if diagnostics.is_empty() {
+            println!("✓ {} - nothing to fix", file.display());
+            continue;
+        }
+
+        println!("{}:", file.display());
+        let mut applied = 0;
+        for fix in plan_fixes(&diagnostics) {
+            let label = format!("{} {} (confidence {:.2})", fix.code, fix.description, fix.confidence);
+            if interactive {
+                print!("  {}\n  Apply? [y/N] ", label);
+                let _ = std::io::stdout().flush();
+                let mut answer = String::new();
+                let _ = std::io::stdin().lock().read_line(&mut answer);
+                if !answer.trim().eq_ignore_ascii_case("y") {
+                    println!("  - skipped");
+                    continue;
+                }
+            } else if fix.confidence < min_confidence {
+                println!("  - needs review: {}", label);
+                continue;
+            }
+
+            match try_fix(&program, &fix, diagnostics.len()) {
+                Ok((fixed, remaining)) => {
+                    println!("  ✓ fixed {}", label);
+                    program = fixed;
+                    diagnostics = remaining;
+                    applied += 1;
+                }
+                // An earlier fix already covered this one
+                Err(FixError::NoChange) => {}
+                Err(e) => println!("  ✗ rolled back {}: {}", label, e),
+            }
+        }
+
+        if applied > 0 {
+            let fixed_source = splice_fixes(&source, &original, &program);
+            if dry_run {
+                println!("{}", fixed_source);
+            } else if let Err(e) = fs::write(file, &fixed_source) {
+                eprintln!("Error writing {}: {}", file.display(), e);
+                all_ok = false;
+                continue;
+            }
+        }
+
+        if diagnostics.is_empty() {
+            println!("✓ {} - {} fix(es) applied, no errors remain", file.display(), applied);
+        } else {
+            all_ok = false;
+            eprintln!(
+                "✗ {} - {} fix(es) applied, {} error(s) remain:",
+                file.display(),
+                applied,
+                diagnostics.len()
+            );
+            for diagnostic in &diagnostics {
+                eprintln!("  {}: {}", diagnostic.code, diagnostic.message());
+            }
+        }
+    }
+
+    if !all_ok {
+        std::process::exit(1);
+    }
+}
+
 async fn cmd_explain(file: &PathBuf, format: &str, verbosity: &str, no_cache: bool) {
     // Read and parse the file
     let source = match fs::read_to_string(file) {
@@ -894,6 +1965,34 @@ async fn cmd_explain(file: &PathBuf, format: &str, verbosity: &str, no_cache: bo
     }
 }
 
+fn cmd_explain_error(code: &str) {
+    let Some(entry) = lookup_code(code) else {
+        eprintln!("Unknown error code: {}", code);
+        // Suggest the codes of the same group, e.g. E-EFFECT-xxx
+        let group = code.rsplit_once('-').map_or(code, |(group, _)| group).to_uppercase();
+        let related: Vec<&str> = ERROR_CODES
+            .iter()
+            .map(|c| c.code)
+            .filter(|c| c.rsplit_once('-').is_some_and(|(g, _)| g == group))
+            .collect();
+        if !related.is_empty() {
+            eprintln!("Known {} codes: {}", group, related.join(", "));
+        }
+        std::process::exit(1);
+    };
+
+    println!("{}: {} ({})", entry.code, entry.title, entry.severity);
+    println!();
+    println!("{}", entry.description);
+    println!();
+    println!("Example fix:");
+    for line in entry.example_fix.lines() {
+        println!("    {}", line);
+    }
+    println!();
+    println!("See {}", entry.doc_link());
+}
+
 fn cmd_repl() {
     use rustyline::DefaultEditor;
 
@@ -1041,65 +2140,31 @@ fn command_exists(cmd: &str) -> bool {
         .is_ok()
 }
 
-fn cmd_run(file: &PathBuf, opt_level: u8) {
-    use std::process::Command;
+/// The output of `node --version`, e.g. `v20.11.1`
+fn node_version() -> Option<String> {
+    let output = std::process::Command::new("node").arg("--version").output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
 
-    // Map optimization level
-    let opt_level = match opt_level {
-        0 => OptLevel::O0,
-        1 => OptLevel::O1,
-        2 => OptLevel::O2,
-        _ => OptLevel::O3,
-    };
+fn cmd_run(file: &PathBuf, opt_level: u8, format: DiagnosticFormat) {
+    use std::process::Command;
 
-    let source = match fs::read_to_string(file) {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!("Error reading file: {}", e);
-            std::process::exit(1);
-        }
-    };
+    let opt_level = map_opt_level(opt_level);
 
-    let mut program = match parse(&source) {
-        Ok(p) => p,
-        Err(e) => {
-            report_parse_error(&source, file, &e);
-            std::process::exit(1);
-        }
-    };
+    let mut diagnostics = DiagnosticOutput::new(format);
+    let (mut program, file_id, result) = check_file(file, &mut diagnostics);
+    for warning in optimize_program(&mut program, opt_level) {
+        diagnostics.report_line(file_id, warning);
+    }
+    // Diagnostics are complete before the program writes to stdout
+    diagnostics.finish();
 
-    let result = match check(&program) {
-        Ok(r) => r,
-        Err(errors) => {
-            eprintln!("Type check errors:");
-            for err in errors {
-                eprintln!("  {}", err);
-            }
-            std::process::exit(1);
-        }
+    // Capability manifest for the runtime permissions
+    let manifest = match build_symbol_graph(&program) {
+        Ok(symbol_result) => CapabilityManifest::from_effects(&check_effects(&symbol_result.graph)),
+        Err(_) => CapabilityManifest::default(),
     };
 
-    // Run optimizer if level > 0
-    if opt_level != OptLevel::O0 {
-        let settings = OptSettings {
-            level: opt_level,
-            emit_warnings: true,
-        };
-
-        if let covenant_ast::Program::Snippets { ref mut snippets, .. } = program {
-            for snippet in snippets.iter_mut() {
-                for section in snippet.sections.iter_mut() {
-                    if let covenant_ast::Section::Body(ref mut body) = section {
-                        let opt_result = optimize(&mut body.steps, &settings);
-                        for warning in &opt_result.warnings {
-                            eprintln!("{}: {}", warning.code, warning.message);
-                        }
-                    }
-                }
-            }
-        }
-    }
-
     // Compile to WASM
     let wasm = match compile_pure(&program, &result.symbols) {
         Ok(w) => w,
@@ -1156,20 +2221,23 @@ fn cmd_run(file: &PathBuf, opt_level: u8) {
         std::process::exit(1);
     };
 
-    // Run with detected runtime
+    // Run with detected runtime, granting only the permissions the effects need
     let status = if runtime == "deno" {
         Command::new("deno")
             .arg("run")
-            .arg("--allow-read")
-            .arg("--allow-write")
+            .args(manifest.deno_flags(&temp_wasm))
             .arg(&runner)
             .arg(&temp_wasm)
             .status()
     } else {
-        Command::new("node")
-            .arg(&runner)
-            .arg(&temp_wasm)
-            .status()
+        let mut node = Command::new("node");
+        match node_version().as_deref().and_then(node_permission_flag) {
+            Some(flag) => {
+                node.args(manifest.node_flags(flag, &runner, &temp_wasm));
+            }
+            None => eprintln!("warning: this Node.js has no permission model; running without capability restrictions"),
+        }
+        node.arg(&runner).arg(&temp_wasm).status()
     };
 
     // Clean up temp file
@@ -1192,6 +2260,7 @@ fn cmd_run(file: &PathBuf, opt_level: u8) {
 fn report_parse_error(source: &str, file: &PathBuf, error: &covenant_parser::ParseError) {
     let span = error.span();
     Report::build(ReportKind::Error, file.to_string_lossy().to_string(), span.start)
+        .with_code(error.code())
         .with_message(error.to_string())
         .with_label(
             Label::new((file.to_string_lossy().to_string(), span.start..span.end))
@@ -0,0 +1,349 @@
+//! Project manifest
+//!
+//! `covenant.toml` at the root of a project describes how to check and build
+//! it, in place of per-invocation flags:
+//!
+//! ```toml
+//! [project]
+//! name = "shop"
+//! version = "0.1.0"
+//! sources = ["src"]
+//! entry = ["shop.main"]
+//!
+//! [build]
+//! target = "deno"
+//! optimize = 2
+//! embed = "api"
+//! output = "target"
+//! std = "../covenant/runtime/std"
+//! policy = "covenant-policy.toml"
+//!
+//! [dependencies]
+//! billing = { path = "../billing" }
+//! tax = { tarball = "vendor/tax-1.2.0.tar", prefix = "acme.tax" }
+//! ```
+//!
+//! Every field but `project.name` has a default. Paths are relative to the
+//! manifest's directory. A dependency's snippets must have ids under its
+//! prefix, which defaults to its name.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use covenant_codegen::EmbedLevel;
+use serde::Deserialize;
+use thiserror::Error;
+
+/// File name looked up by [`Manifest::discover`]
+pub const MANIFEST_FILE_NAME: &str = "covenant.toml";
+
+/// Lockfile written next to the manifest
+pub const LOCK_FILE_NAME: &str = "covenant.lock";
+
+/// Platforms `build.target` may name
+pub const TARGETS: [&str; 4] = ["deno", "node", "browser", "wasi"];
+
+/// Errors loading a manifest
+#[derive(Debug, Error)]
+pub enum ManifestError {
+    #[error("cannot read manifest {path}: {message}")]
+    Io { path: String, message: String },
+
+    #[error("invalid manifest: {message}")]
+    Toml { message: String },
+
+    #[error("invalid target `{target}`; expected one of: {}", TARGETS.join(", "))]
+    InvalidTarget { target: String },
+
+    #[error("invalid optimization level {level}; expected 0 to 3")]
+    InvalidOptLevel { level: u8 },
+
+    #[error("dependency `{name}` must have exactly one of `path` and `tarball`")]
+    InvalidDependency { name: String },
+}
+
+/// A parsed `covenant.toml`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub project: ProjectSection,
+    #[serde(default)]
+    pub build: BuildSection,
+    /// Dependencies by name
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Dependency>,
+    /// Directory containing the manifest; paths resolve against it
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+/// `[project]`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectSection {
+    pub name: String,
+    #[serde(default = "default_version")]
+    pub version: String,
+    /// Source roots
+    #[serde(default = "default_sources")]
+    pub sources: Vec<PathBuf>,
+    /// Entry snippet ids; the exports of the built module
+    #[serde(default)]
+    pub entry: Vec<String>,
+}
+
+/// `[build]`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuildSection {
+    #[serde(default = "default_target")]
+    pub target: String,
+    /// Optimization level, 0 to 3
+    #[serde(default)]
+    pub optimize: u8,
+    /// Symbol metadata to embed: none, api or full
+    #[serde(default)]
+    pub embed: EmbedLevel,
+    /// Directory for build artifacts
+    #[serde(default = "default_output")]
+    pub output: PathBuf,
+    /// Standard library directory (default: discovered)
+    pub std: Option<PathBuf>,
+    /// Effect policy file (default: discovered)
+    pub policy: Option<PathBuf>,
+}
+
+impl Default for BuildSection {
+    fn default() -> Self {
+        Self {
+            target: default_target(),
+            optimize: 0,
+            embed: EmbedLevel::default(),
+            output: default_output(),
+            std: None,
+            policy: None,
+        }
+    }
+}
+
+/// A `[dependencies]` entry
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dependency {
+    /// Directory of the dependency's sources
+    pub path: Option<PathBuf>,
+    /// Vendored, uncompressed tar archive of the dependency's sources
+    pub tarball: Option<PathBuf>,
+    /// Snippet id prefix the dependency owns (default: its name)
+    pub prefix: Option<String>,
+}
+
+/// Where a dependency's sources come from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DependencySource {
+    Path(PathBuf),
+    Tarball(PathBuf),
+}
+
+impl std::fmt::Display for DependencySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DependencySource::Path(path) => write!(f, "path:{}", path.display()),
+            DependencySource::Tarball(path) => write!(f, "tarball:{}", path.display()),
+        }
+    }
+}
+
+fn default_version() -> String {
+    "0.1.0".to_string()
+}
+
+fn default_sources() -> Vec<PathBuf> {
+    vec![PathBuf::from("src")]
+}
+
+fn default_target() -> String {
+    "deno".to_string()
+}
+
+fn default_output() -> PathBuf {
+    PathBuf::from("target")
+}
+
+impl Manifest {
+    /// Parse a manifest from TOML; `dir` is the directory it is in
+    pub fn from_toml_str(source: &str, dir: &Path) -> Result<Self, ManifestError> {
+        let mut manifest: Manifest = toml::from_str(source).map_err(|e| ManifestError::Toml {
+            message: e.to_string(),
+        })?;
+        if !TARGETS.contains(&manifest.build.target.as_str()) {
+            return Err(ManifestError::InvalidTarget { target: manifest.build.target });
+        }
+        if manifest.build.optimize > 3 {
+            return Err(ManifestError::InvalidOptLevel { level: manifest.build.optimize });
+        }
+        for (name, dependency) in &manifest.dependencies {
+            if dependency.path.is_some() == dependency.tarball.is_some() {
+                return Err(ManifestError::InvalidDependency { name: name.clone() });
+            }
+        }
+        manifest.dir = dir.to_path_buf();
+        Ok(manifest)
+    }
+
+    /// Load a manifest file
+    pub fn load(path: &Path) -> Result<Self, ManifestError> {
+        let source = std::fs::read_to_string(path).map_err(|e| ManifestError::Io {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+        let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        Self::from_toml_str(&source, dir)
+    }
+
+    /// Find the manifest of the project containing `start`: the nearest
+    /// `covenant.toml` in it or any parent directory
+    pub fn discover(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(MANIFEST_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// Source roots, resolved
+    pub fn source_roots(&self) -> Vec<PathBuf> {
+        self.project.sources.iter().map(|root| self.dir.join(root)).collect()
+    }
+
+    /// Standard library directory, if the manifest names one
+    pub fn std_dir(&self) -> Option<PathBuf> {
+        self.build.std.as_ref().map(|std| self.dir.join(std))
+    }
+
+    /// Effect policy file, if the manifest names one
+    pub fn policy_file(&self) -> Option<PathBuf> {
+        self.build.policy.as_ref().map(|policy| self.dir.join(policy))
+    }
+
+    /// Directory for build artifacts
+    pub fn output_dir(&self) -> PathBuf {
+        self.dir.join(&self.build.output)
+    }
+
+    /// The lockfile next to the manifest
+    pub fn lock_file(&self) -> PathBuf {
+        self.dir.join(LOCK_FILE_NAME)
+    }
+}
+
+impl Dependency {
+    /// Where the dependency's sources come from, relative to the manifest
+    pub fn source(&self) -> DependencySource {
+        match (&self.path, &self.tarball) {
+            (Some(path), _) => DependencySource::Path(path.clone()),
+            (None, Some(tarball)) => DependencySource::Tarball(tarball.clone()),
+            (None, None) => unreachable!("validated when loading the manifest"),
+        }
+    }
+}
+
+/// The manifest `covenant init` writes
+pub fn scaffold_manifest(name: &str) -> String {
+    format!(
+        r#"[project]
+name = "{name}"
+version = "0.1.0"
+sources = ["src"]
+entry = ["{name}.main"]
+
+[build]
+target = "deno"
+optimize = 0
+embed = "api"
+output = "target"
+
+[dependencies]
+"#
+    )
+}
+
+/// The entry snippet `covenant init` writes
+pub fn scaffold_main(name: &str) -> String {
+    format!(
+        r#"snippet id="{name}.main" kind="fn"
+
+effects
+  effect console
+end
+
+signature
+  fn name="main"
+    returns type="Unit"
+  end
+end
+
+body
+  step id="s1" kind="call"
+    fn="console.println"
+    arg name="message" lit="Hello from {name}!"
+    as="_"
+  end
+end
+
+end
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_and_paths() {
+        let manifest = Manifest::from_toml_str(
+            "[project]\nname = \"shop\"\n\n[dependencies]\nbilling = { path = \"../billing\" }\n",
+            Path::new("/work/shop"),
+        )
+        .unwrap();
+        assert_eq!(manifest.project.version, "0.1.0");
+        assert_eq!(manifest.source_roots(), vec![PathBuf::from("/work/shop/src")]);
+        assert_eq!(manifest.build.target, "deno");
+        assert_eq!(manifest.build.embed, EmbedLevel::Api);
+        assert_eq!(manifest.output_dir(), PathBuf::from("/work/shop/target"));
+        assert_eq!(manifest.std_dir(), None);
+        let billing = &manifest.dependencies["billing"];
+        assert_eq!(billing.source().to_string(), "path:../billing");
+        assert_eq!(billing.prefix, None);
+    }
+
+    #[test]
+    fn test_invalid_manifests() {
+        let dir = Path::new(".");
+        assert!(matches!(
+            Manifest::from_toml_str("[project]\nname = \"a\"\n[build]\ntarget = \"jvm\"\n", dir),
+            Err(ManifestError::InvalidTarget { .. })
+        ));
+        assert!(matches!(
+            Manifest::from_toml_str("[project]\nname = \"a\"\n[build]\noptimize = 7\n", dir),
+            Err(ManifestError::InvalidOptLevel { level: 7 })
+        ));
+        assert!(matches!(
+            Manifest::from_toml_str("[project]\nname = \"a\"\n[build]\nembed = \"reachable\"\n", dir),
+            Err(ManifestError::Toml { .. })
+        ));
+        assert!(matches!(Manifest::from_toml_str("[build]\n", dir), Err(ManifestError::Toml { .. })));
+        assert!(matches!(
+            Manifest::from_toml_str("[project]\nname = \"a\"\n[dependencies]\nb = { prefix = \"b\" }\n", dir),
+            Err(ManifestError::InvalidDependency { .. })
+        ));
+    }
+
+    #[test]
+    fn test_scaffold_parses() {
+        let manifest = Manifest::from_toml_str(&scaffold_manifest("hello"), Path::new(".")).unwrap();
+        assert_eq!(manifest.project.entry, vec!["hello.main"]);
+        let program = covenant_parser::parse(&scaffold_main("hello")).unwrap();
+        assert!(covenant_checker::check(&program).is_ok());
+    }
+}
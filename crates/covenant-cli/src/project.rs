@@ -0,0 +1,232 @@
+//! Project mode
+//!
+//! A project is every `.cov` file under a root directory, checked together
+//! with the standard library under `runtime/std`, so a call from one file to
+//! a snippet in another resolves.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use covenant_ast::{FileId, Program, Snippet, SourceMap, Span};
+use covenant_checker::Diagnostic;
+use covenant_parser::{parse_file_with_recovery, ParseError};
+
+/// Environment variable naming the standard library directory
+pub const STD_ENV: &str = "COVENANT_STD";
+
+/// Where the standard library lives, relative to a checkout
+const STD_DIR: &str = "runtime/std";
+
+/// The parsed files of a project and of its libraries
+#[derive(Debug)]
+pub struct Project {
+    /// Snippets that parsed, project files first
+    pub program: Program,
+    /// Project files, in discovery order
+    pub files: Vec<FileId>,
+    /// The standard library and dependencies
+    pub libraries: Vec<Library>,
+    /// Syntax errors, by file
+    pub parse_errors: Vec<(FileId, ParseError)>,
+    /// File of each snippet, by snippet ID
+    snippet_files: HashMap<String, FileId>,
+}
+
+/// A directory of library files: the standard library or a dependency
+#[derive(Debug)]
+pub struct Library {
+    pub dir: PathBuf,
+    pub files: Vec<FileId>,
+}
+
+impl Project {
+    /// Read and parse every `.cov` file under the source `roots`, then those
+    /// under the `libraries` (the standard library and dependencies)
+    pub fn load_all(roots: &[PathBuf], libraries: &[PathBuf], sources: &mut SourceMap) -> io::Result<Self> {
+        // A root inside a library is that library
+        let libraries: Vec<&PathBuf> = libraries
+            .iter()
+            .filter(|library| !roots.iter().any(|root| is_within(root, library)))
+            .collect();
+
+        let mut paths = Vec::new();
+        for root in roots {
+            paths.extend(
+                discover_files(root)?
+                    .into_iter()
+                    .filter(|path| !libraries.iter().any(|library| is_within(path, library))),
+            );
+        }
+
+        let mut project = Self {
+            program: Program::Snippets { snippets: Vec::new(), span: Span::dummy() },
+            files: Vec::new(),
+            libraries: Vec::new(),
+            parse_errors: Vec::new(),
+            snippet_files: HashMap::new(),
+        };
+        let mut loaded = HashSet::new();
+        for path in &paths {
+            if loaded.insert(path.canonicalize()?) {
+                let file = project.add_file(path, sources)?;
+                project.files.push(file);
+            }
+        }
+        for dir in libraries {
+            let mut library = Library { dir: dir.clone(), files: Vec::new() };
+            for path in discover_files(dir)? {
+                if loaded.insert(path.canonicalize()?) {
+                    library.files.push(project.add_file(&path, sources)?);
+                }
+            }
+            project.libraries.push(library);
+        }
+        Ok(project)
+    }
+
+    fn add_file(&mut self, path: &Path, sources: &mut SourceMap) -> io::Result<FileId> {
+        let source = fs::read_to_string(path)?;
+        let file = sources.add(path.display().to_string(), source.as_str());
+        let output = parse_file_with_recovery(&source, file);
+        self.parse_errors.extend(output.errors.into_iter().map(|error| (file, error)));
+        // Declarations outside snippets have no IDs to link by
+        if let Program::Snippets { snippets, .. } = output.program {
+            for snippet in &snippets {
+                self.snippet_files.insert(snippet.id.clone(), file);
+            }
+            self.snippets_mut().extend(snippets);
+        }
+        Ok(file)
+    }
+
+    pub fn snippets(&self) -> &[Snippet] {
+        match &self.program {
+            Program::Snippets { snippets, .. } => snippets,
+            Program::Legacy { .. } => &[],
+        }
+    }
+
+    fn snippets_mut(&mut self) -> &mut Vec<Snippet> {
+        match &mut self.program {
+            Program::Snippets { snippets, .. } => snippets,
+            Program::Legacy { .. } => unreachable!("a project's program is made of snippets"),
+        }
+    }
+
+    pub fn is_library(&self, file: FileId) -> bool {
+        self.libraries.iter().any(|library| library.files.contains(&file))
+    }
+
+    /// The file a diagnostic belongs to: that of its span, or else that of
+    /// its snippet
+    pub fn file_of(&self, diagnostic: &Diagnostic) -> Option<FileId> {
+        if !diagnostic.span.is_dummy() {
+            return Some(diagnostic.span.file);
+        }
+        let snippet_id = diagnostic.snippet_id.as_ref()?;
+        self.snippet_files.get(snippet_id).copied()
+    }
+}
+
+/// Every `.cov` file under `root`, in path order. Hidden directories and
+/// `target` directories are skipped; a file root is returned as is.
+pub fn discover_files(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if root.is_file() {
+        files.push(root.to_path_buf());
+    } else {
+        collect_files(root, &mut files)?;
+    }
+    Ok(files)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    entries.sort();
+    for path in entries {
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                collect_files(&path, files)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext == "cov") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The standard library directory: `explicit`, else `$COVENANT_STD`, else the
+/// nearest `runtime/std` at or above `root`
+pub fn find_std(explicit: Option<&Path>, root: &Path) -> Option<PathBuf> {
+    if let Some(path) = explicit {
+        return Some(path.to_path_buf());
+    }
+    if let Some(path) = std::env::var_os(STD_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let root = root.canonicalize().ok()?;
+    root.ancestors().map(|dir| dir.join(STD_DIR)).find(|std| std.is_dir())
+}
+
+fn is_within(path: &Path, dir: &Path) -> bool {
+    match (path.canonicalize(), dir.canonicalize()) {
+        (Ok(path), Ok(dir)) => path.starts_with(dir),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use covenant_symbols::build_from_snippets;
+
+    fn write(dir: &Path, name: &str, source: &str) {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+
+    #[test]
+    fn test_calls_across_files_resolve() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("app");
+        let std = dir.path().join("std");
+        write(&root, "main.cov", "snippet id=\"app.main\" kind=\"fn\"\nsignature\n  fn name=\"main\"\n    returns type=\"Int\"\n  end\nend\nbody\n  step id=\"s1\" kind=\"call\"\n    fn=\"util.one\"\n    as=\"result\"\n  end\nend\nend\n");
+        write(&root, "lib/util.cov", "snippet id=\"util.one\" kind=\"fn\"\nsignature\n  fn name=\"one\"\n    returns type=\"Int\"\n  end\nend\nend\n");
+        write(&root, "target/out.cov", "not covenant");
+        write(&std, "broken.cov", "snippet id=");
+
+        let mut sources = SourceMap::new();
+        let project = Project::load_all(std::slice::from_ref(&root), &[std], &mut sources).unwrap();
+        assert_eq!(project.files.len(), 2);
+        assert_eq!(sources.name(project.files[0]), root.join("lib/util.cov").display().to_string());
+        assert_eq!(project.parse_errors.len(), 1);
+        assert!(project.is_library(project.parse_errors[0].0));
+
+        let result = build_from_snippets(project.snippets()).unwrap();
+        assert!(result.deferred_errors.is_empty(), "{:?}", result.deferred_errors);
+
+        let main = &project.snippets()[1];
+        assert_eq!(sources.file_of(main.span).unwrap().name, root.join("main.cov").display().to_string());
+        let diagnostic = Diagnostic::from(covenant_checker::CheckError::UndefinedSymbol { name: "x".to_string() })
+            .with_snippet("app.main".to_string());
+        assert_eq!(project.file_of(&diagnostic), Some(project.files[1]));
+    }
+
+    #[test]
+    fn test_root_inside_std_is_not_loaded_twice() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "runtime/std/io/io.cov", "snippet id=\"io.print\" kind=\"extern\"\nend\n");
+        let std = find_std(None, &dir.path().join("runtime/std/io")).unwrap();
+        assert!(std.ends_with(STD_DIR));
+
+        let mut sources = SourceMap::new();
+        let project = Project::load_all(&[dir.path().join("runtime/std")], &[std], &mut sources).unwrap();
+        assert_eq!((project.files.len(), project.libraries.len()), (1, 0));
+    }
+}
@@ -0,0 +1,244 @@
+//! End-to-end tests for the `covenant` binary
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// The workspace root, where `runtime/` and `examples/` live
+fn workspace_root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../..")
+}
+
+fn covenant(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_covenant"))
+        .args(args)
+        .current_dir(workspace_root())
+        .output()
+        .expect("failed to run covenant")
+}
+
+fn has_command(cmd: &str) -> bool {
+    Command::new(cmd).arg("--version").output().is_ok()
+}
+
+#[test]
+fn run_executes_main_under_the_host_runtime() {
+    if !has_command("node") && !has_command("deno") {
+        eprintln!("skipping: neither node nor deno is installed");
+        return;
+    }
+
+    let output = covenant(&["run", "examples/syntax-fundamentals/hello-world.cov"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "covenant run failed: {}", stderr);
+    assert!(!stderr.contains("bad option"), "runtime rejected a flag: {}", stderr);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "Hello, world!");
+}
+
+#[test]
+fn fix_adds_missing_effect_and_keeps_comments() {
+    let source = r#"// Greeting helpers
+snippet id="io.print" kind="extern"
+
+effects
+  effect console
+end
+
+signature
+  fn name="print"
+    param name="msg" type="String"
+    returns type="Unit"
+  end
+end
+
+end
+
+snippet id="app.greet" kind="fn"
+
+// Greets the user
+signature
+  fn name="greet"
+    returns type="Unit"
+  end
+end
+
+body
+  step id="s1" kind="call"
+    fn="io.print"
+    arg name="msg" lit="hello"   // the greeting
+    as="_"
+  end
+end
+
+end
+"#;
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("greet.cov");
+    std::fs::write(&file, source).unwrap();
+
+    let output = covenant(&["fix", file.to_str().unwrap()]);
+    assert!(output.status.success(), "covenant fix failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let expected = source.replace(
+        "snippet id=\"app.greet\" kind=\"fn\"\n",
+        "snippet id=\"app.greet\" kind=\"fn\"\n\neffects\n  effect console\nend\n",
+    );
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), expected);
+}
+
+#[test]
+fn compile_reports_optimizer_warnings_as_json_lines() {
+    let source = r#"snippet id="app.main" kind="fn"
+signature
+  fn name="main"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="compute"
+    op=add
+    input lit=1
+    input lit=2
+    as="unused"
+  end
+  step id="s2" kind="return"
+    lit=0
+    as="_"
+  end
+end
+end
+"#;
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("main.cov");
+    std::fs::write(&file, source).unwrap();
+    let wasm = dir.path().join("main.wasm");
+
+    let output = covenant(&[
+        "compile",
+        file.to_str().unwrap(),
+        "-o",
+        wasm.to_str().unwrap(),
+        "--optimize",
+        "2",
+        "--format",
+        "jsonl",
+    ]);
+    assert!(output.status.success(), "covenant compile failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let warnings: Vec<serde_json::Value> = stdout.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert!(!warnings.is_empty());
+    for warning in &warnings {
+        assert_eq!(warning["code"], "W-DEAD-001");
+        assert_eq!(warning["severity"], "warning");
+        assert_eq!(warning["source_location"]["snippet_id"], "app.main");
+        assert_eq!(warning["source_location"]["step_id"], "s1");
+        assert_eq!(warning["source_location"]["line"], 8);
+    }
+}
+
+#[test]
+fn check_project_reports_unlocated_diagnostics_without_a_file() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("a.cov"),
+        r#"snippet id="app.one" kind="fn"
+signature
+  fn name="one"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="return"
+    lit=1
+    as="_"
+  end
+end
+end
+"#,
+    )
+    .unwrap();
+    // The undefined binding is reported with neither a span nor a snippet
+    std::fs::write(
+        dir.path().join("b.cov"),
+        r#"snippet id="app.two" kind="fn"
+signature
+  fn name="two"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="return"
+    from="missing"
+    as="_"
+  end
+end
+end
+"#,
+    )
+    .unwrap();
+
+    let output = covenant(&["check", "--project", dir.path().to_str().unwrap(), "--format", "jsonl"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let diagnostics: Vec<serde_json::Value> = stdout.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    let undefined = diagnostics
+        .iter()
+        .find(|diagnostic| diagnostic["message"].as_str().unwrap().contains("`missing`"))
+        .expect("the undefined binding is reported");
+    assert_eq!(undefined["source_location"]["file"], "");
+    assert!(!stdout.contains("a.cov"), "a diagnostic was attributed to a.cov: {}", stdout);
+}
+
+#[test]
+fn check_reports_only_the_syntax_error_of_a_recovered_snippet() {
+    let source = r#"snippet id="math.double" kind="fn"
+signature
+  fn name="double"
+    param name="x" type="Int"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="teleport"
+    as="y"
+  end
+  step id="s2" kind="return"
+    from="x"
+    as="_"
+  end
+end
+end
+
+snippet id="math.quad" kind="fn"
+signature
+  fn name="quad"
+    param name="x" type="Int"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="call"
+    fn="math.double"
+    arg name="x" from="x"
+    as="d"
+  end
+  step id="s2" kind="return"
+    from="d"
+    as="_"
+  end
+end
+end
+"#;
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("math.cov");
+    std::fs::write(&file, source).unwrap();
+
+    // The call to the recovered snippet still resolves
+    let output = covenant(&["check", file.to_str().unwrap(), "--format", "jsonl"]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let codes: Vec<String> = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["code"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(codes, vec!["E-PARSE-006"], "{}", stdout);
+}
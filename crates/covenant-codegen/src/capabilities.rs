@@ -0,0 +1,437 @@
+//! Capability manifest
+//!
+//! The capability manifest is the union of a program's effect closures, with
+//! parameters, plus the WASM imports they need. The compiler stores it in the
+//! `covenant.capabilities` custom section and as a sidecar JSON file, and the
+//! runner turns it into the narrowest runtime permissions:
+//!
+//! ```text
+//! effect filesystem(path="/data")       →  deno --allow-read=/data --allow-write=/data
+//! effect network(host="api.example.com") →  deno --allow-net=api.example.com
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use covenant_ast::Literal;
+use covenant_checker::EffectCheckResult;
+use serde::{Deserialize, Serialize};
+
+use crate::embeddable::effects_to_capabilities;
+
+/// Name of the WASM custom section holding the manifest
+pub const CAPABILITY_SECTION: &str = "covenant.capabilities";
+
+/// Effects and imports a compiled module may use at runtime
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CapabilityManifest {
+    /// Effects used anywhere in the module, in name order
+    pub effects: Vec<CapabilityEffect>,
+    /// WASM imports those effects require
+    pub imports: Vec<String>,
+}
+
+/// A single effect of the manifest, e.g. `filesystem(path="/data")`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapabilityEffect {
+    pub name: String,
+    /// Parameters narrowing the effect; empty means unrestricted
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, serde_json::Value>,
+}
+
+impl CapabilityEffect {
+    fn param_str(&self, name: &str) -> Option<&str> {
+        self.params.get(name).and_then(|v| v.as_str())
+    }
+}
+
+impl CapabilityManifest {
+    /// Build the manifest from the effect closures of every callable
+    pub fn from_effects(effect_result: &EffectCheckResult) -> Self {
+        let mut effects: Vec<CapabilityEffect> = Vec::new();
+        for closure in effect_result.closures.values() {
+            for decl in &closure.computed_full {
+                let effect = CapabilityEffect {
+                    name: decl.name.clone(),
+                    params: decl
+                        .params
+                        .iter()
+                        .map(|p| (p.name.clone(), literal_to_json(&p.value)))
+                        .collect(),
+                };
+                if !effects.contains(&effect) {
+                    effects.push(effect);
+                }
+            }
+        }
+
+        // An unrestricted effect makes narrower uses of the same effect redundant
+        let unrestricted: Vec<String> = effects
+            .iter()
+            .filter(|e| e.params.is_empty())
+            .map(|e| e.name.clone())
+            .collect();
+        effects.retain(|e| e.params.is_empty() || !unrestricted.contains(&e.name));
+        effects.sort_by(|a, b| {
+            (&a.name, serde_json::to_string(&a.params).unwrap_or_default())
+                .cmp(&(&b.name, serde_json::to_string(&b.params).unwrap_or_default()))
+        });
+
+        let mut names: Vec<String> = effects.iter().map(|e| e.name.clone()).collect();
+        names.dedup();
+        let mut imports = effects_to_capabilities(&names);
+        imports.sort();
+        imports.dedup();
+
+        Self { effects, imports }
+    }
+
+    /// True if the module uses no effects
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    /// Serialize the manifest as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Parse a manifest from JSON
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Append the manifest to a WASM module as a custom section
+    pub fn embed(&self, wasm: &mut Vec<u8>) {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        let mut payload = Vec::new();
+        write_leb128(&mut payload, CAPABILITY_SECTION.len() as u32);
+        payload.extend_from_slice(CAPABILITY_SECTION.as_bytes());
+        payload.extend_from_slice(&json);
+
+        wasm.push(0); // Custom section id
+        write_leb128(wasm, payload.len() as u32);
+        wasm.extend_from_slice(&payload);
+    }
+
+    /// Read the manifest from a WASM module's custom section
+    pub fn from_wasm(wasm: &[u8]) -> Option<Self> {
+        // Skip the magic number and version
+        let mut pos = 8;
+        while pos < wasm.len() {
+            let id = wasm[pos];
+            pos += 1;
+            let size = read_leb128(wasm, &mut pos)? as usize;
+            let end = pos.checked_add(size).filter(|end| *end <= wasm.len())?;
+            if id == 0 {
+                let mut name_pos = pos;
+                let name_len = read_leb128(wasm, &mut name_pos)? as usize;
+                let name = wasm.get(name_pos..name_pos + name_len)?;
+                if name == CAPABILITY_SECTION.as_bytes() {
+                    return serde_json::from_slice(&wasm[name_pos + name_len..end]).ok();
+                }
+            }
+            pos = end;
+        }
+        None
+    }
+
+    /// Deno permission flags granting exactly the manifest's effects, plus
+    /// reading the module itself
+    pub fn deno_flags(&self, wasm_path: &Path) -> Vec<String> {
+        let mut read = vec![wasm_path.display().to_string()];
+        let mut write = Vec::new();
+        let mut net = Vec::new();
+        let (mut all_fs, mut all_net) = (false, false);
+
+        for effect in &self.effects {
+            match effect.name.as_str() {
+                "filesystem" => match effect.param_str("path") {
+                    Some(path) => {
+                        read.push(permission_path(path));
+                        write.push(permission_path(path));
+                    }
+                    None => all_fs = true,
+                },
+                "network" => {
+                    let url = effect.param_str("host").or_else(|| effect.param_str("url"));
+                    match url.and_then(permission_host) {
+                        Some(host) => net.push(host),
+                        None => all_net = true,
+                    }
+                }
+                "http_server" => all_net = true,
+                _ => {}
+            }
+        }
+
+        let mut flags = Vec::new();
+        if all_fs {
+            flags.push("--allow-read".to_string());
+            flags.push("--allow-write".to_string());
+        } else {
+            flags.push(format!("--allow-read={}", join_unique(read)));
+            if !write.is_empty() {
+                flags.push(format!("--allow-write={}", join_unique(write)));
+            }
+        }
+        if all_net {
+            flags.push("--allow-net".to_string());
+        } else if !net.is_empty() {
+            flags.push(format!("--allow-net={}", join_unique(net)));
+        }
+        flags
+    }
+
+    /// Node.js permission model flags for the manifest's filesystem effects,
+    /// plus reading the runner and the module. Node does not restrict network
+    /// access, so network effects need no flags. `permission_flag` enables the
+    /// permission model; see [`node_permission_flag`].
+    pub fn node_flags(&self, permission_flag: &str, runner_path: &Path, wasm_path: &Path) -> Vec<String> {
+        let mut read = vec![runner_path.display().to_string(), wasm_path.display().to_string()];
+        let mut write: Vec<String> = Vec::new();
+        for effect in self.effects.iter().filter(|e| e.name == "filesystem") {
+            let path = effect.param_str("path").map(permission_path).unwrap_or_else(|| "*".to_string());
+            read.push(path.clone());
+            write.push(path);
+        }
+
+        let mut flags = vec![permission_flag.to_string()];
+        flags.extend(unique(read).into_iter().map(|p| format!("--allow-fs-read={}", p)));
+        flags.extend(unique(write).into_iter().map(|p| format!("--allow-fs-write={}", p)));
+        flags
+    }
+}
+
+/// The flag that enables the permission model for a Node.js version string
+/// as printed by `node --version` (e.g. `v20.11.1`). Node 20 ships it as
+/// `--experimental-permission`, 22.13 and 23.5 onwards as `--permission`;
+/// earlier versions have no permission model and yield `None`.
+pub fn node_permission_flag(version: &str) -> Option<&'static str> {
+    let mut parts = version.trim().trim_start_matches('v').split('.');
+    let major: u32 = parts.next()?.parse().ok()?;
+    let minor: u32 = parts.next().and_then(|m| m.parse().ok()).unwrap_or(0);
+    match (major, minor) {
+        (0..=19, _) => None,
+        (22, 13..) | (23, 5..) | (24.., _) => Some("--permission"),
+        _ => Some("--experimental-permission"),
+    }
+}
+
+fn literal_to_json(lit: &Literal) -> serde_json::Value {
+    match lit {
+        Literal::Int(i) => serde_json::Value::from(*i),
+        Literal::Float(f) => serde_json::Value::from(*f),
+        Literal::String(s) => serde_json::Value::from(s.as_str()),
+        Literal::Bool(b) => serde_json::Value::from(*b),
+        Literal::None => serde_json::Value::Null,
+    }
+}
+
+/// Runtimes grant whole directories, so a path pattern is widened to the
+/// directory before its first wildcard segment
+fn permission_path(path: &str) -> String {
+    let fixed: Vec<&str> = path
+        .split('/')
+        .take_while(|segment| !segment.contains(['*', '?']))
+        .collect();
+    match fixed.join("/") {
+        prefix if prefix.is_empty() && path.starts_with('/') => "/".to_string(),
+        prefix if prefix.is_empty() => ".".to_string(),
+        prefix => prefix,
+    }
+}
+
+/// The `host[:port]` part of a URL or bare host, or `None` for wildcard
+/// hosts, which runtimes cannot grant narrowly
+fn permission_host(url: &str) -> Option<String> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split('/').next().unwrap_or(rest);
+    (!authority.contains('*')).then(|| authority.to_string())
+}
+
+/// Remove repeated values, keeping the first of each
+fn unique(mut values: Vec<String>) -> Vec<String> {
+    let mut seen = Vec::new();
+    values.retain(|v| {
+        let new = !seen.contains(v);
+        seen.push(v.clone());
+        new
+    });
+    values
+}
+
+fn join_unique(values: Vec<String>) -> String {
+    unique(values).join(",")
+}
+
+fn write_leb128(out: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_leb128(bytes: &[u8], pos: &mut usize) -> Option<u32> {
+    let mut result = 0u32;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*pos)?;
+        *pos += 1;
+        result |= ((byte & 0x7f) as u32).checked_shl(shift)?;
+        if byte & 0x80 == 0 {
+            return Some(result);
+        }
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use covenant_checker::check_effects;
+    use covenant_symbols::build_symbol_graph;
+
+    fn manifest_for(source: &str) -> CapabilityManifest {
+        let program = covenant_parser::parse(source).expect("parse failed");
+        let symbol_result = build_symbol_graph(&program).expect("symbol graph failed");
+        CapabilityManifest::from_effects(&check_effects(&symbol_result.graph))
+    }
+
+    fn effect(name: &str, params: &[(&str, &str)]) -> CapabilityEffect {
+        CapabilityEffect {
+            name: name.to_string(),
+            params: params
+                .iter()
+                .map(|(k, v)| (k.to_string(), serde_json::Value::from(*v)))
+                .collect(),
+        }
+    }
+
+    const SOURCE: &str = r#"
+snippet id="io.read" kind="extern"
+
+effects
+  effect filesystem(path="/data/users")
+end
+
+signature
+  fn name="read"
+    returns type="String"
+  end
+end
+
+end
+
+snippet id="app.main" kind="fn"
+
+effects
+  effect filesystem(path="/data/users")
+  effect network(host="api.example.com")
+end
+
+signature
+  fn name="main"
+    returns type="String"
+  end
+end
+
+body
+  step id="s1" kind="call"
+    fn="io.read"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+end
+
+end
+"#;
+
+    #[test]
+    fn test_manifest_is_union_of_effect_closures() {
+        let manifest = manifest_for(SOURCE);
+        assert_eq!(
+            manifest.effects,
+            vec![
+                effect("filesystem", &[("path", "/data/users")]),
+                effect("network", &[("host", "api.example.com")]),
+            ]
+        );
+        assert!(manifest.imports.contains(&"fs.read".to_string()));
+        assert!(manifest.imports.contains(&"http.fetch".to_string()));
+    }
+
+    #[test]
+    fn test_manifest_round_trips_through_custom_section() {
+        let manifest = manifest_for(SOURCE);
+        let mut wasm = wasm_encoder::Module::new().finish();
+        manifest.embed(&mut wasm);
+
+        assert_eq!(CapabilityManifest::from_wasm(&wasm), Some(manifest.clone()));
+        assert_eq!(CapabilityManifest::from_json(&manifest.to_json()).unwrap(), manifest);
+        assert_eq!(CapabilityManifest::from_wasm(&wasm_encoder::Module::new().finish()), None);
+    }
+
+    #[test]
+    fn test_runtime_flags_are_minimal() {
+        let wasm = Path::new("/tmp/app.wasm");
+        let manifest = manifest_for(SOURCE);
+        assert_eq!(
+            manifest.deno_flags(wasm),
+            vec![
+                "--allow-read=/tmp/app.wasm,/data/users",
+                "--allow-write=/data/users",
+                "--allow-net=api.example.com",
+            ]
+        );
+        assert_eq!(
+            manifest.node_flags("--experimental-permission", Path::new("run.mjs"), wasm),
+            vec![
+                "--experimental-permission",
+                "--allow-fs-read=run.mjs",
+                "--allow-fs-read=/tmp/app.wasm",
+                "--allow-fs-read=/data/users",
+                "--allow-fs-write=/data/users",
+            ]
+        );
+
+        let pure = CapabilityManifest::default();
+        assert_eq!(pure.deno_flags(wasm), vec!["--allow-read=/tmp/app.wasm"]);
+
+        let broad = CapabilityManifest {
+            effects: vec![
+                effect("filesystem", &[]),
+                effect("network", &[("host", "*.example.com")]),
+            ],
+            imports: Vec::new(),
+        };
+        assert_eq!(broad.deno_flags(wasm), vec!["--allow-read", "--allow-write", "--allow-net"]);
+
+        let globbed = CapabilityManifest {
+            effects: vec![effect("filesystem", &[("path", "/data/*/logs")])],
+            imports: Vec::new(),
+        };
+        assert_eq!(globbed.deno_flags(wasm)[1], "--allow-write=/data");
+    }
+
+    #[test]
+    fn test_node_permission_flag_by_version() {
+        assert_eq!(node_permission_flag("v18.19.0"), None);
+        assert_eq!(node_permission_flag("v20.20.2\n"), Some("--experimental-permission"));
+        assert_eq!(node_permission_flag("v22.12.0"), Some("--experimental-permission"));
+        assert_eq!(node_permission_flag("v22.13.0"), Some("--permission"));
+        assert_eq!(node_permission_flag("v23.4.0"), Some("--experimental-permission"));
+        assert_eq!(node_permission_flag("v23.5.0"), Some("--permission"));
+        assert_eq!(node_permission_flag("v24.0.0"), Some("--permission"));
+        assert_eq!(node_permission_flag("not a version"), None);
+    }
+}
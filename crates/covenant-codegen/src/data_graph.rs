@@ -156,6 +156,7 @@ impl DataGraph {
                     SnippetKind::ExternAbstract => "extern_abstract",
                     SnippetKind::ExternImpl => "extern_impl",
                     SnippetKind::Test => "test",
+                    SnippetKind::Effect => "effect",
                     SnippetKind::Data => "data", // Won't happen due to continue above
                 };
                 let metadata = vec![("kind".to_string(), kind_str.to_string())];
@@ -344,6 +345,7 @@ mod tests {
             sections,
             implements: None,
             platform: None,
+            comments: Vec::new(),
             span: Span::default(),
         }
     }
@@ -56,6 +56,53 @@ pub struct EmbeddableSymbol {
     pub required_capabilities: Vec<String>,
 }
 
+/// How much of the symbol graph a compiled module embeds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbedLevel {
+    /// Nothing; the smallest module
+    None,
+    /// Exported functions, effect declarations and types
+    #[default]
+    Api,
+    /// The entire symbol graph
+    Full,
+}
+
+impl std::str::FromStr for EmbedLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(EmbedLevel::None),
+            "api" => Ok(EmbedLevel::Api),
+            "full" => Ok(EmbedLevel::Full),
+            _ => Err(format!("Unknown embed level: {}. Expected: none, api, or full", s)),
+        }
+    }
+}
+
+/// Keep the symbols embedded at `level`. At [`EmbedLevel::Api`] these are
+/// the `exports` (every function if there are none), effects and types.
+pub fn select_embedded(
+    symbols: Vec<EmbeddableSymbol>,
+    level: EmbedLevel,
+    exports: &[String],
+) -> Vec<EmbeddableSymbol> {
+    match level {
+        EmbedLevel::None => Vec::new(),
+        EmbedLevel::Full => symbols,
+        EmbedLevel::Api => symbols
+            .into_iter()
+            .filter(|sym| match sym.kind.as_str() {
+                "function" => exports.is_empty() || exports.contains(&sym.id),
+                "struct" | "enum" | "effect" => true,
+                _ => false,
+            })
+            .collect(),
+    }
+}
+
 /// Build embeddable symbols from a SymbolGraph and EffectCheckResult
 ///
 /// This combines compile-time symbol information with computed effect closures
@@ -112,7 +159,7 @@ pub fn build_embeddable_symbols(
 ///
 /// This defines the canonical mapping from Covenant effects to the
 /// concrete WASM imports that must be provided by the runtime host.
-fn effects_to_capabilities(effects: &[String]) -> Vec<String> {
+pub(crate) fn effects_to_capabilities(effects: &[String]) -> Vec<String> {
     let mut capabilities = Vec::new();
 
     for effect in effects {
@@ -221,6 +268,41 @@ mod tests {
         assert!(parsed.required_capabilities.contains(&"console.println".to_string()));
     }
 
+    #[test]
+    fn test_select_embedded() {
+        let symbol = |id: &str, kind: &str| EmbeddableSymbol {
+            id: id.to_string(),
+            kind: kind.to_string(),
+            line: 0,
+            calls: vec![],
+            references: vec![],
+            called_by: vec![],
+            referenced_by: vec![],
+            effects: vec![],
+            effect_closure: vec![],
+            requirements: vec![],
+            tests: vec![],
+            covers: vec![],
+            required_capabilities: vec![],
+        };
+        let symbols = vec![
+            symbol("app.main", "function"),
+            symbol("app.helper", "function"),
+            symbol("app.User", "struct"),
+            symbol("app.test_main", "test"),
+        ];
+        let ids = |level, exports: &[String]| -> Vec<String> {
+            select_embedded(symbols.clone(), level, exports).into_iter().map(|s| s.id).collect()
+        };
+
+        assert_eq!(ids(EmbedLevel::Api, &["app.main".to_string()]), vec!["app.main", "app.User"]);
+        assert_eq!(ids(EmbedLevel::Api, &[]), vec!["app.main", "app.helper", "app.User"]);
+        assert_eq!(ids(EmbedLevel::Full, &[]).len(), 4);
+        assert!(ids(EmbedLevel::None, &[]).is_empty());
+        assert_eq!("FULL".parse::<EmbedLevel>(), Ok(EmbedLevel::Full));
+        assert!("reachable".parse::<EmbedLevel>().is_err());
+    }
+
     #[test]
     fn test_effects_to_capabilities() {
         // Database effect
@@ -896,6 +896,7 @@ mod tests {
             sections,
             implements: None,
             platform: None,
+            comments: Vec::new(),
             span: Span::default(),
         }
     }
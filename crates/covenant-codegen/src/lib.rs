@@ -5,14 +5,19 @@
 mod ir;
 mod wasm;
 mod snippet_wasm;
+mod runtime_lib;
+mod runtime_text;
+mod monomorphize;
+pub mod capabilities;
 pub mod data_graph;
 pub mod embeddable;
 pub mod gai_codegen;
 
 pub use ir::*;
 pub use wasm::*;
-pub use snippet_wasm::SnippetWasmCompiler;
-pub use embeddable::{EmbeddableSymbol, build_embeddable_symbols};
+pub use snippet_wasm::{error_type_tag, SnippetWasmCompiler};
+pub use embeddable::{EmbeddableSymbol, EmbedLevel, build_embeddable_symbols, select_embedded};
+pub use capabilities::{node_permission_flag, CapabilityEffect, CapabilityManifest, CAPABILITY_SECTION};
 
 use covenant_ast::Program;
 use covenant_checker::SymbolTable;
@@ -37,6 +42,9 @@ pub enum CodegenError {
 
     #[error("serialization failed: {0}")]
     SerializationFailed(String),
+
+    #[error("runtime function {name} is used but was not linked")]
+    RuntimeFunctionNotLinked { name: String },
 }
 
 /// Compile a program to WASM
@@ -47,8 +55,9 @@ pub fn compile(program: &Program, symbols: &SymbolTable) -> Result<Vec<u8>, Code
             compiler.compile_legacy(declarations)
         }
         Program::Snippets { snippets, .. } => {
-            let mut compiler = SnippetWasmCompiler::new(symbols);
-            compiler.compile_snippets(snippets)
+            let mono = monomorphize::monomorphize(snippets, symbols);
+            let mut compiler = SnippetWasmCompiler::new(symbols).with_specializations(mono.specializations);
+            compiler.compile_snippets(&mono.snippets)
         }
     }
 }
@@ -62,11 +71,27 @@ pub fn compile_pure(program: &Program, symbols: &SymbolTable) -> Result<Vec<u8>,
 ///
 /// This function builds embeddable symbols from the SymbolGraph and EffectCheckResult,
 /// then embeds them as JSON in the WASM data section alongside the normal data graph.
+/// The capability manifest is appended as a custom section.
 pub fn compile_with_symbols(
     program: &Program,
     symbols: &SymbolTable,
     symbol_graph: &covenant_symbols::SymbolGraph,
     effect_result: &covenant_checker::EffectCheckResult,
+) -> Result<Vec<u8>, CodegenError> {
+    compile_with_embedding(program, symbols, symbol_graph, effect_result, EmbedLevel::Full, &[])
+}
+
+/// Compile a program to WASM, embedding symbol metadata at `level`
+///
+/// `exports` are the entry snippets embedded at [`EmbedLevel::Api`]. The
+/// capability manifest is appended at every level.
+pub fn compile_with_embedding(
+    program: &Program,
+    symbols: &SymbolTable,
+    symbol_graph: &covenant_symbols::SymbolGraph,
+    effect_result: &covenant_checker::EffectCheckResult,
+    level: EmbedLevel,
+    exports: &[String],
 ) -> Result<Vec<u8>, CodegenError> {
     match program {
         Program::Legacy { declarations, .. } => {
@@ -75,9 +100,18 @@ pub fn compile_with_symbols(
             compiler.compile_legacy(declarations)
         }
         Program::Snippets { snippets, .. } => {
-            let embeddable = build_embeddable_symbols(symbol_graph, effect_result);
-            let mut compiler = SnippetWasmCompiler::new(symbols);
-            compiler.compile_snippets_with_symbols(snippets, &embeddable)
+            let mono = monomorphize::monomorphize(snippets, symbols);
+            let snippets = mono.snippets;
+            let mut compiler = SnippetWasmCompiler::new(symbols).with_specializations(mono.specializations);
+            let mut wasm = if level == EmbedLevel::None {
+                compiler.compile_snippets(&snippets)?
+            } else {
+                let embeddable = build_embeddable_symbols(symbol_graph, effect_result);
+                let embeddable = select_embedded(embeddable, level, exports);
+                compiler.compile_snippets_with_symbols(&snippets, &embeddable)?
+            };
+            CapabilityManifest::from_effects(effect_result).embed(&mut wasm);
+            Ok(wasm)
         }
     }
 }
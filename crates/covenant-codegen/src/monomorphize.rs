@@ -0,0 +1,329 @@
+//! Monomorphization of generic functions
+//!
+//! The checker records the type arguments inferred at every call to a generic
+//! function. This pass emits one specialized copy of a generic function per
+//! distinct set of type arguments and retargets each call step at its copy, so
+//! the WASM compiler never sees a type parameter. Generic functions that are
+//! never called are dropped.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use covenant_ast::{
+    FunctionSignature, ReturnType, Section, SignatureKind, Snippet, SnippetKind, Step, StepKind,
+    Type, TypeKind,
+};
+use covenant_checker::{ResolvedType, SymbolTable};
+
+/// A specialized copy of a generic function
+#[derive(Debug, Clone)]
+pub struct Specialization {
+    /// Snippet ID of the generic function the copy was made from
+    pub template: String,
+    /// Type arguments, by type parameter name
+    pub bindings: HashMap<String, ResolvedType>,
+}
+
+/// Snippets after monomorphization, with the specializations by snippet ID
+pub struct Monomorphized<'a> {
+    pub snippets: Cow<'a, [Snippet]>,
+    pub specializations: HashMap<String, Specialization>,
+}
+
+/// Replace generic function snippets with their specializations
+pub fn monomorphize<'a>(snippets: &'a [Snippet], symbols: &SymbolTable) -> Monomorphized<'a> {
+    let mut templates: HashMap<&str, &Snippet> = HashMap::new();
+    for snippet in snippets {
+        if snippet.kind != SnippetKind::Function {
+            continue;
+        }
+        if let Some(sig) = function_signature(snippet).filter(|s| !s.generics.is_empty()) {
+            templates.insert(snippet.id.as_str(), snippet);
+            templates.insert(sig.name.as_str(), snippet);
+        }
+    }
+    if templates.is_empty() {
+        return Monomorphized {
+            snippets: Cow::Borrowed(snippets),
+            specializations: HashMap::new(),
+        };
+    }
+
+    let mut mono = Monomorphizer {
+        symbols,
+        templates,
+        queue: VecDeque::new(),
+        seen: HashSet::new(),
+        specializations: HashMap::new(),
+    };
+
+    let mut output = Vec::new();
+    for snippet in snippets {
+        if mono.is_template(snippet) {
+            continue;
+        }
+        let mut snippet = snippet.clone();
+        let caller = snippet.id.clone();
+        if let Some(steps) = body_steps_mut(&mut snippet) {
+            mono.rewrite_steps(steps, &caller, &HashMap::new());
+        }
+        output.push(snippet);
+    }
+
+    while let Some((template, type_args)) = mono.queue.pop_front() {
+        output.push(mono.specialize(template, &type_args));
+    }
+
+    Monomorphized {
+        snippets: Cow::Owned(output),
+        specializations: mono.specializations,
+    }
+}
+
+struct Monomorphizer<'a> {
+    symbols: &'a SymbolTable,
+    /// Generic function snippets, by snippet ID and by function name
+    templates: HashMap<&'a str, &'a Snippet>,
+    /// Specializations still to be emitted
+    queue: VecDeque<(&'a Snippet, Vec<ResolvedType>)>,
+    /// Mangled IDs of specializations already queued
+    seen: HashSet<String>,
+    /// Specializations emitted so far, by mangled ID
+    specializations: HashMap<String, Specialization>,
+}
+
+impl<'a> Monomorphizer<'a> {
+    fn is_template(&self, snippet: &Snippet) -> bool {
+        self.templates
+            .get(snippet.id.as_str())
+            .is_some_and(|t| std::ptr::eq(*t, snippet))
+    }
+
+    /// Point calls to generic functions at their specializations. `caller`
+    /// is the snippet the steps were checked in; `bindings` are the type
+    /// arguments of the specialization being built, if any.
+    fn rewrite_steps(
+        &mut self,
+        steps: &mut [Step],
+        caller: &str,
+        bindings: &HashMap<String, ResolvedType>,
+    ) {
+        for step in steps {
+            match &mut step.kind {
+                StepKind::Call(call) => {
+                    if let Some(template) = self.templates.get(call.fn_name.as_str()).copied() {
+                        if let Some(args) = self.symbols.type_args(caller, &step.id) {
+                            let args: Vec<ResolvedType> =
+                                args.iter().map(|t| t.substitute(bindings)).collect();
+                            call.fn_name = self.instantiate(template, args);
+                        }
+                    }
+                    if let Some(handle) = &mut call.handle {
+                        for case in &mut handle.cases {
+                            self.rewrite_steps(&mut case.steps, caller, bindings);
+                        }
+                    }
+                }
+                StepKind::If(if_step) => {
+                    self.rewrite_steps(&mut if_step.then_steps, caller, bindings);
+                    if let Some(else_steps) = &mut if_step.else_steps {
+                        self.rewrite_steps(else_steps, caller, bindings);
+                    }
+                }
+                StepKind::Match(match_step) => {
+                    for case in &mut match_step.cases {
+                        self.rewrite_steps(&mut case.steps, caller, bindings);
+                    }
+                }
+                StepKind::For(for_step) => self.rewrite_steps(&mut for_step.steps, caller, bindings),
+                StepKind::Transaction(tx) => self.rewrite_steps(&mut tx.steps, caller, bindings),
+                StepKind::Parallel(parallel) => {
+                    for branch in &mut parallel.branches {
+                        self.rewrite_steps(&mut branch.steps, caller, bindings);
+                    }
+                }
+                StepKind::Race(race) => {
+                    for branch in &mut race.branches {
+                        self.rewrite_steps(&mut branch.steps, caller, bindings);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Queue a specialization (once) and return its mangled snippet ID
+    fn instantiate(&mut self, template: &'a Snippet, type_args: Vec<ResolvedType>) -> String {
+        let id = mangle(&template.id, &type_args);
+        if self.seen.insert(id.clone()) {
+            self.queue.push_back((template, type_args));
+        }
+        id
+    }
+
+    /// Build the specialized copy of `template` for `type_args`
+    fn specialize(&mut self, template: &Snippet, type_args: &[ResolvedType]) -> Snippet {
+        let mut snippet = template.clone();
+        snippet.id = mangle(&template.id, type_args);
+
+        let mut bindings = HashMap::new();
+        let mut type_names = HashMap::new();
+        if let Some(sig) = function_signature_mut(&mut snippet) {
+            for (param, ty) in sig.generics.iter().zip(type_args) {
+                bindings.insert(param.name.clone(), ty.clone());
+                type_names.insert(param.name.clone(), type_source(ty));
+            }
+            sig.name = mangle(&sig.name, type_args);
+            sig.generics.clear();
+            for param in &mut sig.params {
+                substitute_type(&mut param.ty, &type_names);
+            }
+            if let Some(returns) = &mut sig.returns {
+                substitute_return_type(returns, &type_names);
+            }
+        }
+
+        if let Some(steps) = body_steps_mut(&mut snippet) {
+            self.rewrite_steps(steps, &template.id, &bindings);
+        }
+        self.specializations.insert(
+            snippet.id.clone(),
+            Specialization { template: template.id.clone(), bindings },
+        );
+        snippet
+    }
+}
+
+/// Name of a specialization, e.g. `list.first<Int>`
+fn mangle(name: &str, type_args: &[ResolvedType]) -> String {
+    let args: Vec<String> = type_args.iter().map(type_source).collect();
+    format!("{}<{}>", name, args.join(", "))
+}
+
+/// Render a resolved type in the attribute syntax the parser produces
+fn type_source(ty: &ResolvedType) -> String {
+    match ty {
+        ResolvedType::Optional(inner) => format!("Optional<{}>", type_source(inner)),
+        ResolvedType::List(inner) => format!("List<{}>", type_source(inner)),
+        ResolvedType::Set(inner) => format!("Set<{}>", type_source(inner)),
+        ResolvedType::Named { name, args, .. } if !args.is_empty() => {
+            let args: Vec<String> = args.iter().map(type_source).collect();
+            format!("{}<{}>", name, args.join(", "))
+        }
+        ResolvedType::None => "None".to_string(),
+        other => other.display(),
+    }
+}
+
+/// Replace type parameter names in a type with the source of their bindings
+fn substitute_type(ty: &mut Type, type_names: &HashMap<String, String>) {
+    match &mut ty.kind {
+        TypeKind::Named(path) => {
+            for segment in &mut path.segments {
+                *segment = substitute_type_str(segment, type_names);
+            }
+            for generic in &mut path.generics {
+                substitute_type(generic, type_names);
+            }
+        }
+        TypeKind::Optional(inner) | TypeKind::List(inner) => substitute_type(inner, type_names),
+        TypeKind::Union(types) | TypeKind::Tuple(types) => {
+            for t in types {
+                substitute_type(t, type_names);
+            }
+        }
+        TypeKind::Function { params, ret } => {
+            for t in params {
+                substitute_type(t, type_names);
+            }
+            substitute_type(ret, type_names);
+        }
+        TypeKind::Struct(fields) => {
+            for field in fields {
+                substitute_type(&mut field.ty, type_names);
+            }
+        }
+    }
+}
+
+fn substitute_return_type(ret: &mut ReturnType, type_names: &HashMap<String, String>) {
+    match ret {
+        ReturnType::Single { ty, .. } => substitute_type(ty, type_names),
+        ReturnType::Collection { of } => substitute_type(of, type_names),
+        ReturnType::Union { types } => {
+            for member in types {
+                substitute_type(&mut member.ty, type_names);
+            }
+        }
+    }
+}
+
+/// Replace whole identifiers in a type string such as `List<T>`
+fn substitute_type_str(s: &str, type_names: &HashMap<String, String>) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut ident = String::new();
+    for c in s.chars().chain(std::iter::once('\0')) {
+        if c.is_alphanumeric() || c == '_' {
+            ident.push(c);
+            continue;
+        }
+        if !ident.is_empty() {
+            out.push_str(type_names.get(&ident).map(String::as_str).unwrap_or(&ident));
+            ident.clear();
+        }
+        if c != '\0' {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn function_signature(snippet: &Snippet) -> Option<&FunctionSignature> {
+    snippet.sections.iter().find_map(|section| match section {
+        Section::Signature(sig) => match &sig.kind {
+            SignatureKind::Function(fn_sig) => Some(fn_sig),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+fn function_signature_mut(snippet: &mut Snippet) -> Option<&mut FunctionSignature> {
+    snippet.sections.iter_mut().find_map(|section| match section {
+        Section::Signature(sig) => match &mut sig.kind {
+            SignatureKind::Function(fn_sig) => Some(fn_sig),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+fn body_steps_mut(snippet: &mut Snippet) -> Option<&mut Vec<Step>> {
+    snippet.sections.iter_mut().find_map(|section| match section {
+        Section::Body(body) => Some(&mut body.steps),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitute_type_str() {
+        let names: HashMap<String, String> =
+            [("T".to_string(), "Int".to_string()), ("K".to_string(), "String".to_string())]
+                .into_iter()
+                .collect();
+        assert_eq!(substitute_type_str("T", &names), "Int");
+        assert_eq!(substitute_type_str("List<T>", &names), "List<Int>");
+        assert_eq!(substitute_type_str("Map<K, List<T>>", &names), "Map<String, List<Int>>");
+        assert_eq!(substitute_type_str("Tree", &names), "Tree");
+    }
+
+    #[test]
+    fn test_mangle() {
+        let args = vec![ResolvedType::List(Box::new(ResolvedType::String)), ResolvedType::Float];
+        assert_eq!(mangle("util.pick", &args), "util.pick<List<String>, Float>");
+    }
+}
@@ -0,0 +1,2267 @@
+//! Runtime Library Code Generation
+//!
+//! Generates a small WASM-resident library implementing the map, set,
+//! datetime and bytes operations of compute steps, and the pure `text.*` and
+//! `list.*` extern-abstracts (see `runtime_text`). Only the functions a
+//! program actually uses (plus their dependencies) are linked into the
+//! module, after the user and GAI functions. None of them call the host.
+//!
+//! Value representations (all values are i64 in locals):
+//! - Bytes: fat pointer `(ptr << 32) | len`, same as String
+//! - DateTime: seconds since the Unix epoch (UTC)
+//! - Set: fat pointer `(ptr << 32) | count` to `[count:i32][item:i64]...`
+//!   (the same layout as lists, so `set_to_list` is free)
+//! - Map: fat pointer `(ptr << 32) | count` to `[count:i32][(key:i64, value:i64)]...`
+//! - Map entry tuples: pointer to `[key:i64][value:i64]` (struct layout)
+//!
+//! A null pointer is an empty collection. Collections are immutable; every
+//! update allocates a new one from the bump allocator (global 0). Keys and set
+//! elements are compared by value, or by string content when the caller
+//! passes `by_content = 1`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use wasm_encoder::{BlockType, Function, Instruction, MemArg, ValType};
+use covenant_ast::Operation;
+use crate::runtime_text;
+use crate::CodegenError;
+use crate::snippet_wasm::DataSegmentBuilder;
+
+/// Stride of a set element in bytes
+pub const SET_STRIDE: i32 = 8;
+/// Stride of a map entry in bytes
+pub const MAP_STRIDE: i32 = 16;
+
+/// Format used by `dt_format` when no format string is given (ISO 8601)
+pub const DEFAULT_DT_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// Sentinel returned for absent values (same encoding as the `none` literal)
+pub(crate) const NONE_SENTINEL: i64 = i64::MIN;
+
+const BASE64_ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const HEX_ALPHABET: &str = "0123456789abcdef";
+
+/// A function in the runtime library
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RtFunc {
+    /// _rt_alloc(size: i32) -> i32 (8-byte aligned bump allocation, grows memory)
+    Alloc,
+    /// _rt_str_eq(a: i64, b: i64) -> i32 (byte-wise fat pointer comparison)
+    StrEq,
+    /// _rt_key_eq(a: i64, b: i64, by_content: i32) -> i32
+    KeyEq,
+    /// _rt_count(coll: i64) -> i32 (element count, 0 for null)
+    Count,
+    /// _rt_find(coll: i64, key: i64, stride: i32, by_content: i32) -> i32 (index or -1)
+    Find,
+    /// _rt_new_coll(count: i32, stride: i32) -> i32 (pointer with count header written)
+    NewColl,
+    /// _rt_remove(coll: i64, key: i64, stride: i32, by_content: i32) -> i64
+    Remove,
+    /// _rt_map_insert(map: i64, key: i64, value: i64, by_content: i32) -> i64
+    MapInsert,
+    /// _rt_map_project(map: i64, which: i32) -> i64 (0 = keys, 1 = values, 2 = entries)
+    MapProject,
+    /// _rt_map_merge(a: i64, b: i64, by_content: i32) -> i64 (b wins on conflicts)
+    MapMerge,
+    /// _rt_set_add(set: i64, item: i64, by_content: i32) -> i64
+    SetAdd,
+    /// _rt_set_filter(a: i64, b: i64, keep_present: i32, by_content: i32) -> i64
+    SetFilter,
+    /// _rt_set_concat(a: i64, b: i64) -> i64 (no deduplication)
+    SetConcat,
+    /// _rt_set_union(a: i64, b: i64, by_content: i32) -> i64
+    SetUnion,
+    /// _rt_set_symmetric_diff(a: i64, b: i64, by_content: i32) -> i64
+    SetSymmetricDiff,
+    /// _rt_set_is_subset(a: i64, b: i64, by_content: i32) -> i64
+    SetIsSubset,
+    /// _rt_set_is_superset(a: i64, b: i64, by_content: i32) -> i64
+    SetIsSuperset,
+    /// _rt_bytes_get(bytes: i64, index: i64) -> i64 (byte or none)
+    BytesGet,
+    /// _rt_bytes_slice(bytes: i64, start: i64, end: i64) -> i64 (clamped, no copy)
+    BytesSlice,
+    /// _rt_bytes_concat(a: i64, b: i64) -> i64
+    BytesConcat,
+    /// _rt_bytes_to_string(bytes: i64) -> i64 (input if valid UTF-8, none otherwise)
+    BytesToString,
+    /// _rt_bytes_to_base64(bytes: i64) -> i64
+    BytesToBase64,
+    /// _rt_bytes_to_hex(bytes: i64) -> i64
+    BytesToHex,
+    /// _rt_dt_field(time: i64, field: i32) -> i64 (see `DtField`)
+    DtField,
+    /// _rt_write_num(addr: i32, value: i64, width: i32) -> i32 (zero-padded decimal, returns end)
+    WriteNum,
+    /// _rt_dt_format(time: i64, format: i64) -> i64 (strftime subset: %Y %m %d %H %M %S %%)
+    DtFormat,
+
+    // ----- Text helpers (see runtime_text) -----
+    /// _rt_utf8_len(s: i64) -> i32 (code point count)
+    Utf8Len,
+    /// _rt_char_offset(s: i64, index: i32) -> i32 (byte offset of a code point, clamped to len)
+    CharOffset,
+    /// _rt_mem_eq(a: i32, b: i32, len: i32) -> i32
+    MemEq,
+    /// _rt_index_of(haystack: i64, needle: i64, from: i32) -> i32 (byte index or -1)
+    IndexOf,
+    /// _rt_heap_push(src: i32, len: i32) (appends bytes at the heap top, unaligned)
+    HeapPush,
+
+    // ----- text.* extern-abstracts -----
+    /// text.upper / text.lower: (s: i64, upper: i32) -> i64 (ASCII case mapping)
+    TextCase,
+    /// text.trim / trim_start / trim_end: (s: i64, sides: i32) -> i64 (1 = start, 2 = end)
+    TextTrim,
+    /// text.contains(haystack: i64, needle: i64) -> i64
+    TextContains,
+    /// text.starts_with(s: i64, prefix: i64) -> i64
+    TextStartsWith,
+    /// text.ends_with(s: i64, suffix: i64) -> i64
+    TextEndsWith,
+    /// text.index_of(haystack: i64, needle: i64) -> i64 (code point index or -1)
+    TextIndexOf,
+    /// text.slice(s: i64, start: i64, end: i64) -> i64 (code point indices, negative from end)
+    TextSlice,
+    /// text.char_at(s: i64, index: i64) -> i64 (empty string when out of range)
+    TextCharAt,
+    /// text.replace / replace_all: (s: i64, from: i64, to: i64, all: i32) -> i64
+    TextReplace,
+    /// text.split(s: i64, delimiter: i64) -> i64 (list of strings sharing memory with `s`)
+    TextSplit,
+    /// text.join(parts: i64, separator: i64) -> i64
+    TextJoin,
+    /// text.repeat(s: i64, count: i64) -> i64
+    TextRepeat,
+    /// text.str_len(s: i64) -> i64 (code points)
+    TextStrLen,
+    /// text.byte_len(s: i64) -> i64
+    TextByteLen,
+    /// text.is_empty(s: i64) -> i64
+    TextIsEmpty,
+    /// text.str_reverse(s: i64) -> i64 (reverses code points)
+    TextReverse,
+    /// text.pad_start / pad_end: (s: i64, target_len: i64, fill: i64, at_start: i32) -> i64
+    TextPad,
+
+    // ----- list.* extern-abstracts -----
+    /// list.len(list: i64) -> i64
+    ListLen,
+    /// list.is_empty(list: i64) -> i64
+    ListIsEmpty,
+    /// list.get(list: i64, index: i64) -> i64 (none when out of range)
+    ListGet,
+    /// list.first(list: i64) -> i64
+    ListFirst,
+    /// list.last(list: i64) -> i64
+    ListLast,
+    /// list.append(list: i64, item: i64) -> i64
+    ListAppend,
+    /// list.contains(list: i64, item: i64, by_content: i32) -> i64
+    ListContains,
+    /// list.flatten(list: i64) -> i64
+    ListFlatten,
+}
+
+/// A pure extern-abstract implemented by the runtime library instead of a host import
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuiltinExtern {
+    /// Implementing runtime function
+    pub func: RtFunc,
+    /// Constant i32 selector passed after the call arguments, if any
+    pub selector: Option<i32>,
+}
+
+impl BuiltinExtern {
+    /// Look up the built-in implementation of an extern-abstract snippet ID
+    pub fn for_id(id: &str) -> Option<Self> {
+        let (func, selector) = match id {
+            "text.concat" => (RtFunc::BytesConcat, None),
+            "text.upper" => (RtFunc::TextCase, Some(1)),
+            "text.lower" => (RtFunc::TextCase, Some(0)),
+            "text.trim" => (RtFunc::TextTrim, Some(3)),
+            "text.trim_start" => (RtFunc::TextTrim, Some(1)),
+            "text.trim_end" => (RtFunc::TextTrim, Some(2)),
+            "text.contains" => (RtFunc::TextContains, None),
+            "text.starts_with" => (RtFunc::TextStartsWith, None),
+            "text.ends_with" => (RtFunc::TextEndsWith, None),
+            "text.index_of" => (RtFunc::TextIndexOf, None),
+            "text.slice" => (RtFunc::TextSlice, None),
+            "text.char_at" => (RtFunc::TextCharAt, None),
+            "text.replace" => (RtFunc::TextReplace, Some(0)),
+            "text.replace_all" => (RtFunc::TextReplace, Some(1)),
+            "text.split" => (RtFunc::TextSplit, None),
+            "text.join" => (RtFunc::TextJoin, None),
+            "text.repeat" => (RtFunc::TextRepeat, None),
+            "text.str_len" => (RtFunc::TextStrLen, None),
+            "text.byte_len" => (RtFunc::TextByteLen, None),
+            "text.is_empty" => (RtFunc::TextIsEmpty, None),
+            "text.str_reverse" => (RtFunc::TextReverse, None),
+            "text.pad_start" => (RtFunc::TextPad, Some(1)),
+            "text.pad_end" => (RtFunc::TextPad, Some(0)),
+            "list.len" => (RtFunc::ListLen, None),
+            "list.is_empty" => (RtFunc::ListIsEmpty, None),
+            "list.get" => (RtFunc::ListGet, None),
+            "list.first" => (RtFunc::ListFirst, None),
+            "list.last" => (RtFunc::ListLast, None),
+            "list.append" => (RtFunc::ListAppend, None),
+            // The by_content selector depends on the call site
+            "list.contains" => (RtFunc::ListContains, None),
+            "list.flatten" => (RtFunc::ListFlatten, None),
+            _ => return None,
+        };
+        Some(Self { func, selector })
+    }
+}
+
+/// Field selector for `RtFunc::DtField`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DtField {
+    Year = 0,
+    Month = 1,
+    Day = 2,
+    Hour = 3,
+    Minute = 4,
+    Second = 5,
+    /// 0 = Sunday .. 6 = Saturday
+    Weekday = 6,
+}
+
+/// Selector for `RtFunc::MapProject`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapProjection {
+    Keys = 0,
+    Values = 1,
+    Entries = 2,
+}
+
+impl RtFunc {
+    /// Runtime functions called directly by the code generated for an operation
+    pub fn for_operation(op: Operation) -> &'static [RtFunc] {
+        match op {
+            Operation::MapLen | Operation::MapIsEmpty
+            | Operation::SetLen | Operation::SetIsEmpty => &[RtFunc::Count],
+            Operation::MapHas | Operation::SetHas => &[RtFunc::Find],
+            Operation::MapInsert => &[RtFunc::MapInsert],
+            Operation::MapRemove | Operation::SetRemove => &[RtFunc::Remove],
+            Operation::MapKeys | Operation::MapValues | Operation::MapEntries => &[RtFunc::MapProject],
+            Operation::MapMerge => &[RtFunc::MapMerge],
+            Operation::SetAdd => &[RtFunc::SetAdd],
+            Operation::SetIntersect | Operation::SetDiff => &[RtFunc::SetFilter],
+            Operation::SetUnion => &[RtFunc::SetUnion],
+            Operation::SetSymmetricDiff => &[RtFunc::SetSymmetricDiff],
+            Operation::SetIsSubset => &[RtFunc::SetIsSubset],
+            Operation::SetIsSuperset => &[RtFunc::SetIsSuperset],
+            Operation::DtYear | Operation::DtMonth | Operation::DtDay | Operation::DtHour
+            | Operation::DtMinute | Operation::DtSecond | Operation::DtWeekday => &[RtFunc::DtField],
+            Operation::DtFormat => &[RtFunc::DtFormat],
+            Operation::BytesGet => &[RtFunc::BytesGet],
+            Operation::BytesSlice => &[RtFunc::BytesSlice],
+            Operation::BytesConcat => &[RtFunc::BytesConcat],
+            Operation::BytesToString => &[RtFunc::BytesToString],
+            Operation::BytesToBase64 => &[RtFunc::BytesToBase64],
+            Operation::BytesToHex => &[RtFunc::BytesToHex],
+            // Inlined (set_to_list, dt_unix, dt_add_*, dt_diff, bytes_len, bytes_is_empty)
+            // or not part of the runtime library
+            _ => &[],
+        }
+    }
+
+    /// Other runtime functions this function calls
+    fn dependencies(self) -> &'static [RtFunc] {
+        match self {
+            RtFunc::Alloc | RtFunc::StrEq | RtFunc::Count | RtFunc::BytesGet
+            | RtFunc::BytesSlice | RtFunc::BytesToString | RtFunc::DtField
+            | RtFunc::WriteNum => &[],
+            RtFunc::KeyEq => &[RtFunc::StrEq],
+            RtFunc::Find => &[RtFunc::Count, RtFunc::KeyEq],
+            RtFunc::NewColl => &[RtFunc::Alloc],
+            RtFunc::Remove | RtFunc::MapInsert | RtFunc::SetAdd | RtFunc::SetFilter => {
+                &[RtFunc::Find, RtFunc::Count, RtFunc::NewColl]
+            }
+            RtFunc::MapProject => &[RtFunc::Count, RtFunc::NewColl, RtFunc::Alloc],
+            RtFunc::MapMerge => &[RtFunc::Find, RtFunc::Count, RtFunc::NewColl],
+            RtFunc::SetConcat => &[RtFunc::Count, RtFunc::NewColl],
+            RtFunc::SetUnion | RtFunc::SetSymmetricDiff => &[RtFunc::SetFilter, RtFunc::SetConcat],
+            RtFunc::SetIsSubset => &[RtFunc::Find, RtFunc::Count],
+            RtFunc::SetIsSuperset => &[RtFunc::SetIsSubset],
+            RtFunc::BytesConcat | RtFunc::BytesToBase64 | RtFunc::BytesToHex => &[RtFunc::Alloc],
+            RtFunc::DtFormat => &[RtFunc::Alloc, RtFunc::DtField, RtFunc::WriteNum],
+            RtFunc::Utf8Len | RtFunc::CharOffset | RtFunc::MemEq | RtFunc::HeapPush
+            | RtFunc::TextTrim | RtFunc::TextByteLen | RtFunc::TextIsEmpty => &[],
+            RtFunc::IndexOf | RtFunc::TextStartsWith | RtFunc::TextEndsWith => &[RtFunc::MemEq],
+            RtFunc::TextCase | RtFunc::TextReverse => &[RtFunc::Alloc],
+            RtFunc::TextContains => &[RtFunc::IndexOf],
+            RtFunc::TextIndexOf => &[RtFunc::IndexOf, RtFunc::Utf8Len],
+            RtFunc::TextSlice => &[RtFunc::Utf8Len, RtFunc::CharOffset],
+            RtFunc::TextCharAt => &[RtFunc::CharOffset],
+            RtFunc::TextReplace => &[RtFunc::IndexOf, RtFunc::HeapPush],
+            RtFunc::TextSplit => &[RtFunc::Utf8Len, RtFunc::IndexOf, RtFunc::NewColl],
+            RtFunc::TextJoin => &[RtFunc::Count, RtFunc::HeapPush],
+            RtFunc::TextRepeat => &[RtFunc::HeapPush],
+            RtFunc::TextStrLen => &[RtFunc::Utf8Len],
+            RtFunc::TextPad => &[RtFunc::Utf8Len, RtFunc::HeapPush],
+            RtFunc::ListLen | RtFunc::ListIsEmpty | RtFunc::ListGet => &[RtFunc::Count],
+            RtFunc::ListFirst | RtFunc::ListLast => &[RtFunc::ListGet, RtFunc::Count],
+            RtFunc::ListAppend | RtFunc::ListFlatten => &[RtFunc::Count, RtFunc::NewColl],
+            RtFunc::ListContains => &[RtFunc::Find],
+        }
+    }
+
+    /// WASM signature as (params, results)
+    fn signature(self) -> (Vec<ValType>, Vec<ValType>) {
+        use ValType::{I32, I64};
+        match self {
+            RtFunc::Alloc => (vec![I32], vec![I32]),
+            RtFunc::StrEq => (vec![I64, I64], vec![I32]),
+            RtFunc::KeyEq => (vec![I64, I64, I32], vec![I32]),
+            RtFunc::Count => (vec![I64], vec![I32]),
+            RtFunc::Find => (vec![I64, I64, I32, I32], vec![I32]),
+            RtFunc::NewColl => (vec![I32, I32], vec![I32]),
+            RtFunc::Remove => (vec![I64, I64, I32, I32], vec![I64]),
+            RtFunc::MapInsert => (vec![I64, I64, I64, I32], vec![I64]),
+            RtFunc::MapProject => (vec![I64, I32], vec![I64]),
+            RtFunc::MapMerge | RtFunc::SetAdd | RtFunc::SetUnion | RtFunc::SetSymmetricDiff
+            | RtFunc::SetIsSubset | RtFunc::SetIsSuperset => (vec![I64, I64, I32], vec![I64]),
+            RtFunc::SetFilter => (vec![I64, I64, I32, I32], vec![I64]),
+            RtFunc::SetConcat | RtFunc::BytesGet | RtFunc::BytesConcat => (vec![I64, I64], vec![I64]),
+            RtFunc::BytesSlice => (vec![I64, I64, I64], vec![I64]),
+            RtFunc::BytesToString | RtFunc::BytesToBase64 | RtFunc::BytesToHex => (vec![I64], vec![I64]),
+            RtFunc::DtField => (vec![I64, I32], vec![I64]),
+            RtFunc::WriteNum => (vec![I32, I64, I32], vec![I32]),
+            RtFunc::DtFormat => (vec![I64, I64], vec![I64]),
+            RtFunc::Utf8Len => (vec![I64], vec![I32]),
+            RtFunc::CharOffset => (vec![I64, I32], vec![I32]),
+            RtFunc::MemEq => (vec![I32, I32, I32], vec![I32]),
+            RtFunc::IndexOf => (vec![I64, I64, I32], vec![I32]),
+            RtFunc::HeapPush => (vec![I32, I32], vec![]),
+            RtFunc::TextCase | RtFunc::TextTrim => (vec![I64, I32], vec![I64]),
+            RtFunc::TextContains | RtFunc::TextStartsWith | RtFunc::TextEndsWith
+            | RtFunc::TextIndexOf | RtFunc::TextCharAt | RtFunc::TextSplit | RtFunc::TextJoin
+            | RtFunc::TextRepeat | RtFunc::ListGet | RtFunc::ListAppend => (vec![I64, I64], vec![I64]),
+            RtFunc::TextSlice => (vec![I64, I64, I64], vec![I64]),
+            RtFunc::TextReplace => (vec![I64, I64, I64, I32], vec![I64]),
+            RtFunc::TextStrLen | RtFunc::TextByteLen | RtFunc::TextIsEmpty | RtFunc::TextReverse
+            | RtFunc::ListLen | RtFunc::ListIsEmpty | RtFunc::ListFirst | RtFunc::ListLast
+            | RtFunc::ListFlatten => (vec![I64], vec![I64]),
+            RtFunc::TextPad => (vec![I64, I64, I64, I32], vec![I64]),
+            RtFunc::ListContains => (vec![I64, I64, I32], vec![I64]),
+        }
+    }
+}
+
+/// The runtime functions linked into one module, with their function indices
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeLibrary {
+    /// Linked functions in index order
+    funcs: Vec<RtFunc>,
+    /// Function index of each linked function
+    indices: BTreeMap<RtFunc, u32>,
+    /// Data segment offset of the base64 alphabet
+    base64_table: u32,
+    /// Data segment offset of the hex alphabet
+    hex_table: u32,
+}
+
+impl RuntimeLibrary {
+    /// Build the library needed by the given operations (including dependencies)
+    #[cfg(test)]
+    pub fn for_operations(ops: impl IntoIterator<Item = Operation>) -> Self {
+        Self::link(ops.into_iter().flat_map(|op| RtFunc::for_operation(op).iter().copied()))
+    }
+
+    /// Build the library containing `roots` and everything they call
+    pub fn link(roots: impl IntoIterator<Item = RtFunc>) -> Self {
+        let mut needed = BTreeSet::new();
+        let mut pending: Vec<RtFunc> = roots.into_iter().collect();
+        while let Some(f) = pending.pop() {
+            if needed.insert(f) {
+                pending.extend_from_slice(f.dependencies());
+            }
+        }
+        Self {
+            funcs: needed.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Number of linked functions
+    pub fn function_count(&self) -> u32 {
+        self.funcs.len() as u32
+    }
+
+    /// Store lookup tables in the data segment. Must run before the heap start is computed.
+    pub fn allocate_data(&mut self, data: &mut DataSegmentBuilder) {
+        if self.links(RtFunc::BytesToBase64) {
+            self.base64_table = data.add_string(BASE64_ALPHABET);
+        }
+        if self.links(RtFunc::BytesToHex) {
+            self.hex_table = data.add_string(HEX_ALPHABET);
+        }
+    }
+
+    /// Assign function indices starting at `base`
+    pub fn assign_indices(&mut self, base: u32) {
+        self.indices = self
+            .funcs
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (*f, base + i as u32))
+            .collect();
+    }
+
+    /// Function index of a linked runtime function. A function is linked
+    /// when a root collected from the program needs it.
+    pub fn index(&self, f: RtFunc) -> Result<u32, CodegenError> {
+        self.indices.get(&f).copied().ok_or_else(|| CodegenError::RuntimeFunctionNotLinked {
+            name: format!("{:?}", f),
+        })
+    }
+
+    /// Index of a function another linked function depends on; linking
+    /// takes the closure over dependencies, so it is always there
+    pub(crate) fn dependency_index(&self, f: RtFunc) -> u32 {
+        self.indices[&f]
+    }
+
+    /// True if `f` is linked in, whether used directly or as a dependency
+    fn links(&self, f: RtFunc) -> bool {
+        self.funcs.contains(&f)
+    }
+
+    /// Function types for the type section, in index order
+    pub fn function_types(&self) -> Vec<(Vec<ValType>, Vec<ValType>)> {
+        self.funcs.iter().map(|f| f.signature()).collect()
+    }
+
+    /// Function bodies for the code section, in index order
+    pub fn generate_functions(&self) -> Vec<Function> {
+        self.funcs.iter().map(|f| self.generate(*f)).collect()
+    }
+
+    fn generate(&self, f: RtFunc) -> Function {
+        match f {
+            RtFunc::Alloc => gen_alloc(),
+            RtFunc::StrEq => gen_str_eq(),
+            RtFunc::KeyEq => self.gen_key_eq(),
+            RtFunc::Count => gen_count(),
+            RtFunc::Find => self.gen_find(),
+            RtFunc::NewColl => self.gen_new_coll(),
+            RtFunc::Remove => self.gen_remove(),
+            RtFunc::MapInsert => self.gen_map_insert(),
+            RtFunc::MapProject => self.gen_map_project(),
+            RtFunc::MapMerge => self.gen_map_merge(),
+            RtFunc::SetAdd => self.gen_set_add(),
+            RtFunc::SetFilter => self.gen_set_filter(),
+            RtFunc::SetConcat => self.gen_set_concat(),
+            RtFunc::SetUnion => self.gen_set_union(),
+            RtFunc::SetSymmetricDiff => self.gen_set_symmetric_diff(),
+            RtFunc::SetIsSubset => self.gen_set_is_subset(),
+            RtFunc::SetIsSuperset => self.gen_set_is_superset(),
+            RtFunc::BytesGet => gen_bytes_get(),
+            RtFunc::BytesSlice => gen_bytes_slice(),
+            RtFunc::BytesConcat => self.gen_bytes_concat(),
+            RtFunc::BytesToString => gen_bytes_to_string(),
+            RtFunc::BytesToBase64 => self.gen_bytes_to_base64(),
+            RtFunc::BytesToHex => self.gen_bytes_to_hex(),
+            RtFunc::DtField => gen_dt_field(),
+            RtFunc::WriteNum => gen_write_num(),
+            RtFunc::DtFormat => self.gen_dt_format(),
+            RtFunc::Utf8Len => runtime_text::gen_utf8_len(),
+            RtFunc::CharOffset => runtime_text::gen_char_offset(),
+            RtFunc::MemEq => runtime_text::gen_mem_eq(),
+            RtFunc::IndexOf => self.gen_index_of(),
+            RtFunc::HeapPush => runtime_text::gen_heap_push(),
+            RtFunc::TextCase => self.gen_text_case(),
+            RtFunc::TextTrim => runtime_text::gen_text_trim(),
+            RtFunc::TextContains => self.gen_text_contains(),
+            RtFunc::TextStartsWith => self.gen_text_affix(false),
+            RtFunc::TextEndsWith => self.gen_text_affix(true),
+            RtFunc::TextIndexOf => self.gen_text_index_of(),
+            RtFunc::TextSlice => self.gen_text_slice(),
+            RtFunc::TextCharAt => self.gen_text_char_at(),
+            RtFunc::TextReplace => self.gen_text_replace(),
+            RtFunc::TextSplit => self.gen_text_split(),
+            RtFunc::TextJoin => self.gen_text_join(),
+            RtFunc::TextRepeat => self.gen_text_repeat(),
+            RtFunc::TextStrLen => self.gen_text_str_len(),
+            RtFunc::TextByteLen => runtime_text::gen_text_byte_len(),
+            RtFunc::TextIsEmpty => runtime_text::gen_text_is_empty(),
+            RtFunc::TextReverse => self.gen_text_reverse(),
+            RtFunc::TextPad => self.gen_text_pad(),
+            RtFunc::ListLen => self.gen_list_len(false),
+            RtFunc::ListIsEmpty => self.gen_list_len(true),
+            RtFunc::ListGet => self.gen_list_get(),
+            RtFunc::ListFirst => self.gen_list_end(false),
+            RtFunc::ListLast => self.gen_list_end(true),
+            RtFunc::ListAppend => self.gen_list_append(),
+            RtFunc::ListContains => self.gen_list_contains(),
+            RtFunc::ListFlatten => self.gen_list_flatten(),
+        }
+    }
+
+    /// _rt_key_eq(a: i64, b: i64, by_content: i32) -> i32
+    fn gen_key_eq(&self) -> Function {
+        let mut func = Function::new(vec![]);
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::If(BlockType::Result(ValType::I32)));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::StrEq)));
+        func.instruction(&Instruction::Else);
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I64Eq);
+        func.instruction(&Instruction::End);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_find(coll: i64, key: i64, stride: i32, by_content: i32) -> i32
+    /// Linear scan comparing the first i64 of each item/entry with `key`.
+    fn gen_find(&self) -> Function {
+        // Params: 0 = coll, 1 = key, 2 = stride, 3 = by_content
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 4: items base
+            (1, ValType::I32), // local 5: count
+            (1, ValType::I32), // local 6: i
+        ]);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Count)));
+        func.instruction(&Instruction::LocalSet(5));
+        emit_items_base(&mut func, 0);
+        func.instruction(&Instruction::LocalSet(4));
+
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalSet(6));
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::I32GeU);
+        func.instruction(&Instruction::BrIf(1));
+
+        // key_eq(load(base + i * stride), key, by_content)
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I64Load(mem64(0)));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::KeyEq)));
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::Return);
+        func.instruction(&Instruction::End);
+
+        emit_inc(&mut func, 6, 1);
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+
+        func.instruction(&Instruction::I32Const(-1));
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_new_coll(count: i32, stride: i32) -> i32
+    fn gen_new_coll(&self) -> Function {
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 2: ptr
+        ]);
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Alloc)));
+        func.instruction(&Instruction::LocalTee(2));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::I32Store(mem32(0)));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_remove(coll: i64, key: i64, stride: i32, by_content: i32) -> i64
+    /// Returns `coll` unchanged if the key is absent.
+    fn gen_remove(&self) -> Function {
+        // Params: 0 = coll, 1 = key, 2 = stride, 3 = by_content
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 4: idx
+            (1, ValType::I32), // local 5: count
+            (1, ValType::I32), // local 6: dst
+            (1, ValType::I32), // local 7: src items
+        ]);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Find)));
+        func.instruction(&Instruction::LocalTee(4));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::I32LtS);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Return);
+        func.instruction(&Instruction::End);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Count)));
+        func.instruction(&Instruction::LocalTee(5));
+        func.instruction(&Instruction::I32Const(1));
+        func.instruction(&Instruction::I32Sub);
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::NewColl)));
+        func.instruction(&Instruction::LocalSet(6));
+        emit_items_base(&mut func, 0);
+        func.instruction(&Instruction::LocalSet(7));
+
+        // Copy items [0, idx)
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(7));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::MemoryCopy { src_mem: 0, dst_mem: 0 });
+
+        // Copy items (idx, count) to dst + 4 + idx * stride
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(7));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Const(1));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Sub);
+        func.instruction(&Instruction::I32Const(1));
+        func.instruction(&Instruction::I32Sub);
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::MemoryCopy { src_mem: 0, dst_mem: 0 });
+
+        // Pack (dst, count - 1)
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::I32Const(1));
+        func.instruction(&Instruction::I32Sub);
+        func.instruction(&Instruction::LocalSet(5));
+        emit_fat_ptr(&mut func, 6, 5);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_map_insert(map: i64, key: i64, value: i64, by_content: i32) -> i64
+    /// Copies the map, replacing the value of an existing key or appending a new entry.
+    fn gen_map_insert(&self) -> Function {
+        // Params: 0 = map, 1 = key, 2 = value, 3 = by_content
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 4: idx
+            (1, ValType::I32), // local 5: count
+            (1, ValType::I32), // local 6: dst
+            (1, ValType::I32), // local 7: new count
+            (1, ValType::I32), // local 8: entry address
+        ]);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32Const(MAP_STRIDE));
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Find)));
+        func.instruction(&Instruction::LocalSet(4));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Count)));
+        func.instruction(&Instruction::LocalSet(5));
+
+        // new_count = count + (idx < 0)
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::I32LtS);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalTee(7));
+        func.instruction(&Instruction::I32Const(MAP_STRIDE));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::NewColl)));
+        func.instruction(&Instruction::LocalSet(6));
+
+        // Copy existing entries
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Add);
+        emit_items_base(&mut func, 0);
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::I32Const(MAP_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::MemoryCopy { src_mem: 0, dst_mem: 0 });
+
+        // Absent key: append at the end
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::I32LtS);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::LocalSet(4));
+        func.instruction(&Instruction::End);
+
+        // entry = dst + 4 + idx * 16; store key and value
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Const(MAP_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalTee(8));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I64Store(mem64(0)));
+        func.instruction(&Instruction::LocalGet(8));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I64Store(mem64(8)));
+
+        emit_fat_ptr(&mut func, 6, 7);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_map_project(map: i64, which: i32) -> i64
+    /// Builds a list of keys, values, or entry tuples (in insertion order).
+    fn gen_map_project(&self) -> Function {
+        // Params: 0 = map, 1 = which
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 2: count
+            (1, ValType::I32), // local 3: dst
+            (1, ValType::I32), // local 4: i
+            (1, ValType::I32), // local 5: src items
+            (1, ValType::I32), // local 6: tuple ptr
+        ]);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Count)));
+        func.instruction(&Instruction::LocalTee(2));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::NewColl)));
+        func.instruction(&Instruction::LocalSet(3));
+        emit_items_base(&mut func, 0);
+        func.instruction(&Instruction::LocalSet(5));
+
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalSet(4));
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32GeU);
+        func.instruction(&Instruction::BrIf(1));
+
+        // Output address: dst + 4 + i * 8
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32Const(MapProjection::Entries as i32));
+        func.instruction(&Instruction::I32Eq);
+        func.instruction(&Instruction::If(BlockType::Result(ValType::I64)));
+        // Entry: copy (key, value) into a fresh 2-field tuple
+        func.instruction(&Instruction::I32Const(MAP_STRIDE));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Alloc)));
+        func.instruction(&Instruction::LocalTee(6));
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Const(MAP_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I32Const(MAP_STRIDE));
+        func.instruction(&Instruction::MemoryCopy { src_mem: 0, dst_mem: 0 });
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::I64ExtendI32U);
+        func.instruction(&Instruction::Else);
+        // Key or value: load(src + i * 16 + which * 8)
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Const(MAP_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32Const(8));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I64Load(mem64(0)));
+        func.instruction(&Instruction::End);
+        func.instruction(&Instruction::I64Store(mem64(0)));
+
+        emit_inc(&mut func, 4, 1);
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+
+        emit_fat_ptr(&mut func, 3, 2);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_map_merge(a: i64, b: i64, by_content: i32) -> i64
+    /// Entries of `b` override entries of `a` with the same key.
+    fn gen_map_merge(&self) -> Function {
+        // Params: 0 = a, 1 = b, 2 = by_content
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 3: count(a)
+            (1, ValType::I32), // local 4: count(b)
+            (1, ValType::I32), // local 5: dst
+            (1, ValType::I32), // local 6: result count
+            (1, ValType::I32), // local 7: i
+            (1, ValType::I32), // local 8: found index
+            (1, ValType::I32), // local 9: b items
+        ]);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Count)));
+        func.instruction(&Instruction::LocalSet(3));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Count)));
+        func.instruction(&Instruction::LocalSet(4));
+
+        // Allocate room for both maps and copy a's entries
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I32Const(MAP_STRIDE));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::NewColl)));
+        func.instruction(&Instruction::LocalSet(5));
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Add);
+        emit_items_base(&mut func, 0);
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32Const(MAP_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::MemoryCopy { src_mem: 0, dst_mem: 0 });
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::LocalSet(6));
+        emit_items_base(&mut func, 1);
+        func.instruction(&Instruction::LocalSet(9));
+
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalSet(7));
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(7));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32GeU);
+        func.instruction(&Instruction::BrIf(1));
+
+        // Make the partial result searchable: header count = result count
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::I32Store(mem32(0)));
+        emit_fat_ptr(&mut func, 5, 6);
+        func.instruction(&Instruction::LocalGet(9));
+        func.instruction(&Instruction::LocalGet(7));
+        func.instruction(&Instruction::I32Const(MAP_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I64Load(mem64(0)));
+        func.instruction(&Instruction::I32Const(MAP_STRIDE));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Find)));
+        func.instruction(&Instruction::LocalTee(8));
+
+        // Not found: append
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::I32LtS);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::LocalSet(8));
+        emit_inc(&mut func, 6, 1);
+        func.instruction(&Instruction::End);
+
+        // Copy b's entry over dst[found]
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(8));
+        func.instruction(&Instruction::I32Const(MAP_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(9));
+        func.instruction(&Instruction::LocalGet(7));
+        func.instruction(&Instruction::I32Const(MAP_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I32Const(MAP_STRIDE));
+        func.instruction(&Instruction::MemoryCopy { src_mem: 0, dst_mem: 0 });
+
+        emit_inc(&mut func, 7, 1);
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::I32Store(mem32(0)));
+        emit_fat_ptr(&mut func, 5, 6);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_set_add(set: i64, item: i64, by_content: i32) -> i64
+    /// Returns `set` unchanged if the item is already present.
+    fn gen_set_add(&self) -> Function {
+        // Params: 0 = set, 1 = item, 2 = by_content
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 3: count
+            (1, ValType::I32), // local 4: dst
+        ]);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Find)));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::I32GeS);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Return);
+        func.instruction(&Instruction::End);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Count)));
+        func.instruction(&Instruction::LocalTee(3));
+        func.instruction(&Instruction::I32Const(1));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::NewColl)));
+        func.instruction(&Instruction::LocalSet(4));
+
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Add);
+        emit_items_base(&mut func, 0);
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::MemoryCopy { src_mem: 0, dst_mem: 0 });
+
+        // dst[count] = item
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I64Store(mem64(0)));
+
+        emit_inc(&mut func, 3, 1);
+        emit_fat_ptr(&mut func, 4, 3);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_set_filter(a: i64, b: i64, keep_present: i32, by_content: i32) -> i64
+    /// Keeps the items of `a` whose membership in `b` equals `keep_present`.
+    fn gen_set_filter(&self) -> Function {
+        // Params: 0 = a, 1 = b, 2 = keep_present, 3 = by_content
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 4: count(a)
+            (1, ValType::I32), // local 5: dst
+            (1, ValType::I32), // local 6: i
+            (1, ValType::I32), // local 7: out count
+            (1, ValType::I32), // local 8: src items
+            (1, ValType::I64), // local 9: item
+        ]);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Count)));
+        func.instruction(&Instruction::LocalTee(4));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::NewColl)));
+        func.instruction(&Instruction::LocalSet(5));
+        emit_items_base(&mut func, 0);
+        func.instruction(&Instruction::LocalSet(8));
+
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalSet(6));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalSet(7));
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32GeU);
+        func.instruction(&Instruction::BrIf(1));
+
+        func.instruction(&Instruction::LocalGet(8));
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I64Load(mem64(0)));
+        func.instruction(&Instruction::LocalSet(9));
+
+        // (find(b, item) >= 0) == keep_present
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::LocalGet(9));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Find)));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::I32GeS);
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Eq);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(7));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(9));
+        func.instruction(&Instruction::I64Store(mem64(0)));
+        emit_inc(&mut func, 7, 1);
+        func.instruction(&Instruction::End);
+
+        emit_inc(&mut func, 6, 1);
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::LocalGet(7));
+        func.instruction(&Instruction::I32Store(mem32(0)));
+        emit_fat_ptr(&mut func, 5, 7);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_set_concat(a: i64, b: i64) -> i64
+    fn gen_set_concat(&self) -> Function {
+        // Params: 0 = a, 1 = b
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 2: count(a)
+            (1, ValType::I32), // local 3: count(b)
+            (1, ValType::I32), // local 4: dst
+        ]);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Count)));
+        func.instruction(&Instruction::LocalSet(2));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Count)));
+        func.instruction(&Instruction::LocalSet(3));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::NewColl)));
+        func.instruction(&Instruction::LocalSet(4));
+
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Add);
+        emit_items_base(&mut func, 0);
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::MemoryCopy { src_mem: 0, dst_mem: 0 });
+
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        emit_items_base(&mut func, 1);
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::MemoryCopy { src_mem: 0, dst_mem: 0 });
+
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalSet(2));
+        emit_fat_ptr(&mut func, 4, 2);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_set_union(a: i64, b: i64, by_content: i32) -> i64 = a ++ (b - a)
+    fn gen_set_union(&self) -> Function {
+        let mut func = Function::new(vec![]);
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::SetFilter)));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::SetConcat)));
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_set_symmetric_diff(a: i64, b: i64, by_content: i32) -> i64 = (a - b) ++ (b - a)
+    fn gen_set_symmetric_diff(&self) -> Function {
+        let mut func = Function::new(vec![]);
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::SetFilter)));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::SetFilter)));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::SetConcat)));
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_set_is_subset(a: i64, b: i64, by_content: i32) -> i64 (every item of a is in b)
+    fn gen_set_is_subset(&self) -> Function {
+        // Params: 0 = a, 1 = b, 2 = by_content
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 3: count(a)
+            (1, ValType::I32), // local 4: i
+            (1, ValType::I32), // local 5: src items
+        ]);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Count)));
+        func.instruction(&Instruction::LocalSet(3));
+        emit_items_base(&mut func, 0);
+        func.instruction(&Instruction::LocalSet(5));
+
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalSet(4));
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32GeU);
+        func.instruction(&Instruction::BrIf(1));
+
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I64Load(mem64(0)));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Find)));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::I32LtS);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::I64Const(0));
+        func.instruction(&Instruction::Return);
+        func.instruction(&Instruction::End);
+
+        emit_inc(&mut func, 4, 1);
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+
+        func.instruction(&Instruction::I64Const(1));
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_set_is_superset(a: i64, b: i64, by_content: i32) -> i64 = is_subset(b, a)
+    fn gen_set_is_superset(&self) -> Function {
+        let mut func = Function::new(vec![]);
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::SetIsSubset)));
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_bytes_concat(a: i64, b: i64) -> i64
+    fn gen_bytes_concat(&self) -> Function {
+        // Params: 0 = a, 1 = b
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 2: len(a)
+            (1, ValType::I32), // local 3: len(b)
+            (1, ValType::I32), // local 4: dst
+        ]);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::LocalSet(2));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::LocalSet(3));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Alloc)));
+        func.instruction(&Instruction::LocalSet(4));
+
+        func.instruction(&Instruction::LocalGet(4));
+        emit_fat_ptr_base(&mut func, 0);
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::MemoryCopy { src_mem: 0, dst_mem: 0 });
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Add);
+        emit_fat_ptr_base(&mut func, 1);
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::MemoryCopy { src_mem: 0, dst_mem: 0 });
+
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalSet(2));
+        emit_fat_ptr(&mut func, 4, 2);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_bytes_to_base64(bytes: i64) -> i64 (standard alphabet with `=` padding)
+    fn gen_bytes_to_base64(&self) -> Function {
+        // Params: 0 = bytes
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 1: src
+            (1, ValType::I32), // local 2: len
+            (1, ValType::I32), // local 3: dst
+            (1, ValType::I32), // local 4: i
+            (1, ValType::I32), // local 5: out
+            (1, ValType::I32), // local 6: 24-bit chunk
+            (1, ValType::I32), // local 7: out len
+        ]);
+        let table = mem8(self.base64_table);
+
+        emit_fat_ptr_base(&mut func, 0);
+        func.instruction(&Instruction::LocalSet(1));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::LocalSet(2));
+
+        // out_len = (len + 2) / 3 * 4
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Const(2));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I32Const(3));
+        func.instruction(&Instruction::I32DivU);
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::LocalTee(7));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Alloc)));
+        func.instruction(&Instruction::LocalTee(3));
+        func.instruction(&Instruction::LocalSet(5));
+
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalSet(4));
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32GeU);
+        func.instruction(&Instruction::BrIf(1));
+
+        // chunk = b0 << 16 | b1 << 8 | b2 (missing bytes read as 0)
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I32Load8U(mem8(0)));
+        func.instruction(&Instruction::I32Const(16));
+        func.instruction(&Instruction::I32Shl);
+        for k in 1..=2 {
+            func.instruction(&Instruction::LocalGet(4));
+            func.instruction(&Instruction::I32Const(k));
+            func.instruction(&Instruction::I32Add);
+            func.instruction(&Instruction::LocalGet(2));
+            func.instruction(&Instruction::I32LtU);
+            func.instruction(&Instruction::If(BlockType::Result(ValType::I32)));
+            func.instruction(&Instruction::LocalGet(1));
+            func.instruction(&Instruction::LocalGet(4));
+            func.instruction(&Instruction::I32Add);
+            func.instruction(&Instruction::I32Load8U(mem8(k as u32)));
+            func.instruction(&Instruction::I32Const(16 - 8 * k));
+            func.instruction(&Instruction::I32Shl);
+            func.instruction(&Instruction::Else);
+            func.instruction(&Instruction::I32Const(0));
+            func.instruction(&Instruction::End);
+            func.instruction(&Instruction::I32Or);
+        }
+        func.instruction(&Instruction::LocalSet(6));
+
+        // Four output characters; the last two become '=' past the end of input
+        for k in 0..4 {
+            func.instruction(&Instruction::LocalGet(5));
+            if k >= 2 {
+                func.instruction(&Instruction::LocalGet(4));
+                func.instruction(&Instruction::I32Const(k - 1));
+                func.instruction(&Instruction::I32Add);
+                func.instruction(&Instruction::LocalGet(2));
+                func.instruction(&Instruction::I32LtU);
+                func.instruction(&Instruction::If(BlockType::Result(ValType::I32)));
+            }
+            func.instruction(&Instruction::LocalGet(6));
+            func.instruction(&Instruction::I32Const(18 - 6 * k));
+            func.instruction(&Instruction::I32ShrU);
+            func.instruction(&Instruction::I32Const(63));
+            func.instruction(&Instruction::I32And);
+            func.instruction(&Instruction::I32Load8U(table));
+            if k >= 2 {
+                func.instruction(&Instruction::Else);
+                func.instruction(&Instruction::I32Const(b'=' as i32));
+                func.instruction(&Instruction::End);
+            }
+            func.instruction(&Instruction::I32Store8(mem8(k as u32)));
+        }
+
+        emit_inc(&mut func, 5, 4);
+        emit_inc(&mut func, 4, 3);
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+
+        emit_fat_ptr(&mut func, 3, 7);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_bytes_to_hex(bytes: i64) -> i64 (lowercase)
+    fn gen_bytes_to_hex(&self) -> Function {
+        // Params: 0 = bytes
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 1: src
+            (1, ValType::I32), // local 2: len
+            (1, ValType::I32), // local 3: dst
+            (1, ValType::I32), // local 4: i
+            (1, ValType::I32), // local 5: byte
+            (1, ValType::I32), // local 6: out len
+        ]);
+        let table = mem8(self.hex_table);
+
+        emit_fat_ptr_base(&mut func, 0);
+        func.instruction(&Instruction::LocalSet(1));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::LocalTee(2));
+        func.instruction(&Instruction::I32Const(2));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::LocalTee(6));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Alloc)));
+        func.instruction(&Instruction::LocalSet(3));
+
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalSet(4));
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32GeU);
+        func.instruction(&Instruction::BrIf(1));
+
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I32Load8U(mem8(0)));
+        func.instruction(&Instruction::LocalSet(5));
+
+        // dst[2i] = hex[byte >> 4]; dst[2i + 1] = hex[byte & 15]
+        for (k, shift) in [(0u32, 4), (1u32, 0)] {
+            func.instruction(&Instruction::LocalGet(3));
+            func.instruction(&Instruction::LocalGet(4));
+            func.instruction(&Instruction::I32Const(2));
+            func.instruction(&Instruction::I32Mul);
+            func.instruction(&Instruction::I32Add);
+            func.instruction(&Instruction::LocalGet(5));
+            func.instruction(&Instruction::I32Const(shift));
+            func.instruction(&Instruction::I32ShrU);
+            func.instruction(&Instruction::I32Const(15));
+            func.instruction(&Instruction::I32And);
+            func.instruction(&Instruction::I32Load8U(table));
+            func.instruction(&Instruction::I32Store8(mem8(k)));
+        }
+
+        emit_inc(&mut func, 4, 1);
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+
+        emit_fat_ptr(&mut func, 3, 6);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_dt_format(time: i64, format: i64) -> i64
+    /// Supports %Y (at least 4 digits), %m %d %H %M %S (2 digits) and %%;
+    /// other characters are copied through.
+    fn gen_dt_format(&self) -> Function {
+        // Params: 0 = time, 1 = format
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 2: format cursor
+            (1, ValType::I32), // local 3: format end
+            (1, ValType::I32), // local 4: output start
+            (1, ValType::I32), // local 5: output cursor
+            (1, ValType::I32), // local 6: current char
+            (1, ValType::I32), // local 7: specifier char
+        ]);
+        const SPECIFIERS: &[(u8, DtField, i32)] = &[
+            (b'Y', DtField::Year, 4),
+            (b'm', DtField::Month, 2),
+            (b'd', DtField::Day, 2),
+            (b'H', DtField::Hour, 2),
+            (b'M', DtField::Minute, 2),
+            (b'S', DtField::Second, 2),
+        ];
+
+        emit_fat_ptr_base(&mut func, 1);
+        func.instruction(&Instruction::LocalTee(2));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalSet(3));
+
+        // Reserve the worst case (8 bytes per format byte + year slack); trimmed at the end
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::I32Const(8));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Const(24));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Alloc)));
+        func.instruction(&Instruction::LocalTee(4));
+        func.instruction(&Instruction::LocalSet(5));
+
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32GeU);
+        func.instruction(&Instruction::BrIf(1));
+
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Load8U(mem8(0)));
+        func.instruction(&Instruction::LocalSet(6));
+        emit_inc(&mut func, 2, 1);
+
+        // '%' followed by another character starts a specifier
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::I32Const(b'%' as i32));
+        func.instruction(&Instruction::I32Eq);
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32LtU);
+        func.instruction(&Instruction::I32And);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Load8U(mem8(0)));
+        func.instruction(&Instruction::LocalSet(7));
+        emit_inc(&mut func, 2, 1);
+
+        func.instruction(&Instruction::Block(BlockType::Empty)); // dispatch
+        for (ch, field, width) in SPECIFIERS {
+            func.instruction(&Instruction::LocalGet(7));
+            func.instruction(&Instruction::I32Const(*ch as i32));
+            func.instruction(&Instruction::I32Eq);
+            func.instruction(&Instruction::If(BlockType::Empty));
+            func.instruction(&Instruction::LocalGet(5));
+            func.instruction(&Instruction::LocalGet(0));
+            func.instruction(&Instruction::I32Const(*field as i32));
+            func.instruction(&Instruction::Call(self.dependency_index(RtFunc::DtField)));
+            func.instruction(&Instruction::I32Const(*width));
+            func.instruction(&Instruction::Call(self.dependency_index(RtFunc::WriteNum)));
+            func.instruction(&Instruction::LocalSet(5));
+            func.instruction(&Instruction::Br(1)); // exit dispatch
+            func.instruction(&Instruction::End);
+        }
+        // "%%" emits a single '%'
+        func.instruction(&Instruction::LocalGet(7));
+        func.instruction(&Instruction::I32Const(b'%' as i32));
+        func.instruction(&Instruction::I32Eq);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::I32Const(b'%' as i32));
+        func.instruction(&Instruction::I32Store8(mem8(0)));
+        emit_inc(&mut func, 5, 1);
+        func.instruction(&Instruction::Br(1));
+        func.instruction(&Instruction::End);
+        // Unknown specifier: copy it through verbatim
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::I32Const(b'%' as i32));
+        func.instruction(&Instruction::I32Store8(mem8(0)));
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::LocalGet(7));
+        func.instruction(&Instruction::I32Store8(mem8(1)));
+        emit_inc(&mut func, 5, 2);
+        func.instruction(&Instruction::End); // dispatch
+
+        func.instruction(&Instruction::Else);
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::I32Store8(mem8(0)));
+        emit_inc(&mut func, 5, 1);
+        func.instruction(&Instruction::End);
+
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+
+        // Give back the unused part of the reservation (nothing was allocated after it)
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::GlobalSet(0));
+
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Sub);
+        func.instruction(&Instruction::LocalSet(5));
+        emit_fat_ptr(&mut func, 4, 5);
+        func.instruction(&Instruction::End);
+        func
+    }
+}
+
+/// _rt_alloc(size: i32) -> i32
+/// Bump allocator on global 0, 8-byte aligned. Grows memory when the heap
+/// would run past the current size.
+fn gen_alloc() -> Function {
+    // Params: 0 = size
+    let mut func = Function::new(vec![
+        (1, ValType::I32), // local 1: result
+        (1, ValType::I32), // local 2: pages needed
+    ]);
+
+    // result = max((heap + 7) & !7, 8); heap = result + size
+    // (address 0 is reserved: a null pointer is an empty collection)
+    func.instruction(&Instruction::GlobalGet(0));
+    func.instruction(&Instruction::I32Const(7));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::I32Const(-8));
+    func.instruction(&Instruction::I32And);
+    func.instruction(&Instruction::LocalTee(1));
+    func.instruction(&Instruction::I32Const(8));
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I32Const(8));
+    func.instruction(&Instruction::I32GtU);
+    func.instruction(&Instruction::Select);
+    func.instruction(&Instruction::LocalTee(1));
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::GlobalSet(0));
+
+    // pages = ceil(heap / 64K); grow if beyond memory.size
+    func.instruction(&Instruction::GlobalGet(0));
+    func.instruction(&Instruction::I32Const(0xFFFF));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::I32Const(16));
+    func.instruction(&Instruction::I32ShrU);
+    func.instruction(&Instruction::LocalTee(2));
+    func.instruction(&Instruction::MemorySize(0));
+    func.instruction(&Instruction::I32GtU);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::MemorySize(0));
+    func.instruction(&Instruction::I32Sub);
+    func.instruction(&Instruction::MemoryGrow(0));
+    func.instruction(&Instruction::Drop);
+    func.instruction(&Instruction::End);
+
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::End);
+    func
+}
+
+/// _rt_str_eq(a: i64, b: i64) -> i32
+fn gen_str_eq() -> Function {
+    // Params: 0 = a, 1 = b
+    let mut func = Function::new(vec![
+        (1, ValType::I32), // local 2: len
+        (1, ValType::I32), // local 3: ptr a
+        (1, ValType::I32), // local 4: ptr b
+        (1, ValType::I32), // local 5: i
+    ]);
+
+    // Identical fat pointers are trivially equal
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I64Eq);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::I32Const(1));
+    func.instruction(&Instruction::Return);
+    func.instruction(&Instruction::End);
+
+    // Different lengths are never equal
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::I32WrapI64);
+    func.instruction(&Instruction::LocalTee(2));
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I32WrapI64);
+    func.instruction(&Instruction::I32Ne);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::I32Const(0));
+    func.instruction(&Instruction::Return);
+    func.instruction(&Instruction::End);
+
+    emit_fat_ptr_base(&mut func, 0);
+    func.instruction(&Instruction::LocalSet(3));
+    emit_fat_ptr_base(&mut func, 1);
+    func.instruction(&Instruction::LocalSet(4));
+
+    func.instruction(&Instruction::I32Const(0));
+    func.instruction(&Instruction::LocalSet(5));
+    func.instruction(&Instruction::Block(BlockType::Empty));
+    func.instruction(&Instruction::Loop(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(5));
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::I32GeU);
+    func.instruction(&Instruction::BrIf(1));
+
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::LocalGet(5));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::I32Load8U(mem8(0)));
+    func.instruction(&Instruction::LocalGet(4));
+    func.instruction(&Instruction::LocalGet(5));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::I32Load8U(mem8(0)));
+    func.instruction(&Instruction::I32Ne);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::I32Const(0));
+    func.instruction(&Instruction::Return);
+    func.instruction(&Instruction::End);
+
+    emit_inc(&mut func, 5, 1);
+    func.instruction(&Instruction::Br(0));
+    func.instruction(&Instruction::End); // loop
+    func.instruction(&Instruction::End); // block
+
+    func.instruction(&Instruction::I32Const(1));
+    func.instruction(&Instruction::End);
+    func
+}
+
+/// _rt_count(coll: i64) -> i32
+/// Reads the count header; a null pointer is an empty collection.
+fn gen_count() -> Function {
+    // Params: 0 = coll
+    let mut func = Function::new(vec![
+        (1, ValType::I32), // local 1: ptr
+    ]);
+    emit_fat_ptr_base(&mut func, 0);
+    func.instruction(&Instruction::LocalTee(1));
+    func.instruction(&Instruction::I32Eqz);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::I32Const(0));
+    func.instruction(&Instruction::Return);
+    func.instruction(&Instruction::End);
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I32Load(mem32(0)));
+    func.instruction(&Instruction::End);
+    func
+}
+
+/// _rt_bytes_get(bytes: i64, index: i64) -> i64
+fn gen_bytes_get() -> Function {
+    // Params: 0 = bytes, 1 = index
+    let mut func = Function::new(vec![]);
+
+    // index < 0 || index >= len → none
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I64Const(0));
+    func.instruction(&Instruction::I64LtS);
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::I64Const(0xFFFF_FFFF));
+    func.instruction(&Instruction::I64And);
+    func.instruction(&Instruction::I64GeS);
+    func.instruction(&Instruction::I32Or);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::I64Const(NONE_SENTINEL));
+    func.instruction(&Instruction::Return);
+    func.instruction(&Instruction::End);
+
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::I64Const(32));
+    func.instruction(&Instruction::I64ShrU);
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I64Add);
+    func.instruction(&Instruction::I32WrapI64);
+    func.instruction(&Instruction::I32Load8U(mem8(0)));
+    func.instruction(&Instruction::I64ExtendI32U);
+    func.instruction(&Instruction::End);
+    func
+}
+
+/// _rt_bytes_slice(bytes: i64, start: i64, end: i64) -> i64
+/// Both bounds are clamped to [0, len] and `end` to at least `start`.
+/// The result shares memory with the input.
+fn gen_bytes_slice() -> Function {
+    // Params: 0 = bytes, 1 = start, 2 = end
+    let mut func = Function::new(vec![
+        (1, ValType::I64), // local 3: len
+    ]);
+
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::I64Const(0xFFFF_FFFF));
+    func.instruction(&Instruction::I64And);
+    func.instruction(&Instruction::LocalSet(3));
+
+    // start = min(max(start, 0), len)
+    emit_select_i64(&mut func, 1, None, Instruction::I64GtS);
+    emit_select_i64(&mut func, 1, Some(3), Instruction::I64LtS);
+    // end = max(min(end, len), start)
+    emit_select_i64(&mut func, 2, Some(3), Instruction::I64LtS);
+    emit_select_i64(&mut func, 2, Some(1), Instruction::I64GtS);
+
+    // ((ptr + start) << 32) | (end - start)
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::I64Const(32));
+    func.instruction(&Instruction::I64ShrU);
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I64Add);
+    func.instruction(&Instruction::I64Const(32));
+    func.instruction(&Instruction::I64Shl);
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I64Sub);
+    func.instruction(&Instruction::I64Or);
+    func.instruction(&Instruction::End);
+    func
+}
+
+/// _rt_bytes_to_string(bytes: i64) -> i64
+/// Structural UTF-8 validation (lead bytes, continuation bytes, truncation).
+/// Returns the input on success and none on failure.
+fn gen_bytes_to_string() -> Function {
+    // Params: 0 = bytes
+    let mut func = Function::new(vec![
+        (1, ValType::I32), // local 1: cursor
+        (1, ValType::I32), // local 2: end
+        (1, ValType::I32), // local 3: lead byte
+        (1, ValType::I32), // local 4: continuation bytes expected
+    ]);
+
+    emit_fat_ptr_base(&mut func, 0);
+    func.instruction(&Instruction::LocalTee(1));
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::I32WrapI64);
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::LocalSet(2));
+
+    func.instruction(&Instruction::Block(BlockType::Empty));
+    func.instruction(&Instruction::Loop(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::I32GeU);
+    func.instruction(&Instruction::BrIf(1));
+
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I32Load8U(mem8(0)));
+    func.instruction(&Instruction::LocalSet(3));
+    emit_inc(&mut func, 1, 1);
+
+    // Classify the lead byte: (mask, expected, min, max, continuation count)
+    const CLASSES: &[(i32, i32, i32, i32, i32)] = &[
+        (0x80, 0x00, 0x00, 0x7F, 0),
+        (0xE0, 0xC0, 0xC2, 0xDF, 1),
+        (0xF0, 0xE0, 0xE0, 0xEF, 2),
+        (0xF8, 0xF0, 0xF0, 0xF4, 3),
+    ];
+    func.instruction(&Instruction::Block(BlockType::Empty)); // classified
+    for (mask, expected, min, max, count) in CLASSES {
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32Const(*mask));
+        func.instruction(&Instruction::I32And);
+        func.instruction(&Instruction::I32Const(*expected));
+        func.instruction(&Instruction::I32Eq);
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32Const(*min));
+        func.instruction(&Instruction::I32GeU);
+        func.instruction(&Instruction::I32And);
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32Const(*max));
+        func.instruction(&Instruction::I32LeU);
+        func.instruction(&Instruction::I32And);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::I32Const(*count));
+        func.instruction(&Instruction::LocalSet(4));
+        func.instruction(&Instruction::Br(1));
+        func.instruction(&Instruction::End);
+    }
+    func.instruction(&Instruction::I64Const(NONE_SENTINEL));
+    func.instruction(&Instruction::Return);
+    func.instruction(&Instruction::End); // classified
+
+    // Truncated sequence
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::LocalGet(4));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::I32GtU);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::I64Const(NONE_SENTINEL));
+    func.instruction(&Instruction::Return);
+    func.instruction(&Instruction::End);
+
+    // Every continuation byte must be 10xxxxxx
+    func.instruction(&Instruction::Block(BlockType::Empty));
+    func.instruction(&Instruction::Loop(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(4));
+    func.instruction(&Instruction::I32Eqz);
+    func.instruction(&Instruction::BrIf(1));
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I32Load8U(mem8(0)));
+    func.instruction(&Instruction::I32Const(0xC0));
+    func.instruction(&Instruction::I32And);
+    func.instruction(&Instruction::I32Const(0x80));
+    func.instruction(&Instruction::I32Ne);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::I64Const(NONE_SENTINEL));
+    func.instruction(&Instruction::Return);
+    func.instruction(&Instruction::End);
+    emit_inc(&mut func, 1, 1);
+    emit_inc(&mut func, 4, -1);
+    func.instruction(&Instruction::Br(0));
+    func.instruction(&Instruction::End); // loop
+    func.instruction(&Instruction::End); // block
+
+    func.instruction(&Instruction::Br(0));
+    func.instruction(&Instruction::End); // loop
+    func.instruction(&Instruction::End); // block
+
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::End);
+    func
+}
+
+/// _rt_dt_field(time: i64, field: i32) -> i64
+/// Civil date fields use the days-from-civil inverse algorithm (proleptic Gregorian, UTC).
+fn gen_dt_field() -> Function {
+    // Params: 0 = time (unix seconds), 1 = field
+    let mut func = Function::new(vec![
+        (1, ValType::I64), // local 2: days since epoch
+        (1, ValType::I64), // local 3: seconds of day
+        (1, ValType::I64), // local 4: era
+        (1, ValType::I64), // local 5: day of era
+        (1, ValType::I64), // local 6: year of era
+        (1, ValType::I64), // local 7: day of year (March-based)
+        (1, ValType::I64), // local 8: month (March-based)
+        (1, ValType::I64), // local 9: civil month
+    ]);
+
+    // days = floor(time / 86400); secs = time - days * 86400
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::I64Const(86400));
+    func.instruction(&Instruction::I64DivS);
+    func.instruction(&Instruction::LocalSet(2));
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::I64Const(86400));
+    func.instruction(&Instruction::I64RemS);
+    func.instruction(&Instruction::I64Const(0));
+    func.instruction(&Instruction::I64LtS);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::I64Const(1));
+    func.instruction(&Instruction::I64Sub);
+    func.instruction(&Instruction::LocalSet(2));
+    func.instruction(&Instruction::End);
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::I64Const(86400));
+    func.instruction(&Instruction::I64Mul);
+    func.instruction(&Instruction::I64Sub);
+    func.instruction(&Instruction::LocalSet(3));
+
+    // Time-of-day fields: (secs / div) % modulus
+    for (field, div, modulus) in [
+        (DtField::Hour, 3600, 24),
+        (DtField::Minute, 60, 60),
+        (DtField::Second, 1, 60),
+    ] {
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32Const(field as i32));
+        func.instruction(&Instruction::I32Eq);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I64Const(div));
+        func.instruction(&Instruction::I64DivU);
+        func.instruction(&Instruction::I64Const(modulus));
+        func.instruction(&Instruction::I64RemU);
+        func.instruction(&Instruction::Return);
+        func.instruction(&Instruction::End);
+    }
+
+    // Weekday: 1970-01-01 was a Thursday (4); ((days % 7) + 11) % 7
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I32Const(DtField::Weekday as i32));
+    func.instruction(&Instruction::I32Eq);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::I64Const(7));
+    func.instruction(&Instruction::I64RemS);
+    func.instruction(&Instruction::I64Const(11));
+    func.instruction(&Instruction::I64Add);
+    func.instruction(&Instruction::I64Const(7));
+    func.instruction(&Instruction::I64RemS);
+    func.instruction(&Instruction::Return);
+    func.instruction(&Instruction::End);
+
+    // z = days + 719468; era = (z >= 0 ? z : z - 146096) / 146097
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::I64Const(719_468));
+    func.instruction(&Instruction::I64Add);
+    func.instruction(&Instruction::LocalTee(2));
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::I64Const(146_096));
+    func.instruction(&Instruction::I64Sub);
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::I64Const(0));
+    func.instruction(&Instruction::I64GeS);
+    func.instruction(&Instruction::Select);
+    func.instruction(&Instruction::I64Const(146_097));
+    func.instruction(&Instruction::I64DivS);
+    func.instruction(&Instruction::LocalSet(4));
+
+    // doe = z - era * 146097
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::LocalGet(4));
+    func.instruction(&Instruction::I64Const(146_097));
+    func.instruction(&Instruction::I64Mul);
+    func.instruction(&Instruction::I64Sub);
+    func.instruction(&Instruction::LocalSet(5));
+
+    // yoe = (doe - doe/1460 + doe/36524 - doe/146096) / 365
+    func.instruction(&Instruction::LocalGet(5));
+    func.instruction(&Instruction::LocalGet(5));
+    func.instruction(&Instruction::I64Const(1460));
+    func.instruction(&Instruction::I64DivS);
+    func.instruction(&Instruction::I64Sub);
+    func.instruction(&Instruction::LocalGet(5));
+    func.instruction(&Instruction::I64Const(36_524));
+    func.instruction(&Instruction::I64DivS);
+    func.instruction(&Instruction::I64Add);
+    func.instruction(&Instruction::LocalGet(5));
+    func.instruction(&Instruction::I64Const(146_096));
+    func.instruction(&Instruction::I64DivS);
+    func.instruction(&Instruction::I64Sub);
+    func.instruction(&Instruction::I64Const(365));
+    func.instruction(&Instruction::I64DivS);
+    func.instruction(&Instruction::LocalSet(6));
+
+    // doy = doe - (365 * yoe + yoe/4 - yoe/100)
+    func.instruction(&Instruction::LocalGet(5));
+    func.instruction(&Instruction::LocalGet(6));
+    func.instruction(&Instruction::I64Const(365));
+    func.instruction(&Instruction::I64Mul);
+    func.instruction(&Instruction::LocalGet(6));
+    func.instruction(&Instruction::I64Const(4));
+    func.instruction(&Instruction::I64DivS);
+    func.instruction(&Instruction::I64Add);
+    func.instruction(&Instruction::LocalGet(6));
+    func.instruction(&Instruction::I64Const(100));
+    func.instruction(&Instruction::I64DivS);
+    func.instruction(&Instruction::I64Sub);
+    func.instruction(&Instruction::I64Sub);
+    func.instruction(&Instruction::LocalSet(7));
+
+    // mp = (5 * doy + 2) / 153
+    func.instruction(&Instruction::LocalGet(7));
+    func.instruction(&Instruction::I64Const(5));
+    func.instruction(&Instruction::I64Mul);
+    func.instruction(&Instruction::I64Const(2));
+    func.instruction(&Instruction::I64Add);
+    func.instruction(&Instruction::I64Const(153));
+    func.instruction(&Instruction::I64DivS);
+    func.instruction(&Instruction::LocalSet(8));
+
+    // Day: doy - (153 * mp + 2) / 5 + 1
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I32Const(DtField::Day as i32));
+    func.instruction(&Instruction::I32Eq);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(7));
+    func.instruction(&Instruction::LocalGet(8));
+    func.instruction(&Instruction::I64Const(153));
+    func.instruction(&Instruction::I64Mul);
+    func.instruction(&Instruction::I64Const(2));
+    func.instruction(&Instruction::I64Add);
+    func.instruction(&Instruction::I64Const(5));
+    func.instruction(&Instruction::I64DivS);
+    func.instruction(&Instruction::I64Sub);
+    func.instruction(&Instruction::I64Const(1));
+    func.instruction(&Instruction::I64Add);
+    func.instruction(&Instruction::Return);
+    func.instruction(&Instruction::End);
+
+    // m = mp < 10 ? mp + 3 : mp - 9
+    func.instruction(&Instruction::LocalGet(8));
+    func.instruction(&Instruction::I64Const(3));
+    func.instruction(&Instruction::I64Add);
+    func.instruction(&Instruction::LocalGet(8));
+    func.instruction(&Instruction::I64Const(9));
+    func.instruction(&Instruction::I64Sub);
+    func.instruction(&Instruction::LocalGet(8));
+    func.instruction(&Instruction::I64Const(10));
+    func.instruction(&Instruction::I64LtS);
+    func.instruction(&Instruction::Select);
+    func.instruction(&Instruction::LocalTee(9));
+
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I32Const(DtField::Month as i32));
+    func.instruction(&Instruction::I32Eq);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(9));
+    func.instruction(&Instruction::Return);
+    func.instruction(&Instruction::End);
+    func.instruction(&Instruction::Drop);
+
+    // Year: yoe + era * 400 + (m <= 2)
+    func.instruction(&Instruction::LocalGet(6));
+    func.instruction(&Instruction::LocalGet(4));
+    func.instruction(&Instruction::I64Const(400));
+    func.instruction(&Instruction::I64Mul);
+    func.instruction(&Instruction::I64Add);
+    func.instruction(&Instruction::LocalGet(9));
+    func.instruction(&Instruction::I64Const(2));
+    func.instruction(&Instruction::I64LeS);
+    func.instruction(&Instruction::I64ExtendI32U);
+    func.instruction(&Instruction::I64Add);
+    func.instruction(&Instruction::End);
+    func
+}
+
+/// _rt_write_num(addr: i32, value: i64, width: i32) -> i32
+/// Writes `value` in decimal, zero-padded to `width` digits, with a leading
+/// '-' for negative values. Returns the address after the last byte.
+fn gen_write_num() -> Function {
+    // Params: 0 = addr, 1 = value, 2 = width
+    let mut func = Function::new(vec![
+        (1, ValType::I32), // local 3: digit count
+        (1, ValType::I64), // local 4: scratch
+    ]);
+
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I64Const(0));
+    func.instruction(&Instruction::I64LtS);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::I32Const(b'-' as i32));
+    func.instruction(&Instruction::I32Store8(mem8(0)));
+    emit_inc(&mut func, 0, 1);
+    func.instruction(&Instruction::I64Const(0));
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I64Sub);
+    func.instruction(&Instruction::LocalSet(1));
+    func.instruction(&Instruction::End);
+
+    // digits = number of decimal digits in value (at least 1)
+    func.instruction(&Instruction::I32Const(1));
+    func.instruction(&Instruction::LocalSet(3));
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I64Const(10));
+    func.instruction(&Instruction::I64DivU);
+    func.instruction(&Instruction::LocalSet(4));
+    func.instruction(&Instruction::Block(BlockType::Empty));
+    func.instruction(&Instruction::Loop(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(4));
+    func.instruction(&Instruction::I64Eqz);
+    func.instruction(&Instruction::BrIf(1));
+    emit_inc(&mut func, 3, 1);
+    func.instruction(&Instruction::LocalGet(4));
+    func.instruction(&Instruction::I64Const(10));
+    func.instruction(&Instruction::I64DivU);
+    func.instruction(&Instruction::LocalSet(4));
+    func.instruction(&Instruction::Br(0));
+    func.instruction(&Instruction::End); // loop
+    func.instruction(&Instruction::End); // block
+
+    // digits = max(digits, width)
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::I32GtS);
+    func.instruction(&Instruction::Select);
+    func.instruction(&Instruction::LocalSet(3));
+
+    // Write digits right to left; reuse `width` as the write index
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::LocalSet(2));
+    func.instruction(&Instruction::Loop(BlockType::Empty));
+    emit_inc(&mut func, 2, -1);
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I64Const(10));
+    func.instruction(&Instruction::I64RemU);
+    func.instruction(&Instruction::I32WrapI64);
+    func.instruction(&Instruction::I32Const(b'0' as i32));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::I32Store8(mem8(0)));
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I64Const(10));
+    func.instruction(&Instruction::I64DivU);
+    func.instruction(&Instruction::LocalSet(1));
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::I32Const(0));
+    func.instruction(&Instruction::I32GtS);
+    func.instruction(&Instruction::BrIf(0));
+    func.instruction(&Instruction::End); // loop
+
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::End);
+    func
+}
+
+// ===== Emission Helpers =====
+
+pub(crate) fn mem8(offset: u32) -> MemArg {
+    MemArg { offset: offset as u64, align: 0, memory_index: 0 }
+}
+
+pub(crate) fn mem32(offset: u32) -> MemArg {
+    MemArg { offset: offset as u64, align: 2, memory_index: 0 }
+}
+
+pub(crate) fn mem64(offset: u32) -> MemArg {
+    MemArg { offset: offset as u64, align: 3, memory_index: 0 }
+}
+
+/// Push the i32 pointer part of the fat pointer in i64 local `local`
+pub(crate) fn emit_fat_ptr_base(func: &mut Function, local: u32) {
+    func.instruction(&Instruction::LocalGet(local));
+    func.instruction(&Instruction::I64Const(32));
+    func.instruction(&Instruction::I64ShrU);
+    func.instruction(&Instruction::I32WrapI64);
+}
+
+/// Push the address of the first item of the collection in i64 local `local`
+pub(crate) fn emit_items_base(func: &mut Function, local: u32) {
+    emit_fat_ptr_base(func, local);
+    func.instruction(&Instruction::I32Const(4));
+    func.instruction(&Instruction::I32Add);
+}
+
+/// Push `(ptr << 32) | len` built from two i32 locals
+pub(crate) fn emit_fat_ptr(func: &mut Function, ptr_local: u32, len_local: u32) {
+    func.instruction(&Instruction::LocalGet(ptr_local));
+    func.instruction(&Instruction::I64ExtendI32U);
+    func.instruction(&Instruction::I64Const(32));
+    func.instruction(&Instruction::I64Shl);
+    func.instruction(&Instruction::LocalGet(len_local));
+    func.instruction(&Instruction::I64ExtendI32U);
+    func.instruction(&Instruction::I64Or);
+}
+
+/// `local += delta` for an i32 local
+pub(crate) fn emit_inc(func: &mut Function, local: u32, delta: i32) {
+    func.instruction(&Instruction::LocalGet(local));
+    func.instruction(&Instruction::I32Const(delta));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::LocalSet(local));
+}
+
+/// `local = cmp(local, other) ? local : other` for i64 locals, where `other`
+/// is another local or the constant 0
+pub(crate) fn emit_select_i64(func: &mut Function, local: u32, other: Option<u32>, cmp: Instruction<'static>) {
+    let push_other = |func: &mut Function| {
+        match other {
+            Some(o) => func.instruction(&Instruction::LocalGet(o)),
+            None => func.instruction(&Instruction::I64Const(0)),
+        };
+    };
+    func.instruction(&Instruction::LocalGet(local));
+    push_other(func);
+    func.instruction(&Instruction::LocalGet(local));
+    push_other(func);
+    func.instruction(&cmp);
+    func.instruction(&Instruction::Select);
+    func.instruction(&Instruction::LocalSet(local));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_library_includes_dependencies() {
+        let lib = RuntimeLibrary::for_operations([Operation::SetUnion]);
+        for f in [RtFunc::SetUnion, RtFunc::SetFilter, RtFunc::SetConcat, RtFunc::Find,
+                  RtFunc::KeyEq, RtFunc::StrEq, RtFunc::Count, RtFunc::NewColl, RtFunc::Alloc] {
+            assert!(lib.funcs.contains(&f), "missing {:?}", f);
+        }
+        assert!(!lib.funcs.contains(&RtFunc::MapInsert));
+    }
+
+    #[test]
+    fn test_inlined_operations_need_no_library() {
+        let lib = RuntimeLibrary::for_operations([
+            Operation::Add,
+            Operation::SetToList,
+            Operation::DtUnix,
+            Operation::DtAddDays,
+            Operation::BytesLen,
+        ]);
+        assert!(lib.funcs.is_empty());
+    }
+
+    #[test]
+    fn test_builtin_externs() {
+        let upper = BuiltinExtern::for_id("text.upper").unwrap();
+        assert_eq!(upper, BuiltinExtern { func: RtFunc::TextCase, selector: Some(1) });
+        assert_eq!(BuiltinExtern::for_id("text.concat").unwrap().func, RtFunc::BytesConcat);
+        // Effectful or JSON-based externs stay host imports
+        assert!(BuiltinExtern::for_id("map.get").is_none());
+        assert!(BuiltinExtern::for_id("std.text.regex_test").is_none());
+        assert!(BuiltinExtern::for_id("console.println").is_none());
+
+        let lib = RuntimeLibrary::link([RtFunc::TextSplit]);
+        assert!(lib.links(RtFunc::IndexOf));
+        assert!(lib.links(RtFunc::MemEq));
+        assert!(lib.links(RtFunc::NewColl));
+    }
+
+    #[test]
+    fn test_indices_follow_base() {
+        let mut lib = RuntimeLibrary::for_operations([Operation::DtYear]);
+        lib.assign_indices(10);
+        assert_eq!(lib.function_count(), 1);
+        assert_eq!(lib.index(RtFunc::DtField).unwrap(), 10);
+        assert_eq!(lib.function_types().len(), lib.generate_functions().len());
+    }
+
+    #[test]
+    fn test_tables_only_allocated_when_needed() {
+        let mut data = DataSegmentBuilder::new();
+        let mut lib = RuntimeLibrary::for_operations([Operation::MapHas]);
+        lib.allocate_data(&mut data);
+        assert!(data.is_empty());
+
+        let mut lib = RuntimeLibrary::for_operations([Operation::BytesToHex]);
+        lib.allocate_data(&mut data);
+        assert_eq!(data.len(), HEX_ALPHABET.len() + 1);
+    }
+}
@@ -0,0 +1,1293 @@
+//! Runtime Library: Text and List Built-ins
+//!
+//! WASM implementations of the pure `text.*` and `list.*` extern-abstracts
+//! from `runtime/std`. They are linked through [`RuntimeLibrary`] like the
+//! compute-step functions and replace the corresponding host imports.
+//!
+//! Text functions work on UTF-8 code points where the host works on
+//! JavaScript characters (`str_len`, `slice`, `char_at`, `str_reverse`,
+//! `pad_*`); case mapping and trimming only handle ASCII. Strings that are
+//! built piecewise (`replace`, `join`, `repeat`, `pad_*`) are appended at the
+//! heap top with `_rt_heap_push`, so no allocation may happen while one is
+//! being built.
+
+use wasm_encoder::{BlockType, Function, Instruction, ValType};
+use crate::runtime_lib::{
+    emit_fat_ptr, emit_fat_ptr_base, emit_inc, emit_items_base, emit_select_i64, mem64, mem8,
+    RtFunc, RuntimeLibrary, NONE_SENTINEL, SET_STRIDE,
+};
+
+impl RuntimeLibrary {
+    /// _rt_index_of(haystack: i64, needle: i64, from: i32) -> i32
+    /// Byte index of the first occurrence of `needle` at or after `from`, or -1.
+    pub(crate) fn gen_index_of(&self) -> Function {
+        // Params: 0 = haystack, 1 = needle, 2 = from
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 3: haystack base
+            (1, ValType::I32), // local 4: last candidate (len(h) - len(n))
+            (1, ValType::I32), // local 5: needle base
+            (1, ValType::I32), // local 6: len(n)
+        ]);
+
+        emit_fat_ptr_base(&mut func, 0);
+        func.instruction(&Instruction::LocalSet(3));
+        emit_fat_ptr_base(&mut func, 1);
+        func.instruction(&Instruction::LocalSet(5));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::LocalSet(6));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::I32Sub);
+        func.instruction(&Instruction::LocalSet(4));
+
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32GtS);
+        func.instruction(&Instruction::BrIf(1));
+
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::MemEq)));
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::Return);
+        func.instruction(&Instruction::End);
+
+        emit_inc(&mut func, 2, 1);
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+
+        func.instruction(&Instruction::I32Const(-1));
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_text_case(s: i64, upper: i32) -> i64
+    ///
+    /// Maps ASCII letters only; every other byte is copied unchanged.
+    pub(crate) fn gen_text_case(&self) -> Function {
+        // Params: 0 = s, 1 = upper
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 2: src
+            (1, ValType::I32), // local 3: len
+            (1, ValType::I32), // local 4: dst
+            (1, ValType::I32), // local 5: i
+            (1, ValType::I32), // local 6: byte
+            (1, ValType::I32), // local 7: first letter of the case to flip
+        ]);
+
+        emit_fat_ptr_base(&mut func, 0);
+        func.instruction(&Instruction::LocalSet(2));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::LocalTee(3));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Alloc)));
+        func.instruction(&Instruction::LocalSet(4));
+        func.instruction(&Instruction::I32Const(b'a' as i32));
+        func.instruction(&Instruction::I32Const(b'A' as i32));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::Select);
+        func.instruction(&Instruction::LocalSet(7));
+
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalSet(5));
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32GeU);
+        func.instruction(&Instruction::BrIf(1));
+
+        // dst[i] = byte - first < 26 ? byte ^ 0x20 : byte
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I32Load8U(mem8(0)));
+        func.instruction(&Instruction::LocalTee(6));
+        func.instruction(&Instruction::I32Const(0x20));
+        func.instruction(&Instruction::I32Xor);
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::LocalGet(7));
+        func.instruction(&Instruction::I32Sub);
+        func.instruction(&Instruction::I32Const(26));
+        func.instruction(&Instruction::I32LtU);
+        func.instruction(&Instruction::Select);
+        func.instruction(&Instruction::I32Store8(mem8(0)));
+
+        emit_inc(&mut func, 5, 1);
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+
+        emit_fat_ptr(&mut func, 4, 3);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_text_contains(haystack: i64, needle: i64) -> i64
+    pub(crate) fn gen_text_contains(&self) -> Function {
+        let mut func = Function::new(vec![]);
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::IndexOf)));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::I32GeS);
+        func.instruction(&Instruction::I64ExtendI32U);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_text_starts_with / _rt_text_ends_with(s: i64, affix: i64) -> i64
+    pub(crate) fn gen_text_affix(&self, at_end: bool) -> Function {
+        // Params: 0 = s, 1 = affix
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 2: len(s) - len(affix)
+        ]);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::I32Sub);
+        func.instruction(&Instruction::LocalTee(2));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::I32LtS);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::I64Const(0));
+        func.instruction(&Instruction::Return);
+        func.instruction(&Instruction::End);
+
+        emit_fat_ptr_base(&mut func, 0);
+        if at_end {
+            func.instruction(&Instruction::LocalGet(2));
+            func.instruction(&Instruction::I32Add);
+        }
+        emit_fat_ptr_base(&mut func, 1);
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::MemEq)));
+        func.instruction(&Instruction::I64ExtendI32U);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_text_index_of(haystack: i64, needle: i64) -> i64
+    /// Converts the byte index of the match into a code point index.
+    pub(crate) fn gen_text_index_of(&self) -> Function {
+        // Params: 0 = haystack, 1 = needle
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 2: byte index
+        ]);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::IndexOf)));
+        func.instruction(&Instruction::LocalTee(2));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::I32LtS);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::I64Const(-1));
+        func.instruction(&Instruction::Return);
+        func.instruction(&Instruction::End);
+
+        // utf8_len of the prefix (haystack pointer, byte index as length)
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::I64Const(!0xFFFF_FFFF));
+        func.instruction(&Instruction::I64And);
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I64ExtendI32U);
+        func.instruction(&Instruction::I64Or);
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Utf8Len)));
+        func.instruction(&Instruction::I64ExtendI32U);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_text_slice(s: i64, start: i64, end: i64) -> i64
+    /// Same bounds handling as JavaScript `slice`: negative indices count from
+    /// the end, both are clamped to the length, and `end < start` is empty.
+    pub(crate) fn gen_text_slice(&self) -> Function {
+        // Params: 0 = s, 1 = start, 2 = end
+        let mut func = Function::new(vec![
+            (1, ValType::I64), // local 3: code point count
+            (1, ValType::I32), // local 4: start byte offset
+        ]);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Utf8Len)));
+        func.instruction(&Instruction::I64ExtendI32U);
+        func.instruction(&Instruction::LocalSet(3));
+
+        for bound in [1, 2] {
+            func.instruction(&Instruction::LocalGet(bound));
+            func.instruction(&Instruction::I64Const(0));
+            func.instruction(&Instruction::I64LtS);
+            func.instruction(&Instruction::If(BlockType::Empty));
+            func.instruction(&Instruction::LocalGet(bound));
+            func.instruction(&Instruction::LocalGet(3));
+            func.instruction(&Instruction::I64Add);
+            func.instruction(&Instruction::LocalSet(bound));
+            emit_select_i64(&mut func, bound, None, Instruction::I64GtS);
+            func.instruction(&Instruction::Else);
+            emit_select_i64(&mut func, bound, Some(3), Instruction::I64LtS);
+            func.instruction(&Instruction::End);
+        }
+        emit_select_i64(&mut func, 2, Some(1), Instruction::I64GtS);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::CharOffset)));
+        func.instruction(&Instruction::LocalSet(4));
+
+        // ((ptr + start) << 32) | (end_offset - start)
+        emit_fat_ptr_base(&mut func, 0);
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I64ExtendI32U);
+        func.instruction(&Instruction::I64Const(32));
+        func.instruction(&Instruction::I64Shl);
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::CharOffset)));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Sub);
+        func.instruction(&Instruction::I64ExtendI32U);
+        func.instruction(&Instruction::I64Or);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_text_char_at(s: i64, index: i64) -> i64
+    pub(crate) fn gen_text_char_at(&self) -> Function {
+        // Params: 0 = s, 1 = index
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 2: byte offset
+        ]);
+
+        // A code point index is never beyond the byte length
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I64Const(0));
+        func.instruction(&Instruction::I64LtS);
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::I64Const(0xFFFF_FFFF));
+        func.instruction(&Instruction::I64And);
+        func.instruction(&Instruction::I64GeS);
+        func.instruction(&Instruction::I32Or);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::I64Const(0));
+        func.instruction(&Instruction::Return);
+        func.instruction(&Instruction::End);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::CharOffset)));
+        func.instruction(&Instruction::LocalSet(2));
+
+        emit_fat_ptr_base(&mut func, 0);
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I64ExtendI32U);
+        func.instruction(&Instruction::I64Const(32));
+        func.instruction(&Instruction::I64Shl);
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::I32Const(1));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::CharOffset)));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Sub);
+        func.instruction(&Instruction::I64ExtendI32U);
+        func.instruction(&Instruction::I64Or);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_text_replace(s: i64, from: i64, to: i64, all: i32) -> i64
+    /// An empty `from` inserts `to` at the start (or around every code point when `all`).
+    pub(crate) fn gen_text_replace(&self) -> Function {
+        // Params: 0 = s, 1 = from, 2 = to, 3 = all
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 4: result start
+            (1, ValType::I32), // local 5: src
+            (1, ValType::I32), // local 6: len(s)
+            (1, ValType::I32), // local 7: len(from)
+            (1, ValType::I32), // local 8: pos
+            (1, ValType::I32), // local 9: match / code point end
+        ]);
+        let push = self.dependency_index(RtFunc::HeapPush);
+        let push_to = |func: &mut Function| {
+            emit_fat_ptr_base(func, 2);
+            func.instruction(&Instruction::LocalGet(2));
+            func.instruction(&Instruction::I32WrapI64);
+            func.instruction(&Instruction::Call(push));
+        };
+        // push(src + pos, end - pos)
+        let push_from_pos = |func: &mut Function, end: u32| {
+            func.instruction(&Instruction::LocalGet(5));
+            func.instruction(&Instruction::LocalGet(8));
+            func.instruction(&Instruction::I32Add);
+            func.instruction(&Instruction::LocalGet(end));
+            func.instruction(&Instruction::LocalGet(8));
+            func.instruction(&Instruction::I32Sub);
+            func.instruction(&Instruction::Call(push));
+        };
+
+        func.instruction(&Instruction::GlobalGet(0));
+        func.instruction(&Instruction::LocalSet(4));
+        emit_fat_ptr_base(&mut func, 0);
+        func.instruction(&Instruction::LocalSet(5));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::LocalSet(6));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::LocalSet(7));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalSet(8));
+
+        func.instruction(&Instruction::LocalGet(7));
+        func.instruction(&Instruction::I32Eqz);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        push_to(&mut func);
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(8));
+        func.instruction(&Instruction::LocalGet(6));
+        func.instruction(&Instruction::I32GeU);
+        func.instruction(&Instruction::BrIf(1));
+        emit_next_char(&mut func, 5, 6, 8, 9);
+        push_from_pos(&mut func, 9);
+        push_to(&mut func);
+        func.instruction(&Instruction::LocalGet(9));
+        func.instruction(&Instruction::LocalSet(8));
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+        func.instruction(&Instruction::End); // all
+        func.instruction(&Instruction::Else);
+
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::LocalGet(8));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::IndexOf)));
+        func.instruction(&Instruction::LocalTee(9));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::I32LtS);
+        func.instruction(&Instruction::BrIf(1));
+        push_from_pos(&mut func, 9);
+        push_to(&mut func);
+        func.instruction(&Instruction::LocalGet(9));
+        func.instruction(&Instruction::LocalGet(7));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalSet(8));
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::BrIf(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+        func.instruction(&Instruction::End);
+
+        push_from_pos(&mut func, 6);
+        emit_heap_string(&mut func, 4);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_text_split(s: i64, delimiter: i64) -> i64
+    /// An empty delimiter splits into code points. Parts share memory with `s`.
+    pub(crate) fn gen_text_split(&self) -> Function {
+        // Params: 0 = s, 1 = delimiter
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 2: src
+            (1, ValType::I32), // local 3: len(s)
+            (1, ValType::I32), // local 4: len(delimiter)
+            (1, ValType::I32), // local 5: count
+            (1, ValType::I32), // local 6: list
+            (1, ValType::I32), // local 7: pos
+            (1, ValType::I32), // local 8: match / code point end
+            (1, ValType::I32), // local 9: item slot
+            (1, ValType::I32), // local 10: part pointer
+            (1, ValType::I32), // local 11: part length
+        ]);
+        // *slot = (src + pos, end - pos); slot += 8
+        let store_part = |func: &mut Function, end: u32| {
+            func.instruction(&Instruction::LocalGet(9));
+            func.instruction(&Instruction::LocalGet(2));
+            func.instruction(&Instruction::LocalGet(7));
+            func.instruction(&Instruction::I32Add);
+            func.instruction(&Instruction::LocalSet(10));
+            func.instruction(&Instruction::LocalGet(end));
+            func.instruction(&Instruction::LocalGet(7));
+            func.instruction(&Instruction::I32Sub);
+            func.instruction(&Instruction::LocalSet(11));
+            emit_fat_ptr(func, 10, 11);
+            func.instruction(&Instruction::I64Store(mem64(0)));
+            emit_inc(func, 9, SET_STRIDE);
+        };
+
+        emit_fat_ptr_base(&mut func, 0);
+        func.instruction(&Instruction::LocalSet(2));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::LocalSet(3));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::LocalSet(4));
+
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Eqz);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Utf8Len)));
+        func.instruction(&Instruction::LocalSet(5));
+        func.instruction(&Instruction::Else);
+        // One part more than there are delimiters
+        func.instruction(&Instruction::I32Const(1));
+        func.instruction(&Instruction::LocalSet(5));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalSet(7));
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::LocalGet(7));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::IndexOf)));
+        func.instruction(&Instruction::LocalTee(8));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::I32LtS);
+        func.instruction(&Instruction::BrIf(1));
+        emit_inc(&mut func, 5, 1);
+        func.instruction(&Instruction::LocalGet(8));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalSet(7));
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+        func.instruction(&Instruction::End);
+
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::NewColl)));
+        func.instruction(&Instruction::LocalTee(6));
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalSet(9));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalSet(7));
+
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Eqz);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(7));
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32GeU);
+        func.instruction(&Instruction::BrIf(1));
+        emit_next_char(&mut func, 2, 3, 7, 8);
+        store_part(&mut func, 8);
+        func.instruction(&Instruction::LocalGet(8));
+        func.instruction(&Instruction::LocalSet(7));
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+        func.instruction(&Instruction::Else);
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::LocalGet(7));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::IndexOf)));
+        func.instruction(&Instruction::LocalTee(8));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::I32LtS);
+        func.instruction(&Instruction::BrIf(1));
+        store_part(&mut func, 8);
+        func.instruction(&Instruction::LocalGet(8));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalSet(7));
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+        store_part(&mut func, 3);
+        func.instruction(&Instruction::End);
+
+        emit_fat_ptr(&mut func, 6, 5);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_text_join(parts: i64, separator: i64) -> i64
+    pub(crate) fn gen_text_join(&self) -> Function {
+        // Params: 0 = parts, 1 = separator
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 2: result start
+            (1, ValType::I32), // local 3: count
+            (1, ValType::I32), // local 4: i
+            (1, ValType::I64), // local 5: part
+        ]);
+        let push = self.dependency_index(RtFunc::HeapPush);
+
+        func.instruction(&Instruction::GlobalGet(0));
+        func.instruction(&Instruction::LocalSet(2));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Count)));
+        func.instruction(&Instruction::LocalSet(3));
+
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalSet(4));
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32GeU);
+        func.instruction(&Instruction::BrIf(1));
+
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::If(BlockType::Empty));
+        emit_fat_ptr_base(&mut func, 1);
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::Call(push));
+        func.instruction(&Instruction::End);
+
+        emit_items_base(&mut func, 0);
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I64Load(mem64(0)));
+        func.instruction(&Instruction::LocalSet(5));
+        emit_fat_ptr_base(&mut func, 5);
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::Call(push));
+
+        emit_inc(&mut func, 4, 1);
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+
+        emit_heap_string(&mut func, 2);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_text_repeat(s: i64, count: i64) -> i64 (a negative count repeats zero times)
+    pub(crate) fn gen_text_repeat(&self) -> Function {
+        // Params: 0 = s, 1 = count
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 2: result start
+        ]);
+
+        func.instruction(&Instruction::GlobalGet(0));
+        func.instruction(&Instruction::LocalSet(2));
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I64Const(0));
+        func.instruction(&Instruction::I64LeS);
+        func.instruction(&Instruction::BrIf(1));
+        emit_fat_ptr_base(&mut func, 0);
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::HeapPush)));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I64Const(1));
+        func.instruction(&Instruction::I64Sub);
+        func.instruction(&Instruction::LocalSet(1));
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+
+        emit_heap_string(&mut func, 2);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_text_str_len(s: i64) -> i64
+    pub(crate) fn gen_text_str_len(&self) -> Function {
+        let mut func = Function::new(vec![]);
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Utf8Len)));
+        func.instruction(&Instruction::I64ExtendI32U);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_text_reverse(s: i64) -> i64
+    /// Each code point is copied to the mirrored position, keeping its bytes in order.
+    pub(crate) fn gen_text_reverse(&self) -> Function {
+        // Params: 0 = s
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 1: src
+            (1, ValType::I32), // local 2: len
+            (1, ValType::I32), // local 3: dst
+            (1, ValType::I32), // local 4: pos
+            (1, ValType::I32), // local 5: code point end
+        ]);
+
+        emit_fat_ptr_base(&mut func, 0);
+        func.instruction(&Instruction::LocalSet(1));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::LocalTee(2));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Alloc)));
+        func.instruction(&Instruction::LocalSet(3));
+
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalSet(4));
+        func.instruction(&Instruction::Block(BlockType::Empty));
+        func.instruction(&Instruction::Loop(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32GeU);
+        func.instruction(&Instruction::BrIf(1));
+        emit_next_char(&mut func, 1, 2, 4, 5);
+
+        // copy(dst + len - end, src + pos, end - pos)
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::I32Sub);
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::LocalGet(4));
+        func.instruction(&Instruction::I32Sub);
+        func.instruction(&Instruction::MemoryCopy { src_mem: 0, dst_mem: 0 });
+
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::LocalSet(4));
+        func.instruction(&Instruction::Br(0));
+        func.instruction(&Instruction::End); // loop
+        func.instruction(&Instruction::End); // block
+
+        emit_fat_ptr(&mut func, 3, 2);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_text_pad(s: i64, target_len: i64, fill: i64, at_start: i32) -> i64
+    /// Repeats `fill` (truncated at a code point) until `s` is `target_len` code points long.
+    pub(crate) fn gen_text_pad(&self) -> Function {
+        // Params: 0 = s, 1 = target_len, 2 = fill, 3 = at_start
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 4: result start
+            (1, ValType::I32), // local 5: code points to add
+            (1, ValType::I32), // local 6: fill src
+            (1, ValType::I32), // local 7: len(fill)
+            (1, ValType::I32), // local 8: fill pos
+            (1, ValType::I32), // local 9: code point end
+        ]);
+        let push = self.dependency_index(RtFunc::HeapPush);
+        let push_s = |func: &mut Function| {
+            emit_fat_ptr_base(func, 0);
+            func.instruction(&Instruction::LocalGet(0));
+            func.instruction(&Instruction::I32WrapI64);
+            func.instruction(&Instruction::Call(push));
+        };
+        let push_padding = |func: &mut Function| {
+            func.instruction(&Instruction::Block(BlockType::Empty));
+            func.instruction(&Instruction::Loop(BlockType::Empty));
+            func.instruction(&Instruction::LocalGet(5));
+            func.instruction(&Instruction::I32Eqz);
+            func.instruction(&Instruction::BrIf(1));
+            emit_next_char(func, 6, 7, 8, 9);
+            func.instruction(&Instruction::LocalGet(6));
+            func.instruction(&Instruction::LocalGet(8));
+            func.instruction(&Instruction::I32Add);
+            func.instruction(&Instruction::LocalGet(9));
+            func.instruction(&Instruction::LocalGet(8));
+            func.instruction(&Instruction::I32Sub);
+            func.instruction(&Instruction::Call(push));
+            emit_inc(func, 5, -1);
+            // Wrap around to the start of the fill string
+            func.instruction(&Instruction::LocalGet(9));
+            func.instruction(&Instruction::I32Const(0));
+            func.instruction(&Instruction::LocalGet(9));
+            func.instruction(&Instruction::LocalGet(7));
+            func.instruction(&Instruction::I32LtU);
+            func.instruction(&Instruction::Select);
+            func.instruction(&Instruction::LocalSet(8));
+            func.instruction(&Instruction::Br(0));
+            func.instruction(&Instruction::End); // loop
+            func.instruction(&Instruction::End); // block
+        };
+
+        // Nothing to do when already long enough or the fill is empty
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::LocalTee(7));
+        func.instruction(&Instruction::I32Eqz);
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Utf8Len)));
+        func.instruction(&Instruction::I64ExtendI32U);
+        func.instruction(&Instruction::I64Sub);
+        func.instruction(&Instruction::LocalTee(1));
+        func.instruction(&Instruction::I64Const(0));
+        func.instruction(&Instruction::I64LeS);
+        func.instruction(&Instruction::I32Or);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Return);
+        func.instruction(&Instruction::End);
+
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::LocalSet(5));
+        emit_fat_ptr_base(&mut func, 2);
+        func.instruction(&Instruction::LocalSet(6));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::LocalSet(8));
+        func.instruction(&Instruction::GlobalGet(0));
+        func.instruction(&Instruction::LocalSet(4));
+
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::If(BlockType::Empty));
+        push_padding(&mut func);
+        push_s(&mut func);
+        func.instruction(&Instruction::Else);
+        push_s(&mut func);
+        push_padding(&mut func);
+        func.instruction(&Instruction::End);
+
+        emit_heap_string(&mut func, 4);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_list_len / _rt_list_is_empty(list: i64) -> i64
+    pub(crate) fn gen_list_len(&self, is_empty: bool) -> Function {
+        let mut func = Function::new(vec![]);
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Count)));
+        if is_empty {
+            func.instruction(&Instruction::I32Eqz);
+        }
+        func.instruction(&Instruction::I64ExtendI32U);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_list_get(list: i64, index: i64) -> i64 (none when out of range)
+    pub(crate) fn gen_list_get(&self) -> Function {
+        // Params: 0 = list, 1 = index
+        let mut func = Function::new(vec![]);
+
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I64Const(0));
+        func.instruction(&Instruction::I64LtS);
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Count)));
+        func.instruction(&Instruction::I64ExtendI32U);
+        func.instruction(&Instruction::I64GeS);
+        func.instruction(&Instruction::I32Or);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.instruction(&Instruction::I64Const(NONE_SENTINEL));
+        func.instruction(&Instruction::Return);
+        func.instruction(&Instruction::End);
+
+        emit_items_base(&mut func, 0);
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I64Load(mem64(0)));
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_list_first / _rt_list_last(list: i64) -> i64
+    pub(crate) fn gen_list_end(&self, last: bool) -> Function {
+        let mut func = Function::new(vec![]);
+        func.instruction(&Instruction::LocalGet(0));
+        if last {
+            // count - 1 is -1 (out of range) for an empty list
+            func.instruction(&Instruction::LocalGet(0));
+            func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Count)));
+            func.instruction(&Instruction::I64ExtendI32U);
+            func.instruction(&Instruction::I64Const(1));
+            func.instruction(&Instruction::I64Sub);
+        } else {
+            func.instruction(&Instruction::I64Const(0));
+        }
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::ListGet)));
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_list_append(list: i64, item: i64) -> i64
+    pub(crate) fn gen_list_append(&self) -> Function {
+        // Params: 0 = list, 1 = item
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 2: count
+            (1, ValType::I32), // local 3: dst
+        ]);
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Count)));
+        func.instruction(&Instruction::LocalTee(2));
+        func.instruction(&Instruction::I32Const(1));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::NewColl)));
+        func.instruction(&Instruction::LocalSet(3));
+
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Add);
+        emit_items_base(&mut func, 0);
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::MemoryCopy { src_mem: 0, dst_mem: 0 });
+
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::I32Mul);
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I64Store(mem64(4)));
+
+        emit_inc(&mut func, 2, 1);
+        emit_fat_ptr(&mut func, 3, 2);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_list_contains(list: i64, item: i64, by_content: i32) -> i64
+    pub(crate) fn gen_list_contains(&self) -> Function {
+        let mut func = Function::new(vec![]);
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::LocalGet(1));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::Find)));
+        func.instruction(&Instruction::I32Const(0));
+        func.instruction(&Instruction::I32GeS);
+        func.instruction(&Instruction::I64ExtendI32U);
+        func.instruction(&Instruction::End);
+        func
+    }
+
+    /// _rt_list_flatten(lists: i64) -> i64
+    pub(crate) fn gen_list_flatten(&self) -> Function {
+        // Params: 0 = lists
+        let mut func = Function::new(vec![
+            (1, ValType::I32), // local 1: outer count
+            (1, ValType::I32), // local 2: i
+            (1, ValType::I32), // local 3: total
+            (1, ValType::I32), // local 4: dst
+            (1, ValType::I32), // local 5: write cursor
+            (1, ValType::I64), // local 6: inner list
+            (1, ValType::I32), // local 7: inner count
+        ]);
+        let count = self.dependency_index(RtFunc::Count);
+        // Loop over the inner lists, loading each into local 6 and its count into local 7
+        let for_each_inner = |func: &mut Function, body: &dyn Fn(&mut Function)| {
+            func.instruction(&Instruction::I32Const(0));
+            func.instruction(&Instruction::LocalSet(2));
+            func.instruction(&Instruction::Block(BlockType::Empty));
+            func.instruction(&Instruction::Loop(BlockType::Empty));
+            func.instruction(&Instruction::LocalGet(2));
+            func.instruction(&Instruction::LocalGet(1));
+            func.instruction(&Instruction::I32GeU);
+            func.instruction(&Instruction::BrIf(1));
+            emit_items_base(func, 0);
+            func.instruction(&Instruction::LocalGet(2));
+            func.instruction(&Instruction::I32Const(SET_STRIDE));
+            func.instruction(&Instruction::I32Mul);
+            func.instruction(&Instruction::I32Add);
+            func.instruction(&Instruction::I64Load(mem64(0)));
+            func.instruction(&Instruction::LocalTee(6));
+            func.instruction(&Instruction::Call(count));
+            func.instruction(&Instruction::LocalSet(7));
+            body(func);
+            emit_inc(func, 2, 1);
+            func.instruction(&Instruction::Br(0));
+            func.instruction(&Instruction::End); // loop
+            func.instruction(&Instruction::End); // block
+        };
+
+        func.instruction(&Instruction::LocalGet(0));
+        func.instruction(&Instruction::Call(count));
+        func.instruction(&Instruction::LocalSet(1));
+
+        for_each_inner(&mut func, &|func| {
+            func.instruction(&Instruction::LocalGet(3));
+            func.instruction(&Instruction::LocalGet(7));
+            func.instruction(&Instruction::I32Add);
+            func.instruction(&Instruction::LocalSet(3));
+        });
+
+        func.instruction(&Instruction::LocalGet(3));
+        func.instruction(&Instruction::I32Const(SET_STRIDE));
+        func.instruction(&Instruction::Call(self.dependency_index(RtFunc::NewColl)));
+        func.instruction(&Instruction::LocalTee(4));
+        func.instruction(&Instruction::I32Const(4));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::LocalSet(5));
+
+        for_each_inner(&mut func, &|func| {
+            func.instruction(&Instruction::LocalGet(5));
+            emit_items_base(func, 6);
+            func.instruction(&Instruction::LocalGet(7));
+            func.instruction(&Instruction::I32Const(SET_STRIDE));
+            func.instruction(&Instruction::I32Mul);
+            func.instruction(&Instruction::LocalTee(7));
+            func.instruction(&Instruction::MemoryCopy { src_mem: 0, dst_mem: 0 });
+            func.instruction(&Instruction::LocalGet(5));
+            func.instruction(&Instruction::LocalGet(7));
+            func.instruction(&Instruction::I32Add);
+            func.instruction(&Instruction::LocalSet(5));
+        });
+
+        emit_fat_ptr(&mut func, 4, 3);
+        func.instruction(&Instruction::End);
+        func
+    }
+}
+
+/// _rt_utf8_len(s: i64) -> i32
+/// Counts the bytes that are not UTF-8 continuation bytes.
+pub(crate) fn gen_utf8_len() -> Function {
+    // Params: 0 = s
+    let mut func = Function::new(vec![
+        (1, ValType::I32), // local 1: cursor
+        (1, ValType::I32), // local 2: end
+        (1, ValType::I32), // local 3: count
+    ]);
+
+    emit_fat_ptr_base(&mut func, 0);
+    func.instruction(&Instruction::LocalTee(1));
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::I32WrapI64);
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::LocalSet(2));
+
+    func.instruction(&Instruction::Block(BlockType::Empty));
+    func.instruction(&Instruction::Loop(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::I32GeU);
+    func.instruction(&Instruction::BrIf(1));
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I32Load8U(mem8(0)));
+    emit_is_char_start(&mut func);
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::LocalSet(3));
+    emit_inc(&mut func, 1, 1);
+    func.instruction(&Instruction::Br(0));
+    func.instruction(&Instruction::End); // loop
+    func.instruction(&Instruction::End); // block
+
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::End);
+    func
+}
+
+/// _rt_char_offset(s: i64, index: i32) -> i32
+/// Byte offset of code point `index`, or the byte length when there are fewer code points.
+pub(crate) fn gen_char_offset() -> Function {
+    // Params: 0 = s, 1 = index
+    let mut func = Function::new(vec![
+        (1, ValType::I32), // local 2: base
+        (1, ValType::I32), // local 3: len
+        (1, ValType::I32), // local 4: offset
+    ]);
+
+    emit_fat_ptr_base(&mut func, 0);
+    func.instruction(&Instruction::LocalSet(2));
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::I32WrapI64);
+    func.instruction(&Instruction::LocalSet(3));
+
+    func.instruction(&Instruction::Block(BlockType::Empty));
+    func.instruction(&Instruction::Loop(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(4));
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::I32GeU);
+    func.instruction(&Instruction::BrIf(1));
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::LocalGet(4));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::I32Load8U(mem8(0)));
+    emit_is_char_start(&mut func);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I32Const(0));
+    func.instruction(&Instruction::I32LeS);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(4));
+    func.instruction(&Instruction::Return);
+    func.instruction(&Instruction::End);
+    emit_inc(&mut func, 1, -1);
+    func.instruction(&Instruction::End);
+    emit_inc(&mut func, 4, 1);
+    func.instruction(&Instruction::Br(0));
+    func.instruction(&Instruction::End); // loop
+    func.instruction(&Instruction::End); // block
+
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::End);
+    func
+}
+
+/// _rt_mem_eq(a: i32, b: i32, len: i32) -> i32
+pub(crate) fn gen_mem_eq() -> Function {
+    // Params: 0 = a, 1 = b, 2 = len
+    let mut func = Function::new(vec![
+        (1, ValType::I32), // local 3: i
+    ]);
+
+    func.instruction(&Instruction::Block(BlockType::Empty));
+    func.instruction(&Instruction::Loop(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::I32GeU);
+    func.instruction(&Instruction::BrIf(1));
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::I32Load8U(mem8(0)));
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::I32Load8U(mem8(0)));
+    func.instruction(&Instruction::I32Ne);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::I32Const(0));
+    func.instruction(&Instruction::Return);
+    func.instruction(&Instruction::End);
+    emit_inc(&mut func, 3, 1);
+    func.instruction(&Instruction::Br(0));
+    func.instruction(&Instruction::End); // loop
+    func.instruction(&Instruction::End); // block
+
+    func.instruction(&Instruction::I32Const(1));
+    func.instruction(&Instruction::End);
+    func
+}
+
+/// _rt_heap_push(src: i32, len: i32)
+/// Copies `len` bytes to the heap top (unaligned) and bumps global 0 past them.
+pub(crate) fn gen_heap_push() -> Function {
+    // Params: 0 = src, 1 = len
+    let mut func = Function::new(vec![
+        (1, ValType::I32), // local 2: dst
+        (1, ValType::I32), // local 3: pages needed
+    ]);
+
+    func.instruction(&Instruction::GlobalGet(0));
+    func.instruction(&Instruction::LocalTee(2));
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::GlobalSet(0));
+
+    func.instruction(&Instruction::GlobalGet(0));
+    func.instruction(&Instruction::I32Const(0xFFFF));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::I32Const(16));
+    func.instruction(&Instruction::I32ShrU);
+    func.instruction(&Instruction::LocalTee(3));
+    func.instruction(&Instruction::MemorySize(0));
+    func.instruction(&Instruction::I32GtU);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::MemorySize(0));
+    func.instruction(&Instruction::I32Sub);
+    func.instruction(&Instruction::MemoryGrow(0));
+    func.instruction(&Instruction::Drop);
+    func.instruction(&Instruction::End);
+
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::MemoryCopy { src_mem: 0, dst_mem: 0 });
+    func.instruction(&Instruction::End);
+    func
+}
+
+/// _rt_text_trim(s: i64, sides: i32) -> i64
+/// Strips ASCII whitespace (space, \t, \n, \v, \f, \r) from the selected sides.
+pub(crate) fn gen_text_trim() -> Function {
+    // Params: 0 = s, 1 = sides
+    let mut func = Function::new(vec![
+        (1, ValType::I32), // local 2: base
+        (1, ValType::I32), // local 3: start
+        (1, ValType::I32), // local 4: end
+        (1, ValType::I32), // local 5: byte
+    ]);
+    // Push whether the byte at base + offset is whitespace: b == ' ' || b - '\t' < 5
+    let is_space_at = |func: &mut Function, offset: u32| {
+        func.instruction(&Instruction::LocalGet(2));
+        func.instruction(&Instruction::LocalGet(offset));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::I32Load8U(mem8(0)));
+        func.instruction(&Instruction::LocalTee(5));
+        func.instruction(&Instruction::I32Const(b' ' as i32));
+        func.instruction(&Instruction::I32Eq);
+        func.instruction(&Instruction::LocalGet(5));
+        func.instruction(&Instruction::I32Const(b'\t' as i32));
+        func.instruction(&Instruction::I32Sub);
+        func.instruction(&Instruction::I32Const(5));
+        func.instruction(&Instruction::I32LtU);
+        func.instruction(&Instruction::I32Or);
+    };
+
+    emit_fat_ptr_base(&mut func, 0);
+    func.instruction(&Instruction::LocalSet(2));
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::I32WrapI64);
+    func.instruction(&Instruction::LocalSet(4));
+
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I32Const(1));
+    func.instruction(&Instruction::I32And);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::Block(BlockType::Empty));
+    func.instruction(&Instruction::Loop(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::LocalGet(4));
+    func.instruction(&Instruction::I32GeU);
+    func.instruction(&Instruction::BrIf(1));
+    is_space_at(&mut func, 3);
+    func.instruction(&Instruction::I32Eqz);
+    func.instruction(&Instruction::BrIf(1));
+    emit_inc(&mut func, 3, 1);
+    func.instruction(&Instruction::Br(0));
+    func.instruction(&Instruction::End); // loop
+    func.instruction(&Instruction::End); // block
+    func.instruction(&Instruction::End);
+
+    func.instruction(&Instruction::LocalGet(1));
+    func.instruction(&Instruction::I32Const(2));
+    func.instruction(&Instruction::I32And);
+    func.instruction(&Instruction::If(BlockType::Empty));
+    func.instruction(&Instruction::Block(BlockType::Empty));
+    func.instruction(&Instruction::Loop(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(4));
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::I32LeU);
+    func.instruction(&Instruction::BrIf(1));
+    // Step back first: the byte before `end` cannot be addressed with a negative offset
+    emit_inc(&mut func, 4, -1);
+    is_space_at(&mut func, 4);
+    func.instruction(&Instruction::BrIf(0));
+    emit_inc(&mut func, 4, 1);
+    func.instruction(&Instruction::End); // loop
+    func.instruction(&Instruction::End); // block
+    func.instruction(&Instruction::End);
+
+    func.instruction(&Instruction::LocalGet(2));
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::LocalSet(2));
+    func.instruction(&Instruction::LocalGet(4));
+    func.instruction(&Instruction::LocalGet(3));
+    func.instruction(&Instruction::I32Sub);
+    func.instruction(&Instruction::LocalSet(4));
+    emit_fat_ptr(&mut func, 2, 4);
+    func.instruction(&Instruction::End);
+    func
+}
+
+/// _rt_text_byte_len(s: i64) -> i64
+pub(crate) fn gen_text_byte_len() -> Function {
+    let mut func = Function::new(vec![]);
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::I64Const(0xFFFF_FFFF));
+    func.instruction(&Instruction::I64And);
+    func.instruction(&Instruction::End);
+    func
+}
+
+/// _rt_text_is_empty(s: i64) -> i64
+pub(crate) fn gen_text_is_empty() -> Function {
+    let mut func = Function::new(vec![]);
+    func.instruction(&Instruction::LocalGet(0));
+    func.instruction(&Instruction::I32WrapI64);
+    func.instruction(&Instruction::I32Eqz);
+    func.instruction(&Instruction::I64ExtendI32U);
+    func.instruction(&Instruction::End);
+    func
+}
+
+// ===== Emission Helpers =====
+
+/// Replace the byte on the stack with 1 if it starts a code point, else 0
+fn emit_is_char_start(func: &mut Function) {
+    func.instruction(&Instruction::I32Const(0xC0));
+    func.instruction(&Instruction::I32And);
+    func.instruction(&Instruction::I32Const(0x80));
+    func.instruction(&Instruction::I32Ne);
+}
+
+/// `end_local = pos_local + 1`, then advance past continuation bytes (bounded by `len_local`)
+fn emit_next_char(func: &mut Function, base_local: u32, len_local: u32, pos_local: u32, end_local: u32) {
+    func.instruction(&Instruction::LocalGet(pos_local));
+    func.instruction(&Instruction::I32Const(1));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::LocalSet(end_local));
+    func.instruction(&Instruction::Block(BlockType::Empty));
+    func.instruction(&Instruction::Loop(BlockType::Empty));
+    func.instruction(&Instruction::LocalGet(end_local));
+    func.instruction(&Instruction::LocalGet(len_local));
+    func.instruction(&Instruction::I32GeU);
+    func.instruction(&Instruction::BrIf(1));
+    func.instruction(&Instruction::LocalGet(base_local));
+    func.instruction(&Instruction::LocalGet(end_local));
+    func.instruction(&Instruction::I32Add);
+    func.instruction(&Instruction::I32Load8U(mem8(0)));
+    emit_is_char_start(func);
+    func.instruction(&Instruction::BrIf(1));
+    emit_inc(func, end_local, 1);
+    func.instruction(&Instruction::Br(0));
+    func.instruction(&Instruction::End); // loop
+    func.instruction(&Instruction::End); // block
+}
+
+/// Push the string built at the heap top since `start_local`
+fn emit_heap_string(func: &mut Function, start_local: u32) {
+    func.instruction(&Instruction::LocalGet(start_local));
+    func.instruction(&Instruction::I64ExtendI32U);
+    func.instruction(&Instruction::I64Const(32));
+    func.instruction(&Instruction::I64Shl);
+    func.instruction(&Instruction::GlobalGet(0));
+    func.instruction(&Instruction::LocalGet(start_local));
+    func.instruction(&Instruction::I32Sub);
+    func.instruction(&Instruction::I64ExtendI32U);
+    func.instruction(&Instruction::I64Or);
+}
@@ -9,21 +9,27 @@
 
 use std::collections::HashMap;
 use wasm_encoder::{
-    BlockType, CodeSection, DataSection, ExportKind, ExportSection, Function, FunctionSection,
-    GlobalSection, GlobalType, ImportSection, Instruction, MemArg, MemorySection, MemoryType,
-    Module, TypeSection, ValType,
+    BlockType, CodeSection, DataSection, ElementSection, Elements, ExportKind, ExportSection,
+    Function, FunctionSection, GlobalSection, GlobalType, ImportSection, Instruction, MemArg,
+    MemorySection, MemoryType, Module, RefType, TableSection, TableType, TypeSection, ValType,
 };
 use covenant_ast::{
-    BindSource, BindStep, CallStep, ComputeStep, Condition, EffectsSection, ForStep, FunctionSignature,
-    InputSource, IfStep, Literal, MatchPattern, MatchStep, Operation, OrderClause, QueryContent,
-    QueryStep, ReturnStep, ReturnType, ReturnValue, Section, SignatureKind, Snippet, SnippetKind,
+    BindSource, BindStep, CallArg, CallStep, ComputeStep, Condition, EffectsSection, FieldAssignment, ForStep,
+    FunctionSignature, InputSource, IfStep, Literal, MatchPattern, MatchStep, Operation, OrderClause,
+    QueryContent, QueryStep, ReturnStep, ReturnType, ReturnValue, Section, SignatureKind, Snippet, SnippetKind,
     SnippetOrderDirection, Step, StepKind, StructConstruction, TraverseDepth, TraverseDirection,
-    TraverseStep, Type, TypeKind,
+    TraverseStep, Type, TypeKind, walk_steps,
 };
-use covenant_checker::SymbolTable;
+use covenant_checker::{ResolvedType, Symbol, SymbolKind, SymbolTable};
 use crate::CodegenError;
 use crate::data_graph::DataGraph;
+use crate::monomorphize::Specialization;
 use crate::gai_codegen::{self, GraphLayout, GaiFunctionIndices, GAI_FUNCTION_COUNT};
+use crate::runtime_lib::{self, BuiltinExtern, DtField, MapProjection, RtFunc, RuntimeLibrary};
+
+/// Index of the mutable i32 global holding the error tag of the last failed
+/// call (0 when it succeeded). Global 0 is the heap pointer.
+const ERROR_TAG_GLOBAL: u32 = 1;
 
 // ===== Memory Layout Types =====
 
@@ -53,6 +59,40 @@ pub struct StructLayout {
     pub fields: HashMap<String, FieldLayout>,
 }
 
+/// Layout information for enums
+///
+/// Enums whose variants carry no fields are represented by the variant tag
+/// itself. Otherwise every value is boxed: a pointer to the tag (i64 at
+/// offset 0) followed by the variant's fields, each an i64.
+#[derive(Debug, Clone)]
+pub struct EnumLayout {
+    /// Whether values are pointers to a tag and fields rather than bare tags
+    pub boxed: bool,
+    /// Variant layouts by variant name
+    pub variants: HashMap<String, VariantLayout>,
+}
+
+/// Layout information for one enum variant
+#[derive(Debug, Clone)]
+pub struct VariantLayout {
+    /// Tag value (declaration order)
+    pub tag: i64,
+    /// Total size in bytes, including the tag
+    pub size: u32,
+    /// Field layouts in declaration order, so pattern bindings match by position
+    pub fields: Vec<VariantFieldLayout>,
+}
+
+/// Layout of a single variant field
+#[derive(Debug, Clone)]
+pub struct VariantFieldLayout {
+    pub name: String,
+    /// Offset from the value's base pointer
+    pub offset: u32,
+    /// Declared type name, used to track struct and enum payloads
+    pub type_name: Option<String>,
+}
+
 /// WASM type representation (currently only I64 is used; others reserved for future per-type layout)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
@@ -216,12 +256,16 @@ pub struct SnippetWasmCompiler<'a> {
     data_segment: DataSegmentBuilder,
     /// Struct layouts by type name
     struct_layouts: HashMap<String, StructLayout>,
-    /// Maps local variable names to their struct type name (for field access)
+    /// Enum layouts by type name
+    enum_layouts: HashMap<String, EnumLayout>,
+    /// Maps local variable names to their struct or enum type name (for field access)
     local_types: HashMap<String, String>,
     /// Runtime function indices (set after imports are processed)
     runtime: RuntimeFunctions,
     /// Generic extern-abstract imports: snippet ID → ExternImport
     extern_imports: HashMap<String, ExternImport>,
+    /// Pure extern-abstracts implemented by the runtime library: snippet ID → BuiltinExtern
+    builtin_externs: HashMap<String, BuiltinExtern>,
     /// GAI function indices (set when data snippets are present)
     gai_indices: Option<GaiFunctionIndices>,
     /// Graph layout (set when data snippets are present)
@@ -232,6 +276,31 @@ pub struct SnippetWasmCompiler<'a> {
     symbol_metadata_offset: Option<u32>,
     /// Symbol metadata JSON length in bytes
     symbol_metadata_len: Option<u32>,
+    /// WASM-resident runtime library (compute operations and built-in externs)
+    runtime_lib: RuntimeLibrary,
+    /// Specialized copies of generic functions, by snippet ID
+    specializations: HashMap<String, Specialization>,
+    /// Snippet ID the checker recorded step types under for the function being compiled
+    types_snippet: String,
+    /// Type arguments of the function being compiled, if it is a specialization
+    type_bindings: HashMap<String, ResolvedType>,
+    /// Resolved types of the parameters and step bindings of the function being compiled
+    binding_types: HashMap<String, ResolvedType>,
+    /// Set of function names/IDs that return a union and may fail with an error member
+    fallible_functions: std::collections::HashSet<String>,
+    /// Error members of the union returned by the function being compiled
+    current_errors: Vec<String>,
+    /// Whether the function being compiled has a WASM return value
+    current_has_result: bool,
+    /// Table slots of functions used as values, by function name and snippet ID
+    function_table: HashMap<String, u32>,
+    /// Function indices in table slot order
+    table_entries: Vec<u32>,
+    /// Type indices of function types called indirectly, by WASM signature
+    indirect_types: HashMap<(Vec<ValType>, Vec<ValType>), u32>,
+    /// Function-typed parameters of the function being compiled, and locals
+    /// bound from them: name → (type index, has result)
+    function_params: HashMap<String, (u32, bool)>,
 }
 
 /// Describes a registered extern-abstract import
@@ -277,25 +346,48 @@ impl<'a> SnippetWasmCompiler<'a> {
             imports: ImportTracker::new(),
             data_segment: DataSegmentBuilder::new(),
             struct_layouts: HashMap::new(),
+            enum_layouts: HashMap::new(),
             local_types: HashMap::new(),
             runtime: RuntimeFunctions::default(),
             extern_imports: HashMap::new(),
+            builtin_externs: HashMap::new(),
             gai_indices: None,
             graph_layout: None,
             void_functions: std::collections::HashSet::new(),
             symbol_metadata_offset: None,
             symbol_metadata_len: None,
+            runtime_lib: RuntimeLibrary::default(),
+            specializations: HashMap::new(),
+            types_snippet: String::new(),
+            type_bindings: HashMap::new(),
+            binding_types: HashMap::new(),
+            fallible_functions: std::collections::HashSet::new(),
+            current_errors: Vec::new(),
+            current_has_result: false,
+            function_table: HashMap::new(),
+            table_entries: Vec::new(),
+            indirect_types: HashMap::new(),
+            function_params: HashMap::new(),
         }
     }
 
+    /// Use the specializations made by monomorphization to find the checker's
+    /// types for specialized functions
+    pub(crate) fn with_specializations(mut self, specializations: HashMap<String, Specialization>) -> Self {
+        self.specializations = specializations;
+        self
+    }
+
     /// Compile snippets to WASM
     pub fn compile_snippets(&mut self, snippets: &[Snippet]) -> Result<Vec<u8>, CodegenError> {
         let mut module = Module::new();
 
-        // Register struct layouts from struct snippets
+        // Register struct and enum layouts from type snippets
         for snippet in snippets {
-            if snippet.kind == SnippetKind::Struct {
-                self.register_struct_layout(snippet);
+            match snippet.kind {
+                SnippetKind::Struct => self.register_struct_layout(snippet),
+                SnippetKind::Enum => self.register_enum_layout(snippet),
+                _ => {}
             }
         }
 
@@ -339,6 +431,15 @@ impl<'a> SnippetWasmCompiler<'a> {
         // Determine how many GAI functions we need
         let gai_count = if self.graph_layout.is_some() { GAI_FUNCTION_COUNT } else { 0 };
 
+        // Determine which runtime library functions the compute steps and built-in calls need
+        let mut runtime_roots = Vec::new();
+        for snippet in &functions {
+            if let Some(body) = find_body_section(snippet) {
+                self.collect_runtime_roots(&body.steps, &mut runtime_roots);
+            }
+        }
+        self.runtime_lib = RuntimeLibrary::link(runtime_roots);
+
         // Build type section (imports first, then user functions, then GAI functions)
         let mut types = TypeSection::new();
 
@@ -374,6 +475,15 @@ impl<'a> SnippetWasmCompiler<'a> {
             }
         }
 
+        // Add runtime library function types
+        let runtime_type_base = types.len();
+        for (params, results) in self.runtime_lib.function_types() {
+            types.function(params, results);
+        }
+
+        // Add types for calls through function-typed parameters
+        self.register_indirect_types(&functions, &mut types);
+
         module.section(&types);
 
         // Import section (if there are any imports)
@@ -402,6 +512,10 @@ impl<'a> SnippetWasmCompiler<'a> {
                     self.void_functions.insert(sig.name.clone());
                     self.void_functions.insert(snippet.id.clone());
                 }
+                if !union_error_types(sig).is_empty() {
+                    self.fallible_functions.insert(sig.name.clone());
+                    self.fallible_functions.insert(snippet.id.clone());
+                }
             }
         }
 
@@ -424,7 +538,10 @@ impl<'a> SnippetWasmCompiler<'a> {
             });
         }
 
-        // Function section (user functions + GAI functions)
+        // Runtime library function indices (after GAI functions)
+        self.runtime_lib.assign_indices(gai_base_idx + gai_count);
+
+        // Function section (user functions + GAI functions + runtime library)
         let mut func_section = FunctionSection::new();
         // User function type indices start after imports
         for i in 0..functions.len() {
@@ -435,8 +552,24 @@ impl<'a> SnippetWasmCompiler<'a> {
         for i in 0..gai_count {
             func_section.function(gai_type_base + i);
         }
+        for i in 0..self.runtime_lib.function_count() {
+            func_section.function(runtime_type_base + i);
+        }
         module.section(&func_section);
 
+        // Table of functions used as values, called with call_indirect
+        self.register_function_table(&functions);
+        if !self.table_entries.is_empty() {
+            let mut tables = TableSection::new();
+            let size = self.table_entries.len() as u32;
+            tables.table(TableType {
+                element_type: RefType::FUNCREF,
+                minimum: size,
+                maximum: Some(size),
+            });
+            module.section(&tables);
+        }
+
         // Memory section - always export memory when compiling functions or data
         let needs_memory = !functions.is_empty()
             || self.graph_layout.is_some()
@@ -458,6 +591,7 @@ impl<'a> SnippetWasmCompiler<'a> {
         // Pre-allocate strings used by traverse steps before calculating heap pointer.
         // This ensures the heap doesn't overlap with string data in the data segment.
         self.pre_allocate_step_strings(snippets);
+        self.runtime_lib.allocate_data(&mut self.data_segment);
 
         // Global section for heap pointer
         if needs_memory {
@@ -471,6 +605,14 @@ impl<'a> SnippetWasmCompiler<'a> {
                 },
                 &wasm_encoder::ConstExpr::i32_const(heap_start),
             );
+            // Error tag set by a function returning an error member of its union
+            globals.global(
+                GlobalType {
+                    val_type: ValType::I32,
+                    mutable: true,
+                },
+                &wasm_encoder::ConstExpr::i32_const(0),
+            );
             module.section(&globals);
         }
 
@@ -499,9 +641,21 @@ impl<'a> SnippetWasmCompiler<'a> {
         // Export memory if present
         if needs_memory {
             exports.export("memory", ExportKind::Memory, 0);
+            exports.export("cov_error_tag", ExportKind::Global, ERROR_TAG_GLOBAL);
         }
         module.section(&exports);
 
+        // Element section filling the function table
+        if !self.table_entries.is_empty() {
+            let mut elements = ElementSection::new();
+            elements.active(
+                None,
+                &wasm_encoder::ConstExpr::i32_const(0),
+                Elements::Functions(&self.table_entries),
+            );
+            module.section(&elements);
+        }
+
         // Code section (user functions + GAI functions)
         let mut codes = CodeSection::new();
         for snippet in &functions {
@@ -515,6 +669,10 @@ impl<'a> SnippetWasmCompiler<'a> {
                 codes.function(&gai_func);
             }
         }
+        // Add runtime library function bodies
+        for rt_func in self.runtime_lib.generate_functions() {
+            codes.function(&rt_func);
+        }
         module.section(&codes);
 
         // Data section (graph data + string constants, already combined in data_segment)
@@ -543,10 +701,12 @@ impl<'a> SnippetWasmCompiler<'a> {
     ) -> Result<Vec<u8>, CodegenError> {
         let mut module = Module::new();
 
-        // Register struct layouts from struct snippets
+        // Register struct and enum layouts from type snippets
         for snippet in snippets {
-            if snippet.kind == SnippetKind::Struct {
-                self.register_struct_layout(snippet);
+            match snippet.kind {
+                SnippetKind::Struct => self.register_struct_layout(snippet),
+                SnippetKind::Enum => self.register_enum_layout(snippet),
+                _ => {}
             }
         }
 
@@ -596,6 +756,15 @@ impl<'a> SnippetWasmCompiler<'a> {
         // Determine how many GAI functions we need
         let gai_count = if self.graph_layout.is_some() { GAI_FUNCTION_COUNT } else { 0 };
 
+        // Determine which runtime library functions the compute steps and built-in calls need
+        let mut runtime_roots = Vec::new();
+        for snippet in &functions {
+            if let Some(body) = find_body_section(snippet) {
+                self.collect_runtime_roots(&body.steps, &mut runtime_roots);
+            }
+        }
+        self.runtime_lib = RuntimeLibrary::link(runtime_roots);
+
         // Build type section (imports first, then user functions, then GAI functions, then symbol metadata)
         let mut types = TypeSection::new();
 
@@ -635,6 +804,15 @@ impl<'a> SnippetWasmCompiler<'a> {
         let symbol_metadata_type_idx = types.len();
         types.function(vec![], vec![ValType::I64]);
 
+        // Add runtime library function types
+        let runtime_type_base = types.len();
+        for (params, results) in self.runtime_lib.function_types() {
+            types.function(params, results);
+        }
+
+        // Add types for calls through function-typed parameters
+        self.register_indirect_types(&functions, &mut types);
+
         module.section(&types);
 
         // Import section (if there are any imports)
@@ -663,6 +841,10 @@ impl<'a> SnippetWasmCompiler<'a> {
                     self.void_functions.insert(sig.name.clone());
                     self.void_functions.insert(snippet.id.clone());
                 }
+                if !union_error_types(sig).is_empty() {
+                    self.fallible_functions.insert(sig.name.clone());
+                    self.fallible_functions.insert(snippet.id.clone());
+                }
             }
         }
 
@@ -688,6 +870,9 @@ impl<'a> SnippetWasmCompiler<'a> {
         // Symbol metadata function index (after GAI functions)
         let symbol_metadata_func_idx = gai_base_idx + gai_count;
 
+        // Runtime library function indices (after symbol metadata function)
+        self.runtime_lib.assign_indices(symbol_metadata_func_idx + 1);
+
         // Function section (user functions + GAI functions + symbol metadata function)
         let mut func_section = FunctionSection::new();
         // User function type indices start after imports
@@ -701,8 +886,24 @@ impl<'a> SnippetWasmCompiler<'a> {
         }
         // Symbol metadata function type
         func_section.function(symbol_metadata_type_idx);
+        for i in 0..self.runtime_lib.function_count() {
+            func_section.function(runtime_type_base + i);
+        }
         module.section(&func_section);
 
+        // Table of functions used as values, called with call_indirect
+        self.register_function_table(&functions);
+        if !self.table_entries.is_empty() {
+            let mut tables = TableSection::new();
+            let size = self.table_entries.len() as u32;
+            tables.table(TableType {
+                element_type: RefType::FUNCREF,
+                minimum: size,
+                maximum: Some(size),
+            });
+            module.section(&tables);
+        }
+
         // Memory section - always export memory when compiling functions or data
         let needs_memory = !functions.is_empty()
             || self.graph_layout.is_some()
@@ -724,6 +925,7 @@ impl<'a> SnippetWasmCompiler<'a> {
         // Pre-allocate strings used by traverse steps before calculating heap pointer.
         // This ensures the heap doesn't overlap with string data in the data segment.
         self.pre_allocate_step_strings(snippets);
+        self.runtime_lib.allocate_data(&mut self.data_segment);
 
         // Global section for heap pointer
         if needs_memory {
@@ -737,6 +939,14 @@ impl<'a> SnippetWasmCompiler<'a> {
                 },
                 &wasm_encoder::ConstExpr::i32_const(heap_start),
             );
+            // Error tag set by a function returning an error member of its union
+            globals.global(
+                GlobalType {
+                    val_type: ValType::I32,
+                    mutable: true,
+                },
+                &wasm_encoder::ConstExpr::i32_const(0),
+            );
             module.section(&globals);
         }
 
@@ -767,9 +977,21 @@ impl<'a> SnippetWasmCompiler<'a> {
         // Export memory if present
         if needs_memory {
             exports.export("memory", ExportKind::Memory, 0);
+            exports.export("cov_error_tag", ExportKind::Global, ERROR_TAG_GLOBAL);
         }
         module.section(&exports);
 
+        // Element section filling the function table
+        if !self.table_entries.is_empty() {
+            let mut elements = ElementSection::new();
+            elements.active(
+                None,
+                &wasm_encoder::ConstExpr::i32_const(0),
+                Elements::Functions(&self.table_entries),
+            );
+            module.section(&elements);
+        }
+
         // Code section (user functions + GAI functions + symbol metadata function)
         let mut codes = CodeSection::new();
         for snippet in &functions {
@@ -786,6 +1008,10 @@ impl<'a> SnippetWasmCompiler<'a> {
         // Add symbol metadata function body
         let symbol_metadata_func = self.gen_symbol_metadata_function();
         codes.function(&symbol_metadata_func);
+        // Add runtime library function bodies
+        for rt_func in self.runtime_lib.generate_functions() {
+            codes.function(&rt_func);
+        }
         module.section(&codes);
 
         // Data section (graph data + string constants + symbol JSON, already combined in data_segment)
@@ -854,7 +1080,54 @@ impl<'a> SnippetWasmCompiler<'a> {
                             _ => {}
                         }
                     }
+                    // Runtime library functions allocate from the heap, so their
+                    // literal inputs must not end up past the heap start
+                    self.pre_allocate_runtime_strings(&body.steps);
+                }
+            }
+        }
+    }
+
+    /// Recursively pre-allocate string literals passed to runtime library operations
+    /// and built-in extern calls
+    fn pre_allocate_runtime_strings(&mut self, steps: &[Step]) {
+        for step in steps {
+            match &step.kind {
+                StepKind::Compute(compute) => {
+                    if RtFunc::for_operation(compute.op).is_empty() {
+                        continue;
+                    }
+                    for input in &compute.inputs {
+                        if let InputSource::Lit(Literal::String(s)) = &input.source {
+                            self.data_segment.add_string(s);
+                        }
+                    }
+                    if compute.op == Operation::DtFormat && compute.inputs.len() < 2 {
+                        self.data_segment.add_string(runtime_lib::DEFAULT_DT_FORMAT);
+                    }
+                }
+                StepKind::Call(call) if self.builtin_externs.contains_key(&call.fn_name) => {
+                    for arg in &call.args {
+                        if let InputSource::Lit(Literal::String(s)) = &arg.source {
+                            self.data_segment.add_string(s);
+                        }
+                    }
+                }
+                StepKind::If(if_step) => {
+                    self.pre_allocate_runtime_strings(&if_step.then_steps);
+                    if let Some(else_steps) = &if_step.else_steps {
+                        self.pre_allocate_runtime_strings(else_steps);
+                    }
                 }
+                StepKind::Match(match_step) => {
+                    for case in &match_step.cases {
+                        self.pre_allocate_runtime_strings(&case.steps);
+                    }
+                }
+                StepKind::For(for_step) => {
+                    self.pre_allocate_runtime_strings(&for_step.steps);
+                }
+                _ => {}
             }
         }
     }
@@ -954,9 +1227,22 @@ impl<'a> SnippetWasmCompiler<'a> {
 
     /// Register a single extern-abstract snippet as a WASM import.
     /// Splits snippet ID on last dot to derive (module, function) for the import.
+    /// Pure extern-abstracts with a runtime library implementation get no import.
     fn register_single_extern(&mut self, snippet: &Snippet) {
         let id = &snippet.id;
 
+        if snippet.kind == SnippetKind::ExternAbstract {
+            if let Some(builtin) = BuiltinExtern::for_id(id) {
+                self.builtin_externs.insert(id.clone(), builtin);
+                if let Some(sig) = find_function_signature(snippet) {
+                    if sig.name != *id && !self.extern_imports.contains_key(&sig.name) {
+                        self.builtin_externs.entry(sig.name.clone()).or_insert(builtin);
+                    }
+                }
+                return;
+            }
+        }
+
         // Split ID on last dot: "text.concat" → ("text", "concat")
         // "std.text.regex_test" → ("std.text", "regex_test")
         // "to_uppercase" (no dot) → ("extern", "to_uppercase")
@@ -1004,7 +1290,7 @@ impl<'a> SnippetWasmCompiler<'a> {
         };
         self.extern_imports.insert(id.clone(), ext_import.clone());
         // Also register by function name (from signature) for short-name calls
-        if sig.name != *id {
+        if sig.name != *id && !self.builtin_externs.contains_key(&sig.name) {
             self.extern_imports.entry(sig.name.clone()).or_insert(ext_import);
         }
     }
@@ -1042,7 +1328,107 @@ impl<'a> SnippetWasmCompiler<'a> {
         }
     }
 
+    /// Register an enum snippet's layout for variant construction and matching
+    fn register_enum_layout(&mut self, snippet: &Snippet) {
+        for section in &snippet.sections {
+            if let Section::Signature(sig) = section {
+                if let SignatureKind::Enum(enum_sig) = &sig.kind {
+                    let boxed = enum_sig.variants.iter()
+                        .any(|v| v.fields.as_ref().is_some_and(|f| !f.is_empty()));
+                    let mut variants = HashMap::new();
+                    for (tag, variant) in enum_sig.variants.iter().enumerate() {
+                        let fields: Vec<VariantFieldLayout> = variant.fields.iter()
+                            .flatten()
+                            .enumerate()
+                            .map(|(i, field)| VariantFieldLayout {
+                                name: field.name.clone(),
+                                offset: 8 + (i as u32) * 8,
+                                type_name: match &field.ty.kind {
+                                    TypeKind::Named(path) => Some(path.name().to_string()),
+                                    _ => None,
+                                },
+                            })
+                            .collect();
+                        variants.insert(variant.name.clone(), VariantLayout {
+                            tag: tag as i64,
+                            size: 8 + (fields.len() as u32) * 8,
+                            fields,
+                        });
+                    }
+                    self.enum_layouts.insert(enum_sig.name.clone(), EnumLayout { boxed, variants });
+                }
+            }
+        }
+    }
+
+    /// Whether a type name has a struct or enum layout
+    fn has_layout(&self, type_name: &str) -> bool {
+        self.struct_layouts.contains_key(type_name) || self.enum_layouts.contains_key(type_name)
+    }
+
+    /// Look up the layout of a variant named `Enum::Variant`
+    fn variant_layout(&self, variant: &str) -> Option<(&EnumLayout, &VariantLayout)> {
+        let (enum_name, variant_name) = variant.split_once("::")?;
+        let layout = self.enum_layouts.get(enum_name)?;
+        Some((layout, layout.variants.get(variant_name)?))
+    }
+
     /// Compile a single function snippet
+    /// Add a type for each distinct function-typed parameter signature, for
+    /// use by call_indirect
+    fn register_indirect_types(&mut self, functions: &[&Snippet], types: &mut TypeSection) {
+        for snippet in functions {
+            let Some(sig) = find_function_signature(snippet) else { continue };
+            for param in &sig.params {
+                let TypeKind::Function { params, ret } = &param.ty.kind else { continue };
+                let wasm_params: Vec<ValType> = params.iter()
+                    .filter_map(|t| self.type_to_valtype(t))
+                    .collect();
+                let wasm_results: Vec<ValType> = self.type_to_valtype(ret).into_iter().collect();
+                let key = (wasm_params, wasm_results);
+                if !self.indirect_types.contains_key(&key) {
+                    self.indirect_types.insert(key.clone(), types.len());
+                    types.function(key.0, key.1);
+                }
+            }
+        }
+    }
+
+    /// Type index and result presence of a call through a value of this type
+    fn indirect_call_type(&self, ty: &Type) -> Option<(u32, bool)> {
+        let TypeKind::Function { params, ret } = &ty.kind else { return None };
+        let wasm_params: Vec<ValType> = params.iter()
+            .filter_map(|t| self.type_to_valtype(t))
+            .collect();
+        let wasm_results: Vec<ValType> = self.type_to_valtype(ret).into_iter().collect();
+        let has_result = !wasm_results.is_empty();
+        self.indirect_types.get(&(wasm_params, wasm_results)).map(|&index| (index, has_result))
+    }
+
+    /// Assign a table slot to every user function passed as a value
+    fn register_function_table(&mut self, functions: &[&Snippet]) {
+        let mut refs = Vec::new();
+        for snippet in functions {
+            if let Some(body) = find_body_section(snippet) {
+                collect_function_refs(&body.steps, &mut refs);
+            }
+        }
+        for name in refs {
+            if self.function_table.contains_key(&name) {
+                continue;
+            }
+            let Some(&func_index) = self.function_indices.get(&name) else { continue };
+            let slot = match self.table_entries.iter().position(|&f| f == func_index) {
+                Some(slot) => slot as u32,
+                None => {
+                    self.table_entries.push(func_index);
+                    self.table_entries.len() as u32 - 1
+                }
+            };
+            self.function_table.insert(name, slot);
+        }
+    }
+
     fn compile_function_snippet(&mut self, snippet: &Snippet) -> Result<Function, CodegenError> {
         let sig = find_function_signature(snippet)
             .ok_or_else(|| CodegenError::UndefinedFunction {
@@ -1055,15 +1441,35 @@ impl<'a> SnippetWasmCompiler<'a> {
         self.locals.clear();
         self.local_count = 0;
         self.local_types.clear();
+        self.function_params.clear();
+        self.binding_types.clear();
+        match self.specializations.get(&snippet.id) {
+            Some(specialization) => {
+                self.types_snippet = specialization.template.clone();
+                self.type_bindings = specialization.bindings.clone();
+            }
+            None => {
+                self.types_snippet = snippet.id.clone();
+                self.type_bindings.clear();
+            }
+        }
+        if let Some(Symbol { kind: SymbolKind::Function { params, .. }, .. }) = self.symbols.lookup(&self.types_snippet) {
+            for (name, ty) in params {
+                self.binding_types.insert(name.clone(), ty.substitute(&self.type_bindings));
+            }
+        }
 
         // Add parameters as locals and track their struct types
         for param in &sig.params {
             self.locals.insert(param.name.clone(), self.local_count);
             self.local_count += 1;
-            // If the parameter type is a known struct, register it in local_types
+            if let Some(indirect) = self.indirect_call_type(&param.ty) {
+                self.function_params.insert(param.name.clone(), indirect);
+            }
+            // If the parameter type is a known struct or enum, register it in local_types
             if let TypeKind::Named(path) = &param.ty.kind {
                 let type_name = path.name().to_string();
-                if self.struct_layouts.contains_key(&type_name) {
+                if self.has_layout(&type_name) {
                     self.local_types.insert(param.name.clone(), type_name);
                 }
             }
@@ -1078,6 +1484,16 @@ impl<'a> SnippetWasmCompiler<'a> {
 
         let mut wasm_func = Function::new(vec![(additional_locals, ValType::I64)]);
 
+        // A fallible function starts out successful; error returns set the tag
+        self.current_errors = union_error_types(sig);
+        self.current_has_result = sig.returns.as_ref()
+            .and_then(|r| self.return_type_to_valtype(r))
+            .is_some();
+        if !self.current_errors.is_empty() {
+            wasm_func.instruction(&Instruction::I32Const(0));
+            wasm_func.instruction(&Instruction::GlobalSet(ERROR_TAG_GLOBAL));
+        }
+
         // Compile body steps
         if let Some(body) = body {
             for step in &body.steps {
@@ -1088,10 +1504,7 @@ impl<'a> SnippetWasmCompiler<'a> {
         // If function returns a value (non-Unit), we need something on the stack for the
         // implicit return. Push a dummy value (0) in case all paths returned early via
         // explicit returns. Unit-returning functions have no WASM return value.
-        let has_wasm_return = sig.returns.as_ref()
-            .and_then(|r| self.return_type_to_valtype(r))
-            .is_some();
-        if has_wasm_return {
+        if self.current_has_result {
             wasm_func.instruction(&Instruction::I64Const(0));
         }
 
@@ -1132,16 +1545,22 @@ impl<'a> SnippetWasmCompiler<'a> {
                     count += self.count_step_bindings(&for_step.steps);
                 }
                 StepKind::Call(call) => {
-                    // Extern calls need a temp local per argument for fat pointer unpacking
-                    count += call.args.len() as u32;
+                    // Extern calls need a temp local per argument for fat pointer unpacking,
+                    // plus one to hold a discarded result while dispatching on its error tag
+                    count += call.args.len() as u32 + 1;
+                    if let Some(handle) = &call.handle {
+                        for case in &handle.cases {
+                            count += self.count_step_bindings(&case.steps);
+                        }
+                    }
                 }
                 StepKind::Construct(_) => {
                     // Struct construction needs a temp local for the pointer
                     count += 1;
                 }
                 StepKind::Return(ret) => {
-                    // Return with struct construction needs a temp local for the pointer
-                    if matches!(&ret.value, ReturnValue::Struct(_)) {
+                    // Return with struct or variant construction needs a temp local for the pointer
+                    if matches!(&ret.value, ReturnValue::Struct(_) | ReturnValue::Variant(_)) {
                         count += 1;
                     }
                 }
@@ -1179,6 +1598,11 @@ impl<'a> SnippetWasmCompiler<'a> {
 
     /// Compile a single step
     fn compile_step(&mut self, step: &Step, func: &mut Function) -> Result<(), CodegenError> {
+        if step.output_binding != "_" {
+            if let Some(ty) = self.step_type(step) {
+                self.binding_types.insert(step.output_binding.clone(), ty);
+            }
+        }
         match &step.kind {
             StepKind::Compute(compute) => {
                 self.compile_compute_step(compute, func)?;
@@ -1193,12 +1617,25 @@ impl<'a> SnippetWasmCompiler<'a> {
             StepKind::Call(call) => {
                 self.compile_call_step(call, func)?;
                 let has_return = self.call_has_return_value(&call.fn_name);
+                let fallible = self.fallible_functions.contains(&call.fn_name);
                 if step.output_binding != "_" && has_return {
                     let local = self.allocate_local(&step.output_binding);
                     func.instruction(&Instruction::LocalSet(local));
+                    if fallible {
+                        self.compile_error_dispatch(call, Some(local), func)?;
+                    }
                 } else if step.output_binding == "_" && has_return {
-                    // Function returns a value but result is discarded - pop it
-                    func.instruction(&Instruction::Drop);
+                    if fallible {
+                        // Keep the discarded result in case an error must be propagated
+                        let local = self.allocate_local("__call_result");
+                        func.instruction(&Instruction::LocalSet(local));
+                        self.compile_error_dispatch(call, Some(local), func)?;
+                    } else {
+                        // Function returns a value but result is discarded - pop it
+                        func.instruction(&Instruction::Drop);
+                    }
+                } else if fallible {
+                    self.compile_error_dispatch(call, None, func)?;
                 }
             }
             StepKind::Return(ret) => {
@@ -1206,9 +1643,14 @@ impl<'a> SnippetWasmCompiler<'a> {
                 func.instruction(&Instruction::Return);
             }
             StepKind::If(if_step) => {
-                self.compile_if_step(if_step, func)?;
+                self.compile_if_step(if_step, &step.output_binding, func)?;
             }
             StepKind::Bind(bind) => {
+                if let BindSource::Var(name) = &bind.source {
+                    if let Some(&indirect) = self.function_params.get(name) {
+                        self.function_params.insert(step.output_binding.clone(), indirect);
+                    }
+                }
                 self.compile_bind_step(bind, func)?;
                 // Store result if not discarded
                 if step.output_binding != "_" {
@@ -1240,14 +1682,22 @@ impl<'a> SnippetWasmCompiler<'a> {
                     TypeKind::Named(path) => path.name().to_string(),
                     _ => format!("{:?}", construct.ty.kind),
                 };
-                if !self.struct_layouts.contains_key(&type_name) {
-                    let layout = Self::compute_struct_layout(construct);
-                    self.struct_layouts.insert(type_name.clone(), layout);
-                }
-                if step.output_binding != "_" {
-                    self.local_types.insert(step.output_binding.clone(), type_name);
+                if self.variant_layout(&type_name).is_some() {
+                    let (enum_name, _) = type_name.split_once("::").unwrap_or_default();
+                    if step.output_binding != "_" {
+                        self.local_types.insert(step.output_binding.clone(), enum_name.to_string());
+                    }
+                    self.compile_variant_construction(&type_name, &construct.fields, func)?;
+                } else {
+                    if !self.struct_layouts.contains_key(&type_name) {
+                        let layout = Self::compute_struct_layout(construct);
+                        self.struct_layouts.insert(type_name.clone(), layout);
+                    }
+                    if step.output_binding != "_" {
+                        self.local_types.insert(step.output_binding.clone(), type_name);
+                    }
+                    self.compile_construct_step(construct, func)?;
                 }
-                self.compile_construct_step(construct, func)?;
                 // Store result if not discarded
                 if step.output_binding != "_" {
                     let local = self.allocate_local(&step.output_binding);
@@ -1344,32 +1794,81 @@ impl<'a> SnippetWasmCompiler<'a> {
             return Ok(());
         }
 
+        // Variants named without their enum (`Circle`) belong to the matched value's enum
+        let matched_enum = self.local_types.get(&match_step.on)
+            .filter(|t| self.enum_layouts.contains_key(*t))
+            .cloned();
+
         // Compile each case as an if-else chain
         for (i, case) in match_step.cases.iter().enumerate() {
             match &case.pattern {
-                MatchPattern::Variant { variant: _, bindings } => {
-                    // Load the value (or its tag)
-                    func.instruction(&Instruction::LocalGet(match_local));
-
-                    // Use ordinal-based matching: case index maps to expected tag value
-                    // This assumes enums use 0, 1, 2, ... for variant tags
-                    let tag_value = i as i64;
-                    func.instruction(&Instruction::I64Const(tag_value));
-                    func.instruction(&Instruction::I64Eq);
-                    func.instruction(&Instruction::If(BlockType::Empty));
-
-                    // Set up bindings for destructured values
-                    // For now, we assume single binding gets the value
-                    if !bindings.is_empty() {
+                MatchPattern::Variant { variant, bindings } => {
+                    let qualified = match (&matched_enum, variant.contains("::")) {
+                        (Some(enum_name), false) => format!("{}::{}", enum_name, variant),
+                        _ => variant.clone(),
+                    };
+                    if let Some((layout, variant_layout)) = self.variant_layout(&qualified) {
+                        // Compare the value's tag with the variant's
+                        let (boxed, variant_layout) = (layout.boxed, variant_layout.clone());
+                        Self::compile_variant_tag(match_local, boxed, func);
+                        func.instruction(&Instruction::I64Const(variant_layout.tag));
+                        func.instruction(&Instruction::I64Eq);
+                        func.instruction(&Instruction::If(BlockType::Empty));
+
+                        // Destructure the fields into the bindings, by position
+                        for (binding, field) in bindings.iter().zip(&variant_layout.fields) {
+                            func.instruction(&Instruction::LocalGet(match_local));
+                            func.instruction(&Instruction::I32WrapI64);
+                            func.instruction(&Instruction::I64Load(MemArg {
+                                offset: field.offset as u64,
+                                align: 3,
+                                memory_index: 0,
+                            }));
+                            let binding_local = self.allocate_local(binding);
+                            func.instruction(&Instruction::LocalSet(binding_local));
+                            match field.type_name.as_ref().filter(|t| self.has_layout(t)) {
+                                Some(type_name) => {
+                                    self.local_types.insert(binding.clone(), type_name.clone());
+                                }
+                                None => {
+                                    self.local_types.remove(binding);
+                                }
+                            }
+                        }
+                    } else if variant.eq_ignore_ascii_case("some") || variant.eq_ignore_ascii_case("none") {
+                        // Optionals: None is the sentinel, Some binds the value itself
+                        func.instruction(&Instruction::LocalGet(match_local));
+                        func.instruction(&Instruction::I64Const(runtime_lib::NONE_SENTINEL));
+                        if variant.eq_ignore_ascii_case("some") {
+                            func.instruction(&Instruction::I64Ne);
+                        } else {
+                            func.instruction(&Instruction::I64Eq);
+                        }
+                        func.instruction(&Instruction::If(BlockType::Empty));
+                        if let Some(binding) = bindings.first() {
+                            func.instruction(&Instruction::LocalGet(match_local));
+                            let binding_local = self.allocate_local(binding);
+                            func.instruction(&Instruction::LocalSet(binding_local));
+                        }
+                    } else {
+                        // Unknown type: fall back to ordinal matching on the case index,
+                        // binding the value itself
                         func.instruction(&Instruction::LocalGet(match_local));
-                        let binding_local = self.allocate_local(&bindings[0]);
-                        func.instruction(&Instruction::LocalSet(binding_local));
+                        func.instruction(&Instruction::I64Const(i as i64));
+                        func.instruction(&Instruction::I64Eq);
+                        func.instruction(&Instruction::If(BlockType::Empty));
+                        if let Some(binding) = bindings.first() {
+                            func.instruction(&Instruction::LocalGet(match_local));
+                            let binding_local = self.allocate_local(binding);
+                            func.instruction(&Instruction::LocalSet(binding_local));
+                        }
                     }
 
                     // Compile case body
                     for step in &case.steps {
                         self.compile_step(step, func)?;
                     }
+                    self.compile_branch_result(&case.steps, output_binding, func);
 
                     // Add else if there are more cases
                     if i < num_cases - 1 {
@@ -1382,6 +1881,7 @@ impl<'a> SnippetWasmCompiler<'a> {
                     for step in &case.steps {
                         self.compile_step(step, func)?;
                     }
+                    self.compile_branch_result(&case.steps, output_binding, func);
                 }
             }
         }
@@ -1397,15 +1897,30 @@ impl<'a> SnippetWasmCompiler<'a> {
             }
         }
 
-        // Store result if needed
-        if output_binding != "_" {
-            // Match results would need additional infrastructure to collect
-            // For now, we leave this as a TODO for full implementation
-        }
-
         Ok(())
     }
 
+    /// Copy the value bound by the last step of a branch into the output
+    /// binding of the `if` or `match` step owning the branch. Branches ending
+    /// without a binding (e.g. in a return) leave it unset.
+    fn compile_branch_result(&mut self, steps: &[Step], output_binding: &str, func: &mut Function) {
+        if output_binding == "_" {
+            return;
+        }
+        let Some(last) = steps.last().filter(|s| s.output_binding != "_") else {
+            return;
+        };
+        let Some(&value_local) = self.locals.get(&last.output_binding) else {
+            return;
+        };
+        let result_local = self.allocate_local(output_binding);
+        func.instruction(&Instruction::LocalGet(value_local));
+        func.instruction(&Instruction::LocalSet(result_local));
+        if let Some(type_name) = self.local_types.get(&last.output_binding).cloned() {
+            self.local_types.insert(output_binding.to_string(), type_name);
+        }
+    }
+
     /// Compile a for loop step
     ///
     /// For loops iterate over collections. We compile to a WASM loop with
@@ -1570,6 +2085,110 @@ impl<'a> SnippetWasmCompiler<'a> {
         Ok(())
     }
 
+    /// Compile the construction of an enum variant (`Enum::Variant`) or of a
+    /// struct member of a union, leaving the value on the stack.
+    ///
+    /// Boxed variants are heap-allocated like structs, with the tag at offset 0
+    /// and every declared field after it; fields left out of the construction
+    /// (optional ones) hold None. Variants of fieldless enums are bare tags.
+    fn compile_variant_construction(
+        &mut self,
+        ty: &str,
+        fields: &[FieldAssignment],
+        func: &mut Function,
+    ) -> Result<(), CodegenError> {
+        let (boxed, variant) = match self.variant_layout(ty) {
+            Some((layout, variant)) => (layout.boxed, variant.clone()),
+            None => {
+                // A struct type: store the fields in declaration order if known
+                let layout = self.struct_layouts.get(ty);
+                let mut ordered: Vec<(u32, &FieldAssignment)> = fields.iter()
+                    .enumerate()
+                    .map(|(i, field)| {
+                        let offset = layout
+                            .and_then(|l| l.fields.get(&field.name))
+                            .map(|f| f.offset)
+                            .unwrap_or((i as u32) * 8);
+                        (offset, field)
+                    })
+                    .collect();
+                ordered.sort_by_key(|(offset, _)| *offset);
+                let size = layout.map(|l| l.size).unwrap_or((fields.len() as u32) * 8);
+                let ptr_local = self.compile_heap_alloc(size, func);
+                for (offset, field) in ordered {
+                    func.instruction(&Instruction::LocalGet(ptr_local));
+                    func.instruction(&Instruction::I32WrapI64);
+                    self.compile_input(&field.value, func)?;
+                    func.instruction(&Instruction::I64Store(MemArg {
+                        offset: offset as u64,
+                        align: 3,
+                        memory_index: 0,
+                    }));
+                }
+                func.instruction(&Instruction::LocalGet(ptr_local));
+                return Ok(());
+            }
+        };
+
+        if !boxed {
+            func.instruction(&Instruction::I64Const(variant.tag));
+            return Ok(());
+        }
+
+        let ptr_local = self.compile_heap_alloc(variant.size, func);
+        func.instruction(&Instruction::LocalGet(ptr_local));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::I64Const(variant.tag));
+        func.instruction(&Instruction::I64Store(MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+        for field in &variant.fields {
+            func.instruction(&Instruction::LocalGet(ptr_local));
+            func.instruction(&Instruction::I32WrapI64);
+            match fields.iter().find(|f| f.name == field.name) {
+                Some(assignment) => self.compile_input(&assignment.value, func)?,
+                None => {
+                    func.instruction(&Instruction::I64Const(runtime_lib::NONE_SENTINEL));
+                }
+            }
+            func.instruction(&Instruction::I64Store(MemArg {
+                offset: field.offset as u64,
+                align: 3,
+                memory_index: 0,
+            }));
+        }
+        func.instruction(&Instruction::LocalGet(ptr_local));
+        Ok(())
+    }
+
+    /// Bump-allocate `size` bytes, returning the local holding the pointer as i64
+    fn compile_heap_alloc(&mut self, size: u32, func: &mut Function) -> u32 {
+        let ptr_local = self.allocate_local("__variant_ptr");
+        func.instruction(&Instruction::GlobalGet(0));
+        func.instruction(&Instruction::I64ExtendI32U);
+        func.instruction(&Instruction::LocalTee(ptr_local));
+        func.instruction(&Instruction::I32WrapI64);
+        func.instruction(&Instruction::I32Const(size as i32));
+        func.instruction(&Instruction::I32Add);
+        func.instruction(&Instruction::GlobalSet(0));
+        ptr_local
+    }
+
+    /// Push the tag of the enum value held in `local`
+    fn compile_variant_tag(local: u32, boxed: bool, func: &mut Function) {
+        func.instruction(&Instruction::LocalGet(local));
+        if boxed {
+            func.instruction(&Instruction::I32WrapI64);
+            func.instruction(&Instruction::I64Load(MemArg {
+                offset: 0,
+                align: 3,
+                memory_index: 0,
+            }));
+        }
+    }
+
 
     /// Compile a query step
     ///
@@ -2997,6 +3616,9 @@ impl<'a> SnippetWasmCompiler<'a> {
             self.compile_input(&input.source, func)?;
         }
 
+        // Map keys and set elements are compared by content when strings are involved
+        let by_content = self.compute_compares_strings(compute) as i32;
+
         // Emit operation instruction
         // Note: Comparison operations return i32, we extend to i64 for uniform storage
         match compute.op {
@@ -3154,6 +3776,128 @@ impl<'a> SnippetWasmCompiler<'a> {
                 func.instruction(&Instruction::End);
             }
 
+            // Map and set operations
+            Operation::MapLen | Operation::SetLen => {
+                func.instruction(&Instruction::Call(self.runtime_lib.index(RtFunc::Count)?));
+                func.instruction(&Instruction::I64ExtendI32U);
+            }
+            Operation::MapIsEmpty | Operation::SetIsEmpty => {
+                func.instruction(&Instruction::Call(self.runtime_lib.index(RtFunc::Count)?));
+                func.instruction(&Instruction::I32Eqz);
+                func.instruction(&Instruction::I64ExtendI32U);
+            }
+            Operation::MapHas | Operation::SetHas => {
+                let stride = if compute.op == Operation::MapHas {
+                    runtime_lib::MAP_STRIDE
+                } else {
+                    runtime_lib::SET_STRIDE
+                };
+                func.instruction(&Instruction::I32Const(stride));
+                func.instruction(&Instruction::I32Const(by_content));
+                func.instruction(&Instruction::Call(self.runtime_lib.index(RtFunc::Find)?));
+                func.instruction(&Instruction::I32Const(0));
+                func.instruction(&Instruction::I32GeS);
+                func.instruction(&Instruction::I64ExtendI32U);
+            }
+            Operation::MapRemove | Operation::SetRemove => {
+                let stride = if compute.op == Operation::MapRemove {
+                    runtime_lib::MAP_STRIDE
+                } else {
+                    runtime_lib::SET_STRIDE
+                };
+                func.instruction(&Instruction::I32Const(stride));
+                func.instruction(&Instruction::I32Const(by_content));
+                func.instruction(&Instruction::Call(self.runtime_lib.index(RtFunc::Remove)?));
+            }
+            Operation::MapKeys | Operation::MapValues | Operation::MapEntries => {
+                let which = match compute.op {
+                    Operation::MapKeys => MapProjection::Keys,
+                    Operation::MapValues => MapProjection::Values,
+                    _ => MapProjection::Entries,
+                };
+                func.instruction(&Instruction::I32Const(which as i32));
+                func.instruction(&Instruction::Call(self.runtime_lib.index(RtFunc::MapProject)?));
+            }
+            Operation::SetIntersect | Operation::SetDiff => {
+                // Keep the items of the first set that are (intersect) or are not (diff) in the second
+                let keep_present = (compute.op == Operation::SetIntersect) as i32;
+                func.instruction(&Instruction::I32Const(keep_present));
+                func.instruction(&Instruction::I32Const(by_content));
+                func.instruction(&Instruction::Call(self.runtime_lib.index(RtFunc::SetFilter)?));
+            }
+            Operation::MapInsert | Operation::MapMerge | Operation::SetAdd | Operation::SetUnion
+            | Operation::SetSymmetricDiff | Operation::SetIsSubset | Operation::SetIsSuperset => {
+                let rt = RtFunc::for_operation(compute.op)[0];
+                func.instruction(&Instruction::I32Const(by_content));
+                func.instruction(&Instruction::Call(self.runtime_lib.index(rt)?));
+            }
+            Operation::SetToList => {
+                // Sets share the list layout, so the value is already a list
+            }
+
+            // DateTime operations (Unix seconds, UTC)
+            Operation::DtYear | Operation::DtMonth | Operation::DtDay | Operation::DtHour
+            | Operation::DtMinute | Operation::DtSecond | Operation::DtWeekday => {
+                let field = match compute.op {
+                    Operation::DtYear => DtField::Year,
+                    Operation::DtMonth => DtField::Month,
+                    Operation::DtDay => DtField::Day,
+                    Operation::DtHour => DtField::Hour,
+                    Operation::DtMinute => DtField::Minute,
+                    Operation::DtSecond => DtField::Second,
+                    _ => DtField::Weekday,
+                };
+                func.instruction(&Instruction::I32Const(field as i32));
+                func.instruction(&Instruction::Call(self.runtime_lib.index(RtFunc::DtField)?));
+            }
+            Operation::DtUnix => {
+                // DateTime is already represented as Unix seconds
+            }
+            Operation::DtAddDays | Operation::DtAddHours | Operation::DtAddMinutes | Operation::DtAddSeconds => {
+                let seconds = match compute.op {
+                    Operation::DtAddDays => 86_400,
+                    Operation::DtAddHours => 3_600,
+                    Operation::DtAddMinutes => 60,
+                    _ => 1,
+                };
+                func.instruction(&Instruction::I64Const(seconds));
+                func.instruction(&Instruction::I64Mul);
+                func.instruction(&Instruction::I64Add);
+            }
+            Operation::DtDiff => {
+                func.instruction(&Instruction::I64Sub);
+            }
+            Operation::DtFormat => {
+                if compute.inputs.len() < 2 {
+                    self.compile_literal(&Literal::String(runtime_lib::DEFAULT_DT_FORMAT.to_string()), func)?;
+                }
+                func.instruction(&Instruction::Call(self.runtime_lib.index(RtFunc::DtFormat)?));
+            }
+
+            // Bytes operations (fat pointers, same as String)
+            Operation::BytesLen => {
+                func.instruction(&Instruction::I64Const(0xFFFF_FFFF));
+                func.instruction(&Instruction::I64And);
+            }
+            Operation::BytesIsEmpty => {
+                func.instruction(&Instruction::I64Const(0xFFFF_FFFF));
+                func.instruction(&Instruction::I64And);
+                func.instruction(&Instruction::I64Eqz);
+                func.instruction(&Instruction::I64ExtendI32U);
+            }
+            Operation::BytesSlice => {
+                if compute.inputs.len() < 3 {
+                    // Slice to the end
+                    func.instruction(&Instruction::I64Const(i64::MAX));
+                }
+                func.instruction(&Instruction::Call(self.runtime_lib.index(RtFunc::BytesSlice)?));
+            }
+            Operation::BytesGet | Operation::BytesConcat | Operation::BytesToString
+            | Operation::BytesToBase64 | Operation::BytesToHex => {
+                let rt = RtFunc::for_operation(compute.op)[0];
+                func.instruction(&Instruction::Call(self.runtime_lib.index(rt)?));
+            }
+
             // All other operations are not yet supported in WASM codegen
             _ => {
                 return Err(CodegenError::UnsupportedExpression);
@@ -3163,6 +3907,78 @@ impl<'a> SnippetWasmCompiler<'a> {
         Ok(())
     }
 
+    /// Collect the runtime library functions used by compute steps and
+    /// built-in extern calls (including nested steps)
+    fn collect_runtime_roots(&self, steps: &[Step], roots: &mut Vec<RtFunc>) {
+        walk_steps(steps, &mut |step| match &step.kind {
+            StepKind::Compute(compute) => roots.extend_from_slice(RtFunc::for_operation(compute.op)),
+            StepKind::Call(call) if !self.extern_imports.contains_key(&call.fn_name) => {
+                if let Some(builtin) = self.builtin_externs.get(&call.fn_name) {
+                    roots.push(builtin.func);
+                }
+            }
+            _ => {}
+        });
+    }
+
+    /// Check if a compute step operates on string keys or elements
+    fn compute_compares_strings(&self, compute: &ComputeStep) -> bool {
+        let key_is_string = matches!(
+            compute.op,
+            Operation::MapHas | Operation::MapInsert | Operation::MapRemove
+                | Operation::SetHas | Operation::SetAdd | Operation::SetRemove
+        ) && compute.inputs.get(1).is_some_and(|key| {
+            matches!(self.input_type(&key.source), Some(ResolvedType::String))
+        });
+        key_is_string
+            || compute.inputs.first().is_some_and(|collection| self.input_is_string_keyed(&collection.source))
+    }
+
+    /// Resolved type of the value bound by `step`, as recorded by the checker
+    fn step_type(&self, step: &Step) -> Option<ResolvedType> {
+        let ty = self.symbols.step_type(&self.types_snippet, &step.id)?.substitute(&self.type_bindings);
+        match &step.kind {
+            StepKind::Call(call) if mentions_any(&ty) => self.list_builtin_result(call).or(Some(ty)),
+            _ => Some(ty),
+        }
+    }
+
+    /// The std list externs are typed with `Any`; recover the type of their
+    /// result from the type of the list argument
+    fn list_builtin_result(&self, call: &CallStep) -> Option<ResolvedType> {
+        let builtin = self.builtin_externs.get(&call.fn_name)?;
+        let list = self.input_type(&self.ordered_args(call).first()?.source)?;
+        match (builtin.func, list) {
+            (RtFunc::ListGet | RtFunc::ListFirst | RtFunc::ListLast, ResolvedType::List(item)) => Some(*item),
+            (RtFunc::ListFlatten, ResolvedType::List(inner)) => Some(*inner),
+            (RtFunc::ListAppend, list @ ResolvedType::List(_)) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Resolved type of an input, if the checker knows it
+    fn input_type(&self, source: &InputSource) -> Option<ResolvedType> {
+        match source {
+            InputSource::Lit(lit) => match lit {
+                Literal::String(_) => Some(ResolvedType::String),
+                _ => None,
+            },
+            InputSource::Var(name) => self.binding_types.get(name).cloned(),
+            InputSource::Field { of, field } => {
+                let fields = match self.binding_types.get(of)? {
+                    ResolvedType::Struct(fields) => fields,
+                    ResolvedType::Named { name, .. } => match self.symbols.lookup(name) {
+                        Some(Symbol { kind: SymbolKind::Type, ty: ResolvedType::Struct(fields), .. }) => fields,
+                        _ => return None,
+                    },
+                    _ => return None,
+                };
+                fields.iter().find(|(name, _)| name == field).map(|(_, ty)| ty.clone())
+            }
+            InputSource::FnRef(_) => None,
+        }
+    }
+
     /// Compile a call step
     fn compile_call_step(&mut self, call: &CallStep, func: &mut Function) -> Result<(), CodegenError> {
         // Check for runtime/builtin functions first
@@ -3171,9 +3987,21 @@ impl<'a> SnippetWasmCompiler<'a> {
             return Ok(());
         }
 
+        // Call through a function-typed parameter: arguments by position,
+        // then the table slot
+        if let Some(&(type_index, _)) = self.function_params.get(&call.fn_name) {
+            for arg in &call.args {
+                self.compile_input(&arg.source, func)?;
+            }
+            func.instruction(&Instruction::LocalGet(self.locals[&call.fn_name]));
+            func.instruction(&Instruction::I32WrapI64);
+            func.instruction(&Instruction::CallIndirect { ty: type_index, table: 0 });
+            return Ok(());
+        }
+
         // Regular user-defined function call
         // Push arguments onto stack
-        for arg in &call.args {
+        for arg in self.ordered_args(call) {
             self.compile_input(&arg.source, func)?;
         }
 
@@ -3185,17 +4013,28 @@ impl<'a> SnippetWasmCompiler<'a> {
         Ok(())
     }
 
+    /// Arguments of a call in the callee's parameter order. The checker
+    /// matches arguments by name, so a call site may list them in any order.
+    fn ordered_args<'c>(&self, call: &'c CallStep) -> Vec<&'c CallArg> {
+        let mut args: Vec<&CallArg> = call.args.iter().collect();
+        if let Some(Symbol { kind: SymbolKind::Function { params, .. }, .. }) = self.symbols.lookup(&call.fn_name) {
+            args.sort_by_key(|arg| params.iter().position(|(name, _)| *name == arg.name));
+        }
+        args
+    }
+
     /// Try to compile a call to an extern-abstract function.
-    /// Returns Some(import_index) if this is a registered extern function, None otherwise.
+    /// Returns Some(function_index) if this is a registered extern function
+    /// (host import or built-in), None otherwise.
     fn try_compile_runtime_call(&mut self, call: &CallStep, func: &mut Function) -> Result<Option<u32>, CodegenError> {
         // Look up the function name in registered extern imports
         let ext = match self.extern_imports.get(&call.fn_name) {
             Some(ext) => ext.clone(),
-            None => return Ok(None),
+            None => return self.try_compile_builtin_call(call, func),
         };
 
         // Compile each argument and unpack according to its type
-        for (i, arg) in call.args.iter().enumerate() {
+        for (i, arg) in self.ordered_args(call).into_iter().enumerate() {
             self.compile_input(&arg.source, func)?;
 
             let param_kind = ext.param_types.get(i).copied().unwrap_or(ExternParamKind::FatPointer);
@@ -3223,6 +4062,33 @@ impl<'a> SnippetWasmCompiler<'a> {
         Ok(Some(ext.func_index))
     }
 
+    /// Try to compile a call to a built-in extern-abstract.
+    /// Arguments stay in the native i64 representation.
+    fn try_compile_builtin_call(&mut self, call: &CallStep, func: &mut Function) -> Result<Option<u32>, CodegenError> {
+        let builtin = match self.builtin_externs.get(&call.fn_name) {
+            Some(builtin) => *builtin,
+            None => return Ok(None),
+        };
+
+        for arg in self.ordered_args(call) {
+            self.compile_input(&arg.source, func)?;
+        }
+        if let Some(selector) = builtin.selector {
+            func.instruction(&Instruction::I32Const(selector));
+        }
+        if builtin.func == RtFunc::ListContains {
+            let by_content = call.args.iter().any(|arg| self.input_is_string_keyed(&arg.source));
+            func.instruction(&Instruction::I32Const(by_content as i32));
+        }
+
+        Ok(Some(self.runtime_lib.index(builtin.func)?))
+    }
+
+    /// Check if an input is a string or holds string keys/items
+    fn input_is_string_keyed(&self, source: &InputSource) -> bool {
+        self.input_type(source).is_some_and(|ty| is_string_keyed(&ty))
+    }
+
     /// Compile a return step
     fn compile_return_step(&mut self, ret: &ReturnStep, func: &mut Function) -> Result<(), CodegenError> {
         match &ret.value {
@@ -3265,18 +4131,93 @@ impl<'a> SnippetWasmCompiler<'a> {
                 func.instruction(&Instruction::LocalGet(ptr_local));
             }
             ReturnValue::Variant(v) => {
-                // For variants, use a tag-based encoding:
-                // Return a tagged value (tag in high bits, payload in low bits)
-                // Simple approach: hash the variant name to get a tag value
-                let tag: i64 = v.ty.bytes().map(|b| b as i64).sum();
+                self.compile_variant_construction(&v.ty, &v.fields, func)?;
+            }
+        }
+
+        // Returning an error member of the function's union sets the error tag
+        let returned_type = match &ret.value {
+            ReturnValue::Var(name) => self.local_types.get(name).cloned(),
+            ReturnValue::Struct(s) => match &s.ty.kind {
+                TypeKind::Named(path) => Some(path.name().to_string()),
+                _ => None,
+            },
+            ReturnValue::Variant(v) => Some(v.ty.split("::").next().unwrap_or(&v.ty).to_string()),
+            ReturnValue::Lit(_) => None,
+        };
+        if let Some(error_type) = returned_type.filter(|t| self.current_errors.contains(t)) {
+            func.instruction(&Instruction::I32Const(error_type_tag(&error_type)));
+            func.instruction(&Instruction::GlobalSet(ERROR_TAG_GLOBAL));
+        }
+        Ok(())
+    }
+
+    /// Dispatch on the error tag after a call to a fallible function. Each
+    /// `handle` case runs when the tag names its error type (and, for a case
+    /// naming a single variant, when the result is that variant). Any other
+    /// error is returned to the caller with the tag still set, whether or not
+    /// the current function declares it, so the host sees it at worst.
+    fn compile_error_dispatch(
+        &mut self,
+        call: &CallStep,
+        result: Option<u32>,
+        func: &mut Function,
+    ) -> Result<(), CodegenError> {
+        let cases = call.handle.as_ref().map(|h| h.cases.as_slice()).unwrap_or(&[]);
+
+        func.instruction(&Instruction::GlobalGet(ERROR_TAG_GLOBAL));
+        func.instruction(&Instruction::If(wasm_encoder::BlockType::Empty));
+
+        let mut open_blocks = 0;
+        for case in cases {
+            let (error_type, variant) = match case.error_type.split_once("::") {
+                Some((error_type, _)) => (error_type, Some(case.error_type.as_str())),
+                None => (case.error_type.as_str(), None),
+            };
+            func.instruction(&Instruction::GlobalGet(ERROR_TAG_GLOBAL));
+            func.instruction(&Instruction::I32Const(error_type_tag(error_type)));
+            func.instruction(&Instruction::I32Eq);
+            let variant_tag = variant.and_then(|v| self.variant_layout(v))
+                .map(|(layout, variant)| (layout.boxed, variant.tag));
+            if let (Some((boxed, tag)), Some(local)) = (variant_tag, result) {
+                Self::compile_variant_tag(local, boxed, func);
                 func.instruction(&Instruction::I64Const(tag));
+                func.instruction(&Instruction::I64Eq);
+                func.instruction(&Instruction::I32And);
+            }
+            func.instruction(&Instruction::If(wasm_encoder::BlockType::Empty));
+            func.instruction(&Instruction::I32Const(0));
+            func.instruction(&Instruction::GlobalSet(ERROR_TAG_GLOBAL));
+            for step in &case.steps {
+                self.compile_step(step, func)?;
             }
+            func.instruction(&Instruction::Else);
+            open_blocks += 1;
+        }
+
+        // Not handled here: propagate
+        if self.current_has_result {
+            match result {
+                Some(local) => func.instruction(&Instruction::LocalGet(local)),
+                None => func.instruction(&Instruction::I64Const(0)),
+            };
+        }
+        func.instruction(&Instruction::Return);
+
+        for _ in 0..open_blocks {
+            func.instruction(&Instruction::End);
         }
+        func.instruction(&Instruction::End);
         Ok(())
     }
 
-    /// Compile an if step
-    fn compile_if_step(&mut self, if_step: &IfStep, func: &mut Function) -> Result<(), CodegenError> {
+    /// Compile an if step, binding the value of the taken branch's last step to `output_binding`
+    fn compile_if_step(
+        &mut self,
+        if_step: &IfStep,
+        output_binding: &str,
+        func: &mut Function,
+    ) -> Result<(), CodegenError> {
         // Load condition value (variable, field access, or literal)
         self.compile_input(&if_step.condition, func)?;
 
@@ -3290,6 +4231,7 @@ impl<'a> SnippetWasmCompiler<'a> {
         for step in &if_step.then_steps {
             self.compile_step(step, func)?;
         }
+        self.compile_branch_result(&if_step.then_steps, output_binding, func);
 
         // Compile else steps if present
         if let Some(else_steps) = &if_step.else_steps {
@@ -3297,6 +4239,7 @@ impl<'a> SnippetWasmCompiler<'a> {
             for step in else_steps {
                 self.compile_step(step, func)?;
             }
+            self.compile_branch_result(else_steps, output_binding, func);
         }
 
         func.instruction(&Instruction::End);
@@ -3392,6 +4335,12 @@ impl<'a> SnippetWasmCompiler<'a> {
                     func.instruction(&Instruction::LocalGet(local));
                 }
             }
+            InputSource::FnRef(name) => {
+                // A function value is its slot in the function table
+                let slot = self.function_table.get(name)
+                    .ok_or_else(|| CodegenError::UndefinedFunction { name: name.clone() })?;
+                func.instruction(&Instruction::I64Const(*slot as i64));
+            }
         }
         Ok(())
     }
@@ -3440,6 +4389,12 @@ impl<'a> SnippetWasmCompiler<'a> {
                 .map(|(_, _, _, results)| !results.is_empty())
                 .unwrap_or(true);
         }
+        if self.builtin_externs.contains_key(fn_name) {
+            return true;
+        }
+        if let Some(&(_, has_result)) = self.function_params.get(fn_name) {
+            return has_result;
+        }
         // Check if this is a known void (Unit-returning) user function
         if self.void_functions.contains(fn_name) {
             return false;
@@ -3474,6 +4429,7 @@ impl<'a> SnippetWasmCompiler<'a> {
             },
             TypeKind::Optional(inner) => self.type_to_valtype(inner),
             TypeKind::List(_) => Some(ValType::I64), // Fat pointer (ptr << 32 | len)
+            TypeKind::Function { .. } => Some(ValType::I64), // Function table slot
             _ => None,
         }
     }
@@ -3606,6 +4562,65 @@ fn steps_have_string_literals(steps: &[Step]) -> bool {
     false
 }
 
+/// Collect the functions passed as values in a body, in source order
+fn collect_function_refs(steps: &[Step], refs: &mut Vec<String>) {
+    for step in steps {
+        match &step.kind {
+            StepKind::Call(call) => {
+                for arg in &call.args {
+                    if let InputSource::FnRef(name) = &arg.source {
+                        refs.push(name.clone());
+                    }
+                }
+                if let Some(handle) = &call.handle {
+                    for case in &handle.cases {
+                        collect_function_refs(&case.steps, refs);
+                    }
+                }
+            }
+            StepKind::If(if_step) => {
+                collect_function_refs(&if_step.then_steps, refs);
+                if let Some(else_steps) = &if_step.else_steps {
+                    collect_function_refs(else_steps, refs);
+                }
+            }
+            StepKind::Match(match_step) => {
+                for case in &match_step.cases {
+                    collect_function_refs(&case.steps, refs);
+                }
+            }
+            StepKind::For(for_step) => collect_function_refs(&for_step.steps, refs),
+            StepKind::Transaction(tx) => collect_function_refs(&tx.steps, refs),
+            _ => {}
+        }
+    }
+}
+
+/// Check if values of this type are compared by string content when used as
+/// keys or items: `String`, `Map<String, _>`, `Set<String>` and `List<String>`
+/// (optionally `?`)
+fn is_string_keyed(ty: &ResolvedType) -> bool {
+    match ty {
+        ResolvedType::String => true,
+        ResolvedType::Set(item) | ResolvedType::List(item) => matches!(**item, ResolvedType::String),
+        ResolvedType::Named { name, args, .. } => {
+            name == "Map" && matches!(args.first(), Some(ResolvedType::String))
+        }
+        ResolvedType::Optional(inner) => is_string_keyed(inner),
+        ResolvedType::Union(members) => members.iter().any(is_string_keyed),
+        _ => false,
+    }
+}
+
+/// Check if a type is or contains the `Any` placeholder of the std library
+fn mentions_any(ty: &ResolvedType) -> bool {
+    match ty {
+        ResolvedType::Named { name, args, .. } => name == "Any" || args.iter().any(mentions_any),
+        ResolvedType::Optional(inner) | ResolvedType::List(inner) | ResolvedType::Set(inner) => mentions_any(inner),
+        ResolvedType::Union(members) => members.iter().any(mentions_any),
+        _ => false,
+    }
+}
 
 /// Map a Covenant type to the extern parameter calling convention
 fn type_to_extern_param_kind(ty: &Type) -> ExternParamKind {
@@ -3625,12 +4640,28 @@ fn type_to_extern_param_kind(ty: &Type) -> ExternParamKind {
     }
 }
 
-/// Compute a deterministic tag value for a variant name
-#[allow(dead_code)]
-fn variant_tag(variant: &str) -> i64 {
-    // Simple hash: sum of byte values
-    // In a real implementation, this would use the type registry
-    variant.bytes().map(|b| b as i64).sum()
+/// Compute the nonzero error tag identifying an error type (32-bit FNV-1a)
+pub fn error_type_tag(error_type: &str) -> i32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in error_type.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    if hash == 0 { 1 } else { hash as i32 }
+}
+
+/// Names of the error members of a function's union return type
+fn union_error_types(sig: &FunctionSignature) -> Vec<String> {
+    match &sig.returns {
+        Some(ReturnType::Union { types }) => types.iter()
+            .skip(1)
+            .filter_map(|member| match &member.ty.kind {
+                TypeKind::Named(path) => Some(path.name().to_string()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
 }
 
 /// Generate SQL from a Covenant query
@@ -3719,6 +4750,7 @@ fn input_source_to_sql(source: &InputSource) -> String {
         InputSource::Var(name) => format!(":{}", name), // Parameter placeholder
         InputSource::Lit(lit) => literal_to_sql(lit),
         InputSource::Field { of, field } => format!("{}.{}", of, field),
+        InputSource::FnRef(name) => name.clone(),
     }
 }
 
@@ -3774,13 +4806,14 @@ mod tests {
     }
 
     #[test]
-    fn test_variant_tag() {
-        let tag1 = variant_tag("Some");
-        let tag2 = variant_tag("None");
+    fn test_error_type_tag() {
+        let tag1 = error_type_tag("ParseError");
+        let tag2 = error_type_tag("IoError");
         assert_ne!(tag1, tag2);
+        assert_ne!(tag1, 0);
 
-        // Same variant should produce same tag
-        assert_eq!(variant_tag("Some"), variant_tag("Some"));
+        // Same error type should produce same tag
+        assert_eq!(error_type_tag("ParseError"), error_type_tag("ParseError"));
     }
 
     #[test]
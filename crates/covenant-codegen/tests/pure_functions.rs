@@ -465,6 +465,59 @@ end
     assert_eq!(quadruple.call(&mut store, -3).unwrap(), -12);
 }
 
+#[test]
+fn test_compile_call_args_in_callee_order() {
+    // Arguments are matched by name, whatever order the call lists them in
+    let source = r#"
+snippet id="math.sub" kind="fn"
+signature
+  fn name="sub"
+    param name="a" type="Int"
+    param name="b" type="Int"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="compute"
+    op=sub
+    input var="a"
+    input var="b"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+end
+end
+
+snippet id="math.nine" kind="fn"
+signature
+  fn name="nine"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="call"
+    fn="math.sub"
+    arg name="b" lit=1
+    arg name="a" lit=10
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+end
+end
+"#;
+    let (mut store, instance) = compile_and_instantiate(source);
+    let nine = instance
+        .get_typed_func::<(), i64>(&mut store, "nine")
+        .expect("Failed to get 'nine' function");
+    assert_eq!(nine.call(&mut store, ()).unwrap(), 9);
+}
+
 // === Boolean Operation Tests ===
 
 #[test]
@@ -1362,3 +1415,743 @@ end
     assert_eq!(maybe_double.call(&mut store, (5, 0)).unwrap(), i64::MIN);
     assert_eq!(maybe_double.call(&mut store, (0, 0)).unwrap(), i64::MIN);
 }
+
+// === Generic Function Tests ===
+
+#[test]
+fn test_compile_generic_function_monomorphized() {
+    let source = r#"
+snippet id="util.pick" kind="fn"
+signature
+  fn name="pick"
+    generic name="T"
+    param name="first" type="Bool"
+    param name="a" type="T"
+    param name="b" type="T"
+    returns type="T"
+  end
+end
+body
+  step id="s1" kind="if"
+    condition="first"
+    then
+      step id="t1" kind="return"
+        from="a"
+        as="_"
+      end
+    end
+    else
+      step id="e1" kind="return"
+        from="b"
+        as="_"
+      end
+    end
+    as="_"
+  end
+end
+end
+
+snippet id="util.pick_last" kind="fn"
+signature
+  fn name="pick_last"
+    generic name="U"
+    param name="a" type="U"
+    param name="b" type="U"
+    returns type="U"
+  end
+end
+body
+  step id="s1" kind="call"
+    fn="util.pick"
+    arg name="first" lit=false
+    arg name="a" from="a"
+    arg name="b" from="b"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+end
+end
+
+snippet id="test.pick_int" kind="fn"
+signature
+  fn name="pick_int"
+    param name="first" type="Bool"
+    param name="a" type="Int"
+    param name="b" type="Int"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="call"
+    fn="util.pick"
+    arg name="first" from="first"
+    arg name="a" from="a"
+    arg name="b" from="b"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+end
+end
+
+snippet id="test.last_string" kind="fn"
+signature
+  fn name="last_string"
+    param name="a" type="String"
+    param name="b" type="String"
+    returns type="String"
+  end
+end
+body
+  step id="s1" kind="call"
+    fn="util.pick_last"
+    arg name="a" from="a"
+    arg name="b" from="b"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+end
+end
+"#;
+    let (mut store, instance) = compile_and_instantiate(source);
+
+    let pick_int = instance
+        .get_typed_func::<(i64, i64, i64), i64>(&mut store, "pick_int")
+        .expect("Failed to get 'pick_int' function");
+    assert_eq!(pick_int.call(&mut store, (1, 3, 7)).unwrap(), 3);
+    assert_eq!(pick_int.call(&mut store, (0, 3, 7)).unwrap(), 7);
+
+    // Specialized through a generic caller; strings are fat pointers
+    let last_string = instance
+        .get_typed_func::<(i64, i64), i64>(&mut store, "last_string")
+        .expect("Failed to get 'last_string' function");
+    let (a, b) = ((16i64 << 32) | 3, (32i64 << 32) | 5);
+    assert_eq!(last_string.call(&mut store, (a, b)).unwrap(), b);
+
+    // The unspecialized templates are not emitted
+    assert!(instance.get_func(&mut store, "pick").is_none());
+    assert!(instance.get_func(&mut store, "pick<Int>").is_some());
+    assert!(instance.get_func(&mut store, "pick<String>").is_some());
+    assert!(instance.get_func(&mut store, "pick_last<String>").is_some());
+}
+
+#[test]
+fn test_compile_handle_block_dispatch_and_propagation() {
+    let source = r#"
+snippet id="math.MathError" kind="enum"
+signature
+  enum name="MathError"
+    variant name="DivByZero"
+      field name="dividend" type="Int"
+    end
+  end
+end
+end
+
+snippet id="math.safe_div" kind="fn"
+signature
+  fn name="safe_div"
+    param name="a" type="Int"
+    param name="b" type="Int"
+    returns union
+      type="Int"
+      type="MathError"
+    end
+  end
+end
+body
+  step id="s1" kind="compute"
+    op=equals
+    input var="b"
+    input lit=0
+    as="is_zero"
+  end
+  step id="s2" kind="if"
+    condition="is_zero"
+    then
+      step id="s2a" kind="return"
+        variant type="MathError::DivByZero"
+          field name="dividend" from="a"
+        end
+        as="_"
+      end
+    end
+    as="_"
+  end
+  step id="s3" kind="compute"
+    op=div
+    input var="a"
+    input var="b"
+    as="quotient"
+  end
+  step id="s4" kind="return"
+    from="quotient"
+    as="_"
+  end
+end
+end
+
+snippet id="math.div_or_zero" kind="fn"
+signature
+  fn name="div_or_zero"
+    param name="a" type="Int"
+    param name="b" type="Int"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="call"
+    fn="math.safe_div"
+    arg name="a" from="a"
+    arg name="b" from="b"
+    as="result"
+    handle
+      case type="MathError"
+        step id="s1a" kind="return"
+          lit=0
+          as="_"
+        end
+      end
+    end
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+end
+end
+
+snippet id="math.div_twice" kind="fn"
+signature
+  fn name="div_twice"
+    param name="a" type="Int"
+    param name="b" type="Int"
+    param name="c" type="Int"
+    returns union
+      type="Int"
+      type="MathError"
+    end
+  end
+end
+body
+  step id="s1" kind="call"
+    fn="math.safe_div"
+    arg name="a" from="a"
+    arg name="b" from="b"
+    as="first"
+  end
+  step id="s2" kind="call"
+    fn="math.safe_div"
+    arg name="a" from="first"
+    arg name="b" from="c"
+    as="second"
+  end
+  step id="s3" kind="return"
+    from="second"
+    as="_"
+  end
+end
+end
+
+snippet id="math.check_div" kind="fn"
+signature
+  fn name="check_div"
+    param name="a" type="Int"
+    param name="b" type="Int"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="call"
+    fn="math.safe_div"
+    arg name="a" from="a"
+    arg name="b" from="b"
+    as="_"
+  end
+  step id="s2" kind="return"
+    lit=1
+    as="_"
+  end
+end
+end
+"#;
+    let (mut store, instance) = compile_and_instantiate(source);
+    let error_tag = instance
+        .get_global(&mut store, "cov_error_tag")
+        .expect("Failed to get 'cov_error_tag' global");
+    let math_error = covenant_codegen::error_type_tag("MathError");
+
+    // A handled error runs the case, which returns the fallback
+    let div_or_zero = instance
+        .get_typed_func::<(i64, i64), i64>(&mut store, "div_or_zero")
+        .expect("Failed to get 'div_or_zero' function");
+    assert_eq!(div_or_zero.call(&mut store, (8, 2)).unwrap(), 4);
+    assert_eq!(div_or_zero.call(&mut store, (7, 0)).unwrap(), 0);
+    assert_eq!(error_tag.get(&mut store).unwrap_i32(), 0);
+
+    // An unhandled error propagates to the caller's declared union
+    let div_twice = instance
+        .get_typed_func::<(i64, i64, i64), i64>(&mut store, "div_twice")
+        .expect("Failed to get 'div_twice' function");
+    div_twice.call(&mut store, (8, 0, 1)).unwrap();
+    assert_eq!(error_tag.get(&mut store).unwrap_i32(), math_error);
+    div_twice.call(&mut store, (8, 2, 0)).unwrap();
+    assert_eq!(error_tag.get(&mut store).unwrap_i32(), math_error);
+    assert_eq!(div_twice.call(&mut store, (8, 2, 2)).unwrap(), 2);
+    assert_eq!(error_tag.get(&mut store).unwrap_i32(), 0);
+
+    // An error the caller neither handles nor declares still propagates
+    let check_div = instance
+        .get_typed_func::<(i64, i64), i64>(&mut store, "check_div")
+        .expect("Failed to get 'check_div' function");
+    assert_eq!(check_div.call(&mut store, (8, 2)).unwrap(), 1);
+    assert_eq!(error_tag.get(&mut store).unwrap_i32(), 0);
+    assert_ne!(check_div.call(&mut store, (8, 0)).unwrap(), 1);
+    assert_eq!(error_tag.get(&mut store).unwrap_i32(), math_error);
+}
+
+#[test]
+fn test_compile_enum_multi_field_payloads() {
+    let source = r#"
+snippet id="geo.Point" kind="struct"
+signature
+  struct name="Point"
+    field name="x" type="Int"
+    field name="y" type="Int"
+  end
+end
+end
+
+snippet id="geo.Shape" kind="enum"
+signature
+  enum name="Shape"
+    variant name="Empty"
+    end
+    variant name="Rect"
+      field name="width" type="Int"
+      field name="height" type="Int"
+    end
+    variant name="Dot"
+      field name="at" type="Point"
+    end
+  end
+end
+end
+
+snippet id="geo.Labeled" kind="enum"
+signature
+  enum name="Labeled"
+    variant name="Shape"
+      field name="shape" type="Shape"
+      field name="label" type="Int"
+    end
+  end
+end
+end
+
+snippet id="geo.area" kind="fn"
+signature
+  fn name="area"
+    param name="shape" type="Shape"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="match"
+    on="shape"
+    case variant type="Shape::Empty"
+      step id="c1" kind="return"
+        lit=0
+        as="_"
+      end
+    end
+    case variant type="Shape::Rect" bindings=("w", "h")
+      step id="c2" kind="compute"
+        op=mul
+        input var="w"
+        input var="h"
+        as="a"
+      end
+      step id="c3" kind="return"
+        from="a"
+        as="_"
+      end
+    end
+    case variant type="Shape::Dot" bindings=("p")
+      step id="c4" kind="compute"
+        op=add
+        input field="x" of="p"
+        input field="y" of="p"
+        as="sum"
+      end
+      step id="c5" kind="return"
+        from="sum"
+        as="_"
+      end
+    end
+    as="_"
+  end
+end
+end
+
+snippet id="geo.make_rect" kind="fn"
+signature
+  fn name="make_rect"
+    param name="w" type="Int"
+    param name="h" type="Int"
+    returns type="Shape"
+  end
+end
+body
+  step id="s1" kind="return"
+    variant type="Shape::Rect"
+      field name="height" from="h"
+      field name="width" from="w"
+    end
+    as="_"
+  end
+end
+end
+
+snippet id="geo.rect_area" kind="fn"
+signature
+  fn name="rect_area"
+    param name="w" type="Int"
+    param name="h" type="Int"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="call"
+    fn="geo.make_rect"
+    arg name="w" from="w"
+    arg name="h" from="h"
+    as="shape"
+  end
+  step id="s2" kind="call"
+    fn="geo.area"
+    arg name="shape" from="shape"
+    as="a"
+  end
+  step id="s3" kind="return"
+    from="a"
+    as="_"
+  end
+end
+end
+
+snippet id="geo.dot_area" kind="fn"
+signature
+  fn name="dot_area"
+    param name="x" type="Int"
+    param name="y" type="Int"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="construct"
+    type="Point"
+    field name="x" from="x"
+    field name="y" from="y"
+    as="p"
+  end
+  step id="s2" kind="construct"
+    type="Shape::Dot"
+    field name="at" from="p"
+    as="shape"
+  end
+  step id="s3" kind="call"
+    fn="geo.area"
+    arg name="shape" from="shape"
+    as="a"
+  end
+  step id="s4" kind="return"
+    from="a"
+    as="_"
+  end
+end
+end
+
+snippet id="geo.labeled_area" kind="fn"
+signature
+  fn name="labeled_area"
+    param name="w" type="Int"
+    param name="h" type="Int"
+    param name="label" type="Int"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="construct"
+    type="Shape::Rect"
+    field name="width" from="w"
+    field name="height" from="h"
+    as="shape"
+  end
+  step id="s2" kind="construct"
+    type="Labeled::Shape"
+    field name="shape" from="shape"
+    field name="label" from="label"
+    as="labeled"
+  end
+  step id="s3" kind="match"
+    on="labeled"
+    case variant type="Labeled::Shape" bindings=("inner", "n")
+      step id="c1" kind="match"
+        on="inner"
+        case variant type="Shape::Rect" bindings=("w2", "h2")
+          step id="c1a" kind="compute"
+            op=mul
+            input var="w2"
+            input var="h2"
+            as="a"
+          end
+          step id="c1b" kind="compute"
+            op=add
+            input var="a"
+            input var="n"
+            as="total"
+          end
+          step id="c1c" kind="return"
+            from="total"
+            as="_"
+          end
+        end
+        case wildcard
+          step id="c1d" kind="return"
+            lit=0
+            as="_"
+          end
+        end
+        as="_"
+      end
+    end
+    as="_"
+  end
+end
+end
+"#;
+    let (mut store, instance) = compile_and_instantiate(source);
+
+    // Fields are stored by declaration, not by the order they are written in
+    let rect_area = instance
+        .get_typed_func::<(i64, i64), i64>(&mut store, "rect_area")
+        .expect("Failed to get 'rect_area' function");
+    assert_eq!(rect_area.call(&mut store, (3, 4)).unwrap(), 12);
+
+    // Struct payloads keep their field layout
+    let dot_area = instance
+        .get_typed_func::<(i64, i64), i64>(&mut store, "dot_area")
+        .expect("Failed to get 'dot_area' function");
+    assert_eq!(dot_area.call(&mut store, (5, 7)).unwrap(), 12);
+
+    // Nested enums are destructured in turn
+    let labeled_area = instance
+        .get_typed_func::<(i64, i64, i64), i64>(&mut store, "labeled_area")
+        .expect("Failed to get 'labeled_area' function");
+    assert_eq!(labeled_area.call(&mut store, (2, 5, 100)).unwrap(), 110);
+}
+
+#[test]
+fn test_compile_if_step_output_binding() {
+    let source = r#"
+snippet id="math.clamp" kind="fn"
+
+signature
+  fn name="clamp"
+    param name="n" type="Int"
+    returns type="Int"
+  end
+end
+
+body
+  step id="s1" kind="compute"
+    op=greater
+    input var="n"
+    input lit=0
+    as="positive"
+  end
+  step id="s2" kind="if"
+    condition="positive"
+    then
+      step id="s2a" kind="bind"
+        from="n"
+        as="kept"
+      end
+    end
+    else
+      step id="s2b" kind="bind"
+        lit=0
+        as="floor"
+      end
+    end
+    as="clamped"
+  end
+  step id="s3" kind="return"
+    from="clamped"
+    as="_"
+  end
+end
+
+end
+"#;
+
+    let (mut store, instance) = compile_and_instantiate(source);
+    let clamp = instance
+        .get_typed_func::<i64, i64>(&mut store, "clamp")
+        .expect("Failed to get 'clamp' function");
+
+    // Each branch's last binding becomes the if step's output
+    assert_eq!(clamp.call(&mut store, 7).unwrap(), 7);
+    assert_eq!(clamp.call(&mut store, -3).unwrap(), 0);
+}
+
+#[test]
+fn test_compile_function_values_call_indirect() {
+    let source = r#"
+snippet id="fn.apply_twice" kind="fn"
+
+signature
+  fn name="apply_twice"
+    param name="f" type="(Int) -> Int"
+    param name="x" type="Int"
+    returns type="Int"
+  end
+end
+
+body
+  step id="s1" kind="call"
+    fn="f"
+    arg name="x" from="x"
+    as="once"
+  end
+  step id="s2" kind="call"
+    fn="f"
+    arg name="x" from="once"
+    as="twice"
+  end
+  step id="s3" kind="return"
+    from="twice"
+    as="_"
+  end
+end
+
+end
+
+snippet id="math.double" kind="fn"
+
+signature
+  fn name="double"
+    param name="x" type="Int"
+    returns type="Int"
+  end
+end
+
+body
+  step id="s1" kind="compute"
+    op=mul
+    input var="x"
+    input lit=2
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+end
+
+end
+
+snippet id="math.increment" kind="fn"
+
+signature
+  fn name="increment"
+    param name="x" type="Int"
+    returns type="Int"
+  end
+end
+
+body
+  step id="s1" kind="compute"
+    op=add
+    input var="x"
+    input lit=1
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+end
+
+end
+
+snippet id="app.quadruple" kind="fn"
+
+signature
+  fn name="quadruple"
+    param name="x" type="Int"
+    returns type="Int"
+  end
+end
+
+body
+  step id="s1" kind="call"
+    fn="fn.apply_twice"
+    arg name="f" fn="math.double"
+    arg name="x" from="x"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+end
+
+end
+
+snippet id="app.add_two" kind="fn"
+
+signature
+  fn name="add_two"
+    param name="x" type="Int"
+    returns type="Int"
+  end
+end
+
+body
+  step id="s1" kind="call"
+    fn="fn.apply_twice"
+    arg name="f" fn="math.increment"
+    arg name="x" from="x"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+end
+
+end
+"#;
+
+    let (mut store, instance) = compile_and_instantiate(source);
+    let quadruple = instance
+        .get_typed_func::<i64, i64>(&mut store, "quadruple")
+        .expect("Failed to get 'quadruple' function");
+    let add_two = instance
+        .get_typed_func::<i64, i64>(&mut store, "add_two")
+        .expect("Failed to get 'add_two' function");
+
+    // The same call_indirect site dispatches to whichever function was passed
+    assert_eq!(quadruple.call(&mut store, 5).unwrap(), 20);
+    assert_eq!(add_two.call(&mut store, 5).unwrap(), 7);
+}
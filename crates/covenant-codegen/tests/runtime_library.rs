@@ -0,0 +1,993 @@
+//! Integration tests for the WASM-resident runtime library
+//!
+//! Map, set, datetime and bytes operations and the built-in `text.*` and
+//! `list.*` extern-abstracts must run entirely inside the module: every host
+//! import traps, so any host call fails the test.
+
+use wasmtime::{Engine, Instance, Linker, Memory, Module, Store};
+
+/// Helper to compile source code to WASM and instantiate it with trapping imports
+fn compile_and_instantiate(source: &str) -> (Store<()>, Instance) {
+    let program = covenant_parser::parse(source)
+        .expect("Failed to parse");
+
+    let check_result = covenant_checker::check(&program)
+        .expect("Type checking failed");
+
+    let wasm_bytes = covenant_codegen::compile(&program, &check_result.symbols)
+        .expect("WASM compilation failed");
+
+    let engine = Engine::default();
+    let module = Module::new(&engine, &wasm_bytes)
+        .expect("Failed to create WASM module");
+
+    let mut store = Store::new(&engine, ());
+    let mut linker = Linker::new(&engine);
+
+    for import in module.imports() {
+        if let wasmtime::ExternType::Func(func_ty) = import.ty() {
+            let name = format!("{}.{}", import.module(), import.name());
+            linker
+                .func_new(import.module(), import.name(), func_ty.clone(), move |_caller, _params, _results| {
+                    Err(wasmtime::Error::msg(format!("unexpected host call: {}", name)))
+                })
+                .unwrap();
+        }
+    }
+
+    let instance = linker.instantiate(&mut store, &module)
+        .expect("Failed to instantiate module");
+
+    (store, instance)
+}
+
+/// Build a single-function snippet from a signature and body steps
+fn function_source(name: &str, params: &[(&str, &str)], returns: &str, steps: &str) -> String {
+    let params: String = params
+        .iter()
+        .map(|(p, ty)| format!("    param name=\"{}\" type=\"{}\"\n", p, ty))
+        .collect();
+    format!(
+        r#"
+snippet id="rt.{name}" kind="fn"
+
+signature
+  fn name="{name}"
+{params}    returns type="{returns}"
+  end
+end
+
+body
+{steps}
+end
+
+end
+"#
+    )
+}
+
+/// Build a function that forwards to one extern call. Each argument is the
+/// attribute text of an `arg` line, e.g. `from="s"` or `lit=3`.
+fn call_source(name: &str, params: &[(&str, &str)], returns: &str, callee: &str, args: &[(&str, &str)]) -> String {
+    let args: String = args
+        .iter()
+        .map(|(arg, value)| format!("    arg name=\"{}\" {}\n", arg, value))
+        .collect();
+    let steps = format!(
+        r#"  step id="s1" kind="call"
+    fn="{callee}"
+{args}    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end"#
+    );
+    function_source(name, params, returns, &steps)
+}
+
+/// Read the string behind a fat pointer `(ptr << 32) | len`
+fn read_string(store: &Store<()>, memory: &Memory, fat_ptr: i64) -> String {
+    let ptr = (fat_ptr >> 32) as usize;
+    let len = (fat_ptr & 0xFFFF_FFFF) as usize;
+    String::from_utf8(memory.data(store)[ptr..ptr + len].to_vec()).unwrap()
+}
+
+/// Write a string into linear memory and return its fat pointer
+fn write_string(store: &mut Store<()>, memory: &Memory, addr: usize, s: &str) -> i64 {
+    memory.data_mut(store)[addr..addr + s.len()].copy_from_slice(s.as_bytes());
+    ((addr as i64) << 32) | s.len() as i64
+}
+
+/// Read the i64 items of a list/set fat pointer
+fn read_items(store: &Store<()>, memory: &Memory, fat_ptr: i64) -> Vec<i64> {
+    let ptr = (fat_ptr >> 32) as usize;
+    let data = memory.data(store);
+    let count = u32::from_le_bytes(data[ptr..ptr + 4].try_into().unwrap()) as usize;
+    (0..count)
+        .map(|i| {
+            let at = ptr + 4 + i * 8;
+            i64::from_le_bytes(data[at..at + 8].try_into().unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn test_datetime_fields() {
+    let mut source = String::new();
+    for (name, op) in [
+        ("year", "dt_year"),
+        ("month", "dt_month"),
+        ("day", "dt_day"),
+        ("hour", "dt_hour"),
+        ("minute", "dt_minute"),
+        ("second", "dt_second"),
+        ("weekday", "dt_weekday"),
+    ] {
+        let steps = format!(
+            r#"  step id="s1" kind="compute"
+    op={op}
+    input var="t"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end"#
+        );
+        source.push_str(&function_source(name, &[("t", "DateTime")], "Int", &steps));
+    }
+
+    let (mut store, instance) = compile_and_instantiate(&source);
+    let mut field = |name: &str, t: i64| -> i64 {
+        let f = instance.get_typed_func::<i64, i64>(&mut store, name).unwrap();
+        f.call(&mut store, t).unwrap()
+    };
+
+    // 1970-01-01T00:00:00Z was a Thursday
+    assert_eq!(field("year", 0), 1970);
+    assert_eq!(field("month", 0), 1);
+    assert_eq!(field("day", 0), 1);
+    assert_eq!(field("weekday", 0), 4);
+
+    // 2000-02-29T13:45:30Z (leap day, Tuesday)
+    let leap_day = 951_831_930;
+    assert_eq!(field("year", leap_day), 2000);
+    assert_eq!(field("month", leap_day), 2);
+    assert_eq!(field("day", leap_day), 29);
+    assert_eq!(field("hour", leap_day), 13);
+    assert_eq!(field("minute", leap_day), 45);
+    assert_eq!(field("second", leap_day), 30);
+    assert_eq!(field("weekday", leap_day), 2);
+
+    // One second before the epoch
+    assert_eq!(field("year", -1), 1969);
+    assert_eq!(field("month", -1), 12);
+    assert_eq!(field("day", -1), 31);
+    assert_eq!(field("hour", -1), 23);
+    assert_eq!(field("second", -1), 59);
+    assert_eq!(field("weekday", -1), 3);
+}
+
+#[test]
+fn test_datetime_arithmetic() {
+    let source = function_source(
+        "days_later",
+        &[("t", "DateTime"), ("n", "Int")],
+        "Int",
+        r#"  step id="s1" kind="compute"
+    op=dt_add_days
+    input var="t"
+    input var="n"
+    as="later"
+  end
+  step id="s2" kind="compute"
+    op=dt_diff
+    input var="later"
+    input var="t"
+    as="delta"
+  end
+  step id="s3" kind="return"
+    from="delta"
+    as="_"
+  end"#,
+    );
+
+    let (mut store, instance) = compile_and_instantiate(&source);
+    let f = instance.get_typed_func::<(i64, i64), i64>(&mut store, "days_later").unwrap();
+    assert_eq!(f.call(&mut store, (1_000, 3)).unwrap(), 3 * 86_400);
+    assert_eq!(f.call(&mut store, (1_000, -1)).unwrap(), -86_400);
+}
+
+#[test]
+fn test_datetime_format() {
+    let mut source = function_source(
+        "fmt",
+        &[("t", "DateTime")],
+        "String",
+        r#"  step id="s1" kind="compute"
+    op=dt_format
+    input var="t"
+    input lit="%d/%m/%Y %H:%M:%S (100%%)"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end"#,
+    );
+    source.push_str(&function_source(
+        "iso",
+        &[("t", "DateTime")],
+        "String",
+        r#"  step id="s1" kind="compute"
+    op=dt_format
+    input var="t"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end"#,
+    ));
+
+    let (mut store, instance) = compile_and_instantiate(&source);
+    let memory = instance.get_memory(&mut store, "memory").unwrap();
+
+    let fmt = instance.get_typed_func::<i64, i64>(&mut store, "fmt").unwrap();
+    let result = fmt.call(&mut store, 951_831_930).unwrap();
+    assert_eq!(read_string(&store, &memory, result), "29/02/2000 13:45:30 (100%)");
+
+    let iso = instance.get_typed_func::<i64, i64>(&mut store, "iso").unwrap();
+    let first = iso.call(&mut store, 0).unwrap();
+    let second = iso.call(&mut store, 1_700_000_000).unwrap();
+    assert_eq!(read_string(&store, &memory, first), "1970-01-01T00:00:00Z");
+    assert_eq!(read_string(&store, &memory, second), "2023-11-14T22:13:20Z");
+}
+
+#[test]
+fn test_bytes_encoding() {
+    let mut source = String::new();
+    for (name, op, lit) in [
+        ("hex", "bytes_to_hex", "Hi!"),
+        ("b64_full", "bytes_to_base64", "hello!"),
+        ("b64_pad1", "bytes_to_base64", "hello"),
+        ("b64_pad2", "bytes_to_base64", "hi"),
+    ] {
+        let steps = format!(
+            r#"  step id="s1" kind="compute"
+    op={op}
+    input lit="{lit}"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end"#
+        );
+        source.push_str(&function_source(name, &[], "String", &steps));
+    }
+
+    let (mut store, instance) = compile_and_instantiate(&source);
+    let memory = instance.get_memory(&mut store, "memory").unwrap();
+    for (name, expected) in [
+        ("hex", "486921"),
+        ("b64_full", "aGVsbG8h"),
+        ("b64_pad1", "aGVsbG8="),
+        ("b64_pad2", "aGk="),
+    ] {
+        let f = instance.get_typed_func::<(), i64>(&mut store, name).unwrap();
+        let result = f.call(&mut store, ()).unwrap();
+        assert_eq!(read_string(&store, &memory, result), expected, "{}", name);
+    }
+}
+
+#[test]
+fn test_bytes_access() {
+    let mut source = function_source(
+        "middle",
+        &[("b", "Bytes"), ("start", "Int"), ("end", "Int")],
+        "Bytes",
+        r#"  step id="s1" kind="compute"
+    op=bytes_slice
+    input var="b"
+    input var="start"
+    input var="end"
+    as="part"
+  end
+  step id="s2" kind="compute"
+    op=bytes_concat
+    input var="part"
+    input lit="!"
+    as="result"
+  end
+  step id="s3" kind="return"
+    from="result"
+    as="_"
+  end"#,
+    );
+    source.push_str(&function_source(
+        "byte_at",
+        &[("b", "Bytes"), ("i", "Int")],
+        "Optional<Int>",
+        r#"  step id="s1" kind="compute"
+    op=bytes_get
+    input var="b"
+    input var="i"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end"#,
+    ));
+    let (mut store, instance) = compile_and_instantiate(&source);
+    let memory = instance.get_memory(&mut store, "memory").unwrap();
+    let bytes = write_string(&mut store, &memory, 0x80000, "covenant");
+
+    let middle = instance.get_typed_func::<(i64, i64, i64), i64>(&mut store, "middle").unwrap();
+    let result = middle.call(&mut store, (bytes, 2, 5)).unwrap();
+    assert_eq!(read_string(&store, &memory, result), "ven!");
+    // Bounds are clamped
+    let result = middle.call(&mut store, (bytes, -3, 100)).unwrap();
+    assert_eq!(read_string(&store, &memory, result), "covenant!");
+    let result = middle.call(&mut store, (bytes, 6, 2)).unwrap();
+    assert_eq!(read_string(&store, &memory, result), "!");
+
+    let byte_at = instance.get_typed_func::<(i64, i64), i64>(&mut store, "byte_at").unwrap();
+    assert_eq!(byte_at.call(&mut store, (bytes, 0)).unwrap(), b'c' as i64);
+    assert_eq!(byte_at.call(&mut store, (bytes, 7)).unwrap(), b't' as i64);
+    assert_eq!(byte_at.call(&mut store, (bytes, 8)).unwrap(), i64::MIN);
+    assert_eq!(byte_at.call(&mut store, (bytes, -1)).unwrap(), i64::MIN);
+}
+
+#[test]
+fn test_bytes_to_string() {
+    let source = function_source(
+        "utf8",
+        &[("b", "Bytes")],
+        "String",
+        r#"  step id="s1" kind="compute"
+    op=bytes_to_string
+    input var="b"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end"#,
+    ).replace(
+        r#"returns type="String""#,
+        "returns union\n      type=\"String\"\n      type=\"DecodeError\"\n    end",
+    );
+
+    let (mut store, instance) = compile_and_instantiate(&source);
+    let memory = instance.get_memory(&mut store, "memory").unwrap();
+
+    let utf8 = instance.get_typed_func::<i64, i64>(&mut store, "utf8").unwrap();
+    let valid = write_string(&mut store, &memory, 0x81000, "héllo ✓");
+    assert_eq!(utf8.call(&mut store, valid).unwrap(), valid);
+    memory.data_mut(&mut store)[0x82000..0x82003].copy_from_slice(&[b'a', 0xC3, b'b']);
+    let invalid = (0x82000_i64 << 32) | 3;
+    assert_eq!(utf8.call(&mut store, invalid).unwrap(), i64::MIN);
+    let truncated = (0x82000_i64 << 32) | 2;
+    assert_eq!(utf8.call(&mut store, truncated).unwrap(), i64::MIN);
+}
+
+#[test]
+fn test_map_operations() {
+    let steps = r#"  step id="s1" kind="compute"
+    op=map_insert
+    input var="m"
+    input lit="apple"
+    input lit=1
+    as="m1"
+  end
+  step id="s2" kind="compute"
+    op=map_insert
+    input var="m1"
+    input lit="banana"
+    input lit=2
+    as="m2"
+  end
+  step id="s3" kind="compute"
+    op=map_insert
+    input var="m2"
+    input lit="apple"
+    input lit=3
+    as="m3"
+  end
+  step id="s4" kind="compute"
+    op=map_has
+    input var="m3"
+    input var="key"
+    as="found"
+  end
+  step id="s5" kind="if"
+    condition="found"
+    then
+      step id="s5a" kind="compute"
+        op=map_values
+        input var="m3"
+        as="values"
+      end
+      step id="s5b" kind="return"
+        from="values"
+        as="_"
+      end
+    end
+    else
+      step id="s5c" kind="compute"
+        op=map_remove
+        input var="m3"
+        input lit="apple"
+        as="m4"
+      end
+      step id="s5d" kind="compute"
+        op=map_values
+        input var="m4"
+        as="rest"
+      end
+      step id="s5e" kind="return"
+        from="rest"
+        as="_"
+      end
+    end
+    as="_"
+  end"#;
+    let source = function_source(
+        "map_demo",
+        &[("m", "Map<String, Int>"), ("key", "String")],
+        "List<Int>",
+        steps,
+    );
+
+    let (mut store, instance) = compile_and_instantiate(&source);
+    let memory = instance.get_memory(&mut store, "memory").unwrap();
+    let map_demo = instance.get_typed_func::<(i64, i64), i64>(&mut store, "map_demo").unwrap();
+
+    // Keys are compared by content, not by pointer
+    let key = write_string(&mut store, &memory, 0x80000, "banana");
+    let values = map_demo.call(&mut store, (0, key)).unwrap();
+    assert_eq!(read_items(&store, &memory, values), vec![3, 2]);
+
+    // Missing key: "apple" is removed, leaving only "banana"
+    let key = write_string(&mut store, &memory, 0x80000, "cherry");
+    let rest = map_demo.call(&mut store, (0, key)).unwrap();
+    assert_eq!(read_items(&store, &memory, rest), vec![2]);
+}
+
+#[test]
+fn test_set_operations() {
+    let mut source = String::new();
+    let build = |name: &str, op: &str, returns: &str| -> String {
+        let steps = format!(
+            r#"  step id="s1" kind="compute"
+    op=set_add
+    input var="a"
+    input var="x"
+    as="a1"
+  end
+  step id="s2" kind="compute"
+    op={op}
+    input var="a1"
+    input var="b"
+    as="result"
+  end
+  step id="s3" kind="return"
+    from="result"
+    as="_"
+  end"#
+        );
+        function_source(name, &[("a", "Set<Int>"), ("x", "Int"), ("b", "Set<Int>")], returns, &steps)
+    };
+    for (name, op, returns) in [
+        ("union", "set_union", "Set<Int>"),
+        ("intersect", "set_intersect", "Set<Int>"),
+        ("diff", "set_diff", "Set<Int>"),
+        ("sym_diff", "set_symmetric_diff", "Set<Int>"),
+        ("subset", "set_is_subset", "Bool"),
+        ("superset", "set_is_superset", "Bool"),
+    ] {
+        source.push_str(&build(name, op, returns));
+    }
+
+    let (mut store, instance) = compile_and_instantiate(&source);
+    let memory = instance.get_memory(&mut store, "memory").unwrap();
+
+    // Build a = {1, 2, 3} and b = {2, 3, 4} in memory
+    let write_set = |store: &mut Store<()>, addr: usize, items: &[i64]| -> i64 {
+        let data = memory.data_mut(store);
+        data[addr..addr + 4].copy_from_slice(&(items.len() as u32).to_le_bytes());
+        for (i, item) in items.iter().enumerate() {
+            let at = addr + 4 + i * 8;
+            data[at..at + 8].copy_from_slice(&item.to_le_bytes());
+        }
+        ((addr as i64) << 32) | items.len() as i64
+    };
+    let a = write_set(&mut store, 0x80000, &[1, 2]);
+    let b = write_set(&mut store, 0x81000, &[2, 3, 4]);
+
+    let mut run = |name: &str, x: i64| -> i64 {
+        let f = instance.get_typed_func::<(i64, i64, i64), i64>(&mut store, name).unwrap();
+        f.call(&mut store, (a, x, b)).unwrap()
+    };
+    let union = run("union", 3);
+    let intersect = run("intersect", 3);
+    let diff = run("diff", 3);
+    let sym_diff = run("sym_diff", 3);
+    // Adding an existing element leaves the set unchanged
+    let unchanged = run("union", 1);
+    assert_eq!(run("subset", 3), 0);
+    assert_eq!(run("superset", 3), 0);
+
+    assert_eq!(read_items(&store, &memory, union), vec![1, 2, 3, 4]);
+    assert_eq!(read_items(&store, &memory, intersect), vec![2, 3]);
+    assert_eq!(read_items(&store, &memory, diff), vec![1]);
+    assert_eq!(read_items(&store, &memory, sym_diff), vec![1, 4]);
+    assert_eq!(read_items(&store, &memory, unchanged), vec![1, 2, 3, 4]);
+
+    // {2, 3} is a subset of {2, 3, 4}
+    let small = write_set(&mut store, 0x82000, &[2]);
+    let subset = instance.get_typed_func::<(i64, i64, i64), i64>(&mut store, "subset").unwrap();
+    assert_eq!(subset.call(&mut store, (small, 3, b)).unwrap(), 1);
+    let superset = instance.get_typed_func::<(i64, i64, i64), i64>(&mut store, "superset").unwrap();
+    assert_eq!(superset.call(&mut store, (b, 2, small)).unwrap(), 1);
+}
+
+#[test]
+fn test_string_sets_from_calls_and_fields() {
+    let mut source = function_source(
+        "single",
+        &[("empty", "Set<String>"), ("s", "String")],
+        "Set<String>",
+        r#"  step id="s1" kind="compute"
+    op=set_add
+    input var="empty"
+    input var="s"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end"#,
+    );
+    source.push_str(&function_source(
+        "call_union_len",
+        &[("empty", "Set<String>"), ("a", "String"), ("b", "String")],
+        "Int",
+        r#"  step id="s1" kind="call"
+    fn="rt.single"
+    arg name="empty" from="empty"
+    arg name="s" from="a"
+    as="left"
+  end
+  step id="s2" kind="call"
+    fn="rt.single"
+    arg name="empty" from="empty"
+    arg name="s" from="b"
+    as="right"
+  end
+  step id="s3" kind="compute"
+    op=set_union
+    input var="left"
+    input var="right"
+    as="both"
+  end
+  step id="s4" kind="compute"
+    op=set_len
+    input var="both"
+    as="result"
+  end
+  step id="s5" kind="return"
+    from="result"
+    as="_"
+  end"#,
+    ));
+    source.push_str(
+        r#"
+snippet id="rt.Pair" kind="struct"
+signature
+  struct name="Pair"
+    field name="left" type="Set<String>"
+    field name="right" type="Set<String>"
+  end
+end
+end
+"#,
+    );
+    source.push_str(&function_source(
+        "field_union_len",
+        &[("l", "Set<String>"), ("r", "Set<String>")],
+        "Int",
+        r#"  step id="s1" kind="construct"
+    type="Pair"
+    field name="left" from="l"
+    field name="right" from="r"
+    as="pair"
+  end
+  step id="s2" kind="compute"
+    op=set_union
+    input field="left" of="pair"
+    input field="right" of="pair"
+    as="both"
+  end
+  step id="s3" kind="compute"
+    op=set_len
+    input var="both"
+    as="result"
+  end
+  step id="s4" kind="return"
+    from="result"
+    as="_"
+  end"#,
+    ));
+
+    let (mut store, instance) = compile_and_instantiate(&source);
+    let memory = instance.get_memory(&mut store, "memory").unwrap();
+    // Equal strings at different addresses
+    let a = write_string(&mut store, &memory, 0x80000, "tag");
+    let b = write_string(&mut store, &memory, 0x80100, "tag");
+
+    // Sets returned by calls keep comparing their items by content
+    let call_union_len = instance.get_typed_func::<(i64, i64, i64), i64>(&mut store, "call_union_len").unwrap();
+    assert_eq!(call_union_len.call(&mut store, (0, a, b)).unwrap(), 1);
+
+    // So do sets read from struct fields
+    let write_set = |store: &mut Store<()>, addr: usize, item: i64| -> i64 {
+        let data = memory.data_mut(store);
+        data[addr..addr + 4].copy_from_slice(&1u32.to_le_bytes());
+        data[addr + 4..addr + 12].copy_from_slice(&item.to_le_bytes());
+        ((addr as i64) << 32) | 1
+    };
+    let left = write_set(&mut store, 0x81000, a);
+    let right = write_set(&mut store, 0x82000, b);
+    let field_union_len = instance.get_typed_func::<(i64, i64), i64>(&mut store, "field_union_len").unwrap();
+    assert_eq!(field_union_len.call(&mut store, (left, right)).unwrap(), 1);
+}
+
+#[test]
+fn test_empty_collections() {
+    let source = function_source(
+        "sizes",
+        &[("m", "Map<Int, Int>"), ("s", "Set<Int>")],
+        "Int",
+        r#"  step id="s1" kind="compute"
+    op=map_is_empty
+    input var="m"
+    as="m_empty"
+  end
+  step id="s2" kind="compute"
+    op=set_add
+    input var="s"
+    input lit=7
+    as="s1"
+  end
+  step id="s3" kind="compute"
+    op=set_len
+    input var="s1"
+    as="n"
+  end
+  step id="s4" kind="compute"
+    op=add
+    input var="m_empty"
+    input var="n"
+    as="result"
+  end
+  step id="s5" kind="return"
+    from="result"
+    as="_"
+  end"#,
+    );
+
+    let (mut store, instance) = compile_and_instantiate(&source);
+    let sizes = instance.get_typed_func::<(i64, i64), i64>(&mut store, "sizes").unwrap();
+    // Null pointers are empty collections: true (1) + len {7} (1)
+    assert_eq!(sizes.call(&mut store, (0, 0)).unwrap(), 2);
+}
+
+#[test]
+fn test_runtime_function_used_only_in_handle_case() {
+    let source = r#"
+snippet id="sizes.SizeError" kind="enum"
+signature
+  enum name="SizeError"
+    variant name="Negative"
+    end
+  end
+end
+end
+
+snippet id="sizes.checked" kind="fn"
+signature
+  fn name="checked"
+    param name="n" type="Int"
+    returns union
+      type="Int"
+      type="SizeError"
+    end
+  end
+end
+body
+  step id="s1" kind="compute"
+    op=less
+    input var="n"
+    input lit=0
+    as="negative"
+  end
+  step id="s2" kind="if"
+    condition="negative"
+    then
+      step id="s2a" kind="return"
+        variant type="SizeError::Negative"
+        end
+        as="_"
+      end
+    end
+    as="_"
+  end
+  step id="s3" kind="return"
+    from="n"
+    as="_"
+  end
+end
+end
+
+snippet id="sizes.size_or_len" kind="fn"
+signature
+  fn name="size_or_len"
+    param name="n" type="Int"
+    param name="s" type="Set<Int>"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="call"
+    fn="sizes.checked"
+    arg name="n" from="n"
+    as="result"
+    handle
+      case type="SizeError"
+        step id="s1a" kind="compute"
+          op=set_len
+          input var="s"
+          as="len"
+        end
+        step id="s1b" kind="return"
+          from="len"
+          as="_"
+        end
+      end
+    end
+  end
+  step id="s2" kind="return"
+    from="result"
+    as="_"
+  end
+end
+end
+"#;
+
+    // set_len is only reachable from the handle case, so it must still be linked
+    let (mut store, instance) = compile_and_instantiate(source);
+    let size_or_len = instance.get_typed_func::<(i64, i64), i64>(&mut store, "size_or_len").unwrap();
+    assert_eq!(size_or_len.call(&mut store, (5, 0)).unwrap(), 5);
+    assert_eq!(size_or_len.call(&mut store, (-1, 0)).unwrap(), 0);
+}
+
+#[test]
+fn test_text_builtins() {
+    let s = [("s", "String")];
+    let sources = [
+        call_source("upper", &s, "String", "text.upper", &[("s", r#"from="s""#)]),
+        call_source("lower", &s, "String", "text.lower", &[("s", r#"from="s""#)]),
+        call_source("trim", &s, "String", "text.trim", &[("s", r#"from="s""#)]),
+        call_source("trim_end", &s, "String", "text.trim_end", &[("s", r#"from="s""#)]),
+        call_source("reverse", &s, "String", "text.str_reverse", &[("s", r#"from="s""#)]),
+        call_source("length", &s, "Int", "text.str_len", &[("s", r#"from="s""#)]),
+        call_source("bytes", &s, "Int", "text.byte_len", &[("s", r#"from="s""#)]),
+        call_source("empty", &s, "Bool", "text.is_empty", &[("s", r#"from="s""#)]),
+        call_source("concat", &s, "String", "text.concat", &[("a", r#"from="s""#), ("b", r#"lit="!""#)]),
+        call_source("concat_b_first", &s, "String", "text.concat", &[("b", r#"from="s""#), ("a", r#"lit="!""#)]),
+        call_source("has_lo", &s, "Bool", "text.contains", &[("haystack", r#"from="s""#), ("needle", r#"lit="lo""#)]),
+        call_source("starts_he", &s, "Bool", "text.starts_with", &[("s", r#"from="s""#), ("prefix", r#"lit="he""#)]),
+        call_source("ends_lo", &s, "Bool", "text.ends_with", &[("s", r#"from="s""#), ("suffix", r#"lit="lo""#)]),
+        call_source("find_l", &s, "Int", "text.index_of", &[("haystack", r#"from="s""#), ("needle", r#"lit="l""#)]),
+        call_source(
+            "slice",
+            &[("s", "String"), ("start", "Int"), ("end", "Int")],
+            "String",
+            "text.slice",
+            &[("s", r#"from="s""#), ("start", r#"from="start""#), ("end", r#"from="end""#)],
+        ),
+        call_source("second", &s, "String", "text.char_at", &[("s", r#"from="s""#), ("index", "lit=1")]),
+        call_source("dash_l", &s, "String", "text.replace", &[("s", r#"from="s""#), ("from", r#"lit="l""#), ("to", r#"lit="-""#)]),
+        call_source("dash_all", &s, "String", "text.replace_all", &[("s", r#"from="s""#), ("from", r#"lit="l""#), ("to", r#"lit="-""#)]),
+        call_source("around", &s, "String", "text.replace_all", &[("s", r#"from="s""#), ("from", r#"lit="""#), ("to", r#"lit=".""#)]),
+        call_source("twice", &s, "String", "text.repeat", &[("s", r#"from="s""#), ("count", "lit=2")]),
+        call_source("pad_left", &s, "String", "text.pad_start", &[("s", r#"from="s""#), ("target_len", "lit=8"), ("fill", r#"lit="ab""#)]),
+        call_source("pad_right", &s, "String", "text.pad_end", &[("s", r#"from="s""#), ("target_len", "lit=7"), ("fill", r#"lit="*""#)]),
+    ];
+
+    let (mut store, instance) = compile_and_instantiate(&sources.concat());
+    let memory = instance.get_memory(&mut store, "memory").unwrap();
+    let call = |store: &mut Store<()>, name: &str, s: &str| -> i64 {
+        let arg = write_string(store, &memory, 0x80000, s);
+        let f = instance.get_typed_func::<i64, i64>(&mut *store, name).unwrap();
+        f.call(store, arg).unwrap()
+    };
+    let mut text = |name: &str, s: &str| -> String {
+        let result = call(&mut store, name, s);
+        read_string(&store, &memory, result)
+    };
+
+    assert_eq!(text("upper", "Hello, wörld"), "HELLO, WöRLD");
+    assert_eq!(text("lower", "Hello, WORLD"), "hello, world");
+    assert_eq!(text("lower", "ÀÉ ÖK"), "ÀÉ Ök");
+    assert_eq!(text("trim", " \t hi there\n "), "hi there");
+    assert_eq!(text("trim_end", "  hi  "), "  hi");
+    assert_eq!(text("trim", "   "), "");
+    assert_eq!(text("reverse", "añb€"), "€bña");
+    assert_eq!(text("concat", "hey"), "hey!");
+    assert_eq!(text("concat_b_first", "hey"), "!hey");
+    assert_eq!(text("second", "a€c"), "€");
+    assert_eq!(text("second", "a"), "");
+    assert_eq!(text("dash_l", "hello"), "he-lo");
+    assert_eq!(text("dash_all", "hello"), "he--o");
+    assert_eq!(text("dash_all", "abc"), "abc");
+    assert_eq!(text("around", "ab"), ".a.b.");
+    assert_eq!(text("twice", "ab"), "abab");
+    assert_eq!(text("pad_left", "xyz"), "ababaxyz");
+    assert_eq!(text("pad_left", "long enough"), "long enough");
+    assert_eq!(text("pad_right", "x"), "x******");
+
+    let mut slice = |s: &str, start: i64, end: i64| -> String {
+        let arg = write_string(&mut store, &memory, 0x80000, s);
+        let f = instance.get_typed_func::<(i64, i64, i64), i64>(&mut store, "slice").unwrap();
+        let result = f.call(&mut store, (arg, start, end)).unwrap();
+        read_string(&store, &memory, result)
+    };
+    assert_eq!(slice("héllo", 1, -1), "éll");
+    assert_eq!(slice("héllo", -3, 99), "llo");
+    assert_eq!(slice("héllo", 3, 1), "");
+    assert_eq!(slice("a", 1, -1), "");
+
+    let mut int = |name: &str, s: &str| call(&mut store, name, s);
+    assert_eq!(int("length", "héllo"), 5);
+    assert_eq!(int("bytes", "héllo"), 6);
+    assert_eq!(int("empty", ""), 1);
+    assert_eq!(int("empty", "x"), 0);
+    assert_eq!(int("has_lo", "hello"), 1);
+    assert_eq!(int("has_lo", "hell"), 0);
+    assert_eq!(int("starts_he", "hello"), 1);
+    assert_eq!(int("starts_he", "h"), 0);
+    assert_eq!(int("ends_lo", "hello"), 1);
+    assert_eq!(int("ends_lo", "low"), 0);
+    assert_eq!(int("find_l", "héllo"), 2);
+    assert_eq!(int("find_l", "abc"), -1);
+}
+
+#[test]
+fn test_text_split_join() {
+    let split_join = r#"  step id="s1" kind="call"
+    fn="text.split"
+    arg name="s" from="s"
+    arg name="delimiter" from="delimiter"
+    as="parts"
+  end
+  step id="s2" kind="call"
+    fn="text.join"
+    arg name="parts" from="parts"
+    arg name="separator" lit="|"
+    as="result"
+  end
+  step id="s3" kind="return"
+    from="result"
+    as="_"
+  end"#;
+    let params = [("s", "String"), ("delimiter", "String")];
+    let mut source = function_source("resplit", &params, "String", split_join);
+    source.push_str(&call_source(
+        "split",
+        &params,
+        "List<String>",
+        "text.split",
+        &[("s", r#"from="s""#), ("delimiter", r#"from="delimiter""#)],
+    ));
+
+    let (mut store, instance) = compile_and_instantiate(&source);
+    let memory = instance.get_memory(&mut store, "memory").unwrap();
+    let call = |store: &mut Store<()>, name: &str, s: &str, delimiter: &str| -> i64 {
+        let s = write_string(store, &memory, 0x80000, s);
+        let delimiter = write_string(store, &memory, 0x81000, delimiter);
+        let f = instance.get_typed_func::<(i64, i64), i64>(&mut *store, name).unwrap();
+        f.call(store, (s, delimiter)).unwrap()
+    };
+
+    for (s, delimiter, expected) in [
+        ("a, b, c", ", ", "a|b|c"),
+        (",a,,b,", ",", "|a||b|"),
+        ("abc", "x", "abc"),
+        ("", ",", ""),
+        ("añb", "", "a|ñ|b"),
+    ] {
+        let result = call(&mut store, "resplit", s, delimiter);
+        assert_eq!(read_string(&store, &memory, result), expected, "split({:?}, {:?})", s, delimiter);
+    }
+
+    let parts = call(&mut store, "split", "x::yy::", "::");
+    let parts: Vec<String> = read_items(&store, &memory, parts)
+        .into_iter()
+        .map(|part| read_string(&store, &memory, part))
+        .collect();
+    assert_eq!(parts, ["x", "yy", ""]);
+    let empty = call(&mut store, "split", "", "");
+    assert!(read_items(&store, &memory, empty).is_empty());
+}
+
+#[test]
+fn test_list_builtins() {
+    let ints = [("list", "List<Int>")];
+    let strings = [("list", "List<String>")];
+    let list = ("list", r#"from="list""#);
+    let sources = [
+        call_source("len", &ints, "Int", "list.len", &[list]),
+        call_source("is_empty", &ints, "Bool", "list.is_empty", &[list]),
+        call_source("third", &ints, "Int", "list.get", &[list, ("index", "lit=2")]),
+        call_source("first", &ints, "Int", "list.first", &[list]),
+        call_source("last", &ints, "Int", "list.last", &[list]),
+        call_source("push", &ints, "List<Int>", "list.append", &[list, ("item", "lit=9")]),
+        call_source("has_seven", &ints, "Bool", "list.contains", &[list, ("item", "lit=7")]),
+        call_source("has_b", &strings, "Bool", "list.contains", &[list, ("item", r#"lit="b""#)]),
+        call_source("flat", &[("list", "List<List<Int>>")], "List<Int>", "list.flatten", &[list]),
+    ];
+
+    let (mut store, instance) = compile_and_instantiate(&sources.concat());
+    let memory = instance.get_memory(&mut store, "memory").unwrap();
+    let write_list = |store: &mut Store<()>, addr: usize, items: &[i64]| -> i64 {
+        let data = memory.data_mut(store);
+        data[addr..addr + 4].copy_from_slice(&(items.len() as u32).to_le_bytes());
+        for (i, item) in items.iter().enumerate() {
+            let at = addr + 4 + i * 8;
+            data[at..at + 8].copy_from_slice(&item.to_le_bytes());
+        }
+        ((addr as i64) << 32) | items.len() as i64
+    };
+    let call = |store: &mut Store<()>, name: &str, list: i64| -> i64 {
+        let f = instance.get_typed_func::<i64, i64>(&mut *store, name).unwrap();
+        f.call(store, list).unwrap()
+    };
+
+    let nums = write_list(&mut store, 0x80000, &[5, 7, 11]);
+    let empty = write_list(&mut store, 0x80100, &[]);
+    assert_eq!(call(&mut store, "len", nums), 3);
+    assert_eq!(call(&mut store, "len", 0), 0);
+    assert_eq!(call(&mut store, "is_empty", empty), 1);
+    assert_eq!(call(&mut store, "is_empty", nums), 0);
+    assert_eq!(call(&mut store, "third", nums), 11);
+    assert_eq!(call(&mut store, "first", nums), 5);
+    assert_eq!(call(&mut store, "last", nums), 11);
+    assert_eq!(call(&mut store, "first", empty), i64::MIN);
+    assert_eq!(call(&mut store, "last", empty), i64::MIN);
+    assert_eq!(call(&mut store, "third", empty), i64::MIN);
+    assert_eq!(call(&mut store, "has_seven", nums), 1);
+    assert_eq!(call(&mut store, "has_seven", empty), 0);
+
+    let pushed = call(&mut store, "push", nums);
+    assert_eq!(read_items(&store, &memory, pushed), [5, 7, 11, 9]);
+    assert_eq!(read_items(&store, &memory, nums), [5, 7, 11]);
+
+    let a = write_list(&mut store, 0x80200, &[1, 2]);
+    let b = write_list(&mut store, 0x80300, &[3]);
+    let nested = write_list(&mut store, 0x80400, &[a, empty, b]);
+    let flat = call(&mut store, "flat", nested);
+    assert_eq!(read_items(&store, &memory, flat), [1, 2, 3]);
+
+    // String items are compared by content, not by address
+    let x = write_string(&mut store, &memory, 0x80500, "a");
+    let y = write_string(&mut store, &memory, 0x80510, "b");
+    let words = write_list(&mut store, 0x80600, &[x, y]);
+    assert_eq!(call(&mut store, "has_b", words), 1);
+    let only_a = write_list(&mut store, 0x80700, &[x]);
+    assert_eq!(call(&mut store, "has_b", only_a), 0);
+}
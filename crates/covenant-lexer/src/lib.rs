@@ -10,30 +10,47 @@ mod token;
 pub use token::*;
 
 use logos::Logos;
-use covenant_ast::Span;
+use covenant_ast::{FileId, Span};
 
 /// Tokenize a source string into a vector of tokens
 pub fn tokenize(source: &str) -> Vec<Token> {
+    tokenize_with_comments(source).0
+}
+
+/// Tokenize a source string, returning comments (`// ...` and `(* ... *)`)
+/// separately from the tokens
+pub fn tokenize_with_comments(source: &str) -> (Vec<Token>, Vec<Token>) {
+    tokenize_file(source, FileId::default())
+}
+
+/// Tokenize a file of a [`SourceMap`](covenant_ast::SourceMap); token spans
+/// carry `file`. Comments are returned separately from the tokens.
+pub fn tokenize_file(source: &str, file: FileId) -> (Vec<Token>, Vec<Token>) {
     let mut tokens = Vec::new();
+    let mut comments = Vec::new();
     let mut lexer = TokenKind::lexer(source);
 
     while let Some(result) = lexer.next() {
-        let span = Span::new(lexer.span().start, lexer.span().end);
+        let span = Span::new(lexer.span().start, lexer.span().end).in_file(file);
         let kind = match result {
             Ok(kind) => kind,
             Err(_) => TokenKind::Error,
         };
-        tokens.push(Token { kind, span });
+        if kind.is_comment() {
+            comments.push(Token { kind, span });
+        } else {
+            tokens.push(Token { kind, span });
+        }
     }
 
     // Add EOF token
     let end = source.len();
     tokens.push(Token {
         kind: TokenKind::Eof,
-        span: Span::new(end, end),
+        span: Span::new(end, end).in_file(file),
     });
 
-    tokens
+    (tokens, comments)
 }
 
 /// A token with its span
@@ -307,6 +324,42 @@ string""""#;
         assert_eq!(tokens[4].kind, TokenKind::Eof);
     }
 
+    #[test]
+    fn test_comments_kept_separately() {
+        let source = "(* header\n   comment *) let x // trailing\n= COUNT(*)";
+        let (tokens, comments) = tokenize_with_comments(source);
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].kind, TokenKind::BlockComment);
+        assert_eq!(comments[0].text(source), "(* header\n   comment *)");
+        assert_eq!(comments[1].kind, TokenKind::LineComment);
+        assert_eq!(comments[1].text(source), "// trailing");
+
+        // `(*)` is not a block comment
+        let kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Let,
+                TokenKind::Ident,
+                TokenKind::Eq,
+                TokenKind::Ident,
+                TokenKind::LParen,
+                TokenKind::Star,
+                TokenKind::RParen,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_block_comment_without_spaces() {
+        let source = "(*note*) let (**) x";
+        let (tokens, comments) = tokenize_with_comments(source);
+        let texts: Vec<&str> = comments.iter().map(|c| c.text(source)).collect();
+        assert_eq!(texts, vec!["(*note*)", "(**)"]);
+        assert_eq!(tokens.len(), 3);
+    }
+
     #[test]
     fn test_span_accuracy() {
         let source = "let x = 5";
@@ -4,7 +4,6 @@ use logos::Logos;
 
 #[derive(Logos, Debug, Clone, Copy, PartialEq, Eq)]
 #[logos(skip r"[ \t\r\n\f]+")]  // Skip whitespace
-#[logos(skip r"//[^\n]*")]      // Skip line comments
 pub enum TokenKind {
     // === Keywords ===
     #[token("let")]
@@ -288,11 +287,36 @@ pub enum TokenKind {
     #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*")]
     Ident,
 
+    // === Comments ===
+    // Filtered out by `tokenize`; `tokenize_with_comments` keeps them
+    #[regex(r"//[^\n]*")]
+    LineComment,
+    // `(*` must not be followed by `)` so SQL `COUNT(*)` is not a comment
+    #[regex(r"\(\*[^)]", block_comment)]
+    BlockComment,
+
     // === Special ===
     Error,
     Eof,
 }
 
+/// Consume the rest of a block comment through its closing `*)`
+fn block_comment(lex: &mut logos::Lexer<TokenKind>) -> bool {
+    // The third character of `(**)` is already the closing `*`
+    if lex.slice().ends_with("**") && lex.remainder().starts_with(')') {
+        lex.bump(1);
+        return true;
+    }
+    let rest = lex.remainder();
+    match rest.find("*)") {
+        Some(end) => {
+            lex.bump(end + 2);
+            true
+        }
+        None => false,
+    }
+}
+
 impl TokenKind {
     pub fn is_keyword(&self) -> bool {
         matches!(
@@ -362,6 +386,10 @@ impl TokenKind {
         )
     }
 
+    pub fn is_comment(&self) -> bool {
+        matches!(self, TokenKind::LineComment | TokenKind::BlockComment)
+    }
+
     pub fn describe(&self) -> &'static str {
         match self {
             TokenKind::Let => "'let'",
@@ -490,6 +518,7 @@ impl TokenKind {
             TokenKind::TripleString => "triple-quoted string",
             TokenKind::String => "string",
             TokenKind::Ident => "identifier",
+            TokenKind::LineComment | TokenKind::BlockComment => "comment",
             TokenKind::Error => "error",
             TokenKind::Eof => "end of file",
         }
@@ -376,6 +376,7 @@ fn snippet_kind_str(kind: SnippetKind) -> String {
         SnippetKind::ExternImpl => "extern-impl".to_string(),
         SnippetKind::Test => "test".to_string(),
         SnippetKind::Data => "data".to_string(),
+        SnippetKind::Effect => "effect".to_string(),
     }
 }
 
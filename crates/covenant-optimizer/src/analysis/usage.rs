@@ -91,7 +91,7 @@ fn collect_consumed_bindings(kind: &StepKind, consumed: &mut HashSet<String>) {
             match &if_step.condition {
                 InputSource::Var(name) => { consumed.insert(name.clone()); }
                 InputSource::Field { of, .. } => { consumed.insert(of.clone()); }
-                InputSource::Lit(_) => {}
+                InputSource::Lit(_) | InputSource::FnRef(_) => {}
             }
         }
         StepKind::Match(match_step) => {
@@ -175,7 +175,7 @@ fn collect_from_input_source(source: &InputSource, consumed: &mut HashSet<String
         InputSource::Var(name) => {
             consumed.insert(name.clone());
         }
-        InputSource::Lit(_) => {}
+        InputSource::Lit(_) | InputSource::FnRef(_) => {}
         InputSource::Field { of, .. } => {
             consumed.insert(of.clone());
         }
@@ -21,7 +21,7 @@
 //! };
 //! let result = optimize(&mut body.steps, &settings);
 //! for warning in result.warnings {
-//!     eprintln!("{}: {}", warning.code, warning.message);
+//!     eprintln!("{}: {}", warning.code(), warning.message);
 //! }
 //! ```
 
@@ -30,7 +30,7 @@ pub mod passes;
 
 pub use passes::{
     ConstantFolding, DeadCodeElimination, OptContext, OptLevel, OptSettings, OptWarning,
-    OptimizationPass, PassResult, UnusedBindingDetection,
+    OptWarningKind, OptimizationPass, PassResult, UnusedBindingDetection,
 };
 
 use covenant_ast::Step;
@@ -189,7 +189,7 @@ mod tests {
         let result = optimize(&mut steps, &settings);
 
         assert!(!result.modified); // O1 doesn't modify
-        assert!(result.warnings.iter().any(|w| w.code == "W-DEAD-002"));
+        assert!(result.warnings.iter().any(|w| w.code() == "W-DEAD-002"));
         assert_eq!(steps.len(), 3); // Still there
     }
 
@@ -252,7 +252,7 @@ mod tests {
 
         let result = optimize(&mut steps, &settings);
 
-        assert!(result.warnings.iter().any(|w| w.code == "W-DEAD-001"
+        assert!(result.warnings.iter().any(|w| w.code() == "W-DEAD-001"
             && w.message.contains("unused_value")));
     }
 
@@ -271,7 +271,7 @@ mod tests {
         let result = optimize(&mut steps, &settings);
 
         // No unused binding warnings
-        assert!(!result.warnings.iter().any(|w| w.code == "W-DEAD-001"));
+        assert!(!result.warnings.iter().any(|w| w.code() == "W-DEAD-001"));
     }
 
     #[test]
@@ -315,8 +315,8 @@ mod tests {
         assert!(result.modified);
 
         // Should have warnings for unused binding and unreachable code
-        assert!(result.warnings.iter().any(|w| w.code == "W-DEAD-001")); // unused
-        assert!(result.warnings.iter().any(|w| w.code == "W-DEAD-002")); // unreachable
+        assert!(result.warnings.iter().any(|w| w.code() == "W-DEAD-001")); // unused
+        assert!(result.warnings.iter().any(|w| w.code() == "W-DEAD-002")); // unreachable
 
         // s4 should be removed
         assert_eq!(steps.len(), 3);
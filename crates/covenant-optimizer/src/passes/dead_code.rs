@@ -6,7 +6,7 @@
 use covenant_ast::{Step, StepKind};
 
 use crate::analysis::{compute_reachable, analyze_usage};
-use crate::passes::{OptContext, OptLevel, OptWarning, OptimizationPass, PassResult};
+use crate::passes::{OptContext, OptLevel, OptWarning, OptWarningKind, OptimizationPass, PassResult};
 
 /// Dead code elimination pass
 ///
@@ -31,7 +31,7 @@ impl OptimizationPass for DeadCodeElimination {
         for step in steps.iter() {
             if !reachable.contains(&step.id) {
                 warnings.push(OptWarning {
-                    code: "W-DEAD-002",
+                    kind: OptWarningKind::UnreachableStep,
                     message: format!("Unreachable code: step '{}' is after a return", step.id),
                     step_id: Some(step.id.clone()),
                 });
@@ -48,7 +48,7 @@ impl OptimizationPass for DeadCodeElimination {
             // Check if binding is ever used
             if !usage.used_by.contains_key(&step.output_binding) && !step_has_effects(&step.kind) {
                 warnings.push(OptWarning {
-                    code: "W-DEAD-001",
+                    kind: OptWarningKind::UnusedBinding,
                     message: format!(
                         "Unused binding '{}' in step '{}'",
                         step.output_binding, step.id
@@ -164,7 +164,7 @@ mod tests {
         let pass = DeadCodeElimination;
         let result = pass.run(&mut steps, &make_ctx(OptLevel::O1));
 
-        assert!(result.warnings.iter().any(|w| w.code == "W-DEAD-002" && w.step_id == Some("s3".into())));
+        assert!(result.warnings.iter().any(|w| w.code() == "W-DEAD-002" && w.step_id == Some("s3".into())));
         assert!(!result.modified); // O1 doesn't remove code
     }
 
@@ -195,7 +195,7 @@ mod tests {
         let result = pass.run(&mut steps, &make_ctx(OptLevel::O1));
 
         assert!(result.warnings.iter().any(|w|
-            w.code == "W-DEAD-001" &&
+            w.code() == "W-DEAD-001" &&
             w.message.contains("unused")
         ));
     }
@@ -220,7 +220,7 @@ mod tests {
         let result = pass.run(&mut steps, &make_ctx(OptLevel::O1));
 
         // No unused binding warnings - "value" is used by s2, "result" is used by s3
-        assert!(!result.warnings.iter().any(|w| w.code == "W-DEAD-001"));
+        assert!(!result.warnings.iter().any(|w| w.code() == "W-DEAD-001"));
     }
 
     #[test]
@@ -234,6 +234,6 @@ mod tests {
         let result = pass.run(&mut steps, &make_ctx(OptLevel::O1));
 
         // No warnings for "_" bindings
-        assert!(!result.warnings.iter().any(|w| w.code == "W-DEAD-001"));
+        assert!(!result.warnings.iter().any(|w| w.code() == "W-DEAD-001"));
     }
 }
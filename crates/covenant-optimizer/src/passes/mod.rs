@@ -10,7 +10,8 @@ pub use constant_fold::ConstantFolding;
 pub use dead_code::DeadCodeElimination;
 pub use unused_binding::UnusedBindingDetection;
 
-use covenant_ast::Step;
+use covenant_ast::{Span, Step};
+use covenant_checker::{Diagnostic, DiagnosticKind, Severity};
 
 /// Optimization level controlling which passes run
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
@@ -59,17 +60,59 @@ pub struct PassResult {
     pub warnings: Vec<OptWarning>,
 }
 
+/// What an optimization pass warns about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptWarningKind {
+    /// A binding no step reads (W-DEAD-001)
+    UnusedBinding,
+    /// A step after an unconditional return (W-DEAD-002)
+    UnreachableStep,
+}
+
+impl OptWarningKind {
+    /// Warning code, e.g. "W-DEAD-001"
+    pub fn code(&self) -> &'static str {
+        match self {
+            OptWarningKind::UnusedBinding => "W-DEAD-001",
+            OptWarningKind::UnreachableStep => "W-DEAD-002",
+        }
+    }
+}
+
 /// A warning generated by an optimization pass
 #[derive(Debug, Clone)]
 pub struct OptWarning {
-    /// Warning code (e.g., "W-DEAD-001")
-    pub code: &'static str,
+    /// What the warning is about
+    pub kind: OptWarningKind,
     /// Human-readable message
     pub message: String,
     /// Optional step ID where the warning occurred
     pub step_id: Option<String>,
 }
 
+impl OptWarning {
+    /// Warning code, e.g. "W-DEAD-001"
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+impl From<OptWarning> for Diagnostic {
+    fn from(warning: OptWarning) -> Self {
+        let diagnostic = Diagnostic::new(
+            DiagnosticKind::Message(warning.message),
+            Span::dummy(),
+            warning.kind.code(),
+            String::new(),
+        )
+        .with_severity(Severity::Warning);
+        match warning.step_id {
+            Some(step_id) => diagnostic.with_step(step_id),
+            None => diagnostic,
+        }
+    }
+}
+
 /// Trait for optimization passes
 pub trait OptimizationPass {
     /// Name of this pass for logging/debugging
@@ -8,7 +8,7 @@ use covenant_ast::Step;
 
 use crate::analysis::analyze_usage;
 use crate::passes::dead_code::step_has_effects;
-use crate::passes::{OptContext, OptWarning, OptimizationPass, PassResult};
+use crate::passes::{OptContext, OptWarning, OptWarningKind, OptimizationPass, PassResult};
 
 /// Unused binding detection pass
 ///
@@ -50,7 +50,7 @@ impl OptimizationPass for UnusedBindingDetection {
                 };
 
                 warnings.push(OptWarning {
-                    code: "W-DEAD-001",
+                    kind: OptWarningKind::UnusedBinding,
                     message,
                     step_id: Some(step.id.clone()),
                 });
@@ -135,7 +135,7 @@ mod tests {
         let result = pass.run(&mut steps, &make_ctx());
 
         assert!(result.warnings.iter().any(|w|
-            w.code == "W-DEAD-001" &&
+            w.code() == "W-DEAD-001" &&
             w.message.contains("unused") &&
             !w.message.contains("effectful")
         ));
@@ -154,7 +154,7 @@ mod tests {
 
         // Should warn but mention it's effectful
         assert!(result.warnings.iter().any(|w|
-            w.code == "W-DEAD-001" &&
+            w.code() == "W-DEAD-001" &&
             w.message.contains("effectful")
         ));
     }
@@ -46,6 +46,16 @@ pub enum ParseError {
 
     #[error("unexpected section: {section}")]
     UnexpectedSection { section: String, span: Span },
+
+    #[error("invalid subsumption rule: {rule} (expected exact, path, glob, url, range, or set)")]
+    InvalidSubsumptionRule { rule: String, span: Span },
+
+    #[error("{message}; expected order: {expected}")]
+    InvalidOrdering {
+        message: String,
+        expected: String,
+        span: Span,
+    },
 }
 
 impl ParseError {
@@ -62,6 +72,27 @@ impl ParseError {
             ParseError::InvalidStepKind { span, .. } => *span,
             ParseError::InvalidOperation { span, .. } => *span,
             ParseError::UnexpectedSection { span, .. } => *span,
+            ParseError::InvalidSubsumptionRule { span, .. } => *span,
+            ParseError::InvalidOrdering { span, .. } => *span,
+        }
+    }
+
+    /// Error code for machine-readable output
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::UnexpectedToken { .. }
+            | ParseError::Unexpected { .. }
+            | ParseError::ExpectedDeclaration { .. }
+            | ParseError::InvalidExpression { .. }
+            | ParseError::InvalidPattern { .. }
+            | ParseError::InvalidType { .. } => "E-PARSE-001",
+            ParseError::InvalidOrdering { .. } => "E-PARSE-003",
+            ParseError::UnexpectedEof { .. } => "E-PARSE-004",
+            ParseError::InvalidSnippetKind { .. } => "E-PARSE-005",
+            ParseError::InvalidStepKind { .. } => "E-PARSE-006",
+            ParseError::InvalidOperation { .. } => "E-PARSE-007",
+            ParseError::UnexpectedSection { .. } => "E-PARSE-008",
+            ParseError::InvalidSubsumptionRule { .. } => "E-PARSE-009",
         }
     }
 
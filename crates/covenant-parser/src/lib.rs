@@ -12,14 +12,123 @@ mod parser;
 pub use error::*;
 pub use parser::*;
 
-use covenant_ast::Program;
-use covenant_lexer::tokenize;
+use covenant_ast::{Comment, EffectDecl, FileId, Program};
+use covenant_lexer::{tokenize, tokenize_file, Token};
 
 /// Parse a source string into a Program AST
 pub fn parse(source: &str) -> Result<Program, ParseError> {
-    let tokens = tokenize(source);
+    parse_file(source, FileId::default())
+}
+
+/// Parse a file of a [`SourceMap`](covenant_ast::SourceMap). Every span in
+/// the program, and in a parse error, carries `file`.
+pub fn parse_file(source: &str, file: FileId) -> Result<Program, ParseError> {
+    let (tokens, comments) = tokenize_file(source, file);
+    let mut parser = Parser::new(source, tokens);
+    let mut program = parser.parse_program()?;
+    attach_comments(&mut program, source, &comments);
+    Ok(program)
+}
+
+/// A program parsed with error recovery
+#[derive(Debug)]
+pub struct ParseOutput {
+    /// The snippets that parsed without syntax errors
+    pub program: Program,
+    /// Every syntax error and every block out of canonical order
+    /// (E-PARSE-003), in source order
+    pub errors: Vec<ParseError>,
+}
+
+impl ParseOutput {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Parse a source string, recovering from syntax errors at `snippet`,
+/// section and `step` boundaries instead of stopping at the first.
+/// Sections and step attributes out of canonical order are reported too;
+/// [`parse`] accepts them, and printing the program puts them in order.
+pub fn parse_with_recovery(source: &str) -> ParseOutput {
+    parse_file_with_recovery(source, FileId::default())
+}
+
+/// [`parse_with_recovery`] for a file of a
+/// [`SourceMap`](covenant_ast::SourceMap)
+pub fn parse_file_with_recovery(source: &str, file: FileId) -> ParseOutput {
+    let (tokens, comments) = tokenize_file(source, file);
     let mut parser = Parser::new(source, tokens);
-    parser.parse_program()
+    let (mut program, errors) = parser.parse_program_recovering();
+    attach_comments(&mut program, source, &comments);
+    ParseOutput { program, errors }
+}
+
+/// Attach each comment to the snippet it appears in or precedes; comments
+/// after the last snippet go to the last snippet
+fn attach_comments(program: &mut Program, source: &str, comments: &[Token]) {
+    let Program::Snippets { snippets, .. } = program else {
+        return;
+    };
+    if snippets.is_empty() {
+        return;
+    }
+    for token in comments {
+        let index = snippets
+            .iter()
+            .position(|s| token.span.start < s.span.end)
+            .unwrap_or(snippets.len() - 1);
+        let (trailing, line) = comment_line(source, token, comments);
+        snippets[index].comments.push(Comment {
+            text: token.text(source).to_string(),
+            trailing,
+            line,
+            span: token.span,
+        });
+    }
+}
+
+/// Whether a comment trails code on its line, and the trimmed code it
+/// belongs to: that code if trailing, otherwise the next line of code
+fn comment_line(source: &str, comment: &Token, comments: &[Token]) -> (bool, String) {
+    let line_start = source[..comment.span.start].rfind('\n').map_or(0, |i| i + 1);
+    let before = source[line_start..comment.span.start].trim();
+    if !before.is_empty() {
+        return (true, before.to_string());
+    }
+
+    let mut offset = comment.span.end;
+    while offset < source.len() {
+        let end = source[offset..].find('\n').map_or(source.len(), |i| offset + i);
+        // Code on the line stops at the first comment starting on it
+        let code_end = comments
+            .iter()
+            .map(|c| c.span.start)
+            .filter(|&start| start >= offset && start < end)
+            .min()
+            .unwrap_or(end);
+        let code = source[offset..code_end].trim();
+        if !code.is_empty() {
+            return (false, code.to_string());
+        }
+        // Skip past comments spanning several lines
+        let resume = comments
+            .iter()
+            .filter(|c| c.span.start >= offset && c.span.start < end)
+            .map(|c| c.span.end)
+            .max()
+            .unwrap_or(end);
+        offset = if resume > end { resume } else { end + 1 };
+    }
+    (false, String::new())
+}
+
+/// Parse a single effect as written after `effect`, e.g. `filesystem(path="/data")`
+pub fn parse_effect(source: &str) -> Result<EffectDecl, ParseError> {
+    let source = format!("effect {}", source);
+    let tokens = tokenize(&source);
+    let mut parser = Parser::new(&source, tokens);
+    parser.parse_standalone_effect()
 }
 
 #[cfg(test)]
@@ -2010,4 +2119,467 @@ end
             panic!("Expected Snippets program");
         }
     }
+
+    #[test]
+    fn test_parse_function_value_types_and_args() {
+        use covenant_ast::{InputSource, SignatureKind, StepKind, TypeKind};
+
+        let source = r#"
+snippet id="fn.compose" kind="fn"
+
+signature
+  fn name="compose"
+    param name="f" type="(Int, List<String>) -> (Int) -> Bool"
+    returns type="Int"
+  end
+end
+
+body
+  step id="s1" kind="call"
+    fn="fn.apply"
+    arg name="f" fn="math.double"
+    as="result"
+  end
+end
+
+end
+"#;
+        let result = parse(source);
+        assert!(result.is_ok(), "Failed to parse function types: {:?}", result.err());
+        let Program::Snippets { snippets, .. } = result.unwrap() else {
+            panic!("Expected Snippets program");
+        };
+
+        let sig = snippets[0].sections.iter().find_map(|s| match s {
+            Section::Signature(sig) => match &sig.kind {
+                SignatureKind::Function(f) => Some(f),
+                _ => None,
+            },
+            _ => None,
+        }).expect("signature not found");
+        let TypeKind::Function { params, ret } = &sig.params[0].ty.kind else {
+            panic!("Expected a function type, got {:?}", sig.params[0].ty.kind);
+        };
+        let names: Vec<String> = params.iter().map(|t| match &t.kind {
+            TypeKind::Named(path) => path.name().to_string(),
+            other => panic!("Expected a named type, got {:?}", other),
+        }).collect();
+        assert_eq!(names, vec!["Int", "List<String>"]);
+        assert!(matches!(&ret.kind, TypeKind::Function { params, .. } if params.len() == 1));
+
+        let body = snippets[0].sections.iter().find_map(|s| match s {
+            Section::Body(b) => Some(b),
+            _ => None,
+        }).expect("body not found");
+        let StepKind::Call(call) = &body.steps[0].kind else {
+            panic!("Expected a call step");
+        };
+        assert!(matches!(&call.args[0].source, InputSource::FnRef(name) if name == "math.double"));
+    }
+
+    #[test]
+    fn test_parse_effect_variables() {
+        let source = r#"
+snippet id="list.map" kind="fn"
+
+effects
+  effect console
+  effect of="f"
+end
+
+signature
+  fn name="map"
+    param name="f" type="(Int) -> Int"
+    returns type="Int"
+  end
+end
+
+end
+"#;
+        let result = parse(source);
+        assert!(result.is_ok(), "Failed to parse effect variables: {:?}", result.err());
+        let Program::Snippets { snippets, .. } = result.unwrap() else {
+            panic!("Expected Snippets program");
+        };
+
+        let effects = snippets[0].sections.iter().find_map(|s| match s {
+            Section::Effects(e) => Some(e),
+            _ => None,
+        }).expect("effects section not found");
+        assert_eq!(effects.effects.len(), 1);
+        assert_eq!(effects.effects[0].name, "console");
+        assert_eq!(effects.vars.len(), 1);
+        assert_eq!(effects.vars[0].param, "f");
+    }
+
+    #[test]
+    fn test_parse_standalone_effect() {
+        let effect = parse_effect(r#"filesystem(path="/data", mode="read")"#).unwrap();
+        assert_eq!(effect.name, "filesystem");
+        assert_eq!(effect.params.len(), 2);
+        assert_eq!(effect.params[1].name, "mode");
+
+        assert_eq!(parse_effect("database.read").unwrap().name, "database.read");
+        assert!(parse_effect("database extra").is_err());
+        assert!(parse_effect("").is_err());
+    }
+
+    #[test]
+    fn test_parse_effect_snippet_with_rules() {
+        use covenant_ast::{SignatureKind, SnippetKind, SubsumptionRule};
+
+        let source = r#"
+snippet id="queue" kind="effect"
+
+signature
+  effect name="queue"
+    param name="topic" rule="glob"
+    param name="region"
+  end
+end
+
+end
+"#;
+        let result = parse(source);
+        assert!(result.is_ok(), "Failed to parse effect snippet: {:?}", result.err());
+        let Program::Snippets { snippets, .. } = result.unwrap() else {
+            panic!("Expected Snippets program");
+        };
+        assert_eq!(snippets[0].kind, SnippetKind::Effect);
+
+        let Some(Section::Signature(sig)) = snippets[0].sections.first() else {
+            panic!("Expected a signature section");
+        };
+        let SignatureKind::Effect(effect) = &sig.kind else {
+            panic!("Expected an effect signature");
+        };
+        assert_eq!(effect.name, "queue");
+        let rules: Vec<(&str, SubsumptionRule)> =
+            effect.params.iter().map(|p| (p.name.as_str(), p.rule)).collect();
+        assert_eq!(rules, vec![("topic", SubsumptionRule::Glob), ("region", SubsumptionRule::Exact)]);
+
+        let related = source.replace(
+            "    param name=\"topic\"",
+            "    parent effect=\"messaging\"\n    implies effect=\"queue.read\"\n    param name=\"topic\"",
+        );
+        let Program::Snippets { snippets, .. } = parse(&related).expect("parse failed") else {
+            panic!("Expected Snippets program");
+        };
+        let Some(Section::Signature(sig)) = snippets[0].sections.first() else {
+            panic!("Expected a signature section");
+        };
+        let SignatureKind::Effect(effect) = &sig.kind else {
+            panic!("Expected an effect signature");
+        };
+        assert_eq!(effect.parents, vec!["messaging"]);
+        assert_eq!(effect.implies, vec!["queue.read"]);
+        assert_eq!(effect.params.len(), 2);
+
+        let invalid = source.replace("rule=\"glob\"", "rule=\"fuzzy\"");
+        assert!(matches!(
+            parse(&invalid),
+            Err(ParseError::InvalidSubsumptionRule { rule, .. }) if rule == "fuzzy"
+        ));
+    }
+
+    #[test]
+    fn test_comments_survive_formatting() {
+        use covenant_ast::printer::to_cov;
+
+        let source = r#"// Header comment
+snippet id="app.double" kind="fn"
+
+(* Signature comes after effects
+   in canonical order *)
+signature
+  fn name="double"
+    // the number to double
+    param name="n" type="Int"
+    returns type="Int" (*never negative*)
+    // nothing follows on this line's level
+  end
+end
+
+effects
+  effect console // why console
+end
+
+body
+  // doubled
+  step id="s1" kind="compute"
+    op=add
+    input var="n"
+    input var="n"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result" // the sum
+    as="_"
+  end
+  // end of body
+end
+
+end
+
+// Between snippets
+snippet id="app.zero" kind="fn"
+signature
+  fn name="zero"
+    returns type="Int"
+  end
+end
+end
+// Trailing
+"#;
+        let expected = r#"// Header comment
+snippet id="app.double" kind="fn"
+effects
+  effect console // why console
+end
+(* Signature comes after effects
+   in canonical order *)
+signature
+  fn name="double"
+    // the number to double
+    param name="n" type="Int"
+    returns type="Int" (*never negative*)
+  end
+  // nothing follows on this line's level
+end
+body
+  // doubled
+  step id="s1" kind="compute"
+    op=add input var="n" input var="n"
+    as="result"
+  end
+  step id="s2" kind="return"
+    from="result" // the sum
+    as="_"
+  end
+  // end of body
+end
+end
+
+// Between snippets
+snippet id="app.zero" kind="fn"
+signature
+  fn name="zero"
+    returns type="Int"
+  end
+end
+end
+// Trailing"#;
+
+        let formatted = to_cov(&parse(source).unwrap());
+        assert_eq!(formatted, expected);
+        assert_eq!(to_cov(&parse(&formatted).unwrap()), formatted);
+    }
+
+    #[test]
+    fn test_parse_file_stamps_file_id() {
+        let source = "snippet id=\"a.b\" kind=\"fn\"\nend\n";
+        let Program::Snippets { snippets, .. } = parse_file(source, FileId(3)).unwrap() else {
+            panic!("expected snippets");
+        };
+        assert_eq!(snippets[0].span.file, FileId(3));
+        assert_eq!(parse_file("snippet id=", FileId(3)).unwrap_err().span().file, FileId(3));
+    }
+
+    #[test]
+    fn test_recovery_collects_errors_and_keeps_good_snippets() {
+        let source = r#"snippet id="app.one" kind="fn"
+signature
+  fn name="one"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="compute"
+    op=add
+    input var="x"
+  end
+  step id="s2" kind="return"
+    lit=1
+    as="_"
+  end
+end
+end
+
+snippet id="app.bad" kind="widget"
+end
+
+snippet id="app.two" kind="fn"
+signature
+  fn name="two"
+    returns type="Int"
+  end
+end
+body
+  step id="s1" kind="if"
+    condition="c"
+    then
+      step id="s1a" kind="teleport"
+        as="_"
+      end
+    end
+    as="_"
+  end
+end
+sideeffects
+  effect console
+end
+end
+
+snippet id="app.ok" kind="fn"
+signature
+  fn name="ok"
+    returns type="Int"
+  end
+end
+end
+"#;
+        let output = parse_with_recovery(source);
+        let lines: Vec<usize> = output
+            .errors
+            .iter()
+            .map(|e| source[..e.span().start].matches('\n').count() + 1)
+            .collect();
+        let codes: Vec<&str> = output.errors.iter().map(ParseError::code).collect();
+        assert_eq!(codes, vec!["E-PARSE-001", "E-PARSE-005", "E-PARSE-006", "E-PARSE-008"]);
+        assert_eq!(lines, vec![11, 20, 33, 39]);
+
+        // Snippets with errors inside are kept as signature stubs
+        let Program::Snippets { snippets, .. } = &output.program else {
+            panic!("expected snippets");
+        };
+        let ids: Vec<&str> = snippets.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["app.one", "app.two", "app.ok"]);
+        for stub in &snippets[..2] {
+            assert!(matches!(stub.sections.as_slice(), [Section::Signature(_)]), "{:?}", stub.sections);
+        }
+
+        // The first error is the one `parse` stops at
+        assert_eq!(parse(source).unwrap_err().to_string(), output.errors[0].to_string());
+    }
+
+    #[test]
+    fn test_recovery_from_missing_end() {
+        // s1 lacks its `end`, so the skip takes s2's and the body's, and the
+        // body ends at the snippet's: the first snippet runs into the
+        // second. That one is cut off, so its step, body and snippet are each
+        // unterminated.
+        let source = "snippet id=\"a.f\" kind=\"fn\"\nbody\n  step id=\"s1\" kind=\"return\"\n    lit=1\n  step id=\"s2\" kind=\"return\"\n    lit=2\n    as=\"_\"\n  end\nend\nend\nsnippet id=\"a.g\" kind=\"fn\"\nbody\n  step id=\"s1\" kind=\"return\"\n    lit=1\n";
+        let output = parse_with_recovery(source);
+        assert!(!output.is_ok());
+        assert_eq!(output.errors.len(), 5, "{:?}", output.errors);
+        assert_eq!(&source[output.errors[0].span().start..][..4], "step");
+        assert_eq!(&source[output.errors[1].span().start..][..7], "snippet");
+        assert!(output.errors[2..].iter().all(|e| e.span().start == source.len()));
+        assert!(matches!(output.program, Program::Snippets { ref snippets, .. } if snippets.is_empty()));
+
+        let output = parse_with_recovery("snippet id=\"a.b\" kind=\"fn\"\nend\n");
+        assert!(output.is_ok());
+    }
+
+    #[test]
+    fn test_recovery_without_indentation() {
+        // The skipped step holds a nested block; nothing is indented
+        let source = r#"snippet id="a.f" kind="fn"
+signature
+fn name="f"
+returns type="Int"
+end
+end
+body
+step id="s1" kind="teleport"
+then
+step id="s1a" kind="return"
+lit=1
+as="_"
+end
+end
+as="_"
+end
+step id="s2" kind="return"
+lit=2
+as="_"
+end
+end
+end
+snippet id="a.g" kind="fn"
+end
+"#;
+        let output = parse_with_recovery(source);
+        let codes: Vec<&str> = output.errors.iter().map(ParseError::code).collect();
+        assert_eq!(codes, vec!["E-PARSE-006"], "{:?}", output.errors);
+        let Program::Snippets { snippets, .. } = &output.program else {
+            panic!("expected snippets");
+        };
+        let ids: Vec<&str> = snippets.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["a.f", "a.g"]);
+    }
+
+    #[test]
+    fn test_canonical_ordering_is_reported_and_printed_in_order() {
+        use covenant_ast::printer::to_cov;
+
+        let source = r#"snippet id="app.main" kind="fn"
+body
+  step kind="call" id="s1"
+    as="greeting"
+    arg name="name" lit="world"
+    fn="text.greet"
+  end
+  step id="s2" kind="return"
+    from="greeting"
+    as="_"
+  end
+end
+signature
+  fn name="main"
+    returns type="String"
+  end
+end
+effects
+  effect console
+end
+end
+"#;
+        let output = parse_with_recovery(source);
+        let messages: Vec<String> = output.errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "attribute `id` must come before `kind`; expected order: id, kind, call attributes, as",
+                "attribute `as` must come after the call step's attributes; expected order: id, kind, call attributes, as",
+                "attribute `fn` must come before `arg`; expected order: fn, arg",
+                "section `signature` must come before `body`; expected order: effects, signature, body",
+                "section `effects` must come before `body`; expected order: effects, signature, body",
+            ]
+        );
+        assert!(output.errors.iter().all(|e| e.code() == "E-PARSE-003"));
+        assert_eq!(&source[output.errors[1].span().start..][..2], "as");
+
+        // Ordering errors keep the snippet, and printing puts it in order
+        let program = parse(source).unwrap();
+        let printed = to_cov(&program);
+        assert!(parse_with_recovery(&printed).is_ok(), "{}", printed);
+        assert!(printed.starts_with("snippet id=\"app.main\" kind=\"fn\"\neffects"));
+        assert!(printed.contains("step id=\"s1\" kind=\"call\"\n    fn=\"text.greet\"\n    arg name=\"name\" lit=\"world\"\n    as=\"greeting\""));
+
+        // An `as` with nothing to precede is in its place
+        let output = parse_with_recovery(
+            "snippet id=\"a.f\" kind=\"fn\"\nbody\n  step id=\"s1\" kind=\"transaction\"\n    as=\"_\"\n  end\nend\nend\n",
+        );
+        assert!(output.is_ok(), "{:?}", output.errors);
+
+        // A compute step's inputs ahead of its op are recorded and reordered
+        let source = "snippet id=\"a.f\" kind=\"fn\"\nbody\n  step id=\"s1\" kind=\"compute\"\n    input lit=1\n    op=add\n    input lit=2\n    as=\"x\"\n  end\nend\nend\n";
+        let output = parse_with_recovery(source);
+        let messages: Vec<String> = output.errors.iter().map(ToString::to_string).collect();
+        assert_eq!(messages, vec!["attribute `op` must come before `input`; expected order: op, input"]);
+        assert_eq!(&source[output.errors[0].span().start..][..2], "op");
+        let printed = to_cov(&output.program);
+        assert!(printed.contains("op=add input lit=1 input lit=2\n"), "{}", printed);
+    }
 }
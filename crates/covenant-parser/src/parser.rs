@@ -9,6 +9,12 @@ pub struct Parser<'a> {
     source: &'a str,
     tokens: Vec<Token>,
     pos: usize,
+    /// Record errors and resynchronize instead of stopping at the first
+    recover: bool,
+    /// Errors recovered from
+    errors: Vec<ParseError>,
+    /// Blocks parsed in spite of a non-canonical order (E-PARSE-003)
+    ordering_errors: Vec<ParseError>,
 }
 
 impl<'a> Parser<'a> {
@@ -17,6 +23,9 @@ impl<'a> Parser<'a> {
             source,
             tokens,
             pos: 0,
+            recover: false,
+            errors: Vec::new(),
+            ordering_errors: Vec::new(),
         }
     }
 
@@ -161,6 +170,195 @@ impl<'a> Parser<'a> {
             .replace("\\\\", "\\")
     }
 
+    // === Error Recovery ===
+
+    /// Text of the token at `index`
+    fn text_at(&self, index: usize) -> &str {
+        let span = self.tokens[index].span;
+        &self.source[span.start..span.end]
+    }
+
+    /// True if the token at `index` opens a block closed by `end`. Keywords
+    /// in attributes (`fn="..."`, `op=and`) open none, nor do the forms
+    /// without an `end`: `effect console`, `collection of="..."`, a
+    /// `variant` pattern and a note with a plain string.
+    fn opens_block(&self, index: usize) -> bool {
+        let kind_at = |i: usize| self.tokens.get(i).map_or(TokenKind::Eof, |token| token.kind);
+        let next = kind_at(index + 1);
+        let after_case = index > 0 && kind_at(index - 1) == TokenKind::Ident && self.text_at(index - 1) == "case";
+        if next == TokenKind::Eq || (index > 0 && kind_at(index - 1) == TokenKind::Eq) {
+            return false;
+        }
+        match kind_at(index) {
+            TokenKind::Effects
+            | TokenKind::Requires
+            | TokenKind::Types
+            | TokenKind::Signature
+            | TokenKind::Body
+            | TokenKind::Tests
+            | TokenKind::Metadata
+            | TokenKind::Relations
+            | TokenKind::Content
+            | TokenKind::Schema
+            | TokenKind::Step
+            | TokenKind::Fn
+            | TokenKind::Struct
+            | TokenKind::Enum
+            | TokenKind::Table
+            | TokenKind::Handle
+            | TokenKind::Branch
+            | TokenKind::Join
+            | TokenKind::Where
+            | TokenKind::And
+            | TokenKind::Or
+            | TokenKind::Else => true,
+            // `effect name="..."` declares an effect; `effect console` uses one
+            TokenKind::Effect => next == TokenKind::Ident && self.text_at(index + 1) == "name",
+            TokenKind::Collection => next != TokenKind::Of,
+            TokenKind::Note => {
+                let content = if next == TokenKind::Lang { index + 4 } else { index + 1 };
+                kind_at(content) == TokenKind::TripleString
+            }
+            TokenKind::Ident => match self.text_at(index) {
+                "tools" | "tool" | "req" | "test" | "params" | "then" | "case" | "union" | "refactor" => true,
+                "variant" => !after_case,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Skip the rest of the block opened by the token at `start`, through
+    /// its `end`, found by counting the blocks opened and closed since. The
+    /// raw text of a dialect query's `body` counts only `body` and `end`, as
+    /// when parsed. The skip stops early at the next `snippet`.
+    fn skip_block(&mut self, start: usize) {
+        let mut depth = 1;
+        // Depth around the raw `body` being skipped, if in one
+        let mut raw_from = None;
+        let mut dialect = false;
+        let mut index = start + 1;
+        while let Some(kind) = self.tokens.get(index).map(|token| token.kind) {
+            if kind == TokenKind::Eof || kind == TokenKind::Snippet && raw_from.is_none() {
+                break;
+            }
+            index += 1;
+            match kind {
+                TokenKind::End => {
+                    depth -= 1;
+                    if raw_from == Some(depth) {
+                        raw_from = None;
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                TokenKind::Body if raw_from.is_some() => depth += 1,
+                _ if raw_from.is_some() => {}
+                TokenKind::Dialect => dialect = true,
+                _ if self.opens_block(index - 1) => {
+                    if kind == TokenKind::Body && dialect {
+                        raw_from = Some(depth);
+                        dialect = false;
+                    }
+                    depth += 1;
+                }
+                _ => {}
+            }
+        }
+        self.pos = index;
+    }
+
+    /// Skip to the next `snippet`, past the one at `start`
+    fn skip_snippet(&mut self, start: usize) {
+        if self.pos == start {
+            self.advance();
+        }
+        while !self.at_any(&[TokenKind::Eof, TokenKind::Snippet]) {
+            self.advance();
+        }
+    }
+
+    /// Parse a block with `parse`. When recovering, an error is recorded and
+    /// the block skipped, giving `None`.
+    fn recovering<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Option<T>, ParseError> {
+        let start = self.pos;
+        match parse(self) {
+            Ok(node) => Ok(Some(node)),
+            // At the next `snippet`, the enclosing snippet recovers instead
+            Err(error) if self.recover && !(self.pos == start && self.at(TokenKind::Snippet)) => {
+                self.errors.push(error);
+                self.skip_block(start);
+                Ok(None)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Parse a program, recovering from errors at `snippet`, section and
+    /// `step` boundaries. The program holds the snippets parsed without
+    /// syntax errors, and the signature and effects of those recovered
+    /// inside. The errors, with those of non-canonical ordering, are
+    /// in source order.
+    pub fn parse_program_recovering(&mut self) -> (Program, Vec<ParseError>) {
+        self.recover = true;
+        let start = self.span();
+        let program = match self.parse_program() {
+            Ok(program) => program,
+            // Only declarations outside snippets have no recovery
+            Err(error) => {
+                self.errors.push(error);
+                Program::Legacy { declarations: Vec::new(), span: start }
+            }
+        };
+        let mut errors = std::mem::take(&mut self.errors);
+        errors.append(&mut self.ordering_errors);
+        errors.sort_by_key(|error| error.span().start);
+        (program, errors)
+    }
+
+    // === Canonical Ordering ===
+
+    /// Record an E-PARSE-003 for a block parsed out of order
+    fn out_of_order(&mut self, message: String, expected: impl Into<String>, span: Span) {
+        self.ordering_errors.push(ParseError::InvalidOrdering { message, expected: expected.into(), span });
+    }
+
+    /// Record each section that comes after one it canonically precedes
+    fn check_section_order(&mut self, sections: &[Section]) {
+        let mut ranks: Vec<usize> = sections.iter().map(Section::canonical_rank).collect();
+        ranks.sort_unstable();
+        ranks.dedup();
+        let expected: Vec<&str> = ranks.iter().map(|&rank| CANONICAL_SECTION_ORDER[rank]).collect();
+
+        for (index, section) in sections.iter().enumerate() {
+            let rank = section.canonical_rank();
+            if let Some(later) = sections[..index].iter().find(|s| s.canonical_rank() > rank) {
+                self.out_of_order(
+                    format!("section `{}` must come before `{}`", section.keyword(), later.keyword()),
+                    expected.join(", "),
+                    section.span(),
+                );
+            }
+        }
+    }
+
+    /// At `as="..."`, possibly after `mut`
+    fn at_binding(&self) -> bool {
+        self.at(TokenKind::As) || (self.at(TokenKind::Mut) && self.peek_ahead(1) == TokenKind::As)
+    }
+
+    /// Parse `as="..."`, skipping an optional `mut` modifier
+    fn parse_binding(&mut self) -> Result<String, ParseError> {
+        if self.at(TokenKind::Mut) {
+            self.advance();
+        }
+        self.parse_attribute("as")
+    }
+
     // === Program ===
 
     pub fn parse_program(&mut self) -> Result<Program, ParseError> {
@@ -194,7 +392,22 @@ impl<'a> Parser<'a> {
         let mut snippets = Vec::new();
 
         while !self.at(TokenKind::Eof) {
-            snippets.push(self.parse_snippet()?);
+            let start = self.pos;
+            let errors = self.errors.len();
+            match self.parse_snippet() {
+                Ok(snippet) if self.errors.len() == errors => snippets.push(snippet),
+                // Recovered inside: kept as a stub of what callers see, its
+                // signature and effects, as other parts of it are missing
+                Ok(mut snippet) => {
+                    snippet.sections.retain(|section| matches!(section, Section::Signature(_) | Section::Effects(_)));
+                    snippets.push(snippet);
+                }
+                Err(error) if self.recover => {
+                    self.errors.push(error);
+                    self.skip_snippet(start);
+                }
+                Err(error) => return Err(error),
+            }
         }
 
         let end = self.span();
@@ -2000,14 +2213,14 @@ impl<'a> Parser<'a> {
             notes.push(self.parse_note()?);
         }
 
-        // Parse sections (order-independent)
+        // Parse sections in any order, recording those out of canonical order
         let mut sections = Vec::new();
         while !self.at(TokenKind::End) && !self.at(TokenKind::Eof) {
-            sections.push(self.parse_section()?);
+            sections.extend(self.recovering(Self::parse_section)?);
         }
+        self.check_section_order(&sections);
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(Snippet {
             id,
@@ -2016,6 +2229,7 @@ impl<'a> Parser<'a> {
             sections,
             implements,
             platform,
+            comments: Vec::new(),
             span: start.merge(end),
         })
     }
@@ -2088,6 +2302,7 @@ impl<'a> Parser<'a> {
             "extern-impl" => Ok(SnippetKind::ExternImpl),
             "test" => Ok(SnippetKind::Test),
             "data" => Ok(SnippetKind::Data),
+            "effect" => Ok(SnippetKind::Effect),
             _ => Err(ParseError::InvalidSnippetKind {
                 kind: kind_str,
                 span: self.span(),
@@ -2153,17 +2368,17 @@ impl<'a> Parser<'a> {
             TokenKind::Fn => SignatureKind::Function(self.parse_function_signature()?),
             TokenKind::Struct => SignatureKind::Struct(self.parse_struct_signature()?),
             TokenKind::Enum => SignatureKind::Enum(self.parse_enum_signature()?),
+            TokenKind::Effect => SignatureKind::Effect(self.parse_effect_signature()?),
             _ => {
                 return Err(ParseError::Unexpected {
-                    expected: "'fn', 'struct', or 'enum'".to_string(),
+                    expected: "'fn', 'struct', 'enum', or 'effect'".to_string(),
                     found: self.peek(),
                     span: self.span(),
                 })
             }
         };
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(SignatureSection {
             kind,
@@ -2190,8 +2405,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        self.consume(TokenKind::End)?; // fn end
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span; // fn end
 
         Ok(FunctionSignature {
             name,
@@ -2231,15 +2445,32 @@ impl<'a> Parser<'a> {
         let start = self.span();
         let type_str = self.parse_attribute(attr_name)?;
         let end = self.span();
-        // Simple type parsing for now - just convert string to Type
-        Ok(Type {
+        Ok(Self::attribute_type_from_str(&type_str, start.merge(end)))
+    }
+
+    /// Convert a type attribute string to a Type. Function types such as
+    /// `(Int, String) -> Bool` are split into their parts; any other type is
+    /// kept whole as a named type and resolved by the checker.
+    fn attribute_type_from_str(type_str: &str, span: Span) -> Type {
+        if let Some((params, ret)) = split_function_type(type_str) {
+            return Type {
+                kind: TypeKind::Function {
+                    params: params.into_iter()
+                        .map(|p| Self::attribute_type_from_str(p, span))
+                        .collect(),
+                    ret: Box::new(Self::attribute_type_from_str(ret, span)),
+                },
+                span,
+            };
+        }
+        Type {
             kind: TypeKind::Named(TypePath {
-                segments: vec![type_str],
+                segments: vec![type_str.to_string()],
                 generics: Vec::new(),
-                span: start.merge(end),
+                span,
             }),
-            span: start.merge(end),
-        })
+            span,
+        }
     }
 
     fn parse_returns_decl(&mut self) -> Result<ReturnType, ParseError> {
@@ -2304,8 +2535,7 @@ impl<'a> Parser<'a> {
             fields.push(self.parse_field_decl()?);
         }
 
-        self.consume(TokenKind::End)?; // struct end
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span; // struct end
 
         Ok(StructSignature {
             name,
@@ -2413,8 +2643,7 @@ impl<'a> Parser<'a> {
             variants.push(self.parse_variant_decl()?);
         }
 
-        self.consume(TokenKind::End)?; // enum end
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span; // enum end
 
         Ok(EnumSignature {
             name,
@@ -2423,6 +2652,69 @@ impl<'a> Parser<'a> {
         })
     }
 
+    fn parse_effect_signature(&mut self) -> Result<EffectSignature, ParseError> {
+        let start = self.span();
+        self.consume(TokenKind::Effect)?;
+        let name = self.parse_attribute("name")?;
+
+        let mut params = Vec::new();
+        let mut parents = Vec::new();
+        let mut implies = Vec::new();
+        loop {
+            if self.at(TokenKind::Param) {
+                params.push(self.parse_effect_param_decl()?);
+            } else if self.at(TokenKind::Ident) && self.peek_text() == "parent" {
+                parents.push(self.parse_effect_relation()?);
+            } else if self.at(TokenKind::Ident) && self.peek_text() == "implies" {
+                implies.push(self.parse_effect_relation()?);
+            } else {
+                break;
+            }
+        }
+
+        let end = self.consume(TokenKind::End)?.span; // effect end
+
+        Ok(EffectSignature {
+            name,
+            params,
+            parents,
+            implies,
+            span: start.merge(end),
+        })
+    }
+
+    /// Parse `parent effect="database"` or `implies effect="database.read"`
+    fn parse_effect_relation(&mut self) -> Result<String, ParseError> {
+        self.consume(TokenKind::Ident)?; // "parent" or "implies"
+        self.consume(TokenKind::Effect)?;
+        self.consume(TokenKind::Eq)?;
+        self.consume_string_literal()
+    }
+
+    /// Parse `param name="path" rule="path"`; the rule defaults to exact
+    fn parse_effect_param_decl(&mut self) -> Result<EffectParamDecl, ParseError> {
+        let start = self.span();
+        self.consume(TokenKind::Param)?;
+        let name = self.parse_attribute("name")?;
+
+        let rule = if self.at(TokenKind::Ident) && self.peek_text() == "rule" {
+            let rule_span = self.span();
+            let rule_name = self.parse_attribute("rule")?;
+            SubsumptionRule::from_name(&rule_name).ok_or(ParseError::InvalidSubsumptionRule {
+                rule: rule_name,
+                span: rule_span,
+            })?
+        } else {
+            SubsumptionRule::Exact
+        };
+
+        Ok(EffectParamDecl {
+            name,
+            rule,
+            span: start.merge(self.span()),
+        })
+    }
+
     fn parse_variant_decl(&mut self) -> Result<SnippetVariantDecl, ParseError> {
         let start = self.span();
         self.consume(TokenKind::Ident)?; // "variant"
@@ -2439,8 +2731,7 @@ impl<'a> Parser<'a> {
             None
         };
 
-        self.consume(TokenKind::End)?; // variant end
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span; // variant end
 
         Ok(SnippetVariantDecl {
             name,
@@ -2455,11 +2746,10 @@ impl<'a> Parser<'a> {
 
         let mut steps = Vec::new();
         while self.at(TokenKind::Step) {
-            steps.push(self.parse_step()?);
+            steps.extend(self.recovering(Self::parse_step)?);
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(BodySection {
             steps,
@@ -2471,8 +2761,29 @@ impl<'a> Parser<'a> {
         let start = self.span();
         self.consume(TokenKind::Step)?;
 
-        let id = self.parse_attribute("id")?;
-        let step_kind_str = self.parse_attribute("kind")?;
+        // id, kind, the kind's attributes, then as. A swapped id and kind,
+        // or an `as` ahead of the kind's attributes, parse but are recorded.
+        let (id, step_kind_str) = if self.at(TokenKind::Kind) {
+            let kind = self.parse_attribute("kind")?;
+            let span = self.span();
+            let id = self.parse_attribute("id")?;
+            self.out_of_order(
+                "attribute `id` must come before `kind`".to_string(),
+                format!("id, kind, {} attributes, as", kind),
+                span,
+            );
+            (id, kind)
+        } else {
+            let id = self.parse_attribute("id")?;
+            (id, self.parse_attribute("kind")?)
+        };
+        let kind_end = self.span();
+        let early_binding = if self.at_binding() {
+            let span = self.span();
+            Some((self.parse_binding()?, span, self.pos))
+        } else {
+            None
+        };
 
         let kind = match step_kind_str.as_str() {
             "return" => StepKind::Return(self.parse_return_step()?),
@@ -2494,17 +2805,32 @@ impl<'a> Parser<'a> {
             _ => {
                 return Err(ParseError::InvalidStepKind {
                     kind: step_kind_str,
-                    span: self.span(),
+                    span: kind_end,
                 })
             }
         };
 
-        // Skip optional 'mut' modifier
-        if self.at(TokenKind::Mut) {
-            self.advance();
-        }
-
-        let output_binding = self.parse_attribute("as")?;
+        let output_binding = match early_binding {
+            None => self.parse_binding()?,
+            Some((binding, span, pos)) => {
+                if self.at_binding() {
+                    return Err(ParseError::Unexpected {
+                        expected: "'end'".to_string(),
+                        found: self.peek(),
+                        span: self.span(),
+                    });
+                }
+                // With no attributes in between, `as` was in its place
+                if self.pos != pos {
+                    self.out_of_order(
+                        format!("attribute `as` must come after the {} step's attributes", step_kind_str),
+                        format!("id, kind, {} attributes, as", step_kind_str),
+                        span,
+                    );
+                }
+                binding
+            }
+        };
 
         // Parse optional handle block (only valid for call steps)
         let kind = if self.at(TokenKind::Handle) {
@@ -2525,8 +2851,7 @@ impl<'a> Parser<'a> {
             kind
         };
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(Step {
             id,
@@ -2578,8 +2903,7 @@ impl<'a> Parser<'a> {
             fields.push(self.parse_inline_field_assignment()?);
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(StructConstruction {
             ty,
@@ -2599,8 +2923,7 @@ impl<'a> Parser<'a> {
             fields.push(self.parse_inline_field_assignment()?);
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(VariantConstruction {
             ty,
@@ -2626,13 +2949,21 @@ impl<'a> Parser<'a> {
     fn parse_compute_step(&mut self) -> Result<ComputeStep, ParseError> {
         let start = self.span();
 
+        // Inputs belong after op, but are accepted before it
+        let mut inputs = Vec::new();
+        while self.at(TokenKind::Input) {
+            inputs.push(self.parse_input()?);
+        }
+
         // op=add
+        let op_span = self.span();
         self.consume(TokenKind::Op)?;
         self.consume(TokenKind::Eq)?;
         let op = self.parse_operation()?;
+        if !inputs.is_empty() {
+            self.out_of_order("attribute `op` must come before `input`".to_string(), "op, input", op_span);
+        }
 
-        // Parse inputs
-        let mut inputs = Vec::new();
         while self.at(TokenKind::Input) {
             inputs.push(self.parse_input()?);
         }
@@ -2858,9 +3189,18 @@ impl<'a> Parser<'a> {
 
     fn parse_call_step(&mut self) -> Result<CallStep, ParseError> {
         let start = self.span();
-        let fn_name = self.parse_attribute("fn")?;
 
+        // Arguments belong after fn, but are accepted before it
         let mut args = Vec::new();
+        while self.at(TokenKind::Ident) && self.peek_text() == "arg" {
+            args.push(self.parse_call_arg()?);
+        }
+        let fn_span = self.span();
+        let fn_name = self.parse_attribute("fn")?;
+        if !args.is_empty() {
+            self.out_of_order("attribute `fn` must come before `arg`".to_string(), "fn, arg", fn_span);
+        }
+
         while self.at(TokenKind::Ident) && self.peek_text() == "arg" {
             args.push(self.parse_call_arg()?);
         }
@@ -2885,8 +3225,7 @@ impl<'a> Parser<'a> {
             cases.push(self.parse_handle_case()?);
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(HandleBlock {
             cases,
@@ -2901,11 +3240,10 @@ impl<'a> Parser<'a> {
 
         let mut steps = Vec::new();
         while self.at(TokenKind::Step) {
-            steps.push(self.parse_step()?);
+            steps.extend(self.recovering(Self::parse_step)?);
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(HandleCase {
             error_type,
@@ -2937,10 +3275,10 @@ impl<'a> Parser<'a> {
             let of = self.consume_string_literal()?;
             InputSource::Field { of, field }
         } else if self.at(TokenKind::Fn) {
-            // fn="|f| equals(f.name, field_name)" - lambda syntax
+            // fn="math.double" - a function passed as a value
             self.advance();
             self.consume(TokenKind::Eq)?;
-            InputSource::Var(self.consume_string_literal()?)
+            InputSource::FnRef(self.consume_string_literal()?)
         } else {
             return Err(ParseError::Unexpected {
                 expected: "'from', 'lit', 'field', or 'fn'".to_string(),
@@ -3010,7 +3348,7 @@ impl<'a> Parser<'a> {
         self.consume(TokenKind::Ident)?; // "then" is not a keyword
         let mut then_steps = Vec::new();
         while self.at(TokenKind::Step) {
-            then_steps.push(self.parse_step()?);
+            then_steps.extend(self.recovering(Self::parse_step)?);
         }
         self.consume(TokenKind::End)?;
 
@@ -3019,7 +3357,7 @@ impl<'a> Parser<'a> {
             self.advance();
             let mut steps = Vec::new();
             while self.at(TokenKind::Step) {
-                steps.push(self.parse_step()?);
+                steps.extend(self.recovering(Self::parse_step)?);
             }
             self.consume(TokenKind::End)?;
             Some(steps)
@@ -3101,11 +3439,10 @@ impl<'a> Parser<'a> {
         // Parse steps until end
         let mut steps = Vec::new();
         while self.at(TokenKind::Step) {
-            steps.push(self.parse_step()?);
+            steps.extend(self.recovering(Self::parse_step)?);
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(MatchCase {
             pattern,
@@ -3286,8 +3623,7 @@ impl<'a> Parser<'a> {
 
         let kind = self.parse_condition_kind()?;
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(Condition {
             kind,
@@ -3671,7 +4007,7 @@ impl<'a> Parser<'a> {
         // Parse nested steps
         let mut steps = Vec::new();
         while self.at(TokenKind::Step) {
-            steps.push(self.parse_step()?);
+            steps.extend(self.recovering(Self::parse_step)?);
         }
 
         let end = self.span();
@@ -3706,7 +4042,7 @@ impl<'a> Parser<'a> {
         // Parse nested steps
         let mut steps = Vec::new();
         while self.at(TokenKind::Step) {
-            steps.push(self.parse_step()?);
+            steps.extend(self.recovering(Self::parse_step)?);
         }
 
         let end = self.span();
@@ -3885,11 +4221,10 @@ impl<'a> Parser<'a> {
         // Parse nested steps
         let mut steps = Vec::new();
         while self.at(TokenKind::Step) {
-            steps.push(self.parse_step()?);
+            steps.extend(self.recovering(Self::parse_step)?);
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(Branch {
             id,
@@ -3903,19 +4238,42 @@ impl<'a> Parser<'a> {
         self.consume(TokenKind::Effects)?; // "effects" section keyword
 
         let mut effects = Vec::new();
+        let mut vars = Vec::new();
         while self.at(TokenKind::Effect) {
-            effects.push(self.parse_effect_decl()?);
+            if self.peek_ahead(1) == TokenKind::Of {
+                // effect of="f": the effects of a function-typed parameter
+                let var_start = self.span();
+                self.advance(); // consume 'effect'
+                self.advance(); // consume 'of'
+                self.consume(TokenKind::Eq)?;
+                let param = self.consume_string_literal()?;
+                vars.push(EffectVar {
+                    param,
+                    span: var_start.merge(self.span()),
+                });
+            } else {
+                effects.push(self.parse_effect_decl()?);
+            }
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(EffectsSection {
             effects,
+            vars,
             span: start.merge(end),
         })
     }
 
+    /// Parse an effect declaration that must make up the whole input
+    pub fn parse_standalone_effect(&mut self) -> Result<EffectDecl, ParseError> {
+        let effect = self.parse_effect_decl()?;
+        if !self.at(TokenKind::Eof) {
+            return Err(ParseError::unexpected("end of effect", self.peek(), self.span()));
+        }
+        Ok(effect)
+    }
+
     fn parse_effect_decl(&mut self) -> Result<EffectDecl, ParseError> {
         let start = self.span();
         self.consume(TokenKind::Effect)?;
@@ -4001,8 +4359,7 @@ impl<'a> Parser<'a> {
             entries.push(self.parse_metadata_entry()?);
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(MetadataSection {
             entries,
@@ -4069,8 +4426,7 @@ impl<'a> Parser<'a> {
             requirements.push(self.parse_requirement()?);
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(RequiresSection {
             requirements,
@@ -4106,8 +4462,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(Requirement {
             id,
@@ -4127,8 +4482,7 @@ impl<'a> Parser<'a> {
             tests.push(self.parse_test_decl()?);
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(TestsSection {
             tests,
@@ -4160,11 +4514,10 @@ impl<'a> Parser<'a> {
         // Parse test steps
         let mut steps = Vec::new();
         while self.at(TokenKind::Step) {
-            steps.push(self.parse_step()?);
+            steps.extend(self.recovering(Self::parse_step)?);
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(TestDecl {
             id,
@@ -4184,8 +4537,7 @@ impl<'a> Parser<'a> {
             relations.push(self.parse_relation_decl()?);
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(RelationsSection {
             relations,
@@ -4276,8 +4628,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(ContentSection {
             content,
@@ -4309,8 +4660,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(SchemaSection {
             tables,
@@ -4328,8 +4678,7 @@ impl<'a> Parser<'a> {
             fields.push(self.parse_field_decl()?);
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(SnippetTableDecl {
             name,
@@ -4378,8 +4727,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        self.consume(TokenKind::End)?;
-        let end = self.span();
+        let end = self.consume(TokenKind::End)?.span;
 
         Ok(TypesSection {
             types,
@@ -4462,3 +4810,59 @@ impl<'a> Parser<'a> {
         }
     }
 }
+
+/// Split a function type string `(A, B) -> R` into its parameter and return
+/// type strings. Returns None for any other type.
+fn split_function_type(type_str: &str) -> Option<(Vec<&str>, &str)> {
+    let type_str = type_str.trim();
+    if !type_str.starts_with('(') {
+        return None;
+    }
+
+    // Find the parenthesis closing the parameter list
+    let mut depth = 0;
+    let mut close = None;
+    for (i, c) in type_str.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(i);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let close = close?;
+    let ret = type_str[close + 1..].trim_start().strip_prefix("->")?.trim();
+    if ret.is_empty() {
+        return None;
+    }
+
+    // Split the parameters at top-level commas; the `>` of a nested `->` is
+    // not a closing bracket
+    let inner = &type_str[1..close];
+    let mut params = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut prev = ' ';
+    for (i, c) in inner.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' if prev != '-' => depth -= 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                params.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+    if !inner.trim().is_empty() {
+        params.push(inner[start..].trim());
+    }
+    Some((params, ret))
+}
@@ -12,6 +12,7 @@ pub use report::{format_report, ReportFormat};
 
 use std::collections::HashMap;
 use covenant_ast::{Priority, ReqStatus, TestKind, Span};
+use covenant_checker::{Diagnostic, DiagnosticKind};
 use serde::{Serialize, Deserialize};
 use thiserror::Error;
 
@@ -190,6 +191,36 @@ impl RequirementError {
     }
 }
 
+impl From<Severity> for covenant_checker::Severity {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Error => covenant_checker::Severity::Error,
+            Severity::Warning => covenant_checker::Severity::Warning,
+            Severity::Info => covenant_checker::Severity::Info,
+        }
+    }
+}
+
+impl From<RequirementError> for Diagnostic {
+    fn from(error: RequirementError) -> Self {
+        let severity = error.severity().into();
+        let snippet_id = match &error {
+            RequirementError::UncoveredRequirement { snippet_id, .. }
+            | RequirementError::NonexistentRequirement { snippet_id, .. } => snippet_id.clone(),
+            RequirementError::DuplicateRequirement { second, .. }
+            | RequirementError::DuplicateTest { second, .. } => second.clone(),
+        };
+        Diagnostic::new(
+            DiagnosticKind::Message(error.to_string()),
+            error.span(),
+            error.code(),
+            String::new(),
+        )
+        .with_severity(severity)
+        .with_snippet(snippet_id)
+    }
+}
+
 /// Validate requirements coverage for a program
 ///
 /// This is the main entry point for the requirement validator.
@@ -256,7 +256,7 @@ mod tests {
             status: ReqStatus::Draft,
             snippet_id: "test.fn".to_string(),
             covered_by: vec!["T-001".to_string()],
-            span: Span { start: 0, end: 0 },
+            span: Span::dummy(),
         });
 
         // Uncovered requirement
@@ -267,7 +267,7 @@ mod tests {
             status: ReqStatus::Draft,
             snippet_id: "test.fn".to_string(),
             covered_by: Vec::new(),
-            span: Span { start: 0, end: 0 },
+            span: Span::dummy(),
         });
 
         let mut tests = HashMap::new();
@@ -276,7 +276,7 @@ mod tests {
             kind: TestKind::Unit,
             covers: vec!["R-001".to_string()],
             snippet_id: "test.fn".to_string(),
-            span: Span { start: 0, end: 0 },
+            span: Span::dummy(),
         });
 
         let mut by_priority = HashMap::new();
@@ -300,7 +300,7 @@ mod tests {
                     id: "R-002".to_string(),
                     priority: Priority::Critical,
                     snippet_id: "test.fn".to_string(),
-                    span: Span { start: 0, end: 0 },
+                    span: Span::dummy(),
                 },
             ],
         }
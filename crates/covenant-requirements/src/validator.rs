@@ -195,7 +195,7 @@ mod tests {
             status: ReqStatus::Draft,
             snippet_id: "test.fn".to_string(),
             covered_by: Vec::new(),
-            span: Span { start: 0, end: 0 },
+            span: Span::dummy(),
         }
     }
 
@@ -205,7 +205,7 @@ mod tests {
             kind: TestKind::Unit,
             covers: covers.into_iter().map(String::from).collect(),
             snippet_id: "test.fn".to_string(),
-            span: Span { start: 0, end: 0 },
+            span: Span::dummy(),
         }
     }
 
@@ -353,7 +353,7 @@ mod tests {
                     id: "R-002".to_string(),
                     priority: Priority::Low,
                     snippet_id: "test.fn".to_string(),
-                    span: Span { start: 0, end: 0 },
+                    span: Span::dummy(),
                 },
             ],
         };
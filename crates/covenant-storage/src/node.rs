@@ -85,6 +85,36 @@ impl Node {
         }
     }
 
+    /// Create a node from a parsed snippet, located in `sources`
+    pub fn from_snippet(snippet: &covenant_ast::Snippet, sources: &covenant_ast::SourceMap) -> Self {
+        use covenant_ast::Section;
+
+        let mut node = Self::new(snippet.id.clone(), SnippetKind::from(snippet.kind));
+        node.ast = serde_json::to_string(snippet).unwrap_or_else(|_| "null".to_string());
+        node.set_location(snippet.span, sources);
+        node.notes = snippet
+            .notes
+            .iter()
+            .map(|n| Note { lang: n.lang.clone(), content: n.content.clone() })
+            .collect();
+        for section in &snippet.sections {
+            match section {
+                Section::Effects(effects) => {
+                    node.effects = effects.effects.iter().map(|e| e.name.clone()).collect();
+                }
+                Section::Relations(relations) => {
+                    node.relations = relations
+                        .relations
+                        .iter()
+                        .map(|r| Relation::new(r.target.clone(), r.rel_type.clone().unwrap_or_else(|| "related_to".to_string())))
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+        node
+    }
+
     /// Increment version (for optimistic locking)
     pub fn increment_version(&mut self) {
         self.version += 1;
@@ -124,6 +154,15 @@ impl Node {
         Ok(())
     }
 
+    /// Set `source_file`, `line_start` and `line_end` from a snippet's span
+    pub fn set_location(&mut self, span: covenant_ast::Span, sources: &covenant_ast::SourceMap) {
+        if let Some(file) = sources.file_of(span) {
+            self.source_file = file.name.clone();
+            self.line_start = file.line_column(span.start).0;
+            self.line_end = file.line_column(span.end).0;
+        }
+    }
+
     /// Set the AST directly from a JSON string
     ///
     /// This is useful when the compiler already has a JSON string from serializing
@@ -156,6 +195,23 @@ pub enum SnippetKind {
     Test,
 }
 
+impl From<covenant_ast::SnippetKind> for SnippetKind {
+    fn from(kind: covenant_ast::SnippetKind) -> Self {
+        use covenant_ast::SnippetKind as Ast;
+        match kind {
+            Ast::Function => SnippetKind::Function,
+            Ast::Struct => SnippetKind::Struct,
+            Ast::Enum => SnippetKind::Enum,
+            Ast::Module => SnippetKind::Module,
+            Ast::Database => SnippetKind::Database,
+            Ast::Extern | Ast::ExternAbstract | Ast::ExternImpl => SnippetKind::Extern,
+            Ast::Test => SnippetKind::Test,
+            // Effect declarations carry no code
+            Ast::Data | Ast::Effect => SnippetKind::Data,
+        }
+    }
+}
+
 /// A bidirectional relation between nodes
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Relation {
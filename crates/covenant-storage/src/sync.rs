@@ -1,6 +1,7 @@
 //! File watching and incremental synchronization
 
-use crate::{Result, StorageProvider, StorageError};
+use crate::{Node, Result, StorageProvider, StorageError};
+use covenant_ast::{Program, SourceMap};
 use notify::{Watcher, RecursiveMode, Event, EventKind};
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver};
@@ -79,23 +80,18 @@ impl<P: StorageProvider> StorageSync<P> {
     ///
     /// Parses the file and updates all snippets it contains.
     pub fn sync_file(&mut self, path: &Path) -> Result<()> {
-        // TODO: This needs the parser to be implemented
-        // For now, this is a placeholder that shows the structure
-
-        let _source = std::fs::read_to_string(path)?;
-
-        // Parse snippets from the source
-        // let snippets = parse_snippets(&source)?;
-
-        // For each snippet, create a Node and store it
-        // for snippet in snippets {
-        //     let node = Node::from_snippet(snippet, path)?;
-        //     self.storage.put(&node.id, &node)?;
-        // }
-
-        // Placeholder: Just log that we would sync this file
-        eprintln!("Would sync file: {}", path.display());
-
+        let source = std::fs::read_to_string(path)?;
+        let mut sources = SourceMap::new();
+        let file = sources.add(path.display().to_string(), source.as_str());
+        let program = covenant_parser::parse_file(&source, file)
+            .map_err(|e| StorageError::Parse(format!("{}: {}", path.display(), e)))?;
+
+        if let Program::Snippets { snippets, .. } = &program {
+            for snippet in snippets {
+                let node = Node::from_snippet(snippet, &sources);
+                self.storage.put(&node.id, &node)?;
+            }
+        }
         Ok(())
     }
 
@@ -197,13 +193,22 @@ mod tests {
 
         // Create a test .cov file
         let test_file = project_root.join("test.cov");
-        fs::write(&test_file, "# Test file\n").unwrap();
+        fs::write(
+            &test_file,
+            "// Test file\n\nsnippet id=\"test.main\" kind=\"fn\"\nnote \"Entry point\"\nsignature\n  fn name=\"main\"\n    returns type=\"Int\"\n  end\nend\nend\n",
+        )
+        .unwrap();
 
         let storage = InMemoryStorage::new();
         let mut sync = StorageSync::new(storage, project_root.clone());
 
-        // Rebuild should not fail (even though parsing is not implemented yet)
         let result = sync.rebuild_index();
         assert!(result.is_ok());
+
+        let node = sync.storage().get("test.main").unwrap().unwrap();
+        assert_eq!(node.kind, SnippetKind::Function);
+        assert_eq!(node.source_file, test_file.display().to_string());
+        assert_eq!((node.line_start, node.line_end), (3, 10));
+        assert_eq!(node.notes[0].content, "Entry point");
     }
 }
@@ -2,11 +2,13 @@
 //!
 //! Extracts symbols and forward references from snippets.
 
-use crate::{RelationRef, SymbolError, SymbolGraph, SymbolInfo, SymbolKind};
+use crate::{
+    FunctionArg, FunctionValue, RelationRef, SymbolError, SymbolGraph, SymbolInfo, SymbolKind,
+};
 use covenant_ast::{
-    BodySection, EffectDecl, EffectsSection, RelationsSection, RequiresSection, ReturnType, ReturnValue,
-    Section, SignatureKind, SignatureSection, Snippet, SnippetKind, Step, StepKind, TestsSection,
-    Type, TypeKind,
+    BodySection, EffectDecl, EffectsSection, InputSource, RelationsSection, RequiresSection,
+    ReturnType, ReturnValue, Section, SignatureKind, SignatureSection, Snippet, SnippetKind, Step,
+    StepKind, TestsSection, Type, TypeKind,
 };
 use std::collections::HashSet;
 
@@ -58,8 +60,14 @@ impl SymbolExtractor {
             match section {
                 Section::Effects(effects) => {
                     symbol.declared_effects = self.extract_effects(effects);
+                    symbol.effect_vars = effects.vars.iter().map(|v| v.param.clone()).collect();
                 }
                 Section::Signature(sig) => {
+                    if let SignatureKind::Effect(effect_sig) = &sig.kind {
+                        symbol.effect_params = effect_sig.params.clone();
+                        symbol.effect_parents = effect_sig.parents.clone();
+                        symbol.effect_implies = effect_sig.implies.clone();
+                    }
                     let type_refs = self.extract_signature_types(sig);
                     symbol.references.extend(type_refs);
                 }
@@ -67,6 +75,7 @@ impl SymbolExtractor {
                     let (body_calls, body_refs) = self.extract_body_refs(body);
                     symbol.calls.extend(body_calls);
                     symbol.references.extend(body_refs);
+                    collect_function_args(&body.steps, &mut symbol.function_args);
                 }
                 Section::Relations(rels) => {
                     symbol.relations_to = self.extract_relations(rels);
@@ -85,6 +94,24 @@ impl SymbolExtractor {
             }
         }
 
+        // A call through a function-typed parameter is not a call to a symbol
+        let mut function_params = HashSet::new();
+        for section in &snippet.sections {
+            if let Section::Signature(SignatureSection { kind: SignatureKind::Function(sig), .. }) = section {
+                for param in &sig.params {
+                    symbol.calls.remove(&param.name);
+                    if matches!(param.ty.kind, TypeKind::Function { .. }) {
+                        function_params.insert(param.name.as_str());
+                    }
+                }
+            }
+        }
+        // Only the snippet's own function-typed parameters can be passed on by name
+        symbol.function_args.retain(|arg| match &arg.value {
+            FunctionValue::Symbol(_) => true,
+            FunctionValue::Param(name) => function_params.contains(name.as_str()),
+        });
+
         symbol
     }
 
@@ -149,6 +176,8 @@ impl SymbolExtractor {
                     }
                 }
             }
+            // Effect parameters have no types to reference
+            SignatureKind::Effect(_) => {}
         }
 
         refs
@@ -234,6 +263,13 @@ impl SymbolExtractor {
         match &step.kind {
             StepKind::Call(call) => {
                 calls.insert(call.fn_name.clone());
+                // A function passed as a value is referenced; whether its effects
+                // reach this symbol depends on the callee's effect variables
+                for arg in &call.args {
+                    if let InputSource::FnRef(name) = &arg.source {
+                        refs.insert(name.clone());
+                    }
+                }
                 // Check handle block for nested calls
                 if let Some(handle) = &call.handle {
                     for case in &handle.cases {
@@ -389,3 +425,56 @@ mod tests {
         assert!(!is_primitive_type("DbError"));
     }
 }
+
+/// Collect the function values passed to calls, including through nested steps.
+/// Variable arguments are candidates until checked against the function-typed
+/// parameters of the snippet.
+fn collect_function_args(steps: &[Step], out: &mut Vec<FunctionArg>) {
+    for step in steps {
+        match &step.kind {
+            StepKind::Call(call) => {
+                for arg in &call.args {
+                    let value = match &arg.source {
+                        InputSource::FnRef(name) => FunctionValue::Symbol(name.clone()),
+                        InputSource::Var(name) => FunctionValue::Param(name.clone()),
+                        _ => continue,
+                    };
+                    out.push(FunctionArg {
+                        callee: call.fn_name.clone(),
+                        param: arg.name.clone(),
+                        value,
+                    });
+                }
+                if let Some(handle) = &call.handle {
+                    for case in &handle.cases {
+                        collect_function_args(&case.steps, out);
+                    }
+                }
+            }
+            StepKind::If(if_step) => {
+                collect_function_args(&if_step.then_steps, out);
+                if let Some(else_steps) = &if_step.else_steps {
+                    collect_function_args(else_steps, out);
+                }
+            }
+            StepKind::Match(match_step) => {
+                for case in &match_step.cases {
+                    collect_function_args(&case.steps, out);
+                }
+            }
+            StepKind::For(for_step) => collect_function_args(&for_step.steps, out),
+            StepKind::Transaction(tx) => collect_function_args(&tx.steps, out),
+            StepKind::Parallel(parallel) => {
+                for branch in &parallel.branches {
+                    collect_function_args(&branch.steps, out);
+                }
+            }
+            StepKind::Race(race) => {
+                for branch in &race.branches {
+                    collect_function_args(&branch.steps, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -38,7 +38,7 @@ mod symbol;
 
 pub use error::SymbolError;
 pub use graph::{InvariantStatus, SymbolGraph, SymbolResult};
-pub use symbol::{RelationRef, SymbolId, SymbolInfo, SymbolKind};
+pub use symbol::{FunctionArg, FunctionValue, RelationRef, SymbolId, SymbolInfo, SymbolKind};
 
 use covenant_ast::{Program, Snippet};
 use cycle::CycleDetector;
@@ -182,6 +182,7 @@ mod tests {
             sections: vec![],
             implements: None,
             platform: None,
+            comments: Vec::new(),
             span: make_span(),
         };
 
@@ -199,6 +200,7 @@ mod tests {
             sections: vec![],
             implements: None,
             platform: None,
+            comments: Vec::new(),
             span: make_span(),
         };
 
@@ -209,6 +211,7 @@ mod tests {
             sections: vec![],
             implements: None,
             platform: None,
+            comments: Vec::new(),
             span: make_span(),
         };
 
@@ -229,6 +232,7 @@ mod tests {
             sections: vec![],
             implements: None,
             platform: None,
+            comments: Vec::new(),
             span: make_span(),
         };
 
@@ -248,6 +252,7 @@ mod tests {
             sections: vec![],
             implements: None,
             platform: None,
+            comments: Vec::new(),
             span: make_span(),
         };
 
@@ -266,6 +271,7 @@ mod tests {
             sections: vec![],
             implements: None,
             platform: None,
+            comments: Vec::new(),
             span: make_span(),
         };
 
@@ -283,6 +289,7 @@ mod tests {
             sections: vec![],
             implements: None,
             platform: None,
+            comments: Vec::new(),
             span: make_span(),
         };
 
@@ -300,6 +307,7 @@ mod tests {
             sections: vec![],
             implements: None,
             platform: None,
+            comments: Vec::new(),
             span: make_span(),
         };
 
@@ -317,6 +325,7 @@ mod tests {
             sections: vec![],
             implements: None,
             platform: None,
+            comments: Vec::new(),
             span: make_span(),
         };
 
@@ -327,6 +336,7 @@ mod tests {
             sections: vec![],
             implements: None,
             platform: None,
+            comments: Vec::new(),
             span: make_span(),
         };
 
@@ -337,6 +347,7 @@ mod tests {
             sections: vec![],
             implements: None,
             platform: None,
+            comments: Vec::new(),
             span: make_span(),
         };
 
@@ -1,6 +1,6 @@
 //! Symbol information and types
 
-use covenant_ast::{EffectDecl, Span, SnippetKind};
+use covenant_ast::{EffectDecl, EffectParamDecl, Span, SnippetKind};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
@@ -23,6 +23,8 @@ pub enum SymbolKind {
     ExternImpl,
     Test,
     Data,
+    /// Effect declaration
+    Effect,
 }
 
 impl From<SnippetKind> for SymbolKind {
@@ -38,6 +40,7 @@ impl From<SnippetKind> for SymbolKind {
             SnippetKind::ExternImpl => SymbolKind::ExternImpl,
             SnippetKind::Test => SymbolKind::Test,
             SnippetKind::Data => SymbolKind::Data,
+            SnippetKind::Effect => SymbolKind::Effect,
         }
     }
 }
@@ -53,6 +56,26 @@ pub struct RelationRef {
     pub direction: covenant_ast::RelationKind,
 }
 
+/// A function passed as an argument at a call site
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionArg {
+    /// The function being called
+    pub callee: String,
+    /// The callee's parameter receiving the function
+    pub param: String,
+    /// The function passed
+    pub value: FunctionValue,
+}
+
+/// The function passed in a [`FunctionArg`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FunctionValue {
+    /// A snippet passed by name (`fn="io.print"`)
+    Symbol(String),
+    /// One of the caller's own function-typed parameters, passed on
+    Param(String),
+}
+
 /// Information about a symbol extracted from the AST
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SymbolInfo {
@@ -78,6 +101,21 @@ pub struct SymbolInfo {
     /// Declared effects (from effects section)
     pub declared_effects: Vec<EffectDecl>,
 
+    /// Function-typed parameters whose effects this symbol has (`effect of="f"`)
+    pub effect_vars: Vec<String>,
+
+    /// Functions passed as arguments to calls this symbol makes
+    pub function_args: Vec<FunctionArg>,
+
+    /// For effect snippets: parameters and their subsumption rules
+    pub effect_params: Vec<EffectParamDecl>,
+
+    /// For effect snippets: broader effects whose declaration covers this one
+    pub effect_parents: Vec<String>,
+
+    /// For effect snippets: narrower effects that declaring this one covers
+    pub effect_implies: Vec<String>,
+
     /// Relations declared (from relations section)
     pub relations_to: Vec<RelationRef>,
 
@@ -127,6 +165,11 @@ impl SymbolInfo {
             calls: HashSet::new(),
             references: HashSet::new(),
             declared_effects: Vec::new(),
+            effect_vars: Vec::new(),
+            function_args: Vec::new(),
+            effect_params: Vec::new(),
+            effect_parents: Vec::new(),
+            effect_implies: Vec::new(),
             relations_to: Vec::new(),
             called_by: HashSet::new(),
             referenced_by: HashSet::new(),
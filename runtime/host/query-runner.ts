@@ -129,11 +129,11 @@ export class CovenantQueryRunner {
       text: {
         upper: (sPtr: number, sLen: number): bigint => {
           const s = this.readStr(sPtr, sLen);
-          return this.writeStr(s.toUpperCase());
+          return this.writeStr(s.replace(/[a-z]/g, (c) => c.toUpperCase()));
         },
         lower: (sPtr: number, sLen: number): bigint => {
           const s = this.readStr(sPtr, sLen);
-          return this.writeStr(s.toLowerCase());
+          return this.writeStr(s.replace(/[A-Z]/g, (c) => c.toLowerCase()));
         },
         trim: (sPtr: number, sLen: number): bigint => {
           const s = this.readStr(sPtr, sLen);
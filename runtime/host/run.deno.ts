@@ -101,8 +101,8 @@ const imports: WebAssembly.Imports = {
   },
   text: {
     // Unary -> String
-    upper: (ptr: number, len: number): bigint => writeStr(readStr(ptr, len).toUpperCase()),
-    lower: (ptr: number, len: number): bigint => writeStr(readStr(ptr, len).toLowerCase()),
+    upper: (ptr: number, len: number): bigint => writeStr(readStr(ptr, len).replace(/[a-z]/g, (c) => c.toUpperCase())),
+    lower: (ptr: number, len: number): bigint => writeStr(readStr(ptr, len).replace(/[A-Z]/g, (c) => c.toLowerCase())),
     trim: (ptr: number, len: number): bigint => writeStr(readStr(ptr, len).trim()),
     trim_start: (ptr: number, len: number): bigint => writeStr(readStr(ptr, len).trimStart()),
     trim_end: (ptr: number, len: number): bigint => writeStr(readStr(ptr, len).trimEnd()),
@@ -108,8 +108,8 @@ const imports = {
   },
   text: {
     // Unary -> String
-    upper: (ptr, len) => writeStr(readStr(ptr, len).toUpperCase()),
-    lower: (ptr, len) => writeStr(readStr(ptr, len).toLowerCase()),
+    upper: (ptr, len) => writeStr(readStr(ptr, len).replace(/[a-z]/g, (c) => c.toUpperCase())),
+    lower: (ptr, len) => writeStr(readStr(ptr, len).replace(/[A-Z]/g, (c) => c.toLowerCase())),
     trim: (ptr, len) => writeStr(readStr(ptr, len).trim()),
     trim_start: (ptr, len) => writeStr(readStr(ptr, len).trimStart()),
     trim_end: (ptr, len) => writeStr(readStr(ptr, len).trimEnd()),
@@ -416,12 +416,13 @@ const imports = {
 // Create a Proxy-based fallback for dynamically added extern imports
 const proxyHandler = {
   get(target, prop) {
-    if (prop in target) {
-      return target[prop];
-    }
-    // Return a proxy for unknown modules that returns 0n for any function call
-    return new Proxy({}, {
-      get(_moduleTarget, funcName) {
+    // Return a proxy that falls back to 0n for functions this runner does not
+    // implement, in known and unknown modules alike
+    return new Proxy(target[prop] ?? {}, {
+      get(moduleTarget, funcName) {
+        if (funcName in moduleTarget) {
+          return moduleTarget[funcName];
+        }
         return (..._args) => {
           // Unknown function - return 0n as a safe default
           return 0n;
@@ -376,7 +376,6 @@ end
 /// Note: Cross-snippet type checking is a future enhancement. This test verifies the current
 /// behavior and will need updating when inter-snippet type checking is implemented.
 #[test]
-#[ignore = "Cross-snippet type checking not yet implemented"]
 fn e2e_cross_snippet_type_error() {
     let source = r#"
 snippet id="math.add" kind="fn"
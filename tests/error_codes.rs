@@ -0,0 +1,268 @@
+//! Every error code the compiler can emit is registered in ERROR_CODES
+//!
+//! Each error type's `code()` is called on one value of every variant. The
+//! `*_variant` functions match exhaustively, so adding a variant fails to
+//! compile here until it is listed.
+
+use covenant_ast::Span;
+use covenant_checker::{explain_effect_violation, lookup_code, CheckError, EffectError};
+use covenant_lexer::TokenKind;
+use covenant_optimizer::OptWarningKind;
+use covenant_parser::ParseError;
+use covenant_requirements::RequirementError;
+use covenant_runtime::RuntimeError;
+use covenant_symbols::{SymbolError, SymbolGraph};
+
+fn s() -> String {
+    "x".to_string()
+}
+
+fn parse_variant(error: &ParseError) -> usize {
+    match error {
+        ParseError::UnexpectedToken { .. } => 0,
+        ParseError::UnexpectedEof { .. } => 1,
+        ParseError::InvalidExpression { .. } => 2,
+        ParseError::InvalidPattern { .. } => 3,
+        ParseError::InvalidType { .. } => 4,
+        ParseError::ExpectedDeclaration { .. } => 5,
+        ParseError::Unexpected { .. } => 6,
+        ParseError::InvalidSnippetKind { .. } => 7,
+        ParseError::InvalidStepKind { .. } => 8,
+        ParseError::InvalidOperation { .. } => 9,
+        ParseError::UnexpectedSection { .. } => 10,
+        ParseError::InvalidSubsumptionRule { .. } => 11,
+        ParseError::InvalidOrdering { .. } => 12,
+    }
+}
+
+fn parse_errors() -> Vec<ParseError> {
+    let span = Span::dummy();
+    vec![
+        ParseError::UnexpectedToken { expected: s(), found: s(), span },
+        ParseError::UnexpectedEof { span },
+        ParseError::InvalidExpression { span },
+        ParseError::InvalidPattern { span },
+        ParseError::InvalidType { span },
+        ParseError::ExpectedDeclaration { span },
+        ParseError::Unexpected { expected: s(), found: TokenKind::Eof, span },
+        ParseError::InvalidSnippetKind { kind: s(), span },
+        ParseError::InvalidStepKind { kind: s(), span },
+        ParseError::InvalidOperation { name: s(), span },
+        ParseError::UnexpectedSection { section: s(), span },
+        ParseError::InvalidSubsumptionRule { rule: s(), span },
+        ParseError::InvalidOrdering { message: s(), expected: s(), span },
+    ]
+}
+
+fn symbol_variant(error: &SymbolError) -> usize {
+    match error {
+        SymbolError::UndefinedReference { .. } => 0,
+        SymbolError::DuplicateId { .. } => 1,
+        SymbolError::CircularImport { .. } => 2,
+        SymbolError::RelationTargetNotFound { .. } => 3,
+    }
+}
+
+fn symbol_errors() -> Vec<SymbolError> {
+    let span = Span::dummy();
+    vec![
+        SymbolError::UndefinedReference { name: s(), span, referrer: s() },
+        SymbolError::DuplicateId { id: s(), span },
+        SymbolError::CircularImport { cycle: s(), span },
+        SymbolError::RelationTargetNotFound { target: s(), span, from_symbol: s() },
+    ]
+}
+
+fn check_variant(error: &CheckError) -> usize {
+    match error {
+        CheckError::UndefinedSymbol { .. } => 0,
+        CheckError::TypeMismatch { .. } => 1,
+        CheckError::EffectNotAllowed { .. } => 2,
+        CheckError::DuplicateDefinition { .. } => 3,
+        CheckError::IncompatibleUnion { .. } => 4,
+        CheckError::NonExhaustiveMatch { .. } => 5,
+        CheckError::UnknownQueryTarget { .. } => 6,
+        CheckError::UnknownField { .. } => 7,
+        CheckError::MissingField { .. } => 8,
+        CheckError::FieldTypeMismatch { .. } => 9,
+        CheckError::UnknownExternAbstract { .. } => 10,
+        CheckError::NoBindingForTarget { .. } => 11,
+        CheckError::InvalidExternId { .. } => 12,
+        CheckError::ConflictingTypeArgument { .. } => 13,
+        CheckError::UninferredTypeParameter { .. } => 14,
+        CheckError::MissingArgument { .. } => 15,
+        CheckError::UnknownArgument { .. } => 16,
+        CheckError::ArgumentTypeMismatch { .. } => 17,
+        CheckError::ArgumentCountMismatch { .. } => 18,
+        CheckError::InvalidEffectVariable { .. } => 19,
+        CheckError::TooManyPatternBindings { .. } => 20,
+        CheckError::DuplicateBinding { .. } => 21,
+        CheckError::UseBeforeDefinition { .. } => 22,
+        CheckError::BranchLocalBinding { .. } => 23,
+        CheckError::DuplicateStepId { .. } => 24,
+        CheckError::UnhandleableErrorType { .. } => 25,
+        CheckError::DuplicateArgument { .. } => 26,
+    }
+}
+
+fn check_errors() -> Vec<CheckError> {
+    let span = Span::dummy();
+    vec![
+        CheckError::UndefinedSymbol { name: s() },
+        CheckError::TypeMismatch { expected: s(), found: s() },
+        CheckError::EffectNotAllowed { effect: s() },
+        CheckError::DuplicateDefinition { name: s() },
+        CheckError::IncompatibleUnion { value_type: s(), union_type: s() },
+        CheckError::NonExhaustiveMatch { missing: Vec::new(), matched_type: s() },
+        CheckError::UnknownQueryTarget { target: s() },
+        CheckError::UnknownField { field: s(), type_name: s(), span },
+        CheckError::MissingField { field: s(), type_name: s(), span },
+        CheckError::FieldTypeMismatch { field: s(), type_name: s(), expected: s(), found: s(), span },
+        CheckError::UnknownExternAbstract { impl_id: s(), abstract_id: s() },
+        CheckError::NoBindingForTarget { extern_id: s(), target: s() },
+        CheckError::InvalidExternId { id: s() },
+        CheckError::ConflictingTypeArgument { function: s(), param: s(), first: s(), second: s() },
+        CheckError::UninferredTypeParameter { function: s(), param: s() },
+        CheckError::MissingArgument { function: s(), param: s(), expected: Vec::new(), caller: s(), span },
+        CheckError::UnknownArgument { function: s(), arg: s(), expected: Vec::new(), caller: s(), span },
+        CheckError::ArgumentTypeMismatch { function: s(), param: s(), expected: s(), found: s(), caller: s(), span },
+        CheckError::ArgumentCountMismatch { function: s(), expected: 1, found: 2, caller: s(), span },
+        CheckError::InvalidEffectVariable { function: s(), param: s(), span },
+        CheckError::TooManyPatternBindings { variant: s(), expected: 1, found: 2, span },
+        CheckError::DuplicateBinding { name: s(), step_id: s(), first: None, span },
+        CheckError::UseBeforeDefinition { name: s(), step_id: s(), defined_by: s(), span },
+        CheckError::BranchLocalBinding { name: s(), step_id: s(), branch_step: s(), span },
+        CheckError::DuplicateStepId { id: s(), snippet: s(), span },
+        CheckError::UnhandleableErrorType { function: s(), error_type: s(), errors: Vec::new(), span },
+        CheckError::DuplicateArgument { function: s(), arg: s(), caller: s(), span },
+    ]
+}
+
+fn effect_variant(error: &EffectError) -> usize {
+    match error {
+        EffectError::PureCallsEffectful { .. } => 0,
+        EffectError::MissingEffect { .. } => 1,
+        EffectError::ParameterNotCovered { .. } => 2,
+        EffectError::ImpureFunctionArgument { .. } => 3,
+        EffectError::UndeclaredEffectVariable { .. } => 4,
+        EffectError::PolicyViolation { .. } => 5,
+    }
+}
+
+fn effect_errors() -> Vec<EffectError> {
+    let span = Span::dummy();
+    vec![
+        EffectError::PureCallsEffectful { function: s(), callee: s(), effects: vec![s()], span },
+        EffectError::MissingEffect { function: s(), missing: vec![s()], source_callee: s(), span },
+        EffectError::ParameterNotCovered {
+            function: s(),
+            effect_name: s(),
+            param_name: s(),
+            required_value: s(),
+            declared_value: None,
+            source_callee: s(),
+            span,
+        },
+        EffectError::ImpureFunctionArgument { function: s(), callee: s(), param: s(), argument: s(), effects: vec![s()], span },
+        EffectError::UndeclaredEffectVariable { function: s(), callee: s(), param: s(), argument: s(), span },
+        EffectError::PolicyViolation { function: s(), effect: s(), reason: s(), call_chain: vec![s()], span },
+    ]
+}
+
+fn warning_variant(kind: &OptWarningKind) -> usize {
+    match kind {
+        OptWarningKind::UnusedBinding => 0,
+        OptWarningKind::UnreachableStep => 1,
+    }
+}
+
+fn warning_kinds() -> Vec<OptWarningKind> {
+    vec![OptWarningKind::UnusedBinding, OptWarningKind::UnreachableStep]
+}
+
+fn requirement_variant(error: &RequirementError) -> usize {
+    match error {
+        RequirementError::UncoveredRequirement { .. } => 0,
+        RequirementError::NonexistentRequirement { .. } => 1,
+        RequirementError::DuplicateRequirement { .. } => 2,
+        RequirementError::DuplicateTest { .. } => 3,
+    }
+}
+
+fn requirement_errors() -> Vec<RequirementError> {
+    let span = Span::dummy();
+    vec![
+        RequirementError::UncoveredRequirement {
+            id: s(),
+            priority: covenant_ast::Priority::High,
+            snippet_id: s(),
+            span,
+        },
+        RequirementError::NonexistentRequirement { test_id: s(), req_id: s(), snippet_id: s(), span },
+        RequirementError::DuplicateRequirement { id: s(), first: s(), second: s(), span },
+        RequirementError::DuplicateTest { id: s(), first: s(), second: s(), span },
+    ]
+}
+
+fn runtime_variant(error: &RuntimeError) -> usize {
+    match error {
+        RuntimeError::SymbolNotFound(_) => 0,
+        RuntimeError::InvalidQuery(_) => 1,
+        RuntimeError::QueryCancelled => 2,
+        RuntimeError::QueryTimeout => 3,
+        RuntimeError::ParseError(_) => 4,
+        RuntimeError::ValidationError(_) => 5,
+        RuntimeError::CompilationError(_) => 6,
+        RuntimeError::Internal(_) => 7,
+        RuntimeError::DeserializationFailed(_) => 8,
+    }
+}
+
+fn runtime_errors() -> Vec<RuntimeError> {
+    vec![
+        RuntimeError::SymbolNotFound(s()),
+        RuntimeError::InvalidQuery(s()),
+        RuntimeError::QueryCancelled,
+        RuntimeError::QueryTimeout,
+        RuntimeError::ParseError(s()),
+        RuntimeError::ValidationError(s()),
+        RuntimeError::CompilationError(s()),
+        RuntimeError::Internal(s()),
+        RuntimeError::DeserializationFailed(s()),
+    ]
+}
+
+/// The codes of `errors`, asserting they cover every variant
+fn codes_of<E>(errors: Vec<E>, variant: fn(&E) -> usize, code: fn(&E) -> &'static str) -> Vec<&'static str> {
+    let mut variants: Vec<usize> = errors.iter().map(variant).collect();
+    variants.sort_unstable();
+    variants.dedup();
+    assert_eq!(variants, (0..errors.len()).collect::<Vec<_>>(), "each variant must be listed once");
+    errors.iter().map(code).collect()
+}
+
+#[test]
+fn every_emitted_code_is_registered() {
+    let codes = [
+        codes_of(parse_errors(), parse_variant, ParseError::code),
+        codes_of(symbol_errors(), symbol_variant, SymbolError::code),
+        codes_of(check_errors(), check_variant, CheckError::code),
+        codes_of(effect_errors(), effect_variant, EffectError::code),
+        codes_of(warning_kinds(), warning_variant, OptWarningKind::code),
+        codes_of(requirement_errors(), requirement_variant, RequirementError::code),
+        codes_of(runtime_errors(), runtime_variant, RuntimeError::code),
+    ]
+    .concat();
+
+    for code in codes {
+        assert!(lookup_code(code).is_some(), "{} is emitted but not registered", code);
+    }
+}
+
+#[test]
+fn effect_diagnostics_carry_the_error_code() {
+    let graph = SymbolGraph::new();
+    for error in effect_errors() {
+        assert_eq!(explain_effect_violation(&error, &graph).code, error.code());
+    }
+}